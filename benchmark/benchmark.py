@@ -91,4 +91,86 @@ def benchmark_call_def_8name():
     return y
 
 
+REPEAT_1M = 1000000
+
+
+def benchmark_list_eq_self():
+    xs = list(range(REPEAT_1M))
+    y = 0
+    for _x in range(100):
+        if xs == xs:
+            y += 1
+    return y
+
+
+def benchmark_list_eq_copy():
+    xs = list(range(REPEAT_1M))
+    ys = list(xs)
+    y = 0
+    for _x in range(100):
+        if xs == ys:
+            y += 1
+    return y
+
+
+def benchmark_iter_lines():
+    s = "line\n" * REPEAT_1M
+    y = 0
+    for _line in s.iter_splitlines():
+        y += 1
+    return y
+
+
+def benchmark_str_concat_plus():
+    y = 0
+    for _x in range(REPEAT_1M):
+        s = ""
+        for part in ["a", "bc", "d", "ef", "g", "hi", "j", "kl"]:
+            s = s + part
+        y += len(s)
+    return y
+
+
+def benchmark_str_concat_join():
+    y = 0
+    for _x in range(REPEAT_1M):
+        s = "".join(["a", "bc", "d", "ef", "g", "hi", "j", "kl"])
+        y += len(s)
+    return y
+
+
+def benchmark_enumerate_loop():
+    xs = list(range(REPEAT_1M))
+    y = 0
+    for _ in range(100):
+        for i, x in enumerate(xs):
+            y += i + x
+    return y
+
+
+def benchmark_list_alloc():
+    y = 0
+    for _ in range(100):
+        xs = [x for x in range(200000)]
+        ys = [str(x) for x in xs]
+        y += len(xs) + len(ys)
+    return y
+
+
+def benchmark_call_global_many_sites():
+    y = 0
+    for _x in range(REPEAT_1M):
+        y = max(y, 1)
+        y = max(y, 2)
+        y = max(y, 3)
+        y = max(y, 4)
+        y = max(y, 5)
+        y = max(y, 6)
+        y = max(y, 7)
+        y = max(y, 8)
+        y = max(y, 9)
+        y = max(y, 10)
+    return y
+
+
 print(benchmark_call_def_1name())
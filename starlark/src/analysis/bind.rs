@@ -30,6 +30,7 @@ use crate::syntax::ast::Clause;
 use crate::syntax::ast::DefP;
 use crate::syntax::ast::Expr;
 use crate::syntax::ast::ForClause;
+use crate::syntax::ast::LambdaBody;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::Stmt;
 use crate::syntax::AstModule;
@@ -198,7 +199,10 @@ fn expr(x: &AstExpr, res: &mut Vec<Bind>) {
         }) => {
             let mut inner = Vec::new();
             parameters(params, res, &mut inner);
-            expr(body, &mut inner);
+            match body {
+                LambdaBody::Expr(body) => expr(body, &mut inner),
+                LambdaBody::Block(body) => stmt(body, &mut inner),
+            }
             res.push(Bind::Scope(Scope::new(inner)));
         }
         Expr::Dot(lhs, attribute) => dot_access(lhs, attribute, res),
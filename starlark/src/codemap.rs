@@ -36,6 +36,9 @@ use allocative::Allocative;
 use dupe::Dupe;
 use once_cell::sync::Lazy;
 
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+
 /// A small, `Copy`, value representing a position in a `CodeMap`'s file.
 #[derive(
     Copy, Clone, Dupe, Hash, Eq, PartialEq, PartialOrd, Ord, Debug, Default, Allocative
@@ -408,7 +411,16 @@ pub struct FileSpanRef<'a> {
 }
 
 /// A file, and a line and column range within it.
-#[derive(Clone, Dupe, Eq, PartialEq, Debug, Hash, Allocative)]
+#[derive(
+    Clone,
+    Dupe,
+    Eq,
+    PartialEq,
+    Debug,
+    Hash,
+    Allocative,
+    ProvidesStaticType
+)]
 pub struct FileSpan {
     pub(crate) file: CodeMap,
     pub(crate) span: Span,
@@ -16,6 +16,7 @@
  */
 
 use std::collections::HashMap;
+use std::mem;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -41,6 +42,7 @@ use crate::values::function::NativeFunc;
 use crate::values::function::NativeMeth;
 use crate::values::layout::value::ValueLike;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::structs::value::FrozenStruct;
 use crate::values::structs::AllocStruct;
 use crate::values::types::function::NativeFunction;
 use crate::values::types::function::NativeMethod;
@@ -88,6 +90,8 @@ pub struct GlobalsBuilder {
     variables: SymbolMap<FrozenValue>,
     // The list of struct fields, pushed to the end
     struct_fields: Vec<SmallMap<FrozenStringValue, FrozenValue>>,
+    // Depth of `struct_fields` of the innermost namespace, or 0 if not in a namespace
+    namespace_depth: usize,
     // The raw docstring for this module
     docstring: Option<String>,
 }
@@ -142,7 +146,13 @@ impl Globals {
     /// This function is only safe if you first call `heap` and keep a reference to it.
     /// Therefore, don't expose it on the public API.
     pub(crate) fn get_frozen(&self, name: &str) -> Option<FrozenValue> {
-        self.0.variables.get_str(name).copied()
+        self.get_frozen_hashed(Hashed::new(name))
+    }
+
+    /// Like [`get_frozen`](Globals::get_frozen), but reuse the hash of an already hashed
+    /// (e.g. interned) name instead of hashing it again.
+    pub(crate) fn get_frozen_hashed(&self, name: Hashed<&str>) -> Option<FrozenValue> {
+        self.0.variables.get_hashed_str(name).copied()
     }
 
     /// Get all the names defined in this environment.
@@ -181,6 +191,47 @@ impl Globals {
             .map(|(symbol, value)| (symbol.as_str().to_owned(), value.to_value().documentation()))
             .collect()
     }
+
+    /// Find the documented members whose name contains `query`, ignoring case.
+    /// Useful for implementing fuzzy search in an IDE.
+    ///
+    /// Exact matches come first, followed by prefix matches, then matches at the
+    /// start of a `_`-separated word, then any other matches. Within each group
+    /// shorter names come first.
+    pub fn search(&self, query: &str) -> Vec<(String, DocItem)> {
+        let query = query.to_lowercase();
+        self.0
+            .variables
+            .iter()
+            .filter_map(|(symbol, value)| {
+                let name = symbol.as_str();
+                let quality = search_match_quality(&name.to_lowercase(), &query)?;
+                let docs = value.to_value().documentation()?;
+                Some((quality, name, docs))
+            })
+            .sorted_by(|(lq, ln, _), (rq, rn, _)| (lq, ln.len(), ln).cmp(&(rq, rn.len(), rn)))
+            .map(|(_, name, docs)| (name.to_owned(), docs))
+            .collect()
+    }
+}
+
+/// How well `name` matches `query`, lower is better, `None` if it does not match.
+/// Both are expected to be lowercase.
+fn search_match_quality(name: &str, query: &str) -> Option<u8> {
+    if name == query {
+        Some(0)
+    } else if name.starts_with(query) {
+        Some(1)
+    } else if name
+        .match_indices(query)
+        .any(|(i, _)| name[..i].ends_with('_'))
+    {
+        Some(2)
+    } else if name.contains(query) {
+        Some(3)
+    } else {
+        None
+    }
 }
 
 impl Methods {
@@ -234,6 +285,7 @@ impl GlobalsBuilder {
             heap: FrozenHeap::new(),
             variables: SymbolMap::new(),
             struct_fields: Vec::new(),
+            namespace_depth: 0,
             docstring: None,
         }
     }
@@ -269,6 +321,24 @@ impl GlobalsBuilder {
         self.set(name, AllocStruct(fields));
     }
 
+    /// Add a namespace to the builder, grouping the functions defined by `f`
+    /// so that `f` adding `read` makes it accessible as `name.read`.
+    ///
+    /// Unlike [`struct_`](GlobalsBuilder::struct_), defining the same name
+    /// twice within the namespace is an error.
+    ///
+    /// # Panics
+    ///
+    /// If `f` defines a name already defined in the namespace.
+    pub fn namespace(&mut self, name: &str, f: impl FnOnce(&mut GlobalsBuilder)) {
+        self.struct_fields.push(SmallMap::new());
+        let namespace_depth = mem::replace(&mut self.namespace_depth, self.struct_fields.len());
+        f(self);
+        self.namespace_depth = namespace_depth;
+        let fields = self.struct_fields.pop().unwrap();
+        self.set(name, AllocStruct(fields));
+    }
+
     /// A fluent API for modifying [`GlobalsBuilder`] and returning the result.
     pub fn with(mut self, f: impl FnOnce(&mut Self)) -> Self {
         f(&mut self);
@@ -281,6 +351,47 @@ impl GlobalsBuilder {
         self
     }
 
+    /// A fluent API for modifying [`GlobalsBuilder`] using [`namespace`](GlobalsBuilder::namespace).
+    pub fn with_namespace(mut self, name: &str, f: impl Fn(&mut GlobalsBuilder)) -> Self {
+        self.namespace(name, f);
+        self
+    }
+
+    /// Add all the top-level values of `other` to this builder,
+    /// as fields of the struct being built when called within [`struct_`](GlobalsBuilder::struct_).
+    ///
+    /// When a name is defined in both and both values are structs,
+    /// their fields are merged the same way.
+    /// Otherwise `resolver` is called with the name (dotted for struct fields),
+    /// the existing value, the incoming value and the heap of this builder,
+    /// and the value it returns is kept.
+    pub fn merge_with(
+        &mut self,
+        other: &Globals,
+        resolver: impl Fn(&str, FrozenValue, FrozenValue, &FrozenHeap) -> FrozenValue,
+    ) {
+        self.heap.add_reference(&other.0.heap);
+        for (name, incoming) in other.0.variables.iter() {
+            let name = name.as_str();
+            let existing = match self.struct_fields.last() {
+                None => self.variables.get_str(name).copied(),
+                Some(fields) => fields.get_hashed(Hashed::new(name)).copied(),
+            };
+            let value = match existing {
+                Some(existing) => merge_values(&self.heap, name, existing, *incoming, &resolver),
+                None => *incoming,
+            };
+            match self.struct_fields.last_mut() {
+                None => {
+                    self.variables.insert(name, value);
+                }
+                Some(fields) => {
+                    fields.insert(self.heap.alloc_str(name), value);
+                }
+            }
+        }
+    }
+
     /// Called at the end to build a [`Globals`].
     pub fn build(self) -> Globals {
         let variable_names = self
@@ -303,7 +414,11 @@ impl GlobalsBuilder {
             None => self.variables.insert(name, value),
             Some(fields) => {
                 let name = self.heap.alloc_str(name);
-                fields.insert(name, value)
+                let old = fields.insert(name, value);
+                if old.is_some() && self.namespace_depth == self.struct_fields.len() {
+                    panic!("`{}` is already defined in the namespace", name.as_str());
+                }
+                old
             }
         };
     }
@@ -326,6 +441,8 @@ impl GlobalsBuilder {
                 function: Box::new(f),
                 name: name.to_owned(),
                 speculative_exec_safe,
+                speculative_exec_guard: None,
+                mutates_args: false,
                 typ,
                 raw_docs: Some(raw_docs),
             },
@@ -353,6 +470,38 @@ impl GlobalsBuilder {
     }
 }
 
+/// Merge two values bound to `name`, see [`GlobalsBuilder::merge_with`].
+fn merge_values(
+    heap: &FrozenHeap,
+    name: &str,
+    existing: FrozenValue,
+    incoming: FrozenValue,
+    resolver: &dyn Fn(&str, FrozenValue, FrozenValue, &FrozenHeap) -> FrozenValue,
+) -> FrozenValue {
+    let (existing_struct, incoming_struct) = match (
+        existing.downcast_frozen_ref::<FrozenStruct>(),
+        incoming.downcast_frozen_ref::<FrozenStruct>(),
+    ) {
+        (Some(existing), Some(incoming)) => (existing, incoming),
+        _ => return resolver(name, existing, incoming, heap),
+    };
+    let mut fields = existing_struct.fields.clone();
+    for (field, incoming) in incoming_struct.fields.iter() {
+        let value = match fields.get_hashed(field.get_hashed_str()) {
+            Some(existing) => merge_values(
+                heap,
+                &format!("{}.{}", name, field.as_str()),
+                *existing,
+                *incoming,
+                resolver,
+            ),
+            None => *incoming,
+        };
+        fields.insert(*field, value);
+    }
+    heap.alloc(AllocStruct(fields))
+}
+
 impl Methods {
     /// Create an empty [`Globals`], with no functions in scope.
     pub fn new() -> Self {
@@ -607,6 +756,58 @@ mod tests {
     {
     }
 
+    #[test]
+    fn test_get_frozen_hashed() {
+        let globals = Globals::standard();
+        let len = globals.get_frozen("len").unwrap().to_value();
+
+        // The compiler looks up globals by interned names, reusing their cached hash.
+        let heap = FrozenHeap::new();
+        let name = heap.alloc_str_intern("len").get_hashed_str();
+        assert_eq!(Hashed::new("len").hash(), name.hash());
+        assert_eq!("len", *name.key());
+        let len_hashed = globals.get_frozen_hashed(name).unwrap();
+        assert!(len.ptr_eq(len_hashed.to_value()));
+
+        assert!(
+            globals
+                .get_frozen_hashed(Hashed::new("no_such_global"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_global_shadowed_by_module_binding() {
+        let a = Assert::new();
+        a.pass(
+            r#"
+def before():
+    return len([1, 2])
+
+def len(x):
+    return 42
+
+def after():
+    return len([1, 2])
+
+assert_eq(before(), 42)
+assert_eq(after(), 42)
+"#,
+        );
+        a.pass(
+            r#"
+def f(len):
+    return len([1, 2])
+
+def g():
+    return len([1, 2])
+
+assert_eq(f(lambda x: 7), 7)
+assert_eq(g(), 2)
+"#,
+        );
+    }
+
     #[test]
     fn test_set_attribute() {
         #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
@@ -632,4 +833,170 @@ assert_eq(magic.my_type, "magic")
 assert_eq(magic.my_value, 42)"#,
         );
     }
+
+    #[starlark_module]
+    fn fs_members(builder: &mut GlobalsBuilder) {
+        fn read(path: &str) -> anyhow::Result<String> {
+            Ok(format!("contents of {}", path))
+        }
+
+        fn exists(path: &str) -> anyhow::Result<bool> {
+            Ok(path == "a.txt")
+        }
+    }
+
+    #[test]
+    fn test_namespace() {
+        let mut a = Assert::new();
+        a.globals_add(|x| {
+            x.namespace("fs", fs_members);
+        });
+        a.pass(
+            r#"
+assert_eq(fs.read("a.txt"), "contents of a.txt")
+assert_eq(fs.exists("a.txt"), True)
+assert_eq(fs.exists("b.txt"), False)
+assert_eq(dir(fs), ["exists", "read"])"#,
+        );
+        a.fail("fs.write('a.txt')", "has no attribute `write`");
+
+        let globals = GlobalsBuilder::new()
+            .with_namespace("fs", fs_members)
+            .build();
+        assert!(globals.get("fs").is_some());
+        assert!(globals.get("read").is_none());
+    }
+
+    #[starlark_module]
+    fn split_members(builder: &mut GlobalsBuilder) {
+        /// Split a string.
+        fn rsplit(x: &str) -> anyhow::Result<Vec<String>> {
+            Ok(x.rsplit(' ').map(str::to_owned).collect())
+        }
+
+        /// Split a string into lines.
+        fn splitlines(x: &str) -> anyhow::Result<Vec<String>> {
+            Ok(x.lines().map(str::to_owned).collect())
+        }
+
+        /// Split a string.
+        fn split(x: &str) -> anyhow::Result<Vec<String>> {
+            Ok(x.split(' ').map(str::to_owned).collect())
+        }
+
+        fn join(x: Vec<String>) -> anyhow::Result<String> {
+            Ok(x.join(" "))
+        }
+
+        fn str_split_at(x: &str, i: i32) -> anyhow::Result<(String, String)> {
+            let (a, b) = x.split_at(i as usize);
+            Ok((a.to_owned(), b.to_owned()))
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let globals = GlobalsBuilder::new().with(split_members).build();
+        let names = |query| {
+            globals
+                .search(query)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["split", "splitlines", "str_split_at", "rsplit"],
+            names("spl")
+        );
+        assert_eq!(vec!["splitlines"], names("LINES"));
+        assert_eq!(vec!["join"], names("oi"));
+        assert!(names("nothing").is_empty());
+        match &globals.search("split")[0].1 {
+            DocItem::Function(f) => {
+                assert_eq!("Split a string.", f.docs.as_ref().unwrap().summary)
+            }
+            d => panic!("Expected function docs, got {:?}", d),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "`read` is already defined in the namespace")]
+    fn test_namespace_collision() {
+        GlobalsBuilder::new().with_namespace("fs", |x| {
+            fs_members(x);
+            x.set("read", 1);
+        });
+    }
+
+    #[test]
+    fn test_merge_with() {
+        let plugin = GlobalsBuilder::new()
+            .with(|x| {
+                x.set("shared", "plugin");
+                x.set("only_plugin", 2);
+            })
+            .build();
+        let merged = |resolver: fn(&str, FrozenValue, FrozenValue, &FrozenHeap) -> FrozenValue| {
+            let mut builder = GlobalsBuilder::new().with(|x| {
+                x.set("shared", "base");
+                x.set("only_base", 1);
+            });
+            builder.merge_with(&plugin, resolver);
+            builder.build()
+        };
+
+        let keep_existing = merged(|name, existing, _, _| {
+            assert_eq!("shared", name);
+            existing
+        });
+        let take_incoming = merged(|_, _, incoming, _| incoming);
+        for (globals, shared) in [(keep_existing, "base"), (take_incoming, "plugin")] {
+            let get = |name| globals.get_frozen(name).unwrap().to_value();
+            assert_eq!(Some(shared), get("shared").unpack_str());
+            assert_eq!(Some(1), get("only_base").unpack_i32());
+            assert_eq!(Some(2), get("only_plugin").unpack_i32());
+        }
+    }
+
+    #[test]
+    fn test_merge_with_structs() {
+        let plugin = GlobalsBuilder::new()
+            .with_struct("ns", |x| {
+                x.set("shared", "plugin");
+                x.set("only_plugin", 2);
+            })
+            .build();
+        let mut builder = GlobalsBuilder::new().with_struct("ns", |x| {
+            x.set("shared", "base");
+            x.set("only_base", 1);
+        });
+        // Nested fields are merged, and the resolver can combine values on the heap.
+        builder.merge_with(&plugin, |name, existing, incoming, heap| {
+            assert_eq!("ns.shared", name);
+            heap.alloc(format!(
+                "{}+{}",
+                existing.to_value().unpack_str().unwrap(),
+                incoming.to_value().unpack_str().unwrap()
+            ))
+        });
+        // Within `struct_`, values of `other` become fields.
+        builder.struct_("nested", |x| {
+            x.merge_with(&plugin, |_, _, _, _| unreachable!());
+        });
+        let globals = builder.build();
+
+        let heap = Heap::new();
+        let ns = globals.get_frozen("ns").unwrap().to_value();
+        let get = |name| ns.get_attr(name, &heap).unwrap().unwrap();
+        assert_eq!(Some("base+plugin"), get("shared").unpack_str());
+        assert_eq!(Some(1), get("only_base").unpack_i32());
+        assert_eq!(Some(2), get("only_plugin").unpack_i32());
+        let nested = globals.get_frozen("nested").unwrap().to_value();
+        let ns = nested.get_attr("ns", &heap).unwrap().unwrap();
+        assert_eq!(
+            Some("plugin"),
+            ns.get_attr("shared", &heap).unwrap().unwrap().unpack_str()
+        );
+        assert!(globals.get_frozen("shared").is_none());
+    }
 }
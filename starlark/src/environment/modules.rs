@@ -32,6 +32,8 @@ use allocative::Allocative;
 use derive_more::Display;
 use dupe::Dupe;
 use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
@@ -60,6 +62,7 @@ use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 use crate::values::Heap;
 use crate::values::OwnedFrozenValue;
+use crate::values::SharedStringInterner;
 use crate::values::Trace;
 use crate::values::Tracer;
 use crate::values::Value;
@@ -118,8 +121,9 @@ pub(crate) struct FrozenModuleData {
     heap_profile: Option<RetainedHeapProfile>,
 }
 
-/// Container for the documentation for a module
-#[derive(Clone, Debug, PartialEq)]
+/// Container for the documentation for a module.
+/// Serializes like [`DocItem`], so it can be written out to generate a docs site.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ModuleDocs {
     /// The documentation for the module itself
     pub module: Option<DocItem>,
@@ -263,6 +267,12 @@ impl FrozenModule {
         }
     }
 
+    /// Retained memory as a JSON tree of functions and types, or error if not enabled.
+    /// See [`AggregateHeapProfileInfo::gen_summary_json`] for the format.
+    pub fn profile_heap_retained_json(&self) -> anyhow::Result<String> {
+        Ok(self.aggregated_heap_profile_info()?.gen_summary_json())
+    }
+
     /// Retained memory info, or error if not enabled.
     pub fn heap_profile(&self) -> anyhow::Result<ProfileData> {
         match &self.module.0.heap_profile {
@@ -320,8 +330,18 @@ impl Default for Module {
 impl Module {
     /// Create a new module environment with no contents.
     pub fn new() -> Self {
+        Self::with_heap(Heap::new())
+    }
+
+    /// Create a new module environment whose heap preallocates about `bytes`.
+    /// See [`Heap::with_capacity`], this is only a performance hint.
+    pub fn with_heap_capacity(bytes: usize) -> Self {
+        Self::with_heap(Heap::with_capacity(bytes))
+    }
+
+    fn with_heap(heap: Heap) -> Self {
         Self {
-            heap: Heap::new(),
+            heap,
             frozen_heap: FrozenHeap::new(),
             names: MutableNames::new(),
             slots: MutableSlots::new(),
@@ -377,6 +397,43 @@ impl Module {
 
     /// Freeze the environment, all its value will become immutable afterwards.
     pub fn freeze(self) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(None)
+    }
+
+    /// Freeze the environment like [`freeze`](Module::freeze), but reuse strings
+    /// already frozen by other modules sharing the same interner.
+    /// The resulting module keeps alive the interned strings it reuses,
+    /// but not the modules which first froze them.
+    pub fn freeze_with_string_interner(
+        self,
+        interner: &SharedStringInterner,
+    ) -> anyhow::Result<FrozenModule> {
+        self.freeze_impl(Some(interner))
+    }
+
+    /// Freeze only the exported variable `name` and everything reachable from it,
+    /// e.g. to cache the result of an evaluation. The frozen value is kept alive by
+    /// `heap`, and the rest of the module is discarded.
+    ///
+    /// Values shared within the variable (or within cyclic structures) are frozen once.
+    /// Fails if a `def` is reachable from the variable, because frozen functions
+    /// need their module: use [`freeze`](Module::freeze) instead.
+    pub fn freeze_value(self, name: &str, heap: &FrozenHeap) -> anyhow::Result<FrozenValue> {
+        let freezer = Freezer::new(FrozenHeap::new());
+        let frozen = {
+            let value = self
+                .get(name)
+                .ok_or_else(|| EnvironmentError::ModuleHasNoSymbol(name.to_owned()))?;
+            // SAFETY: the module is consumed, so no other value of its heap is used again.
+            unsafe { freezer.freeze_value(&self.heap, value)? }
+        };
+        // Literals frozen during evaluation live in the module frozen heap.
+        freezer.heap.add_reference(&self.frozen_heap.into_ref());
+        heap.add_reference(&freezer.into_ref());
+        Ok(frozen)
+    }
+
+    fn freeze_impl(self, interner: Option<&SharedStringInterner>) -> anyhow::Result<FrozenModule> {
         let Module {
             names,
             slots,
@@ -393,7 +450,10 @@ impl Module {
         // Note that we even freeze anonymous slots, since they are accessed by
         // slot-index in the code, and we don't walk into them, so don't know if
         // they are used.
-        let freezer = Freezer::new(frozen_heap);
+        let mut freezer = Freezer::new(frozen_heap);
+        if let Some(interner) = interner {
+            freezer = freezer.with_string_interner(interner.dupe());
+        }
         let slots = slots.freeze(&freezer)?;
         let stacks = if let Some(mode) = heap_profile_on_freeze.get() {
             // TODO(nga): retained heap profile does not store information about data
@@ -416,6 +476,7 @@ impl Module {
         for frozen_def in freezer.frozen_defs.borrow().as_slice() {
             frozen_def.post_freeze(frozen_module_ref, &heap, &freezer.heap);
         }
+        heap.freeze_provenance(&freezer.heap);
         // The values MUST be alive up until this point (as the above line uses them),
         // but can now be dropped
         mem::drop(heap);
@@ -537,6 +598,61 @@ mod tests {
     use crate::eval::ProfileMode;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
+    use crate::values::list::ListRef;
+    use crate::values::SharedStringInterner;
+
+    fn eval_strings_module(prefix: &str) -> Module {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.eval_module(
+            AstModule::parse(
+                "x.star",
+                format!(
+                    "names = ['{}_identifier_%d' % i for i in range(200)]",
+                    prefix
+                ),
+                &Dialect::Extended,
+            )
+            .unwrap(),
+            &Globals::standard(),
+        )
+        .unwrap();
+        module
+    }
+
+    #[test]
+    fn test_freeze_with_string_interner() {
+        let interner = SharedStringInterner::new();
+        let a = eval_strings_module("shared")
+            .freeze_with_string_interner(&interner)
+            .unwrap();
+        assert_eq!(200, interner.len());
+
+        let b_plain = eval_strings_module("shared").freeze().unwrap();
+        let b_interned = eval_strings_module("shared")
+            .freeze_with_string_interner(&interner)
+            .unwrap();
+        assert!(
+            b_interned.frozen_heap().allocated_bytes() < b_plain.frozen_heap().allocated_bytes(),
+            "interned: {}, plain: {}",
+            b_interned.frozen_heap().allocated_bytes(),
+            b_plain.frozen_heap().allocated_bytes()
+        );
+
+        let a_names = a.get("names").unwrap();
+        let b_names = b_interned.get("names").unwrap();
+        let a_first = ListRef::from_value(a_names.value()).unwrap()[0];
+        let b_first = ListRef::from_value(b_names.value()).unwrap()[0];
+        assert_eq!(a_first, b_first);
+        // The interner holds its own copy, so `b` does not keep `a` alive.
+        assert!(!a_first.ptr_eq(b_first));
+        let c = eval_strings_module("shared")
+            .freeze_with_string_interner(&interner)
+            .unwrap();
+        let c_names = c.get("names").unwrap();
+        assert!(b_first.ptr_eq(ListRef::from_value(c_names.value()).unwrap()[0]));
+        assert_eq!(200, interner.len());
+    }
 
     #[test]
     fn test_gen_heap_summary_profile() {
@@ -567,4 +683,53 @@ x = f(1)
         assert!(profile_info.unused_capacity.get() > 0);
         assert!(heap_summary.contains("\"x.star.f\""), "{:?}", heap_summary);
     }
+
+    #[test]
+    fn test_profile_heap_retained_json() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::HeapSummaryRetained)
+            .unwrap();
+        eval.eval_module(
+            AstModule::parse(
+                "x.star",
+                r"
+def f(x):
+    return [x, str(x)]
+
+x = f(1)
+"
+                .to_owned(),
+                &Dialect::Extended,
+            )
+            .unwrap(),
+            &Globals::standard(),
+        )
+        .unwrap();
+        let module = module.freeze().unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&module.profile_heap_retained_json().unwrap()).unwrap();
+
+        fn find<'a>(node: &'a serde_json::Value, function: &str) -> Option<&'a serde_json::Value> {
+            if node["function"] == function {
+                return Some(node);
+            }
+            node["callees"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find_map(|c| find(c, function))
+        }
+
+        assert_eq!("root", json["function"]);
+        let f = find(&json, "x.star.f").unwrap();
+        let types = f["types"].as_array().unwrap();
+        let list = types.iter().find(|t| t["type"] == "list").unwrap();
+        assert_eq!(1, list["count"]);
+        assert!(list["bytes"].as_u64().unwrap() > 0);
+        // Callees are nested, and totals include them.
+        assert!(find(f, "str").is_some());
+        assert_eq!(f["bytes"], list["bytes"]);
+        assert!(json["bytes"].as_u64().unwrap() > f["bytes"].as_u64().unwrap());
+    }
 }
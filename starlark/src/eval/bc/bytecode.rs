@@ -48,7 +48,11 @@ impl Bc {
     /// Find span for instruction.
     #[cold]
     #[inline(never)]
-    pub(crate) fn slow_arg_at_ptr(addr_ptr: BcPtrAddr) -> &BcInstrSlowArg {
+    pub(crate) fn slow_arg_at_ptr(mut addr_ptr: BcPtrAddr) -> &BcInstrSlowArg {
+        if addr_ptr.get_opcode() == BcOpcode::ProfileBc {
+            // `ProfileBc` has no slow arg, use the one of the instruction it precedes.
+            addr_ptr = addr_ptr.add(BcOpcode::ProfileBc.size_of_repr());
+        }
         let mut ptr = addr_ptr;
         loop {
             let opcode = ptr.get_opcode();
@@ -132,6 +132,12 @@ impl ExprCompiled {
                     x.mark_definitely_assigned_after(bc);
                 }
             }
+            ExprCompiled::FormatN(chunks_args) => {
+                let (_chunks, args) = &**chunks_args;
+                for x in args {
+                    x.mark_definitely_assigned_after(bc);
+                }
+            }
             ExprCompiled::Dict(xs) => {
                 for (k, v) in xs {
                     k.mark_definitely_assigned_after(bc);
@@ -356,6 +362,8 @@ impl IrSpanned<ExprCompiled> {
                         }
                         Builtin1::PercentSOne(before, after) => bc
                             .write_instr::<InstrPercentSOne>(span, (*before, expr, *after, target)),
+                        Builtin1::PercentDOne(before, after) => bc
+                            .write_instr::<InstrPercentDOne>(span, (*before, expr, *after, target)),
                         Builtin1::FormatOne(before, after) => {
                             bc.write_instr::<InstrFormatOne>(span, (*before, expr, *after, target))
                         }
@@ -365,6 +373,15 @@ impl IrSpanned<ExprCompiled> {
                     }
                 });
             }
+            ExprCompiled::FormatN(chunks_args) => {
+                let (chunks, args) = &**chunks_args;
+                write_exprs(args, bc, |args, bc| {
+                    bc.write_instr::<InstrFormatN>(
+                        span,
+                        (chunks.clone().into_boxed_slice(), args, target),
+                    );
+                });
+            }
             ExprCompiled::If(cond_t_f) => {
                 let (cond, t, f) = &**cond_t_f;
                 write_if_else(
@@ -430,6 +447,12 @@ impl IrSpanned<ExprCompiled> {
                         Builtin2::LeftShift => bc.write_instr::<InstrLeftShift>(span, arg),
                         Builtin2::RightShift => bc.write_instr::<InstrRightShift>(span, arg),
                         Builtin2::ArrayIndex => bc.write_instr::<InstrArrayIndex>(span, arg),
+                        Builtin2::MergeKwargs(false) => {
+                            bc.write_instr::<InstrMergeKwargs>(span, arg)
+                        }
+                        Builtin2::MergeKwargs(true) => {
+                            bc.write_instr::<InstrMergeKwargsStrict>(span, arg)
+                        }
                     }
                 });
             }
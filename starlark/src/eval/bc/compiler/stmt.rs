@@ -50,6 +50,50 @@ pub(crate) fn write_for(
 ) {
     let definitely_assigned = bc.save_definitely_assigned();
 
+    if let (Some(enumerated), AssignCompiledValue::Tuple(vars)) = (over.as_enumerate(), &var.node) {
+        if let [index_var, item_var] = vars.as_slice() {
+            // `for i, x in enumerate(...): ...`,
+            // assign index and element without allocating a tuple.
+            enumerated.write_bc_cb(bc, |over, bc| {
+                if let (Some(index_local), Some(item_local)) = (
+                    index_var.as_local_non_captured(),
+                    item_var.as_local_non_captured(),
+                ) {
+                    bc.write_for_enumerate(
+                        over,
+                        index_local.to_bc_slot().to_out(),
+                        item_local.to_bc_slot().to_out(),
+                        span,
+                        |bc| {
+                            bc.mark_definitely_assigned(index_local);
+                            bc.mark_definitely_assigned(item_local);
+                            body(bc);
+                        },
+                    )
+                } else {
+                    bc.alloc_slot(|index_slot, bc| {
+                        bc.alloc_slot(|item_slot, bc| {
+                            bc.write_for_enumerate(
+                                over,
+                                index_slot.to_out(),
+                                item_slot.to_out(),
+                                span,
+                                |bc| {
+                                    index_var.write_bc(index_slot.to_in(), bc);
+                                    item_var.write_bc(item_slot.to_in(), bc);
+                                    var.mark_definitely_assigned_after(bc);
+                                    body(bc);
+                                },
+                            )
+                        })
+                    })
+                }
+            });
+            bc.restore_definitely_assigned(definitely_assigned);
+            return;
+        }
+    }
+
     over.write_bc_cb(bc, |over, bc| {
         if let Some(var) = var.as_local_non_captured() {
             // Typical case: `for x in ...: ...`,
@@ -269,7 +269,10 @@ unsafe impl<'v> Trace<'v> for BcFrame<'v> {
 
 unsafe impl<'v> Trace<'v> for BcFramePtr<'v> {
     fn trace(&mut self, tracer: &Tracer<'v>) {
-        self.frame_mut().trace(tracer);
+        // No frame between top-level statements.
+        if self.is_inititalized() {
+            self.frame_mut().trace(tracer);
+        }
     }
 }
 
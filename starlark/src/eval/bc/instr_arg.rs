@@ -52,6 +52,7 @@ use crate::eval::runtime::slots::LocalSlotId;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
 use crate::values::types::known_methods::KnownMethod;
 use crate::values::FrozenRef;
+use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 use crate::values::FrozenValueTyped;
 use crate::values::StarlarkValue;
@@ -608,6 +609,27 @@ impl BcInstrArg for Box<[FrozenValue]> {
     fn visit_jump_addr(_param: &Self, _consumer: &mut dyn FnMut(BcAddrOffset)) {}
 }
 
+impl BcInstrArg for Box<[FrozenStringValue]> {
+    fn fmt_append(
+        param: &Self,
+        _ip: BcAddr,
+        _end_arg: Option<&BcInstrEndArg>,
+        f: &mut dyn Write,
+    ) -> fmt::Result {
+        write!(f, " [")?;
+        for (i, v) in param.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", TruncateValueRepr(v.to_frozen_value()))?;
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+
+    fn visit_jump_addr(_param: &Self, _consumer: &mut dyn FnMut(BcAddrOffset)) {}
+}
+
 impl BcInstrArg for Box<[Hashed<FrozenValue>]> {
     fn fmt_append(
         param: &Self,
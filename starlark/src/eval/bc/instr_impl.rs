@@ -17,6 +17,7 @@
 
 //! Instruction implementations.
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::marker;
 use std::ptr;
@@ -71,6 +72,7 @@ use crate::eval::runtime::arguments::ResolvedArgName;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
+use crate::eval::runtime::step::step_before_instr;
 use crate::eval::Arguments;
 use crate::eval::DefInfo;
 use crate::eval::Evaluator;
@@ -78,8 +80,12 @@ use crate::eval::ParametersSpec;
 use crate::values::dict::Dict;
 use crate::values::int::PointerI32;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::list::ListRef;
+use crate::values::string::interpolation::format_n;
 use crate::values::string::interpolation::format_one;
+use crate::values::string::interpolation::percent_d_one;
 use crate::values::string::interpolation::percent_s_one;
+use crate::values::tuple::TupleRef;
 use crate::values::types::known_methods::KnownMethod;
 use crate::values::types::list::value::ListData;
 use crate::values::typing::TypeCompiled;
@@ -649,6 +655,8 @@ pub(crate) struct InstrBitXorImpl;
 pub(crate) struct InstrLeftShiftImpl;
 pub(crate) struct InstrRightShiftImpl;
 pub(crate) struct InstrInImpl;
+pub(crate) struct InstrMergeKwargsImpl;
+pub(crate) struct InstrMergeKwargsStrictImpl;
 
 pub(crate) type InstrAdd = InstrBinOp<InstrAddImpl>;
 pub(crate) type InstrAddAssign = InstrBinOp<InstrAddAssignImpl>;
@@ -664,6 +672,8 @@ pub(crate) type InstrBitXor = InstrBinOp<InstrBitXorImpl>;
 pub(crate) type InstrLeftShift = InstrBinOp<InstrLeftShiftImpl>;
 pub(crate) type InstrRightShift = InstrBinOp<InstrRightShiftImpl>;
 pub(crate) type InstrIn = InstrBinOp<InstrInImpl>;
+pub(crate) type InstrMergeKwargs = InstrBinOp<InstrMergeKwargsImpl>;
+pub(crate) type InstrMergeKwargsStrict = InstrBinOp<InstrMergeKwargsStrictImpl>;
 
 impl InstrBinOpImpl for InstrAddImpl {
     #[inline(always)]
@@ -763,10 +773,28 @@ impl InstrBinOpImpl for InstrInImpl {
     }
 }
 
+impl InstrBinOpImpl for InstrMergeKwargsImpl {
+    #[inline(always)]
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Arguments::merge_kwargs(v0, v1, false, heap)
+    }
+}
+
+impl InstrBinOpImpl for InstrMergeKwargsStrictImpl {
+    #[inline(always)]
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Arguments::merge_kwargs(v0, v1, true, heap)
+    }
+}
+
 pub(crate) struct InstrPercentSOneImpl;
 pub(crate) type InstrPercentSOne = InstrNoFlow<InstrPercentSOneImpl>;
+pub(crate) struct InstrPercentDOneImpl;
+pub(crate) type InstrPercentDOne = InstrNoFlow<InstrPercentDOneImpl>;
 pub(crate) struct InstrFormatOneImpl;
 pub(crate) type InstrFormatOne = InstrNoFlow<InstrFormatOneImpl>;
+pub(crate) struct InstrFormatNImpl;
+pub(crate) type InstrFormatN = InstrNoFlow<InstrFormatNImpl>;
 
 impl InstrNoFlowImpl for InstrPercentSOneImpl {
     type Arg = (FrozenStringValue, BcSlotIn, FrozenStringValue, BcSlotOut);
@@ -785,6 +813,23 @@ impl InstrNoFlowImpl for InstrPercentSOneImpl {
     }
 }
 
+impl InstrNoFlowImpl for InstrPercentDOneImpl {
+    type Arg = (FrozenStringValue, BcSlotIn, FrozenStringValue, BcSlotOut);
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (before, arg, after, target): &(FrozenStringValue, BcSlotIn, FrozenStringValue, BcSlotOut),
+    ) -> anyhow::Result<()> {
+        let arg = frame.get_bc_slot(*arg);
+        let r = percent_d_one(before.as_str(), arg, after.as_str(), eval.heap())?;
+        frame.set_bc_slot(*target, r.to_value());
+        Ok(())
+    }
+}
+
 impl InstrNoFlowImpl for InstrFormatOneImpl {
     type Arg = (FrozenStringValue, BcSlotIn, FrozenStringValue, BcSlotOut);
 
@@ -796,7 +841,24 @@ impl InstrNoFlowImpl for InstrFormatOneImpl {
         (before, arg, after, target): &(FrozenStringValue, BcSlotIn, FrozenStringValue, BcSlotOut),
     ) -> anyhow::Result<()> {
         let arg = frame.get_bc_slot(*arg);
-        let r = format_one(before.as_str(), arg, after.as_str(), eval.heap());
+        let r = format_one(before.as_str(), arg, after.as_str(), eval.heap())?;
+        frame.set_bc_slot(*target, r.to_value());
+        Ok(())
+    }
+}
+
+impl InstrNoFlowImpl for InstrFormatNImpl {
+    type Arg = (Box<[FrozenStringValue]>, BcSlotInRange, BcSlotOut);
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_>,
+        frame: BcFramePtr<'v>,
+        _ip: BcPtrAddr,
+        (chunks, args, target): &(Box<[FrozenStringValue]>, BcSlotInRange, BcSlotOut),
+    ) -> anyhow::Result<()> {
+        let args = frame.get_bc_slot_range(*args);
+        let r = format_n(chunks, args, eval.heap())?;
         frame.set_bc_slot(*target, r.to_value());
         Ok(())
     }
@@ -893,6 +955,15 @@ impl InstrUnOpImpl for InstrLenImpl {
     }
 }
 
+/// Record the span of a list or dict literal if
+/// [`track_value_provenance`](Evaluator::track_value_provenance) is enabled.
+#[inline(always)]
+fn record_literal_provenance<'v>(eval: &Evaluator<'v, '_>, value: Value<'v>, ip: BcPtrAddr) {
+    if eval.track_value_provenance {
+        eval.record_literal_provenance(value, &Bc::slow_arg_at_ptr(ip).span);
+    }
+}
+
 pub(crate) struct InstrTupleNPopImpl;
 pub(crate) struct InstrListNPopImpl;
 pub(crate) struct InstrListOfConstsImpl;
@@ -935,11 +1006,12 @@ impl InstrNoFlowImpl for InstrListNPopImpl {
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
-        _: BcPtrAddr,
+        ip: BcPtrAddr,
         (values, target): &(BcSlotInRange, BcSlotOut),
     ) -> anyhow::Result<()> {
         let items = frame.get_bc_slot_range(*values);
         let value = eval.heap().alloc_list(items);
+        record_literal_provenance(eval, value, ip);
         frame.set_bc_slot(*target, value);
         Ok(())
     }
@@ -952,10 +1024,11 @@ impl InstrNoFlowImpl for InstrListOfConstsImpl {
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
-        _: BcPtrAddr,
+        ip: BcPtrAddr,
         (values, target): &(Box<[FrozenValue]>, BcSlotOut),
     ) -> anyhow::Result<()> {
         let list = eval.heap().alloc_list(coerce(&values));
+        record_literal_provenance(eval, list, ip);
         frame.set_bc_slot(*target, list);
         Ok(())
     }
@@ -968,10 +1041,11 @@ impl InstrNoFlowImpl for InstrDictOfConstsImpl {
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
-        _: BcPtrAddr,
+        ip: BcPtrAddr,
         (values, target): &(SmallMap<FrozenValue, FrozenValue>, BcSlotOut),
     ) -> anyhow::Result<()> {
         let dict = eval.heap().alloc(Dict::new((*coerce(values)).clone()));
+        record_literal_provenance(eval, dict, ip);
         frame.set_bc_slot(*target, dict);
         Ok(())
     }
@@ -1007,6 +1081,7 @@ impl InstrNoFlowImpl for InstrDictNPopImpl {
             }
         }
         let dict = eval.heap().alloc(Dict::new(dict));
+        record_literal_provenance(eval, dict, ip);
         frame.set_bc_slot(*target, dict);
         Ok(())
     }
@@ -1018,7 +1093,7 @@ impl InstrNoFlowImpl for InstrDictConstKeysImpl {
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
-        _: BcPtrAddr,
+        ip: BcPtrAddr,
         (keys, values, target): &(Box<[Hashed<FrozenValue>]>, BcSlotInRangeFrom, BcSlotOut),
     ) -> anyhow::Result<()> {
         let values = frame.get_bc_slot_range(values.to_range(keys.len() as u32));
@@ -1028,6 +1103,7 @@ impl InstrNoFlowImpl for InstrDictConstKeysImpl {
             debug_assert!(prev.is_none());
         }
         let dict = eval.heap().alloc(Dict::new(coerce(dict)));
+        record_literal_provenance(eval, dict, ip);
         frame.set_bc_slot(*target, dict);
         Ok(())
     }
@@ -1040,10 +1116,11 @@ impl InstrNoFlowImpl for InstrListNewImpl {
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
-        _: BcPtrAddr,
+        ip: BcPtrAddr,
         target: &BcSlotOut,
     ) -> anyhow::Result<()> {
         let list = eval.heap().alloc_list(&[]);
+        record_literal_provenance(eval, list, ip);
         frame.set_bc_slot(*target, list);
         Ok(())
     }
@@ -1056,10 +1133,11 @@ impl InstrNoFlowImpl for InstrDictNewImpl {
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
-        _: BcPtrAddr,
+        ip: BcPtrAddr,
         target: &BcSlotOut,
     ) -> anyhow::Result<()> {
         let dict = eval.heap().alloc(Dict::default());
+        record_literal_provenance(eval, dict, ip);
         frame.set_bc_slot(*target, dict);
         Ok(())
     }
@@ -1197,6 +1275,7 @@ impl BcInstr for InstrIfNotBr {
 }
 
 pub(crate) struct InstrForLoop;
+pub(crate) struct InstrForLoopEnumerate;
 pub(crate) struct InstrBreak;
 pub(crate) struct InstrContinue;
 
@@ -1222,7 +1301,11 @@ impl BcInstr for InstrForLoop {
             for item in iter {
                 frame.set_bc_slot(*var, item);
                 match run_block(eval, loop_start) {
-                    RunBlockResult::Continue => {}
+                    RunBlockResult::Continue => {
+                        if let Err(e) = eval.check_heap_limit() {
+                            return LoopResult::Err(EvalException(e));
+                        }
+                    }
                     RunBlockResult::Break => return LoopResult::Ok,
                     RunBlockResult::Return(v) => return LoopResult::Return(v),
                     RunBlockResult::Err(e) => return LoopResult::Err(e),
@@ -1239,6 +1322,50 @@ impl BcInstr for InstrForLoop {
     }
 }
 
+/// `for i, x in enumerate(over)` without allocating an `(i, x)` tuple per iteration.
+impl BcInstr for InstrForLoopEnumerate {
+    type Arg = (BcSlotIn, BcSlotOut, BcSlotOut, BcAddrOffset);
+
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (over, index, var, loop_end): &(BcSlotIn, BcSlotOut, BcSlotOut, BcAddrOffset),
+    ) -> InstrControl<'v, 'b> {
+        let collection = frame.get_bc_slot(*over);
+
+        // `enumerate` materializes its argument before the loop starts,
+        // so the loop body is allowed to mutate the collection.
+        let items: Cow<[Value<'v>]> = if let Some(tuple) = TupleRef::from_value(collection) {
+            Cow::Borrowed(tuple.content())
+        } else if let Some(list) = ListRef::from_value(collection) {
+            Cow::Owned(list.content().to_vec())
+        } else {
+            match collection.iterate(eval.heap()) {
+                Ok(iter) => Cow::Owned(iter.collect()),
+                Err(e) => return InstrControl::Err(e),
+            }
+        };
+
+        let loop_start = ip.add_instr::<Self>();
+        for (i, item) in items.iter().enumerate() {
+            frame.set_bc_slot(*index, Value::new_int(i as i32));
+            frame.set_bc_slot(*var, *item);
+            match run_block(eval, loop_start) {
+                RunBlockResult::Continue => {
+                    if let Err(e) = eval.check_heap_limit() {
+                        return InstrControl::Err(e);
+                    }
+                }
+                RunBlockResult::Break => break,
+                RunBlockResult::Return(v) => return InstrControl::Return(v),
+                RunBlockResult::Err(e) => return InstrControl::Err(e.0),
+            }
+        }
+        InstrControl::Next(ip.add_rel(*loop_end))
+    }
+}
+
 impl BcInstr for InstrBreak {
     type Arg = ();
 
@@ -1703,7 +1830,7 @@ impl InstrNoFlowImpl for InstrPossibleGcImpl {
         (): &(),
     ) -> anyhow::Result<()> {
         possible_gc(eval);
-        Ok(())
+        eval.check_heap_limit()
     }
 }
 
@@ -1721,16 +1848,38 @@ impl InstrNoFlowImpl for InstrBeforeStmtImpl {
     }
 }
 
+/// Record the call site and the callee when the instruction after `ip`
+/// calls a frozen `def`.
+fn record_frozen_def_call(eval: &mut Evaluator, ip: BcPtrAddr, opcode: BcOpcode) {
+    let next = ip.add_instr::<InstrProfileBc>();
+    let callee = match opcode {
+        BcOpcode::CallFrozenDef => next.get_instr::<InstrCallFrozenDef>().arg.0,
+        BcOpcode::CallFrozenDefPos => next.get_instr::<InstrCallFrozenDefPos>().arg.0,
+        _ => return,
+    };
+    let span = &Bc::slow_arg_at_ptr(ip).span;
+    eval.bc_profile
+        .record_frozen_def_call(span, callee.as_ref());
+}
+
 impl InstrNoFlowImpl for InstrProfileBcImpl {
     type Arg = BcOpcode;
 
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         _frame: BcFramePtr<'v>,
-        _ip: BcPtrAddr,
+        ip: BcPtrAddr,
         opcode: &BcOpcode,
     ) -> anyhow::Result<()> {
-        eval.bc_profile.before_instr(*opcode);
+        if eval.bc_profile.enabled() {
+            eval.bc_profile.before_instr(*opcode);
+            if eval.bc_profile.records_call_sites() {
+                record_frozen_def_call(eval, ip, *opcode);
+            }
+        }
+        if eval.step_instructions.enabled() {
+            step_before_instr(eval, *opcode, ip)?;
+        }
         Ok(())
     }
 }
@@ -1747,6 +1896,7 @@ impl InstrNoFlowImpl for InstrRecordCallEnterImpl {
         let fun = frame.get_bc_slot(*fun);
         eval.heap_profile.record_call_enter(fun, eval.heap());
         eval.flame_profile.record_call_enter(fun);
+        eval.allocations_profile.record_call_enter(fun, eval.heap());
         Ok(())
     }
 }
@@ -1762,6 +1912,7 @@ impl InstrNoFlowImpl for InstrRecordCallExitImpl {
     ) -> anyhow::Result<()> {
         eval.heap_profile.record_call_exit(eval.heap());
         eval.flame_profile.record_call_exit();
+        eval.allocations_profile.record_call_exit(eval.heap());
         Ok(())
     }
 }
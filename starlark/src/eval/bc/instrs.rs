@@ -35,6 +35,7 @@ use crate::eval::bc::addr::BcPtrAddr;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr_impl::InstrEnd;
 use crate::eval::bc::instr_impl::InstrForLoop;
+use crate::eval::bc::instr_impl::InstrForLoopEnumerate;
 use crate::eval::bc::opcode::BcOpcode;
 use crate::eval::bc::opcode::BcOpcodeHandler;
 use crate::eval::bc::repr::BcInstrHeader;
@@ -206,6 +207,13 @@ impl BcInstrs {
         })
     }
 
+    /// Number of instructions, not counting profiling instrumentation.
+    pub(crate) fn instr_count(&self) -> usize {
+        self.iter()
+            .filter(|(ptr, _ip)| ptr.get_opcode() != BcOpcode::ProfileBc)
+            .count()
+    }
+
     fn end_arg(&self) -> Option<&BcInstrEndArg> {
         self.iter()
             .find_map(|(ptr, _ip)| ptr.get_instr_checked::<InstrEnd>().map(|i| &i.arg))
@@ -253,6 +261,9 @@ impl BcInstrs {
             if opcode == BcOpcode::ForLoop {
                 let for_loop = ptr.get_instr::<InstrForLoop>();
                 loop_ends.push(ip.offset(for_loop.arg.2));
+            } else if opcode == BcOpcode::ForLoopEnumerate {
+                let for_loop = ptr.get_instr::<InstrForLoopEnumerate>();
+                loop_ends.push(ip.offset(for_loop.arg.3));
             }
         }
         Ok(())
@@ -62,6 +62,6 @@ impl BcNativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        self.imp.invoke(eval, args)
+        eval.with_native_call_timeout(&self.fun.name, |eval| self.imp.invoke(eval, args))
     }
 }
@@ -67,7 +67,9 @@ pub(crate) enum BcOpcode {
     Multiply,
     Percent,
     PercentSOne,
+    PercentDOne,
     FormatOne,
+    FormatN,
     Divide,
     FloorDivide,
     BitAnd,
@@ -76,6 +78,8 @@ pub(crate) enum BcOpcode {
     BitXor,
     LeftShift,
     RightShift,
+    MergeKwargs,
+    MergeKwargsStrict,
     Len,
     Type,
     TypeIs,
@@ -94,6 +98,7 @@ pub(crate) enum BcOpcode {
     IfBr,
     IfNotBr,
     ForLoop,
+    ForLoopEnumerate,
     Break,
     Continue,
     Return,
@@ -28,6 +28,7 @@ use crate::eval::bc::instr_impl::InstrBr;
 use crate::eval::bc::instr_impl::InstrConst;
 use crate::eval::bc::instr_impl::InstrContinue;
 use crate::eval::bc::instr_impl::InstrForLoop;
+use crate::eval::bc::instr_impl::InstrForLoopEnumerate;
 use crate::eval::bc::instr_impl::InstrIfBr;
 use crate::eval::bc::instr_impl::InstrIfNotBr;
 use crate::eval::bc::instr_impl::InstrLoadLocal;
@@ -160,7 +161,8 @@ impl<'f> BcWriter<'f> {
         arg: I::Arg,
     ) -> (BcAddr, *const I::Arg) {
         if self.profile {
-            // This instruction does not fail, so do not write span for it.
+            // Do not write span for this instruction,
+            // it shares the span of the instruction it precedes.
             self.instrs
                 .write::<InstrProfileBc>(BcOpcode::for_instr::<I>());
         }
@@ -359,6 +361,29 @@ impl<'f> BcWriter<'f> {
         self.restore_definitely_assigned(definitely_assigned);
     }
 
+    /// Write `for index, var in enumerate(over)` loop.
+    pub(crate) fn write_for_enumerate(
+        &mut self,
+        over: BcSlotIn,
+        index: BcSlotOut,
+        var: BcSlotOut,
+        span: FrameSpan,
+        body: impl FnOnce(&mut Self),
+    ) {
+        let definitely_assigned = self.save_definitely_assigned();
+
+        let (addr, arg) = self.write_instr_ret_arg::<InstrForLoopEnumerate>(
+            span,
+            (over, index, var, BcAddrOffset::FORWARD),
+        );
+        let end_patch = self.instrs.addr_to_patch(addr, unsafe { &(*arg).3 });
+        body(self);
+        self.write_instr::<InstrContinue>(span, ());
+        self.patch_addr(end_patch);
+
+        self.restore_definitely_assigned(definitely_assigned);
+    }
+
     fn stack_add(&mut self, add: u32) {
         self.stack_size += add;
         self.max_stack_size = cmp::max(self.max_stack_size, self.stack_size);
@@ -19,6 +19,7 @@ use gazebo::coerce::coerce;
 use gazebo::prelude::*;
 
 use crate::collections::symbol_map::Symbol;
+use crate::eval::compiler::expr::Builtin2;
 use crate::eval::compiler::expr::ExprCompiled;
 use crate::eval::compiler::opt_ctx::OptCtx;
 use crate::eval::compiler::scope::CstArgument;
@@ -26,19 +27,30 @@ use crate::eval::compiler::span::IrSpanned;
 use crate::eval::compiler::Compiler;
 use crate::eval::runtime::arguments::ArgNames;
 use crate::eval::runtime::arguments::ArgumentsFull;
+use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::Arguments;
 use crate::syntax::ast::ArgumentP;
+use crate::values::FrozenHeap;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 use crate::values::Value;
 
+#[derive(Debug, thiserror::Error)]
+enum ArgsCompiledValueError {
+    #[error("Repeated named argument `{0}`")]
+    RepeatedNamed(String),
+}
+
+/// Compiled arguments of a call, built with [`ArgsCompiledValueBuilder`]
+/// and passed to [`Evaluator::eval_function_with_args`](crate::eval::Evaluator::eval_function_with_args).
 #[derive(Default, Clone, Debug, VisitSpanMut)]
-pub(crate) struct ArgsCompiledValue {
+pub struct ArgsCompiledValue {
     pub(crate) pos_named: Vec<IrSpanned<ExprCompiled>>,
     /// Named arguments compiled.
     ///
     /// Note names are guaranteed to be unique here because names are validated in AST:
-    /// named arguments in [`Expr::Call`] are unique.
+    /// named arguments in [`Expr::Call`] are unique,
+    /// and [`ArgsCompiledValueBuilder`] rejects repeated names.
     pub(crate) names: Vec<(Symbol, FrozenStringValue)>,
     pub(crate) args: Option<IrSpanned<ExprCompiled>>,
     pub(crate) kwargs: Option<IrSpanned<ExprCompiled>>,
@@ -142,25 +154,221 @@ impl ArgsCompiledValue {
     }
 }
 
+/// Build [`ArgsCompiledValue`], the arguments of a call.
+///
+/// Positional and named arguments can be added in any order:
+/// `build` lays out positional arguments before named ones,
+/// and rejects repeated named arguments.
+///
+/// ```
+/// use starlark::environment::Globals;
+/// use starlark::environment::Module;
+/// use starlark::eval::ArgsCompiledValueBuilder;
+/// use starlark::eval::Evaluator;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+/// use starlark::values::FrozenHeap;
+/// use starlark::values::FrozenValue;
+///
+/// let heap = FrozenHeap::new();
+/// let args = ArgsCompiledValueBuilder::new()
+///     .named("y", heap.alloc("b"))
+///     .pos(heap.alloc("a"))
+///     .star_args(heap.alloc(vec![3]))
+///     .build(&heap)
+///     .unwrap();
+///
+/// let module = Module::new();
+/// let mut eval = Evaluator::new(&module);
+/// let ast = AstModule::parse(
+///     "f.star",
+///     "def f(x, z, y): return x + y + str(z)".to_owned(),
+///     &Dialect::Standard,
+/// )
+/// .unwrap();
+/// eval.eval_module(ast, &Globals::standard()).unwrap();
+/// let f = module.get("f").unwrap();
+/// let res = eval.eval_function_with_args(f, &args).unwrap();
+/// assert_eq!("ab3", res.unpack_str().unwrap());
+///
+/// // Named arguments must be unique.
+/// let err = ArgsCompiledValueBuilder::new()
+///     .named("x", FrozenValue::new_int(1))
+///     .named("x", FrozenValue::new_int(2))
+///     .build(&heap)
+///     .unwrap_err();
+/// assert_eq!("Repeated named argument `x`", err.to_string());
+/// ```
+#[derive(Default, Debug)]
+pub struct ArgsCompiledValueBuilder {
+    pos: Vec<IrSpanned<ExprCompiled>>,
+    named: Vec<(String, IrSpanned<ExprCompiled>)>,
+    args: Option<IrSpanned<ExprCompiled>>,
+    kwargs: Option<IrSpanned<ExprCompiled>>,
+}
+
+fn value_expr(value: FrozenValue) -> IrSpanned<ExprCompiled> {
+    IrSpanned {
+        span: FrameSpan::default(),
+        node: ExprCompiled::Value(value),
+    }
+}
+
+impl ArgsCompiledValueBuilder {
+    /// Create a builder with no arguments.
+    pub fn new() -> ArgsCompiledValueBuilder {
+        ArgsCompiledValueBuilder::default()
+    }
+
+    /// Add a positional argument.
+    pub fn pos(self, value: FrozenValue) -> Self {
+        self.pos_expr(value_expr(value))
+    }
+
+    /// Add a named argument.
+    pub fn named(self, name: &str, value: FrozenValue) -> Self {
+        self.named_expr(name, value_expr(value))
+    }
+
+    /// Set the `*args` argument, replacing any previous one.
+    pub fn star_args(self, value: FrozenValue) -> Self {
+        self.star_args_expr(value_expr(value))
+    }
+
+    /// Set the `**kwargs` argument, replacing any previous one.
+    pub fn kwargs(self, value: FrozenValue) -> Self {
+        self.kwargs_expr(value_expr(value))
+    }
+
+    pub(crate) fn pos_expr(mut self, expr: IrSpanned<ExprCompiled>) -> Self {
+        self.pos.push(expr);
+        self
+    }
+
+    pub(crate) fn named_expr(mut self, name: &str, expr: IrSpanned<ExprCompiled>) -> Self {
+        self.named.push((name.to_owned(), expr));
+        self
+    }
+
+    pub(crate) fn star_args_expr(mut self, expr: IrSpanned<ExprCompiled>) -> Self {
+        self.args = Some(expr);
+        self
+    }
+
+    pub(crate) fn kwargs_expr(mut self, expr: IrSpanned<ExprCompiled>) -> Self {
+        self.kwargs = Some(expr);
+        self
+    }
+
+    /// Build the arguments, allocating argument names on the given heap.
+    /// Fails if a named argument is repeated.
+    pub fn build(self, heap: &FrozenHeap) -> anyhow::Result<ArgsCompiledValue> {
+        let ArgsCompiledValueBuilder {
+            mut pos,
+            named,
+            args,
+            kwargs,
+        } = self;
+        let mut names = Vec::with_capacity(named.len());
+        for (name, expr) in named {
+            if names
+                .iter()
+                .any(|(_, n): &(Symbol, FrozenStringValue)| n.as_str() == name)
+            {
+                return Err(ArgsCompiledValueError::RepeatedNamed(name).into());
+            }
+            names.push((Symbol::new(&name), heap.alloc_str(&name)));
+            pos.push(expr);
+        }
+        Ok(ArgsCompiledValue {
+            pos_named: pos,
+            names,
+            args,
+            kwargs,
+        })
+    }
+}
+
 impl Compiler<'_, '_, '_> {
     pub(crate) fn args(&mut self, args: Vec<CstArgument>) -> ArgsCompiledValue {
-        let mut res = ArgsCompiledValue::default();
+        let mut res = ArgsCompiledValueBuilder::new();
+        let mut kwargs: Option<IrSpanned<ExprCompiled>> = None;
         for x in args {
             match x.node {
-                ArgumentP::Positional(x) => res.pos_named.push(self.expr(x)),
-                ArgumentP::Named(name, value) => {
-                    let fv = self
-                        .eval
-                        .module_env
-                        .frozen_heap()
-                        .alloc_str(name.node.as_str());
-                    res.names.push((Symbol::new(&name.node), fv));
-                    res.pos_named.push(self.expr(value));
+                ArgumentP::Positional(x) => res = res.pos_expr(self.expr(x)),
+                ArgumentP::Named(name, value) => res = res.named_expr(&name.node, self.expr(value)),
+                ArgumentP::Args(x) => res = res.star_args_expr(self.expr(x)),
+                ArgumentP::KwArgs(x) => {
+                    let x = self.expr(x);
+                    kwargs = Some(match kwargs.take() {
+                        None => x,
+                        Some(prev) => {
+                            let span = prev.span.merge(&x.span);
+                            IrSpanned {
+                                node: ExprCompiled::bin_op(
+                                    Builtin2::MergeKwargs(self.strict_kwargs),
+                                    prev,
+                                    x,
+                                    &mut self.opt_ctx(),
+                                ),
+                                span,
+                            }
+                        }
+                    });
                 }
-                ArgumentP::Args(x) => res.args = Some(self.expr(x)),
-                ArgumentP::KwArgs(x) => res.kwargs = Some(self.expr(x)),
             }
         }
-        res
+        if let Some(kwargs) = kwargs {
+            res = res.kwargs_expr(kwargs);
+        }
+        res.build(self.eval.module_env.frozen_heap())
+            .expect("named arguments are validated to be unique in AST")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::compiler::args::ArgsCompiledValueBuilder;
+    use crate::values::FrozenHeap;
+    use crate::values::FrozenValue;
+
+    fn int(i: i32) -> FrozenValue {
+        FrozenValue::new_int(i)
+    }
+
+    #[test]
+    fn test_builder() {
+        let heap = FrozenHeap::new();
+        let args = ArgsCompiledValueBuilder::new()
+            .named("x", int(1))
+            .pos(int(2))
+            .named("y", int(3))
+            .kwargs(int(4))
+            .build(&heap)
+            .unwrap();
+        // Positional arguments come first, followed by named arguments in order.
+        let pos_named: Vec<_> = args
+            .pos_named
+            .iter()
+            .map(|e| e.as_value().unwrap().unpack_int().unwrap())
+            .collect();
+        assert_eq!(vec![2, 1, 3], pos_named);
+        let names: Vec<_> = args.names.iter().map(|(_, n)| n.as_str()).collect();
+        assert_eq!(vec!["x", "y"], names);
+        assert_eq!(1, args.split_pos_names().0.len());
+        assert!(args.args.is_none());
+        assert!(args.kwargs.is_some());
+    }
+
+    #[test]
+    fn test_builder_repeated_named() {
+        let heap = FrozenHeap::new();
+        let err = ArgsCompiledValueBuilder::new()
+            .named("x", int(1))
+            .pos(int(2))
+            .named("x", int(3))
+            .build(&heap)
+            .unwrap_err();
+        assert_eq!("Repeated named argument `x`", err.to_string());
     }
 }
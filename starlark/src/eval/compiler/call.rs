@@ -29,10 +29,12 @@ use crate::eval::compiler::def_inline::InlineDefCallSite;
 use crate::eval::compiler::expr::Builtin1;
 use crate::eval::compiler::expr::ExprCompiled;
 use crate::eval::compiler::opt_ctx::OptCtx;
+use crate::eval::compiler::opt_ctx::FOLD_MAX_HEAP_BYTES;
 use crate::eval::compiler::span::IrSpanned;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrameAlloc;
 use crate::eval::runtime::visit_span::VisitSpanMut;
+use crate::values::string::interpolation::parse_format_n;
 use crate::values::string::interpolation::parse_format_one;
 use crate::values::FrozenStringValue;
 use crate::values::Value;
@@ -91,6 +93,14 @@ impl CallCompiled {
         self.args.one_pos()
     }
 
+    /// If this call is `enumerate(x)`, return `x`.
+    pub(crate) fn as_enumerate(&self) -> Option<&IrSpanned<ExprCompiled>> {
+        if !self.fun.is_fn_enumerate() {
+            return None;
+        }
+        self.args.one_pos()
+    }
+
     /// This call is infallible and has no side effects.
     pub(crate) fn is_pure_infallible(&self) -> bool {
         match self.as_type() {
@@ -202,20 +212,39 @@ impl CallCompiled {
     ) -> Option<ExprCompiled> {
         let fun = fun.as_value()?;
 
-        if !fun.speculative_exec_safe() {
+        if !ctx.optimizations() {
             return None;
         }
+        // Mutating functions are never folded, even if marked safe or guarded:
+        // the arguments are constants shared by every execution of the call.
+        if fun.mutates_args() {
+            return None;
+        }
+        let guard = if fun.speculative_exec_safe() {
+            None
+        } else {
+            Some(fun.speculative_exec_guard()?)
+        };
 
         let eval = ctx.eval()?;
 
         // Only if all call arguments are frozen values.
         args.all_values(|arguments| {
-            let v = fun.to_value().invoke(arguments.frozen_to_v(), eval).ok()?;
+            if let Some(guard) = guard {
+                if !guard(arguments) {
+                    return None;
+                }
+            }
+            let v = eval
+                .with_heap_limit(FOLD_MAX_HEAP_BYTES, |eval| {
+                    fun.to_value().invoke(arguments.frozen_to_v(), eval)
+                })
+                .ok()?;
             ExprCompiled::try_value(span, v, eval.module_env.frozen_heap())
         })?
     }
 
-    // Optimize `"aaa{}bbb".format(arg)`.
+    // Optimize `"aaa{}bbb".format(arg)` and `"aaa{}bbb{}ccc".format(arg0, arg1)`.
     fn try_format(
         fun: &IrSpanned<ExprCompiled>,
         args: &ArgsCompiledValue,
@@ -226,13 +255,26 @@ impl CallCompiled {
         if fun.method.name != "format" {
             return None;
         }
-        let arg = args.one_pos()?;
 
-        let (before, after) = parse_format_one(&format)?;
+        if let Some(arg) = args.one_pos() {
+            let (before, after) = parse_format_one(&format)?;
+
+            let before = ctx.frozen_heap().alloc_str(&before);
+            let after = ctx.frozen_heap().alloc_str(&after);
+            return Some(ExprCompiled::format_one(before, arg.clone(), after, ctx));
+        }
 
-        let before = ctx.frozen_heap().alloc_str(&before);
-        let after = ctx.frozen_heap().alloc_str(&after);
-        Some(ExprCompiled::format_one(before, arg.clone(), after, ctx))
+        let args = args.pos_only()?;
+        if args.len() < 2 {
+            return None;
+        }
+        let chunks = parse_format_n(&format)?;
+        // Wrong number of arguments is an error, reported at runtime.
+        if chunks.len() != args.len() + 1 {
+            return None;
+        }
+        let chunks = chunks.map(|c| ctx.frozen_heap().alloc_str(c));
+        Some(ExprCompiled::format_n(chunks, args.to_vec(), ctx))
     }
 
     pub(crate) fn call(
@@ -0,0 +1,190 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Syntactic call graph of the functions defined in a module.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::eval::compiler::scope::BindingId;
+use crate::eval::compiler::scope::CstExpr;
+use crate::eval::compiler::scope::CstPayload;
+use crate::eval::compiler::scope::CstStmt;
+use crate::eval::compiler::scope::ResolvedIdent;
+use crate::eval::compiler::scope::Slot;
+use crate::syntax::ast::DefP;
+use crate::syntax::ast::ExprP;
+use crate::syntax::ast::StmtP;
+use crate::syntax::uniplate::Visit;
+
+/// A call from a module-level `def` which does not resolve to a module-level `def`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicCall {
+    /// Name of the calling function.
+    pub caller: String,
+    /// Source of the called expression, e.g. `self.method` or `callbacks[0]`.
+    pub callee: String,
+    /// Location of the called expression.
+    pub span: FileSpan,
+}
+
+/// Which functions defined at the top level of a module call which,
+/// as written in the source, see [`Evaluator::call_graph`](crate::eval::Evaluator::call_graph).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallGraph {
+    /// Pairs `(caller, callee)` of module-level `def`s, where the body of `caller`
+    /// (including nested functions and lambdas) calls `callee` by name.
+    /// Ordered by the first call site, without duplicates.
+    pub edges: Vec<(String, String)>,
+    /// Calls from module-level `def`s whose target is not known until runtime,
+    /// e.g. calls of parameters, attributes or other values.
+    /// Calls of globals such as `len` are in neither list.
+    pub dynamic: Vec<DynamicCall>,
+}
+
+fn collect_defs<'a>(stmt: &'a CstStmt, defs: &mut Vec<&'a DefP<CstPayload>>) {
+    match &stmt.node {
+        StmtP::Def(def) => defs.push(def),
+        _ => stmt.visit_stmt(|x| collect_defs(x, defs)),
+    }
+}
+
+impl CallGraph {
+    /// Compute the graph for a module whose identifiers have been resolved.
+    pub(crate) fn new(module: &CstStmt, codemap: &CodeMap) -> CallGraph {
+        let mut defs = Vec::new();
+        collect_defs(module, &mut defs);
+        let names: HashMap<BindingId, &str> = defs
+            .iter()
+            .filter_map(|def| Some((def.name.1?, def.name.0.as_str())))
+            .collect();
+
+        let mut graph = CallGraph::default();
+        let mut seen_edges = HashSet::new();
+        for def in defs {
+            let caller = &def.name.0;
+            let mut visit_call = |callee: &CstExpr| match &callee.node {
+                ExprP::Identifier(_, Some(ResolvedIdent::Global(_))) => {}
+                ExprP::Identifier(_, Some(ResolvedIdent::Slot((Slot::Module(_), binding))))
+                    if names.contains_key(binding) =>
+                {
+                    let callee = names[binding];
+                    if seen_edges.insert((caller.as_str(), callee)) {
+                        graph.edges.push((caller.clone(), callee.to_owned()));
+                    }
+                }
+                _ => graph.dynamic.push(DynamicCall {
+                    caller: caller.clone(),
+                    callee: codemap.source_span(callee.span).to_owned(),
+                    span: codemap.file_span(callee.span),
+                }),
+            };
+            fn walk<'a>(x: Visit<'a, CstPayload>, f: &mut dyn FnMut(&'a CstExpr)) {
+                if let Visit::Expr(e) = &x {
+                    if let ExprP::Call(callee, _) = &e.node {
+                        f(callee);
+                    }
+                }
+                x.visit_children(|x| walk(x, f));
+            }
+            walk(Visit::Stmt(&def.body), &mut visit_call);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_call_graph() -> anyhow::Result<()> {
+        let program = r#"
+def leaf(x):
+    return len(x)
+
+def middle(xs, callback):
+    callback(xs)
+    return [leaf(x) for x in xs] + [leaf(xs)]
+
+def top():
+    def local(y):
+        return middle(y, leaf)
+    return local([]) + top_value.count(1)
+
+def shadowed(leaf):
+    return leaf(1)
+
+top_value = []
+"#;
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_call_graph(true);
+        assert!(eval.call_graph().edges.is_empty());
+        eval.eval_module(
+            AstModule::parse("a.star", program.to_owned(), &Dialect::Standard)?,
+            &Globals::standard(),
+        )?;
+
+        let graph = eval.call_graph();
+        assert_eq!(
+            vec![
+                ("middle".to_owned(), "leaf".to_owned()),
+                ("top".to_owned(), "middle".to_owned()),
+            ],
+            graph.edges
+        );
+        let dynamic: Vec<(&str, &str)> = graph
+            .dynamic
+            .iter()
+            .map(|c| (c.caller.as_str(), c.callee.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("middle", "callback"),
+                ("top", "local"),
+                ("top", "top_value.count"),
+                ("shadowed", "leaf"),
+            ],
+            dynamic
+        );
+        assert_eq!("a.star:6:5-13", graph.dynamic[0].span.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_graph_disabled() -> anyhow::Result<()> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.eval_module(
+            AstModule::parse(
+                "a.star",
+                "def f(): pass\ndef g(): f()".to_owned(),
+                &Dialect::Standard,
+            )?,
+            &Globals::standard(),
+        )?;
+        assert!(eval.call_graph().edges.is_empty());
+        Ok(())
+    }
+}
@@ -42,6 +42,7 @@ impl PartialEq<BuiltinFn> for FrozenValue {
 pub(crate) struct Constants {
     pub(crate) fn_len: BuiltinFn,
     pub(crate) fn_type: BuiltinFn,
+    pub(crate) fn_enumerate: BuiltinFn,
 }
 
 impl Constants {
@@ -51,6 +52,7 @@ impl Constants {
             Constants {
                 fn_len: BuiltinFn(g.get_frozen("len").unwrap()),
                 fn_type: BuiltinFn(g.get_frozen("type").unwrap()),
+                fn_enumerate: BuiltinFn(g.get_frozen("enumerate").unwrap()),
             }
         });
         Lazy::force(&RES)
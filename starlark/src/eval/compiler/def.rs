@@ -74,6 +74,7 @@ use crate::syntax::ast::ParameterP;
 use crate::values::frozen_ref::AtomicFrozenRefOption;
 use crate::values::function::FUNCTION_TYPE;
 use crate::values::typing::TypeCompiled;
+use crate::values::Demand;
 use crate::values::Freeze;
 use crate::values::Freezer;
 use crate::values::FrozenHeap;
@@ -610,6 +611,10 @@ where
     fn documentation(&self) -> Option<DocItem> {
         self.docs()
     }
+
+    fn provide(&'v self, demand: &mut Demand<'_, 'v>) {
+        demand.provide_value(self.def_info.signature_span.to_file_span());
+    }
 }
 
 impl<'v, V: ValueLike<'v>> DefGen<V>
@@ -777,6 +782,7 @@ impl FrozenDef {
                     module: def_module.as_ref(),
                     heap,
                     frozen_heap,
+                    optimizations: self.def_info.stmt_compile_context.optimizations,
                 },
                 self.parameters.len().try_into().unwrap(),
             ))
@@ -128,6 +128,10 @@ impl IsSafeToInlineExpr {
             ExprCompiled::Tuple(xs) | ExprCompiled::List(xs) => {
                 xs.iter().all(|x| self.is_safe_to_inline_expr(x))
             }
+            ExprCompiled::FormatN(chunks_args) => {
+                let (_chunks, args) = &**chunks_args;
+                args.iter().all(|x| self.is_safe_to_inline_expr(x))
+            }
             ExprCompiled::Dict(xs) => xs
                 .iter()
                 .all(|(x, y)| self.is_safe_to_inline_expr(x) && self.is_safe_to_inline_expr(y)),
@@ -285,6 +289,17 @@ impl<'s, 'v, 'a, 'e> InlineDefCallSite<'s, 'v, 'a, 'e> {
                     node: ExprCompiled::tuple(xs, self.ctx.frozen_heap()),
                 }
             }
+            ExprCompiled::FormatN(chunks_args) => {
+                let (chunks, args) = &**chunks_args;
+                let args = args
+                    .iter()
+                    .map(|x| self.inline(x))
+                    .collect::<Result<Vec<_>, CannotInline>>()?;
+                IrSpanned {
+                    span,
+                    node: ExprCompiled::format_n(chunks.clone(), args, self.ctx),
+                }
+            }
             ExprCompiled::Dict(xs) => {
                 let xs = xs
                     .iter()
@@ -36,6 +36,7 @@ use crate::eval::compiler::def::FrozenDef;
 use crate::eval::compiler::expr_bool::ExprCompiledBool;
 use crate::eval::compiler::known::list_to_tuple;
 use crate::eval::compiler::opt_ctx::OptCtx;
+use crate::eval::compiler::opt_ctx::FOLD_MAX_HEAP_BYTES;
 use crate::eval::compiler::scope::AssignCount;
 use crate::eval::compiler::scope::Captured;
 use crate::eval::compiler::scope::CstExpr;
@@ -47,12 +48,14 @@ use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
+use crate::eval::Arguments;
 use crate::syntax::ast::AstExprP;
 use crate::syntax::ast::AstLiteral;
 use crate::syntax::ast::AstPayload;
 use crate::syntax::ast::AstString;
 use crate::syntax::ast::BinOp;
 use crate::syntax::ast::ExprP;
+use crate::syntax::ast::LambdaBodyP;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::StmtP;
 use crate::syntax::lexer::TokenInt;
@@ -60,7 +63,7 @@ use crate::values::function::BoundMethodGen;
 use crate::values::function::FrozenBoundMethod;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
 use crate::values::list::ListRef;
-use crate::values::string::interpolation::parse_percent_s_one;
+use crate::values::string::interpolation::parse_percent_one;
 use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::types::bool::StarlarkBool;
 use crate::values::types::dict::Dict;
@@ -68,7 +71,9 @@ use crate::values::types::float::StarlarkFloat;
 use crate::values::types::list::value::FrozenListData;
 use crate::values::types::list::value::ListData;
 use crate::values::types::range::Range;
+use crate::values::types::string::interpolation::format_n;
 use crate::values::types::string::interpolation::format_one;
+use crate::values::types::string::interpolation::percent_d_one;
 use crate::values::types::string::interpolation::percent_s_one;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::types::unbound::MaybeUnboundValue;
@@ -132,6 +137,8 @@ pub(crate) enum Builtin1 {
     TypeIs(FrozenStringValue),
     /// `"aaa%sbbb" % arg`
     PercentSOne(FrozenStringValue, FrozenStringValue),
+    /// `"aaa%dbbb" % arg`
+    PercentDOne(FrozenStringValue, FrozenStringValue),
     /// `"aaa%sbbb".format(arg)`
     FormatOne(FrozenStringValue, FrozenStringValue),
     /// `x.field`.
@@ -147,13 +154,20 @@ impl Builtin1 {
             Builtin1::Not => Some(Value::new_bool(!v.to_value().to_bool())),
             Builtin1::TypeIs(t) => Some(Value::new_bool(v.to_value().get_type_value() == *t)),
             Builtin1::FormatOne(before, after) => {
-                Some(format_one(before, v.to_value(), after, ctx.heap()).to_value())
+                format_one(before, v.to_value(), after, ctx.heap())
+                    .map(|s| s.to_value())
+                    .ok()
             }
             Builtin1::PercentSOne(before, after) => {
                 percent_s_one(before, v.to_value(), after, ctx.heap())
                     .map(|s| s.to_value())
                     .ok()
             }
+            Builtin1::PercentDOne(before, after) => {
+                percent_d_one(before, v.to_value(), after, ctx.heap())
+                    .map(|s| s.to_value())
+                    .ok()
+            }
             Builtin1::Dot(field) => {
                 Some(ExprCompiled::compile_time_getattr(v, field, ctx)?.to_value())
             }
@@ -194,6 +208,9 @@ pub(crate) enum Builtin2 {
     Compare(CompareOp),
     /// `a[b]`.
     ArrayIndex,
+    /// Merge of two `**kwargs` dictionaries in a call like `f(**a, **b)`.
+    /// When `true`, keys present in both are an error.
+    MergeKwargs(bool),
 }
 
 impl Builtin2 {
@@ -214,6 +231,7 @@ impl Builtin2 {
             Builtin2::LeftShift => a.left_shift(b, heap),
             Builtin2::RightShift => a.right_shift(b, heap),
             Builtin2::ArrayIndex => a.at(b, heap),
+            Builtin2::MergeKwargs(strict) => Arguments::merge_kwargs(a, b, strict, heap),
         }
     }
 }
@@ -257,6 +275,9 @@ pub(crate) enum ExprCompiled {
         )>,
     ),
     Builtin1(Builtin1, Box<IrSpanned<ExprCompiled>>),
+    /// `"c0{}c1{}...cn".format(a0, a1, ...)` with at least two arguments,
+    /// format string chunks are one more than arguments.
+    FormatN(Box<(Vec<FrozenStringValue>, Vec<IrSpanned<ExprCompiled>>)>),
     LogicalBinOp(
         ExprLogicalBinOp,
         Box<(IrSpanned<ExprCompiled>, IrSpanned<ExprCompiled>)>,
@@ -314,6 +335,22 @@ impl ExprCompiled {
         }
     }
 
+    /// Expression is builtin `enumerate` function.
+    pub(crate) fn is_fn_enumerate(&self) -> bool {
+        match self.as_value() {
+            Some(value) => value == Constants::get().fn_enumerate,
+            None => false,
+        }
+    }
+
+    /// If expression is `enumerate(x)`, return `x`.
+    pub(crate) fn as_enumerate(&self) -> Option<&IrSpanned<ExprCompiled>> {
+        match self {
+            Self::Call(c) => c.as_enumerate(),
+            _ => None,
+        }
+    }
+
     /// If expression if `type(x) == t`, return `x` and `t`.
     pub(crate) fn as_type_is(&self) -> Option<(&IrSpanned<ExprCompiled>, FrozenStringValue)> {
         match self {
@@ -491,6 +528,10 @@ impl IrSpanned<ExprCompiled> {
                 let e = e.optimize(ctx);
                 ExprCompiled::un_op(span, op, e, ctx)
             }
+            ExprCompiled::FormatN(chunks_args) => {
+                let (chunks, args) = &**chunks_args;
+                ExprCompiled::format_n(chunks.clone(), args.map(|e| e.optimize(ctx)), ctx)
+            }
             ExprCompiled::LogicalBinOp(op, l_r) => {
                 let (l, r) = &**l_r;
                 let l = l.optimize(ctx);
@@ -517,9 +558,13 @@ impl IrSpanned<ExprCompiled> {
 }
 
 impl ExprCompiled {
-    fn equals(l: IrSpanned<ExprCompiled>, r: IrSpanned<ExprCompiled>) -> IrSpanned<ExprCompiled> {
+    fn equals(
+        l: IrSpanned<ExprCompiled>,
+        r: IrSpanned<ExprCompiled>,
+        ctx: &mut OptCtx,
+    ) -> IrSpanned<ExprCompiled> {
         let span = l.span.merge(&r.span);
-        if let (Some(l), Some(r)) = (l.as_value(), r.as_value()) {
+        if let (true, Some(l), Some(r)) = (ctx.optimizations(), l.as_value(), r.as_value()) {
             // If comparison fails, let it fail in runtime.
             if let Ok(r) = l.equals(r.to_value()) {
                 return IrSpanned {
@@ -609,10 +654,13 @@ impl ExprCompiled {
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
         if let Some(v) = l.as_string() {
-            if let Some((before, after)) = parse_percent_s_one(&v) {
+            if let Some((before, conversion, after)) = parse_percent_one(&v) {
                 let before = ctx.frozen_heap().alloc_str(&before);
                 let after = ctx.frozen_heap().alloc_str(&after);
-                return ExprCompiled::percent_s_one(before, r, after, ctx);
+                return match conversion {
+                    's' => ExprCompiled::percent_s_one(before, r, after, ctx),
+                    _ => ExprCompiled::percent_d_one(before, r, after, ctx),
+                };
             }
         }
         ExprCompiled::Builtin2(Builtin2::Percent, Box::new((l, r)))
@@ -624,7 +672,7 @@ impl ExprCompiled {
         after: FrozenStringValue,
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
-        if let Some(arg) = arg.as_value() {
+        if let (true, Some(arg)) = (ctx.optimizations(), arg.as_value()) {
             if let Ok(value) =
                 percent_s_one(before.as_str(), arg.to_value(), after.as_str(), ctx.heap())
             {
@@ -636,21 +684,58 @@ impl ExprCompiled {
         ExprCompiled::Builtin1(Builtin1::PercentSOne(before, after), Box::new(arg))
     }
 
+    fn percent_d_one(
+        before: FrozenStringValue,
+        arg: IrSpanned<ExprCompiled>,
+        after: FrozenStringValue,
+        ctx: &mut OptCtx,
+    ) -> ExprCompiled {
+        if let (true, Some(arg)) = (ctx.optimizations(), arg.as_value()) {
+            if let Ok(value) =
+                percent_d_one(before.as_str(), arg.to_value(), after.as_str(), ctx.heap())
+            {
+                let value = ctx.frozen_heap().alloc_str(value.as_str());
+                return ExprCompiled::Value(value.to_frozen_value());
+            }
+        }
+
+        ExprCompiled::Builtin1(Builtin1::PercentDOne(before, after), Box::new(arg))
+    }
+
     pub(crate) fn format_one(
         before: FrozenStringValue,
         arg: IrSpanned<ExprCompiled>,
         after: FrozenStringValue,
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
-        if let Some(arg) = arg.as_value() {
-            let value = format_one(&before, arg.to_value(), &after, ctx.heap());
-            let value = ctx.frozen_heap().alloc_str(value.as_str());
-            return ExprCompiled::Value(value.to_frozen_value());
+        if let (true, Some(arg)) = (ctx.optimizations(), arg.as_value()) {
+            // Leave the error, such as a string length limit, to runtime.
+            if let Ok(value) = format_one(&before, arg.to_value(), &after, ctx.heap()) {
+                let value = ctx.frozen_heap().alloc_str(value.as_str());
+                return ExprCompiled::Value(value.to_frozen_value());
+            }
         }
 
         ExprCompiled::Builtin1(Builtin1::FormatOne(before, after), Box::new(arg))
     }
 
+    pub(crate) fn format_n(
+        chunks: Vec<FrozenStringValue>,
+        args: Vec<IrSpanned<ExprCompiled>>,
+        ctx: &mut OptCtx,
+    ) -> ExprCompiled {
+        if ctx.optimizations() {
+            if let Ok(values) = args.try_map(|a| a.as_value().map(|v| v.to_value()).ok_or(())) {
+                if let Ok(value) = format_n(&chunks, &values, ctx.heap()) {
+                    let value = ctx.frozen_heap().alloc_str(value.as_str());
+                    return ExprCompiled::Value(value.to_frozen_value());
+                }
+            }
+        }
+
+        ExprCompiled::FormatN(Box::new((chunks, args)))
+    }
+
     fn add(l: IrSpanned<ExprCompiled>, r: IrSpanned<ExprCompiled>) -> ExprCompiled {
         let span = l.span.merge(&r.span);
         if let (Some(l), Some(r)) = (l.as_short_list_of_consts(), r.as_short_list_of_consts()) {
@@ -676,8 +761,15 @@ impl ExprCompiled {
         let span = l.span.merge(&r.span);
         // Binary operators should have no side effects,
         // but to avoid possible problems, we only fold binary operators on builtin types.
-        if let (Some(l), Some(r)) = (l.as_builtin_value(), r.as_builtin_value()) {
-            if let Ok(v) = bin_op.eval(l.to_value(), r.to_value(), ctx.heap()) {
+        if let (true, Some(l), Some(r)) = (
+            ctx.optimizations(),
+            l.as_builtin_value(),
+            r.as_builtin_value(),
+        ) {
+            let heap = ctx.heap();
+            if let Ok(v) = heap.with_max_bytes(FOLD_MAX_HEAP_BYTES, || {
+                bin_op.eval(l.to_value(), r.to_value(), heap)
+            }) {
                 if let Some(v) = ExprCompiled::try_value(span, v, ctx.frozen_heap()) {
                     return v;
                 }
@@ -687,7 +779,7 @@ impl ExprCompiled {
         match bin_op {
             Builtin2::Percent => ExprCompiled::percent(l, r, ctx),
             Builtin2::Add => ExprCompiled::add(l, r),
-            Builtin2::Equals => ExprCompiled::equals(l, r).node,
+            Builtin2::Equals => ExprCompiled::equals(l, r, ctx).node,
             Builtin2::ArrayIndex => ExprCompiled::array_indirection(l, r, ctx),
             bin_op => ExprCompiled::Builtin2(bin_op, Box::new((l, r))),
         }
@@ -730,7 +822,7 @@ impl ExprCompiled {
         expr: IrSpanned<ExprCompiled>,
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
-        if let Some(v) = expr.as_builtin_value() {
+        if let (true, Some(v)) = (ctx.optimizations(), expr.as_builtin_value()) {
             if let Some(v) = op.eval(v, ctx) {
                 if let Some(v) = ExprCompiled::try_value(expr.span, v, ctx.frozen_heap()) {
                     return v;
@@ -744,6 +836,9 @@ impl ExprCompiled {
             Builtin1::PercentSOne(before, after) => {
                 ExprCompiled::percent_s_one(*before, expr, *after, ctx)
             }
+            Builtin1::PercentDOne(before, after) => {
+                ExprCompiled::percent_d_one(*before, expr, *after, ctx)
+            }
             Builtin1::Dot(field) => ExprCompiled::dot(expr, field, ctx),
             Builtin1::TypeIs(t) => ExprCompiled::type_is(expr, *t),
             Builtin1::Not => ExprCompiled::not(span, expr).node,
@@ -848,7 +943,7 @@ impl ExprCompiled {
         field: &Symbol,
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
-        if let Some(left) = object.as_value() {
+        if let (true, Some(left)) = (ctx.optimizations(), object.as_value()) {
             if let Some(v) = Self::compile_time_getattr(left, field, ctx) {
                 return ExprCompiled::Value(v);
             }
@@ -865,7 +960,8 @@ impl ExprCompiled {
         step: Option<IrSpanned<ExprCompiled>>,
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
-        if let (Some(array), Some(start), Some(stop), Some(step)) = (
+        if let (true, Some(array), Some(start), Some(stop), Some(step)) = (
+            ctx.optimizations(),
             array.as_builtin_value(),
             start.as_ref().map(|e| e.as_value()),
             stop.as_ref().map(|e| e.as_value()),
@@ -891,7 +987,11 @@ impl ExprCompiled {
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
         let span = array.span.merge(&index.span);
-        if let (Some(array), Some(index)) = (array.as_builtin_value(), index.as_value()) {
+        if let (true, Some(array), Some(index)) = (
+            ctx.optimizations(),
+            array.as_builtin_value(),
+            index.as_value(),
+        ) {
             if let Ok(v) = array.to_value().at(index.to_value(), ctx.heap()) {
                 if let Some(expr) = ExprCompiled::try_value(span, v, ctx.frozen_heap()) {
                     return expr;
@@ -1163,7 +1263,7 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e> {
         }
     }
 
-    fn opt_ctx<'s>(&'s mut self) -> OptCtx<'v, 'a, 's> {
+    pub(crate) fn opt_ctx<'s>(&'s mut self) -> OptCtx<'v, 'a, 's> {
         let param_count = self.current_scope().param_count();
         OptCtx::new(self.eval, param_count)
     }
@@ -1181,9 +1281,12 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e> {
                     body,
                     payload: scope_id,
                 } = l;
-                let suite = Spanned {
-                    span: expr.span,
-                    node: StmtP::Return(Some(*body)),
+                let suite = match body {
+                    LambdaBodyP::Expr(body) => Spanned {
+                        span: expr.span,
+                        node: StmtP::Return(Some(*body)),
+                    },
+                    LambdaBodyP::Block(body) => *body,
                 };
                 self.function("lambda", signature_span, scope_id, params, None, suite)
             }
@@ -1265,9 +1368,12 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e> {
                     match op {
                         BinOp::Or => return ExprCompiled::or(l, r),
                         BinOp::And => return ExprCompiled::and(l, r),
-                        BinOp::Equal => return ExprCompiled::equals(l, r),
+                        BinOp::Equal => return ExprCompiled::equals(l, r, &mut self.opt_ctx()),
                         BinOp::NotEqual => {
-                            return ExprCompiled::not(span, ExprCompiled::equals(l, r));
+                            return ExprCompiled::not(
+                                span,
+                                ExprCompiled::equals(l, r, &mut self.opt_ctx()),
+                            );
                         }
                         BinOp::Less => ExprCompiled::bin_op(
                             Builtin2::Compare(CompareOp::Less),
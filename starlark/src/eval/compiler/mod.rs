@@ -17,6 +17,7 @@
 
 pub(crate) mod args;
 pub(crate) mod call;
+pub(crate) mod call_graph;
 pub(crate) mod compr;
 pub(crate) mod constants;
 pub(crate) mod def;
@@ -33,7 +34,10 @@ pub(crate) mod stmt;
 
 use std::fmt::Debug;
 
+use starlark_map::small_map::SmallMap;
+
 use crate::codemap::CodeMap;
+use crate::environment::FrozenModule;
 use crate::environment::Globals;
 use crate::errors::Diagnostic;
 use crate::eval::compiler::scope::ScopeData;
@@ -86,8 +90,13 @@ pub(crate) struct Compiler<'v, 'a, 'e> {
     pub(crate) globals: FrozenRef<'static, Globals>,
     pub(crate) codemap: FrozenRef<'static, CodeMap>,
     pub(crate) has_before_stmt: bool,
+    /// Emit `ProfileBc` before each instruction, for bytecode profiling or stepping.
     pub(crate) bc_profile: bool,
     pub(crate) check_types: bool,
+    /// Is a key repeated in several `**kwargs` of one call an error.
+    pub(crate) strict_kwargs: bool,
+    /// Modules loaded by top-level `load` statements, by module path.
+    pub(crate) loaded_modules: SmallMap<String, FrozenModule>,
 }
 
 impl Compiler<'_, '_, '_> {
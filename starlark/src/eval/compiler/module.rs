@@ -17,8 +17,13 @@
 
 //! Compile and evaluate module top-level statements.
 
+use dupe::Dupe;
+
+use crate::codemap::FileSpanRef;
+use crate::codemap::Span;
 use crate::codemap::Spanned;
 use crate::environment::EnvironmentError;
+use crate::environment::FrozenModule;
 use crate::eval::bc::frame::alloca_frame;
 use crate::eval::compiler::add_span_to_expr_error;
 use crate::eval::compiler::expr_throw;
@@ -36,20 +41,26 @@ use crate::values::FrozenStringValue;
 use crate::values::Value;
 
 impl<'v> Compiler<'v, '_, '_> {
+    /// Load a module with the evaluator's loader.
+    fn load_module(&mut self, name: &str, span: Span) -> Result<FrozenModule, EvalException> {
+        let span = FrameSpan::new(FrozenFileSpan::new(self.codemap, span));
+        match self.eval.load_module(name) {
+            None => Err(add_span_to_expr_error(
+                EnvironmentError::NoImportsAvailable(name.to_owned()).into(),
+                span,
+                self.eval,
+            )),
+            Some(loadenv) => expr_throw(loadenv, span, self.eval),
+        }
+    }
+
     fn eval_load(&mut self, load: CstLoad) -> Result<(), EvalException> {
         let name = load.node.module.node;
 
-        let span = FrameSpan::new(FrozenFileSpan::new(self.codemap, load.span));
-
-        let loadenv = match self.eval.loader.as_ref() {
-            None => {
-                return Err(add_span_to_expr_error(
-                    EnvironmentError::NoImportsAvailable(name).into(),
-                    span,
-                    self.eval,
-                ));
-            }
-            Some(loader) => expr_throw(loader.load(&name), span, self.eval)?,
+        // Modules are loaded by `check_loads` before the first statement is executed.
+        let loadenv = match self.loaded_modules.get(&name) {
+            Some(loadenv) => loadenv.dupe(),
+            None => self.load_module(&name, load.span)?,
         };
 
         for (our_name, their_name) in load.node.args {
@@ -72,6 +83,44 @@ impl<'v> Compiler<'v, '_, '_> {
         Ok(())
     }
 
+    /// Load the modules of top-level `load` statements and check that every
+    /// symbol they name is exported, before any statement is executed.
+    /// Loaded modules are kept for [`eval_load`](Compiler::eval_load),
+    /// so each module is requested from the loader once.
+    fn check_loads(&mut self, stmt: &CstStmt) -> Result<(), EvalException> {
+        match &stmt.node {
+            StmtP::Statements(stmts) => {
+                for stmt in stmts {
+                    self.check_loads(stmt)?;
+                }
+                Ok(())
+            }
+            StmtP::Load(load) => {
+                let name = &load.module.node;
+                let loadenv = match self.loaded_modules.get(name) {
+                    Some(loadenv) => loadenv.dupe(),
+                    None => self.load_module(name, stmt.span)?,
+                };
+                for (our_name, their_name) in &load.args {
+                    expr_throw(
+                        self.eval
+                            .module_env
+                            .load_symbol(&loadenv, &their_name.node)
+                            .map(|_| ()),
+                        FrameSpan::new(FrozenFileSpan::new(
+                            self.codemap,
+                            our_name.span.merge(their_name.span),
+                        )),
+                        self.eval,
+                    )?;
+                }
+                self.loaded_modules.insert(name.clone(), loadenv);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn eval_top_level_stmt(
         &mut self,
         stmt: CstStmt,
@@ -93,6 +142,7 @@ impl<'v> Compiler<'v, '_, '_> {
                 Ok(Value::new_none())
             }
             _ => {
+                let span = stmt.span;
                 let stmt = self.module_top_level_stmt(stmt);
                 let bc = stmt.as_bc(
                     &self.compile_context(false),
@@ -104,9 +154,28 @@ impl<'v> Compiler<'v, '_, '_> {
                 // That is OK for now: the only locals used in module evaluation
                 // are comprehension bindings.
                 let local_count = local_names.len().try_into().unwrap();
-                alloca_frame(self.eval, local_count, bc.max_stack_size, |eval| {
+                let retained_before = if self.eval.retained_profile.enabled() {
+                    Some(
+                        self.eval
+                            .retained_profile_heap_bytes(&mut Value::new_none()),
+                    )
+                } else {
+                    None
+                };
+                let mut value = alloca_frame(self.eval, local_count, bc.max_stack_size, |eval| {
                     bc.run(eval)
-                })
+                })?;
+                if let Some(before) = retained_before {
+                    let after = self.eval.retained_profile_heap_bytes(&mut value);
+                    self.eval.retained_profile.add(
+                        FileSpanRef {
+                            file: &self.codemap,
+                            span,
+                        },
+                        after as i64 - before as i64,
+                    );
+                }
+                Ok(value)
             }
         }
     }
@@ -116,6 +185,7 @@ impl<'v> Compiler<'v, '_, '_> {
         stmt: CstStmt,
         local_names: FrozenRef<'static, [FrozenStringValue]>,
     ) -> Result<Value<'v>, EvalException> {
+        self.check_loads(&stmt)?;
         self.enter_scope(ScopeId::module());
         let value = self.eval_top_level_stmt(stmt, local_names)?;
         self.exit_scope();
@@ -21,11 +21,16 @@ use crate::eval::Evaluator;
 use crate::values::FrozenHeap;
 use crate::values::Heap;
 
+/// Values computed at compile time may not take more than this on the heap,
+/// so that huge values such as `"x" * 1000000000` are left to runtime.
+pub(crate) const FOLD_MAX_HEAP_BYTES: usize = 1 << 20;
+
 pub(crate) trait OptCtxEval<'v, 'a> {
     fn heap(&self) -> &'v Heap;
     fn frozen_heap(&self) -> &FrozenHeap;
     fn eval(&mut self) -> Option<&mut Evaluator<'v, 'a>>;
     fn frozen_module(&self) -> Option<&FrozenModuleRef>;
+    fn optimizations(&self) -> bool;
 }
 
 impl<'v, 'a> OptCtxEval<'v, 'a> for OptimizeOnFreezeContext<'v, 'a> {
@@ -44,6 +49,10 @@ impl<'v, 'a> OptCtxEval<'v, 'a> for OptimizeOnFreezeContext<'v, 'a> {
     fn frozen_module(&self) -> Option<&FrozenModuleRef> {
         Some(self.module)
     }
+
+    fn optimizations(&self) -> bool {
+        self.optimizations
+    }
 }
 
 impl<'v, 'a> OptCtxEval<'v, 'a> for Evaluator<'v, 'a> {
@@ -62,6 +71,10 @@ impl<'v, 'a> OptCtxEval<'v, 'a> for Evaluator<'v, 'a> {
     fn frozen_module(&self) -> Option<&FrozenModuleRef> {
         None
     }
+
+    fn optimizations(&self) -> bool {
+        self.optimizations
+    }
 }
 
 /// Optimization context.
@@ -98,4 +111,9 @@ impl<'v, 'a, 'e> OptCtx<'v, 'a, 'e> {
     pub(crate) fn frozen_module(&self) -> Option<&FrozenModuleRef> {
         self.eval.frozen_module()
     }
+
+    /// Constant folding and speculative execution are enabled.
+    pub(crate) fn optimizations(&self) -> bool {
+        self.eval.optimizations()
+    }
 }
@@ -48,6 +48,7 @@ use crate::syntax::ast::ClauseP;
 use crate::syntax::ast::DefP;
 use crate::syntax::ast::ExprP;
 use crate::syntax::ast::ForClauseP;
+use crate::syntax::ast::LambdaBodyP;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::ParameterP;
 use crate::syntax::ast::Stmt;
@@ -72,6 +73,10 @@ pub(crate) struct Scope<'a> {
     unscopes: Vec<Unscope>,
     codemap: FrozenRef<'static, CodeMap>,
     globals: FrozenRef<'static, Globals>,
+    /// Supplies globals not found in `globals`.
+    name_resolution_hook: Option<&'a dyn Fn(&str, &FrozenHeap) -> Option<FrozenValue>>,
+    /// Names resolved by the hook, so it is called once per name.
+    hook_resolved: SmallMap<FrozenStringValue, Option<FrozenValue>>,
     pub(crate) errors: Vec<anyhow::Error>,
 }
 
@@ -220,6 +225,7 @@ impl<'a> Scope<'a> {
         mut scope_data: ScopeData,
         code: &mut CstStmt,
         globals: FrozenRef<'static, Globals>,
+        name_resolution_hook: Option<&'a dyn Fn(&str, &FrozenHeap) -> Option<FrozenValue>>,
         codemap: FrozenRef<'static, CodeMap>,
         dialect: &Dialect,
     ) -> Self {
@@ -266,6 +272,8 @@ impl<'a> Scope<'a> {
             unscopes: Vec::new(),
             codemap,
             globals,
+            name_resolution_hook,
+            hook_resolved: SmallMap::new(),
             errors: Vec::new(),
         };
         scope.resolve_idents(code);
@@ -378,11 +386,43 @@ impl<'a> Scope<'a> {
     ) {
         if let ExprP::Lambda(LambdaP {
             params,
-            body: _,
+            body,
             payload: scope_id,
         }) = &mut code.node
         {
-            Self::collect_defines_in_def(scope_data, *scope_id, params, None, frozen_heap, dialect);
+            match body {
+                LambdaBodyP::Expr(_) => Self::collect_defines_in_def(
+                    scope_data,
+                    *scope_id,
+                    params,
+                    None,
+                    frozen_heap,
+                    dialect,
+                ),
+                LambdaBodyP::Block(body) => {
+                    Self::collect_defines_in_def(
+                        scope_data,
+                        *scope_id,
+                        params,
+                        Some(body),
+                        frozen_heap,
+                        dialect,
+                    );
+                    // Like `def`, traverse the body again for nested defs.
+                    for param in params.iter_mut() {
+                        param.visit_expr_mut(|e| {
+                            Self::collect_defines_recursively_in_expr(
+                                scope_data,
+                                e,
+                                frozen_heap,
+                                dialect,
+                            )
+                        });
+                    }
+                    Self::collect_defines_recursively(scope_data, body, frozen_heap, dialect);
+                    return;
+                }
+            }
         }
 
         code.visit_expr_mut(|e| {
@@ -448,7 +488,14 @@ impl<'a> Scope<'a> {
                 params,
                 body,
                 payload: scope_id,
-            }) => self.resolve_idents_in_def(*scope_id, params, None, None, Some(body)),
+            }) => match body {
+                LambdaBodyP::Expr(body) => {
+                    self.resolve_idents_in_def(*scope_id, params, None, None, Some(body))
+                }
+                LambdaBodyP::Block(body) => {
+                    self.resolve_idents_in_def(*scope_id, params, None, Some(body), None)
+                }
+            },
             ExprP::ListComprehension(expr, first_for, clauses) => {
                 self.resolve_idents_in_compr(&mut [expr], first_for, clauses)
             }
@@ -490,21 +537,35 @@ impl<'a> Scope<'a> {
 
     fn resolve_ident(&mut self, ident: &AstString, resolved_ident: &mut Option<ResolvedIdent>) {
         assert!(resolved_ident.is_none());
-        *resolved_ident = Some(
-            match self.get_name(self.frozen_heap.alloc_str_intern(ident)) {
-                None => {
-                    // Must be a global, since we know all variables
-                    match self.globals.get_frozen(ident) {
-                        None => {
-                            self.errors.push(self.variable_not_found_err(ident));
-                            return;
-                        }
-                        Some(v) => ResolvedIdent::Global(v),
+        let name = self.frozen_heap.alloc_str_intern(ident);
+        *resolved_ident = Some(match self.get_name(name) {
+            None => {
+                // Must be a global, since we know all variables.
+                // Interned strings cache their hash, so this lookup does not rehash the name.
+                match self
+                    .globals
+                    .get_frozen_hashed(name.get_hashed_str())
+                    .or_else(|| self.resolve_with_hook(name))
+                {
+                    None => {
+                        self.errors.push(self.variable_not_found_err(ident));
+                        return;
                     }
+                    Some(v) => ResolvedIdent::Global(v),
                 }
-                Some(slot) => ResolvedIdent::Slot(slot),
-            },
-        );
+            }
+            Some(slot) => ResolvedIdent::Slot(slot),
+        });
+    }
+
+    fn resolve_with_hook(&mut self, name: FrozenStringValue) -> Option<FrozenValue> {
+        let hook = self.name_resolution_hook?;
+        if let Some(v) = self.hook_resolved.get(&name) {
+            return *v;
+        }
+        let v = hook(name.as_str(), self.frozen_heap);
+        self.hook_resolved.insert(name, v);
+        v
     }
 
     fn resolve_idents_in_compr(
@@ -985,6 +1046,7 @@ mod tests {
             scope_data,
             &mut cst,
             FrozenRef::new(Globals::empty()),
+            None,
             codemap,
             &Dialect::Extended,
         );
@@ -108,6 +108,8 @@ pub(crate) struct StmtCompileContext {
     pub(crate) bc_profile: bool,
     /// `RecordCallEnter`/`RecordCallExit` instructions for heap or flame profile.
     pub(crate) record_call_enter_exit: bool,
+    /// Perform constant folding and speculative execution on freeze.
+    pub(crate) optimizations: bool,
 }
 
 pub(crate) struct OptimizeOnFreezeContext<'v, 'a> {
@@ -117,6 +119,8 @@ pub(crate) struct OptimizeOnFreezeContext<'v, 'a> {
     /// (when invoking operations which require heap).
     pub(crate) heap: &'v Heap,
     pub(crate) frozen_heap: &'a FrozenHeap,
+    /// Constant folding and speculative execution are enabled.
+    pub(crate) optimizations: bool,
 }
 
 impl AssignModifyLhs {
@@ -605,7 +609,7 @@ pub(crate) fn bit_or_assign<'v>(
         }
         Ok(lhs)
     } else {
-        lhs_aref.bit_or(rhs, heap)
+        lhs.bit_or(rhs, heap)
     }
 }
 
@@ -658,6 +662,7 @@ impl Compiler<'_, '_, '_> {
             has_before_stmt: self.has_before_stmt,
             bc_profile: self.bc_profile,
             record_call_enter_exit: self.eval.heap_or_flame_profile,
+            optimizations: self.eval.optimizations,
         }
     }
 
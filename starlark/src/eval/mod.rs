@@ -25,18 +25,31 @@ pub(crate) mod runtime;
 use std::mem;
 use std::time::Instant;
 
+pub use compiler::args::ArgsCompiledValue;
+pub use compiler::args::ArgsCompiledValueBuilder;
+pub use compiler::call_graph::CallGraph;
+pub use compiler::call_graph::DynamicCall;
 use dupe::Dupe;
 use gazebo::prelude::*;
 pub use runtime::arguments::Arguments;
+pub use runtime::call_observer::CallEvent;
+pub use runtime::call_observer::CallEventKind;
 pub use runtime::call_stack::CallStack;
+pub use runtime::call_stack::RecursionLimitExceeded;
+pub use runtime::diagnostics::EvalDiagnostic;
 pub use runtime::evaluator::Evaluator;
 pub use runtime::file_loader::FileLoader;
+pub use runtime::file_loader::LoadCache;
 pub use runtime::file_loader::ReturnFileLoader;
 pub use runtime::params::ParametersParser;
 pub use runtime::params::ParametersSpec;
 pub use runtime::params::ParametersSpecBuilder;
+pub use runtime::profile::bc::CallSiteReport;
 pub use runtime::profile::data::ProfileData;
 pub use runtime::profile::ProfileMode;
+pub use runtime::step::StepHandle;
+pub use runtime::step::StepPause;
+use starlark_map::small_map::SmallMap;
 
 use crate::collections::symbol_map::Symbol;
 use crate::docs::DocString;
@@ -90,6 +103,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             scope_data,
             &mut statement,
             globals,
+            self.name_resolution_hook,
             codemap,
             &dialect,
         );
@@ -102,6 +116,9 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         }
 
         let (module_slots, scope_data) = scope.exit_module();
+        if self.compute_call_graph {
+            self.call_graph = CallGraph::new(&statement, &codemap);
+        }
         let scope_names = scope_data.get_scope(ScopeId::module());
         let local_names = self
             .frozen_heap()
@@ -127,6 +144,8 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             self.heap_profile
                 .record_call_enter(Value::new_none(), self.heap());
             self.flame_profile.record_call_enter(Value::new_none());
+            self.allocations_profile
+                .record_call_enter(Value::new_none(), self.heap());
         }
 
         // Evaluation
@@ -136,9 +155,11 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             globals,
             codemap,
             has_before_stmt: self.before_stmt.enabled(),
-            bc_profile: self.bc_profile.enabled(),
+            bc_profile: self.bc_profile.enabled() || self.step_instructions.enabled(),
             eval: self,
             check_types: dialect.enable_types == DialectTypes::Enable,
+            strict_kwargs: dialect.enable_strict_kwargs,
+            loaded_modules: SmallMap::new(),
         };
 
         let res = compiler.eval_module(statement, local_names);
@@ -148,6 +169,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         if unlikely(self.heap_or_flame_profile) {
             self.heap_profile.record_call_exit(self.heap());
             self.flame_profile.record_call_exit();
+            self.allocations_profile.record_call_exit(self.heap());
         }
         self.module_def_info = old_def_info;
 
@@ -158,6 +180,12 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     }
 
     /// Evaluate a function stored in a [`Value`], passing in `positional` and `named` arguments.
+    ///
+    /// This function can be called re-entrantly, e.g. from a native function
+    /// which needs to call back into Starlark: the callee is pushed onto the current
+    /// call stack (so recursion limit and error stack traces take it into account),
+    /// and it is recorded by the heap and flame profilers as a call nested
+    /// in the native function.
     pub fn eval_function(
         &mut self,
         function: Value<'v>,
@@ -173,6 +201,39 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             args: None,
             kwargs: None,
         });
-        function.invoke(&params, self)
+        self.eval_function_arguments(function, &params)
+    }
+
+    /// Evaluate a function stored in a [`Value`], passing in arguments
+    /// built with [`ArgsCompiledValueBuilder`].
+    ///
+    /// Like [`eval_function`](Evaluator::eval_function), this function can be called re-entrantly.
+    pub fn eval_function_with_args(
+        &mut self,
+        function: Value<'v>,
+        args: &ArgsCompiledValue,
+    ) -> anyhow::Result<Value<'v>> {
+        args.all_values(|params| self.eval_function_arguments(function, params))
+            .expect("arguments built with the public builder are values")
+    }
+
+    fn eval_function_arguments(
+        &mut self,
+        function: Value<'v>,
+        params: &Arguments<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        if unlikely(self.heap_or_flame_profile) {
+            self.heap_profile.record_call_enter(function, self.heap());
+            self.flame_profile.record_call_enter(function);
+            self.allocations_profile
+                .record_call_enter(function, self.heap());
+        }
+        let res = function.invoke(params, self);
+        if unlikely(self.heap_or_flame_profile) {
+            self.heap_profile.record_call_exit(self.heap());
+            self.flame_profile.record_call_exit();
+            self.allocations_profile.record_call_exit(self.heap());
+        }
+        res
     }
 }
@@ -326,6 +326,11 @@ impl<'v, 'a> Arguments<'v, 'a> {
         Ok(Dict::new(coerce(self.names_map()?)))
     }
 
+    /// Positional arguments, excluding those passed with `*args`.
+    pub fn positional_args(&self) -> &'a [Value<'v>] {
+        self.0.pos
+    }
+
     /// Unpack all positional parameters into an iterator.
     pub fn positions<'b>(
         &'b self,
@@ -353,6 +358,35 @@ impl<'v, 'a> Arguments<'v, 'a> {
         }
     }
 
+    /// Merge two `**kwargs` dictionaries of a call like `f(**a, **b)` into one,
+    /// with entries in `b` overriding those in `a`.
+    /// If `strict` is set, a key present in both dictionaries is an error instead.
+    pub(crate) fn merge_kwargs(
+        a: Value<'v>,
+        b: Value<'v>,
+        strict: bool,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let (a, b) = match (DictRef::from_value(a), DictRef::from_value(b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Err(FunctionError::KwArgsIsNotDict.into()),
+        };
+        let mut result = SmallMap::with_capacity(a.len() + b.len());
+        for (k, v) in a.iter_hashed() {
+            result.insert_hashed(k, v);
+        }
+        for (k, v) in b.iter_hashed() {
+            let key = *k.key();
+            if result.insert_hashed(k, v).is_some() && strict {
+                return Err(FunctionError::RepeatedArg {
+                    name: Arguments::unpack_kwargs_key(key)?.to_owned(),
+                }
+                .into());
+            }
+        }
+        Ok(heap.alloc(Dict::new(result)))
+    }
+
     /// Confirm that a key in the `kwargs` field is indeed a string, or [`Err`].
     #[inline(always)]
     pub(crate) fn unpack_kwargs_key_as_value(k: Value<'v>) -> anyhow::Result<StringValue<'v>> {
@@ -0,0 +1,60 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Observing function calls.
+
+use dupe::Dupe;
+
+use crate::codemap::FileSpan;
+
+/// Whether a [`CallEvent`] is emitted when a function is entered or exited.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum CallEventKind {
+    /// Function is about to be called.
+    Enter,
+    /// Function returned, successfully or with an error.
+    Exit,
+}
+
+/// Function call event passed to the observer set by
+/// [`set_call_observer`](crate::eval::Evaluator::set_call_observer).
+#[derive(Debug, Clone)]
+pub struct CallEvent {
+    pub(crate) kind: CallEventKind,
+    pub(crate) name: String,
+    pub(crate) span: Option<FileSpan>,
+}
+
+impl CallEvent {
+    /// Entry or exit.
+    pub fn kind(&self) -> CallEventKind {
+        self.kind
+    }
+
+    /// Name of the called function, as it appears in the call stack.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Location of the call, if known.
+    pub fn span(&self) -> Option<&FileSpan> {
+        self.span.as_ref()
+    }
+}
+
+/// Callback invoked on every function entry and exit.
+pub(crate) type CallObserver = Box<dyn FnMut(CallEvent)>;
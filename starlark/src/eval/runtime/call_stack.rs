@@ -29,7 +29,6 @@ use std::fmt::Debug;
 use std::fmt::Display;
 
 use dupe::Dupe;
-use gazebo::prelude::*;
 
 use crate::codemap::FileSpan;
 use crate::errors::Frame;
@@ -79,25 +78,29 @@ impl Debug for CheapFrame<'_> {
 enum CallStackError {
     #[error("Requested {0}-th top frame, but stack size is {1} (internal error)")]
     StackIsTooShallowForNthTopFrame(usize, usize),
-    #[error("Starlark call stack overflow")]
-    Overflow,
+}
+
+/// Error returned when a call would exceed the call stack depth limit
+/// set with [`Evaluator::set_call_stack_depth_limit`](crate::eval::Evaluator::set_call_stack_depth_limit).
+#[derive(Debug, Clone, Dupe, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Starlark call stack overflow: recursion limit of {limit} frames exceeded")]
+pub struct RecursionLimitExceeded {
+    /// The configured limit, in frames.
+    pub limit: usize,
 }
 
 /// Starlark call stack.
 #[derive(Debug)]
 pub(crate) struct CheapCallStack<'v> {
-    count: usize,
-    stack: [CheapFrame<'v>; MAX_CALLSTACK_RECURSION],
+    stack: Vec<CheapFrame<'v>>,
+    max_depth: usize,
 }
 
 impl<'v> Default for CheapCallStack<'v> {
     fn default() -> Self {
         Self {
-            count: 0,
-            stack: [CheapFrame {
-                function: Value::new_none(),
-                span: None,
-            }; MAX_CALLSTACK_RECURSION],
+            stack: Vec::with_capacity(DEFAULT_MAX_CALLSTACK_RECURSION),
+            max_depth: DEFAULT_MAX_CALLSTACK_RECURSION,
         }
     }
 }
@@ -111,27 +114,26 @@ impl<'v> Default for CheapCallStack<'v> {
 // * macOS default stack size is 512KB
 // * Linux default stack size is 8MB
 // * [tokio default stack size is 2MB][1]
+// so the default is conservative, and can be raised with
+// `Evaluator::set_call_stack_depth_limit` when running on a larger stack.
 // [1] https://docs.rs/tokio/0.2.1/tokio/runtime/struct.Builder.html#method.thread_stack_size
 // TODO(nga): count loops in call stack size.
-// TODO(nga): make it configurable.
-const MAX_CALLSTACK_RECURSION: usize = 50;
+const DEFAULT_MAX_CALLSTACK_RECURSION: usize = 50;
 
 unsafe impl<'v> Trace<'v> for CheapCallStack<'v> {
     fn trace(&mut self, tracer: &Tracer<'v>) {
-        let (used, unused) = self.stack.split_at_mut(self.count);
-        for x in used {
+        for x in &mut self.stack {
             x.function.trace(tracer);
         }
-        // Not required, but since we are chosing not to walk those above
-        // the current stack depth, it's good practice to blank those values out
-        for x in unused {
-            x.function = Value::new_none();
-            x.span = None;
-        }
     }
 }
 
 impl<'v> CheapCallStack<'v> {
+    /// Set the maximum number of frames, including the module frame.
+    pub(crate) fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
     /// Push an element to the stack. It is important the each `push` is paired
     /// with a `pop`.
     pub(crate) fn push(
@@ -139,40 +141,39 @@ impl<'v> CheapCallStack<'v> {
         function: Value<'v>,
         span: Option<FrozenRef<'static, FrameSpan>>,
     ) -> anyhow::Result<()> {
-        if unlikely(self.count >= MAX_CALLSTACK_RECURSION) {
-            return Err(CallStackError::Overflow.into());
+        if unlikely(self.stack.len() >= self.max_depth) {
+            return Err(RecursionLimitExceeded {
+                limit: self.max_depth,
+            }
+            .into());
         }
-        self.stack[self.count] = CheapFrame { function, span };
-        self.count += 1;
+        self.stack.push(CheapFrame { function, span });
         Ok(())
     }
 
     /// Remove the top element from the stack. Called after `push`.
     pub(crate) fn pop(&mut self) {
-        debug_assert!(self.count >= 1);
-        // We could clear the elements, but don't need to bother
-        self.count -= 1;
+        let popped = self.stack.pop();
+        debug_assert!(popped.is_some());
     }
 
     /// The location at the top of the stack. May be `None` if
     /// either there the stack is empty, or the top of the stack lacks location
     /// information (e.g. called from Rust).
     pub(crate) fn top_location(&self) -> Option<FileSpan> {
-        if self.count == 0 {
-            None
-        } else {
-            self.stack[self.count - 1].location()
-        }
+        self.stack.last().and_then(|frame| frame.location())
     }
 
     /// `n`-th element from the top of the stack.
     pub(crate) fn top_nth_function(&self, n: usize) -> anyhow::Result<Value<'v>> {
         let index = self
-            .count
+            .stack
+            .len()
             .checked_sub(1)
             .and_then(|x| x.checked_sub(n))
             .ok_or(CallStackError::StackIsTooShallowForNthTopFrame(
-                n, self.count,
+                n,
+                self.stack.len(),
             ))?;
         Ok(self.stack[index].function)
     }
@@ -180,7 +181,7 @@ impl<'v> CheapCallStack<'v> {
     pub(crate) fn to_diagnostic_frames(&self, inlined_frames: InlinedFrames) -> CallStack {
         // The first entry is just the entire module, so skip it
         let mut frames = Vec::new();
-        for frame in &self.stack[1..self.count] {
+        for frame in self.stack.iter().skip(1) {
             frame.extend_frames(&mut frames);
         }
         inlined_frames.extend_frames(&mut frames);
@@ -189,7 +190,7 @@ impl<'v> CheapCallStack<'v> {
 
     /// List the entries on the stack as values
     pub(crate) fn to_function_values(&self) -> Vec<Value<'v>> {
-        self.stack[1..self.count].map(|x| x.function)
+        self.stack.iter().skip(1).map(|x| x.function).collect()
     }
 }
 
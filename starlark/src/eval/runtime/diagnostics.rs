@@ -0,0 +1,153 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Diagnostics reported by native functions during evaluation.
+
+use std::fmt;
+use std::fmt::Display;
+
+use crate::analysis::EvalSeverity;
+use crate::codemap::FileSpan;
+
+/// A non-fatal diagnostic emitted during evaluation,
+/// see [`Evaluator::push_diagnostic`](crate::eval::Evaluator::push_diagnostic).
+#[derive(Debug, Clone)]
+pub struct EvalDiagnostic {
+    /// How severe the problem is.
+    pub severity: EvalSeverity,
+    /// The details of the issue, generally displayed to the user.
+    pub message: String,
+    /// Location of the call which emitted the diagnostic, if known.
+    pub span: Option<FileSpan>,
+}
+
+impl Display for EvalDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let Some(span) = &self.span {
+            write!(f, "{}: ", span)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use starlark_derive::starlark_module;
+
+    use crate as starlark;
+    use crate::analysis::EvalSeverity;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::stdlib::PrintHandler;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::none::NoneType;
+
+    #[starlark_module]
+    fn diagnostics_module(builder: &mut GlobalsBuilder) {
+        fn note(message: &str, eval: &mut Evaluator) -> anyhow::Result<NoneType> {
+            eval.push_diagnostic(EvalSeverity::Advice, message);
+            Ok(NoneType)
+        }
+
+        fn warn(message: &str, eval: &mut Evaluator) -> anyhow::Result<NoneType> {
+            eval.try_push_diagnostic(EvalSeverity::Warning, message)?;
+            Ok(NoneType)
+        }
+    }
+
+    #[test]
+    fn test_push_diagnostic() -> anyhow::Result<()> {
+        let program = r#"
+def f():
+    note("inner")
+note("first")
+f()
+warn("second")
+"#;
+        let module = Module::new();
+        let globals = GlobalsBuilder::standard().with(diagnostics_module).build();
+        let mut eval = Evaluator::new(&module);
+        eval.eval_module(
+            AstModule::parse("a.star", program.to_owned(), &Dialect::Standard)?,
+            &globals,
+        )?;
+
+        let diagnostics = eval.take_diagnostics();
+        assert!(eval.diagnostics().is_empty());
+        let rendered: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            vec![
+                "Advice: a.star:4:1-14: first",
+                "Advice: a.star:3:5-18: inner",
+                "Warning: a.star:6:1-15: second",
+            ],
+            rendered
+        );
+        assert_eq!(
+            vec![
+                EvalSeverity::Advice,
+                EvalSeverity::Advice,
+                EvalSeverity::Warning
+            ],
+            diagnostics.iter().map(|d| d.severity).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct Capture(RefCell<Vec<String>>);
+
+    impl PrintHandler for Capture {
+        fn println(&self, text: &str) -> anyhow::Result<()> {
+            self.0.borrow_mut().push(text.to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_output_capture() -> anyhow::Result<()> {
+        let program = r#"
+print("hello")
+warn("careful")
+print("world")
+"#;
+        let output = Capture::default();
+        let diagnostics = Capture::default();
+        let module = Module::new();
+        let globals = GlobalsBuilder::extended().with(diagnostics_module).build();
+        let mut eval = Evaluator::new(&module);
+        eval.set_output_capture(&output, &diagnostics);
+        eval.eval_module(
+            AstModule::parse("a.star", program.to_owned(), &Dialect::Standard)?,
+            &globals,
+        )?;
+
+        assert_eq!(vec!["hello", "world"], *output.0.borrow());
+        assert_eq!(
+            vec!["Warning: a.star:3:1-16: careful"],
+            *diagnostics.0.borrow()
+        );
+        // Diagnostics are still recorded on the evaluator.
+        assert_eq!(1, eval.diagnostics().len());
+        Ok(())
+    }
+}
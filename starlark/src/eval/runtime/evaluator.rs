@@ -16,14 +16,18 @@
  */
 
 use std::collections::HashSet;
+use std::io::Write;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
 
 use dupe::Dupe;
 use gazebo::cast;
 use thiserror::Error;
 
+use crate::analysis::EvalSeverity;
 use crate::any::AnyLifetime;
 use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
@@ -32,43 +36,58 @@ use crate::collections::alloca::Alloca;
 use crate::collections::string_pool::StringPool;
 use crate::environment::slots::ModuleSlotId;
 use crate::environment::EnvironmentError;
+use crate::environment::FrozenModule;
 use crate::environment::FrozenModuleRef;
 use crate::environment::Module;
 use crate::errors::Diagnostic;
 use crate::eval::bc::frame::BcFramePtr;
+use crate::eval::compiler::call_graph::CallGraph;
 use crate::eval::compiler::def::CopySlotFromParent;
 use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::DefInfo;
 use crate::eval::compiler::def::FrozenDef;
 use crate::eval::runtime::before_stmt::BeforeStmt;
+use crate::eval::runtime::call_observer::CallObserver;
 use crate::eval::runtime::call_stack::CheapCallStack;
+use crate::eval::runtime::diagnostics::EvalDiagnostic;
+use crate::eval::runtime::file_loader::canonical_module_path;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrames;
+use crate::eval::runtime::profile::allocations::AllocationsProfile;
 use crate::eval::runtime::profile::bc::BcProfile;
 use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::profile::heap::HeapProfile;
 use crate::eval::runtime::profile::heap::HeapProfileFormat;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
 use crate::eval::runtime::profile::or_instrumentation::ProfileOrInstrumentationMode;
+use crate::eval::runtime::profile::retained::RetainedProfile;
 use crate::eval::runtime::profile::stmt::StmtProfile;
 use crate::eval::runtime::profile::time_flame::FlameProfile;
 use crate::eval::runtime::profile::typecheck::TypecheckProfile;
 use crate::eval::runtime::profile::ProfileMode;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
+use crate::eval::runtime::step::StepHandle;
+use crate::eval::runtime::step::StepInstructions;
+use crate::eval::CallEvent;
+use crate::eval::CallEventKind;
 use crate::eval::CallStack;
 use crate::eval::FileLoader;
+use crate::eval::LoadCache;
 use crate::stdlib::breakpoint::BreakpointConsole;
 use crate::stdlib::breakpoint::RealBreakpointConsole;
 use crate::stdlib::extra::PrintHandler;
 use crate::stdlib::extra::StderrPrintHandler;
 use crate::values::function::NativeFunction;
+use crate::values::layout::heap::alloc_log::AllocLog;
 use crate::values::layout::value_captured::value_captured_get;
 use crate::values::layout::value_captured::FrozenValueCaptured;
 use crate::values::layout::value_captured::ValueCaptured;
 use crate::values::FrozenHeap;
 use crate::values::FrozenRef;
+use crate::values::FrozenValue;
 use crate::values::Heap;
+use crate::values::HeapLimitExceeded;
 use crate::values::Trace;
 use crate::values::Tracer;
 use crate::values::Value;
@@ -100,6 +119,8 @@ pub(crate) enum EvaluatorError {
     CoverageNotImplemented,
     #[error("Coverage not enabled")]
     CoverageNotEnabled,
+    #[error("Native function `{0}` call took {1:?}, limit is {2:?}")]
+    NativeCallTimeout(String, Duration, Duration),
 }
 
 /// Number of bytes to allocate between GC's.
@@ -117,6 +138,10 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) current_frame: BcFramePtr<'v>,
     // How we deal with a `load` function.
     pub(crate) loader: Option<&'a dyn FileLoader>,
+    // Consulted before `loader`.
+    load_cache: Option<&'a dyn LoadCache>,
+    // Supplies globals which are not defined in `Globals`.
+    pub(crate) name_resolution_hook: Option<&'a dyn Fn(&str, &FrozenHeap) -> Option<FrozenValue>>,
     // `DefInfo` of currently executed module.
     // `DefInfo` of currently execution function can be obtained from call stack.
     pub(crate) module_def_info: FrozenRef<'static, DefInfo>,
@@ -124,6 +149,8 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) heap_profile: HeapProfile,
     // Should we enable flame profiling or not
     pub(crate) flame_profile: FlameProfile<'v>,
+    /// Allocations profile.
+    pub(crate) allocations_profile: AllocationsProfile<'v>,
     // Is either heap or flame profiling enabled, or instrumentation for these profiles enabled.
     pub(crate) heap_or_flame_profile: bool,
     // Is GC disabled for some reason
@@ -139,8 +166,12 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) before_stmt: BeforeStmt<'a>,
     // Used for line profiling
     stmt_profile: StmtProfile,
+    // Memory retained by each top-level statement.
+    pub(crate) retained_profile: RetainedProfile,
     // Bytecode profile.
     pub(crate) bc_profile: BcProfile,
+    // Pause evaluation every few instructions, used by stepping debuggers.
+    pub(crate) step_instructions: StepInstructions,
     // Total time spent in runtime typechecking.
     // Filled only if runtime typechecking profiling is enabled.
     pub(crate) typecheck_profile: TypecheckProfile,
@@ -155,8 +186,36 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) breakpoint_handler: Option<Box<dyn Fn() -> Box<dyn BreakpointConsole>>>,
     /// Use in implementation of `print` function.
     pub(crate) print_handler: &'a (dyn PrintHandler + 'a),
+    /// If set, each diagnostic is also rendered to this handler.
+    diagnostic_handler: Option<&'a (dyn PrintHandler + 'a)>,
+    /// Diagnostics emitted by native functions.
+    diagnostics: Vec<EvalDiagnostic>,
+    /// Compute `call_graph` when compiling a module, see [`Evaluator::enable_call_graph`].
+    pub(crate) compute_call_graph: bool,
+    /// Call graph of the last module compiled.
+    pub(crate) call_graph: CallGraph,
+    /// Perform constant folding and speculative execution when compiling.
+    pub(crate) optimizations: bool,
+    /// Order incomparable values in `sorted`, see [`Evaluator::set_total_order`].
+    pub(crate) total_order: bool,
+    /// Record where values are created, see [`Evaluator::track_value_provenance`].
+    pub(crate) track_value_provenance: bool,
+    /// Set by [`Evaluator::set_call_timeout_per_function`].
+    native_call_timeout: Option<Duration>,
+    /// Clock timing native calls in tests, [`Instant::now`] if not set.
+    #[cfg(test)]
+    pub(crate) native_call_clock: Option<Box<dyn Fn() -> Instant>>,
+    /// Set by [`Evaluator::set_call_observer`].
+    call_observer: Option<CallObserver>,
+    /// Set by [`Evaluator::trace_allocations_to`].
+    trace_allocations: bool,
+    /// Whether allocations are traced or their provenance is tracked,
+    /// so the heap must be told about every call.
+    heap_call_hooks: bool,
+    /// Set by [`Evaluator::set_max_heap_bytes`] and [`Evaluator::with_heap_limit`],
+    /// mirrors the limit stored in the heap, so checking it is cheap when unset.
+    max_heap_bytes: Option<usize>,
     // The Starlark-level call-stack of functions.
-    // Must go last because it's quite a big structure
     pub(crate) call_stack: CheapCallStack<'v>,
 }
 
@@ -166,6 +225,7 @@ unsafe impl<'v> Trace<'v> for Evaluator<'v, '_> {
         self.current_frame.trace(tracer);
         self.call_stack.trace(tracer);
         self.flame_profile.trace(tracer);
+        self.allocations_profile.trace(tracer);
     }
 }
 
@@ -181,6 +241,8 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             module_variables: None,
             current_frame: BcFramePtr::null(),
             loader: None,
+            load_cache: None,
+            name_resolution_hook: None,
             extra: None,
             next_gc_level: GC_THRESHOLD,
             disable_gc: false,
@@ -188,15 +250,32 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             profile_or_instrumentation_mode: ProfileOrInstrumentationMode::None,
             heap_profile: HeapProfile::new(),
             stmt_profile: StmtProfile::new(),
+            retained_profile: RetainedProfile::new(),
             bc_profile: BcProfile::new(),
+            step_instructions: StepInstructions::default(),
+            max_heap_bytes: None,
             typecheck_profile: TypecheckProfile::default(),
             flame_profile: FlameProfile::new(),
+            allocations_profile: AllocationsProfile::new(),
             heap_or_flame_profile: false,
             before_stmt: BeforeStmt::default(),
             module_def_info: DefInfo::empty(), // Will be replaced before it is used
             string_pool: StringPool::default(),
             breakpoint_handler: None,
             print_handler: &StderrPrintHandler,
+            diagnostic_handler: None,
+            diagnostics: Vec::new(),
+            compute_call_graph: false,
+            call_graph: CallGraph::default(),
+            optimizations: true,
+            total_order: false,
+            track_value_provenance: false,
+            native_call_timeout: None,
+            #[cfg(test)]
+            native_call_clock: None,
+            call_observer: None,
+            trace_allocations: false,
+            heap_call_hooks: false,
             verbose_gc: false,
         }
     }
@@ -220,6 +299,43 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.loader = Some(loader);
     }
 
+    /// Set the [`LoadCache`] consulted before the [`FileLoader`] when resolving
+    /// `load()` statements. Modules loaded successfully are added to the cache.
+    pub fn set_load_cache(&mut self, cache: &'a dyn LoadCache) {
+        self.load_cache = Some(cache);
+    }
+
+    /// Resolve the module for a `load()` statement, or [`None`] if no loader is set.
+    pub(crate) fn load_module(&self, path: &str) -> Option<anyhow::Result<FrozenModule>> {
+        let loader = self.loader?;
+        let cache = match self.load_cache {
+            None => return Some(loader.load(path)),
+            Some(cache) => cache,
+        };
+        let key = canonical_module_path(path);
+        if let Some(module) = cache.get(&key) {
+            return Some(Ok(module));
+        }
+        let res = loader.load(path);
+        if let Ok(module) = &res {
+            cache.insert(&key, module.dupe());
+        }
+        Some(res)
+    }
+
+    /// Set a hook to resolve global names which are not defined in the [`Globals`](crate::environment::Globals)
+    /// passed to [`eval_module`](Evaluator::eval_module), for globals which are too many
+    /// or too expensive to register up front. The hook is called when the module is
+    /// compiled, at most once per name, and returns [`None`] if the name is unknown,
+    /// which is reported as an error as usual. Values should be allocated on
+    /// the given heap, which is kept alive by the module.
+    pub fn set_name_resolution_hook(
+        &mut self,
+        hook: &'a dyn Fn(&str, &FrozenHeap) -> Option<FrozenValue>,
+    ) {
+        self.name_resolution_hook = Some(hook);
+    }
+
     /// Enable profiling, allowing [`Evaluator::write_profile`] to be used.
     /// Profilers add overhead, and while some profilers can be used together,
     /// it's better to run at most one profiler at a time.
@@ -255,10 +371,14 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                 self.stmt_profile.enable();
                 self.before_stmt(&|span, eval| eval.stmt_profile.before_stmt(span));
             }
-            ProfileMode::TimeFlame => {
+            ProfileMode::TimeFlame | ProfileMode::TimeCallTree => {
                 self.flame_profile.enable();
                 self.heap_or_flame_profile = true;
             }
+            ProfileMode::Allocations => {
+                self.allocations_profile.enable(self.heap());
+                self.heap_or_flame_profile = true;
+            }
             ProfileMode::Bytecode => {
                 self.bc_profile.enable_1();
             }
@@ -268,6 +388,9 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             ProfileMode::Typecheck => {
                 self.typecheck_profile.enabled = true;
             }
+            ProfileMode::Retained => {
+                self.retained_profile.enable();
+            }
         }
         Ok(())
     }
@@ -295,14 +418,23 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             | ProfileMode::HeapSummaryRetained
             | ProfileMode::HeapFlameAllocated
             | ProfileMode::HeapFlameRetained
-            | ProfileMode::TimeFlame => {
+            | ProfileMode::TimeFlame
+            | ProfileMode::TimeCallTree
+            | ProfileMode::Allocations => {
                 self.heap_or_flame_profile = true;
             }
-            ProfileMode::Typecheck => {}
+            ProfileMode::Typecheck | ProfileMode::Retained => {}
         }
         Ok(())
     }
 
+    /// Should [`ProfileMode::Retained`] garbage collect before and after each top-level
+    /// statement (the default). Without collections the profile is cheaper but coarser:
+    /// it reports how much the heap grew during each statement, garbage included.
+    pub fn set_retained_profile_gc(&mut self, gc: bool) {
+        self.retained_profile.set_gc(gc);
+    }
+
     /// Write a profile to a file.
     /// Only valid if corresponding profiler was enabled.
     pub fn write_profile<P: AsRef<Path>>(&mut self, filename: P) -> anyhow::Result<()> {
@@ -340,7 +472,10 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             ProfileMode::Bytecode => self.bc_profile.gen_bc_profile(),
             ProfileMode::BytecodePairs => self.bc_profile.gen_bc_pairs_profile(),
             ProfileMode::TimeFlame => self.flame_profile.gen(),
+            ProfileMode::TimeCallTree => self.flame_profile.gen_call_tree(),
             ProfileMode::Typecheck => self.typecheck_profile.gen(),
+            ProfileMode::Allocations => self.allocations_profile.gen(self.heap()),
+            ProfileMode::Retained => self.retained_profile.gen(),
         }
     }
 
@@ -360,6 +495,67 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         }
     }
 
+    /// Enable or disable compile time optimizations which evaluate code
+    /// ahead of time: constant folding of operators, indexing and attribute access,
+    /// and speculative execution of calls to pure builtin functions.
+    /// Disabling optimizations does not change the program behavior,
+    /// which makes it useful to isolate a suspected miscompilation.
+    ///
+    /// Optimizations are enabled by default.
+    /// The setting applies to code compiled after this call,
+    /// including the optimization of functions when their module is frozen.
+    pub fn enable_optimizations(&mut self, enable: bool) {
+        self.optimizations = enable;
+    }
+
+    /// Make `sorted` order values which are not comparable, such as values of different types,
+    /// instead of failing. Such values are ordered by type name, then by hash.
+    ///
+    /// This is a deliberate deviation from the Starlark specification,
+    /// for host tooling which needs a stable order of heterogeneous lists.
+    /// Values which are comparable are ordered as usual, equality is not affected,
+    /// and values which are neither comparable nor hashable still fail to sort.
+    ///
+    /// Disabled by default.
+    pub fn set_total_order(&mut self, enable: bool) {
+        self.total_order = enable;
+    }
+
+    /// Iterate dicts in sorted key order (by Starlark comparison) instead of
+    /// insertion order, e.g. to produce stable output from configs built by different code paths.
+    ///
+    /// Only iteration over a dict itself (`for k in d`, `list(d)`, ...) is affected:
+    /// indexing, equality and methods such as `keys()` and `items()` are not.
+    /// Iteration fails if the keys are not comparable.
+    ///
+    /// Disabled by default.
+    pub fn set_canonical_dict_iteration(&mut self, enable: bool) {
+        self.heap().set_canonical_dict_iteration(enable);
+    }
+
+    /// Record where values are created, to be read back with [`Value::provenance`],
+    /// for example to point at the line which produced a bad value in a frozen config.
+    ///
+    /// When enabled, list and dict literals remember their own span, and other
+    /// values allocated on the heap of this evaluator remember the innermost call
+    /// being evaluated, including after the module is frozen.
+    /// Functions defined in Starlark always report their definition.
+    ///
+    /// Spans are kept in a table beside the heap, so values pay nothing when disabled.
+    /// Disabled by default. Disabling it forgets the recorded spans.
+    pub fn track_value_provenance(&mut self, enable: bool) {
+        self.track_value_provenance = enable;
+        self.heap().track_provenance(enable);
+        self.update_heap_call_hooks();
+    }
+
+    /// Record that a list or dict literal was just constructed at `span`.
+    #[cold]
+    #[inline(never)]
+    pub(crate) fn record_literal_provenance(&self, value: Value<'v>, span: &FrameSpan) {
+        self.heap().set_provenance(value, span.span.to_file_span());
+    }
+
     /// Enable interactive `breakpoint()`. When enabled, `breakpoint()`
     /// reads commands from stdin and write to stdout.
     /// When disabled (default), `breakpoint()` function results in error.
@@ -379,6 +575,65 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.call_stack.top_location()
     }
 
+    /// Record a diagnostic which does not stop the evaluation,
+    /// located at the top of the call-stack.
+    /// Intended for native functions which detect a questionable but legal situation.
+    ///
+    /// The diagnostic is also written to the diagnostic handler, if one was set with
+    /// [`set_output_capture`](Evaluator::set_output_capture). Use
+    /// [`try_push_diagnostic`](Evaluator::try_push_diagnostic) to learn if that handler fails.
+    pub fn push_diagnostic(&mut self, severity: EvalSeverity, message: impl Into<String>) {
+        // The diagnostic is recorded even if the handler fails.
+        let _ = self.try_push_diagnostic(severity, message);
+    }
+
+    /// Like [`push_diagnostic`](Evaluator::push_diagnostic), but fails if the diagnostic
+    /// handler set with [`set_output_capture`](Evaluator::set_output_capture) fails.
+    /// The diagnostic is recorded either way.
+    pub fn try_push_diagnostic(
+        &mut self,
+        severity: EvalSeverity,
+        message: impl Into<String>,
+    ) -> anyhow::Result<()> {
+        let span = self.call_stack_top_location();
+        let diagnostic = EvalDiagnostic {
+            severity,
+            message: message.into(),
+            span,
+        };
+        let res = match self.diagnostic_handler {
+            Some(handler) => handler.println(&diagnostic.to_string()),
+            None => Ok(()),
+        };
+        self.diagnostics.push(diagnostic);
+        res
+    }
+
+    /// Diagnostics recorded with [`push_diagnostic`](Evaluator::push_diagnostic) so far.
+    pub fn diagnostics(&self) -> &[EvalDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Compute the [`call_graph`](Evaluator::call_graph) of modules evaluated after this call.
+    ///
+    /// Disabled by default.
+    pub fn enable_call_graph(&mut self, enable: bool) {
+        self.compute_call_graph = enable;
+    }
+
+    /// Call graph of the functions defined in the module most recently evaluated
+    /// with [`eval_module`](Evaluator::eval_module), computed once its names are resolved.
+    /// Empty if no module has been evaluated with
+    /// [`enable_call_graph`](Evaluator::enable_call_graph) set.
+    pub fn call_graph(&self) -> &CallGraph {
+        &self.call_graph
+    }
+
+    /// Take the recorded diagnostics, leaving none recorded.
+    pub fn take_diagnostics(&mut self) -> Vec<EvalDiagnostic> {
+        mem::take(&mut self.diagnostics)
+    }
+
     pub(crate) fn before_stmt(
         &mut self,
         f: &'a dyn for<'v1> Fn(FileSpanRef, &mut Evaluator<'v1, 'a>),
@@ -386,6 +641,212 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.before_stmt.before_stmt.push(f)
     }
 
+    /// Pause evaluation every `n` bytecode instructions, before the instruction
+    /// to be executed next. Fails if `n` is zero.
+    ///
+    /// Paused evaluation waits until resumed or aborted through the returned handle.
+    /// Only code compiled after this call is instrumented.
+    pub fn step_instructions(&mut self, n: u64) -> anyhow::Result<StepHandle> {
+        self.step_instructions.enable(n)
+    }
+
+    /// Abort evaluation once the heap holds more than `bytes` bytes.
+    ///
+    /// The limit is enforced by the heap: it is checked when the heap requests new memory,
+    /// including while copying values during garbage collection, operations such as
+    /// `[0] * n`, `"x" * n` or `list(range(n))` fail before building an oversized result,
+    /// and the limit is checked again whenever a function call returns.
+    /// Evaluation then fails with an error wrapping
+    /// [`HeapLimitExceeded`](crate::values::HeapLimitExceeded).
+    pub fn set_max_heap_bytes(&mut self, bytes: usize) {
+        self.set_heap_limit(Some(bytes));
+    }
+
+    /// Run `f` with the heap allowed to grow by at most `bytes` bytes,
+    /// restoring the previous limit afterwards.
+    ///
+    /// This uses the same limit as [`set_max_heap_bytes`](Evaluator::set_max_heap_bytes),
+    /// and is intended for calling user callbacks of unknown cost from native code.
+    /// Nested limits can only tighten the enclosing one.
+    pub fn with_heap_limit<R>(
+        &mut self,
+        bytes: usize,
+        f: impl FnOnce(&mut Self) -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
+        let limit = self.heap().filled_bytes().saturating_add(bytes);
+        let old_limit = self.max_heap_bytes;
+        self.set_heap_limit(Some(old_limit.map_or(limit, |old| old.min(limit))));
+        let res = f(self).and_then(|r| {
+            self.check_heap_limit()?;
+            Ok(r)
+        });
+        self.set_heap_limit(old_limit);
+        let res = res?;
+        // The callback may have exceeded the enclosing limit too.
+        self.check_heap_limit()?;
+        Ok(res)
+    }
+
+    fn set_heap_limit(&mut self, bytes: Option<usize>) {
+        self.max_heap_bytes = bytes;
+        self.heap().set_max_bytes(bytes);
+    }
+
+    /// Fail string operations which would build a string longer than `length` bytes.
+    ///
+    /// Concatenation, repetition, `join` and formatting check the length of the result
+    /// before building it, and fail with an error wrapping
+    /// [`StringLengthExceeded`](crate::values::StringLengthExceeded).
+    /// By default string length is unlimited.
+    pub fn set_max_string_length(&mut self, length: usize) {
+        self.heap().set_max_string_length(Some(length));
+    }
+
+    /// Limit the depth of the Starlark call stack, including the module itself.
+    ///
+    /// A call which would exceed the limit fails with a recoverable
+    /// [`RecursionLimitExceeded`](crate::eval::RecursionLimitExceeded) error
+    /// pointing at the call, instead of overflowing the native stack.
+    /// The default of 50 frames is safe on small native stacks,
+    /// larger limits require a correspondingly larger native stack.
+    pub fn set_call_stack_depth_limit(&mut self, depth: usize) {
+        self.call_stack.set_max_depth(depth);
+    }
+
+    /// Fail a call to a native function or method which takes longer than `timeout`,
+    /// to catch pathological native callbacks.
+    ///
+    /// A native function cannot be interrupted, so the call runs to completion
+    /// and then fails if it took too long. Time spent in Starlark functions
+    /// called back by the native function, for example by `sorted` with a `key`,
+    /// counts towards the limit.
+    /// By default native calls are not timed.
+    pub fn set_call_timeout_per_function(&mut self, timeout: Duration) {
+        self.native_call_timeout = Some(timeout);
+    }
+
+    /// Invoke `observer` on every function entry and exit,
+    /// for example to build custom tracing on top of the interpreter.
+    /// Exit is reported even if the function fails.
+    ///
+    /// Without an observer calls pay only for a single check.
+    pub fn set_call_observer(&mut self, observer: Box<dyn FnMut(CallEvent)>) {
+        self.call_observer = Some(observer);
+    }
+
+    /// Remove the observer set by [`set_call_observer`](Evaluator::set_call_observer).
+    pub fn clear_call_observer(&mut self) {
+        self.call_observer = None;
+    }
+
+    /// Write every allocation on the heap of this evaluator to `sink`,
+    /// one line per allocation: `type\tsize\tspan`, where `size` is in bytes
+    /// including the value header, and `span` is the location of the innermost
+    /// function call being evaluated, or `-` outside of any call.
+    ///
+    /// Entries are buffered and flushed periodically, and when
+    /// [`stop_tracing_allocations`](Evaluator::stop_tracing_allocations) is called.
+    /// Without tracing allocations pay only for a single check.
+    pub fn trace_allocations_to(&mut self, sink: Box<dyn Write>) {
+        self.trace_allocations = true;
+        self.update_heap_call_hooks();
+        self.heap()
+            .set_alloc_log(Some(Box::new(AllocLog::new(sink))));
+    }
+
+    /// Stop the tracing started by [`trace_allocations_to`](Evaluator::trace_allocations_to),
+    /// flushing the remaining entries.
+    /// Fails with the first error writing to the sink.
+    pub fn stop_tracing_allocations(&mut self) -> anyhow::Result<()> {
+        self.trace_allocations = false;
+        self.update_heap_call_hooks();
+        match self.heap().set_alloc_log(None) {
+            Some(log) => Ok(log.finish()?),
+            None => Ok(()),
+        }
+    }
+
+    fn update_heap_call_hooks(&mut self) {
+        self.heap_call_hooks = self.trace_allocations || self.track_value_provenance;
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn heap_call_enter(&self, span: Option<FrozenRef<'static, FrameSpan>>) {
+        self.heap()
+            .call_enter(span.map(|span| span.span.to_file_span()));
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn observe_call(
+        &mut self,
+        kind: CallEventKind,
+        function: Value<'v>,
+        span: Option<FrozenRef<'static, FrameSpan>>,
+    ) {
+        if let Some(observer) = &mut self.call_observer {
+            observer(CallEvent {
+                kind,
+                name: function.name_for_call_stack(),
+                span: span.map(|span| span.span.to_file_span()),
+            });
+        }
+    }
+
+    /// Call a native function or method named `name`, checking the
+    /// [`set_call_timeout_per_function`](Evaluator::set_call_timeout_per_function) limit.
+    #[inline(always)]
+    pub(crate) fn with_native_call_timeout<R>(
+        &mut self,
+        name: &str,
+        call: impl FnOnce(&mut Self) -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
+        match self.native_call_timeout {
+            None => call(self),
+            Some(limit) => {
+                let start = self.native_call_now();
+                let res = call(self)?;
+                let elapsed = self.native_call_now() - start;
+                if elapsed > limit {
+                    return Err(
+                        EvaluatorError::NativeCallTimeout(name.to_owned(), elapsed, limit).into(),
+                    );
+                }
+                Ok(res)
+            }
+        }
+    }
+
+    fn native_call_now(&self) -> Instant {
+        #[cfg(test)]
+        if let Some(clock) = &self.native_call_clock {
+            return clock();
+        }
+        Instant::now()
+    }
+
+    /// Check the limit set by [`set_max_heap_bytes`](Evaluator::set_max_heap_bytes)
+    /// or [`with_heap_limit`](Evaluator::with_heap_limit).
+    #[inline(always)]
+    pub(crate) fn check_heap_limit(&self) -> anyhow::Result<()> {
+        #[cold]
+        #[inline(never)]
+        fn check(heap: &Heap, limit: usize) -> anyhow::Result<()> {
+            heap.check_max_bytes()?;
+            let allocated = heap.filled_bytes();
+            if allocated > limit {
+                return Err(HeapLimitExceeded { allocated, limit }.into());
+            }
+            Ok(())
+        }
+
+        match self.max_heap_bytes {
+            None => Ok(()),
+            Some(limit) => check(self.heap(), limit),
+        }
+    }
+
     /// This function is used by DAP, and it is not public API.
     // TODO(nga): pull DAP into the crate, and hide this function.
     #[doc(hidden)]
@@ -401,6 +862,21 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.print_handler = handler;
     }
 
+    /// Route normal output and diagnostic output to separate handlers.
+    ///
+    /// `output` receives the text of `print` and `pprint` (as with
+    /// [`set_print_handler`](Evaluator::set_print_handler)), while `diagnostics` receives
+    /// each diagnostic recorded by [`push_diagnostic`](Evaluator::push_diagnostic),
+    /// rendered as a single line.
+    pub fn set_output_capture(
+        &mut self,
+        output: &'a (dyn PrintHandler + 'a),
+        diagnostics: &'a (dyn PrintHandler + 'a),
+    ) {
+        self.print_handler = output;
+        self.diagnostic_handler = Some(diagnostics);
+    }
+
     /// Called to add an entry to the call stack, by the function being invoked.
     /// Called for all types of function, including those written in Rust.
     #[inline(always)]
@@ -420,9 +896,26 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         }
 
         self.call_stack.push(function, span)?;
+        if self.call_observer.is_some() {
+            self.observe_call(CallEventKind::Enter, function, span);
+        }
+        if self.heap_call_hooks {
+            self.heap_call_enter(span);
+        }
         // Must always call .pop regardless
-        let res = within(self).map_err(|e| add_diagnostics(e, self));
+        let res = within(self)
+            .and_then(|r| {
+                self.check_heap_limit()?;
+                Ok(r)
+            })
+            .map_err(|e| add_diagnostics(e, self));
         self.call_stack.pop();
+        if self.heap_call_hooks {
+            self.heap().call_exit();
+        }
+        if self.call_observer.is_some() {
+            self.observe_call(CallEventKind::Exit, function, span);
+        }
         res
     }
 
@@ -688,6 +1181,22 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         }
     }
 
+    /// Heap size for the retained memory profile, garbage collecting first if requested.
+    /// `value` is not reachable from the evaluator, but is kept alive.
+    pub(crate) fn retained_profile_heap_bytes(&mut self, value: &mut Value<'v>) -> usize {
+        if self.retained_profile.gc() && !self.disable_gc {
+            // Safe because all other values are reachable from the evaluator
+            // between top-level statements.
+            unsafe {
+                self.heap().garbage_collect(|tracer| {
+                    self.trace(tracer);
+                    tracer.trace(value);
+                })
+            }
+        }
+        self.heap().allocated_bytes()
+    }
+
     /// Note that the `Drop` for the `T` will not be called. That's safe if there is no `Drop`,
     /// or you call it yourself.
     #[inline(always)]
@@ -30,6 +30,47 @@ pub trait FileLoader {
     fn load(&self, path: &str) -> anyhow::Result<FrozenModule>;
 }
 
+/// A cache of modules returned by a [`FileLoader`], set with
+/// [`Evaluator::set_load_cache`](crate::eval::Evaluator::set_load_cache).
+///
+/// The cache is consulted before the loader, and successfully loaded modules
+/// are inserted into it, so a module referenced by several `load()` statements
+/// (possibly across several evaluations) is only loaded once.
+/// Keys are the `path` given to `load()` normalized lexically: empty and `.`
+/// components are removed and `..` removes the preceding component,
+/// so `a/../b.star`, `./b.star` and `b.star` share an entry.
+pub trait LoadCache {
+    /// Get a previously loaded module by its canonical path.
+    fn get(&self, path: &str) -> Option<FrozenModule>;
+
+    /// Record a module that was successfully loaded, by its canonical path.
+    fn insert(&self, path: &str, module: FrozenModule);
+}
+
+/// Canonical form of a `load()` path, used as the [`LoadCache`] key.
+///
+/// The path is normalized lexically, without accessing the file system:
+/// empty and `.` components are removed, and `..` removes the preceding component.
+/// Leading slashes and leading `..` components are kept.
+pub(crate) fn canonical_module_path(path: &str) -> String {
+    let rest = path.trim_start_matches('/');
+    let mut components: Vec<&str> = Vec::new();
+    for component in rest.split('/') {
+        match component {
+            "" | "." => {}
+            ".." if matches!(components.last(), Some(last) if *last != "..") => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    format!(
+        "{}{}",
+        &path[..path.len() - rest.len()],
+        components.join("/")
+    )
+}
+
 /// [`FileLoader`] that looks up modules by name from a [`HashMap`].
 ///
 /// A list of all load statements can be obtained through
@@ -51,3 +92,17 @@ impl<'a> FileLoader for ReturnFileLoader<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::runtime::file_loader::canonical_module_path;
+
+    #[test]
+    fn test_canonical_module_path() {
+        assert_eq!("b.star", canonical_module_path("b.star"));
+        assert_eq!("b.star", canonical_module_path("a/../b.star"));
+        assert_eq!("a/b.star", canonical_module_path("./a//./b.star"));
+        assert_eq!("../b.star", canonical_module_path("../a/../b.star"));
+        assert_eq!("//a/b.star", canonical_module_path("//a/c/../b.star"));
+    }
+}
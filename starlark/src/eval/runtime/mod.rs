@@ -17,7 +17,9 @@
 
 pub(crate) mod arguments;
 pub(crate) mod before_stmt;
+pub(crate) mod call_observer;
 pub(crate) mod call_stack;
+pub(crate) mod diagnostics;
 pub(crate) mod evaluator;
 pub(crate) mod file_loader;
 pub(crate) mod frame_span;
@@ -28,4 +30,5 @@ pub(crate) mod profile;
 pub(crate) mod rust_loc;
 pub(crate) mod slots;
 pub(crate) mod small_duration;
+pub(crate) mod step;
 pub(crate) mod visit_span;
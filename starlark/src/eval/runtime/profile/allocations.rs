@@ -0,0 +1,270 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Number of heap allocations made by each function.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use starlark_map::small_map::SmallMap;
+
+use crate as starlark;
+use crate::eval::runtime::profile::binary::BinaryReader;
+use crate::eval::runtime::profile::binary::BinaryWriter;
+use crate::eval::runtime::profile::csv::CsvWriter;
+use crate::eval::runtime::profile::data::ProfileData;
+use crate::eval::runtime::profile::data::ProfileDataImpl;
+use crate::eval::ProfileMode;
+use crate::values::layout::heap::profile::alloc_counts::AllocCounts;
+use crate::values::layout::pointer::RawPointer;
+use crate::values::Heap;
+use crate::values::Trace;
+use crate::values::Tracer;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum AllocationsProfileError {
+    #[error("Allocations profile not enabled")]
+    NotEnabled,
+}
+
+/// Allocations made by each function, excluding its callees, keyed by function name.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AllocationsProfileData {
+    functions: SmallMap<String, AllocCounts>,
+}
+
+impl AllocationsProfileData {
+    pub(crate) fn gen_csv(&self) -> String {
+        let mut rows = Vec::from_iter(&self.functions);
+        // Largest first, ties broken by name to make the output stable.
+        rows.sort_by(|(n1, c1), (n2, c2)| c2.bytes.cmp(&c1.bytes).then_with(|| n1.cmp(n2)));
+        let mut csv = CsvWriter::new(["Function", "Allocations", "Bytes"]);
+        for (name, counts) in rows {
+            csv.write_display(name);
+            csv.write_value(counts.count);
+            csv.write_value(counts.bytes);
+            csv.finish_row();
+        }
+        csv.finish()
+    }
+
+    pub(crate) fn merge<'a>(
+        profiles: impl IntoIterator<Item = &'a AllocationsProfileData>,
+    ) -> AllocationsProfileData {
+        let mut result = AllocationsProfileData::default();
+        for profile in profiles {
+            for (name, counts) in &profile.functions {
+                *result.functions.entry(name.clone()).or_default() += *counts;
+            }
+        }
+        result
+    }
+
+    pub(crate) fn write_binary(&self, w: &mut BinaryWriter) {
+        w.usize(self.functions.len());
+        for (name, counts) in &self.functions {
+            w.str(name);
+            w.usize(counts.count);
+            w.usize(counts.bytes);
+        }
+    }
+
+    pub(crate) fn read_binary(r: &mut BinaryReader) -> anyhow::Result<AllocationsProfileData> {
+        let mut functions = SmallMap::new();
+        for _ in 0..r.len()? {
+            let name = r.str()?.to_owned();
+            let count = r.usize()?;
+            let bytes = r.usize()?;
+            functions.insert(name, AllocCounts { bytes, count });
+        }
+        Ok(AllocationsProfileData { functions })
+    }
+}
+
+#[derive(Trace)]
+pub(crate) struct AllocationsProfile<'v>(Option<Box<AllocationsData<'v>>>);
+
+/// Like in the flame profile, functions are deduplicated in `values`,
+/// and `map` is rebuilt on GC.
+#[derive(Default)]
+struct AllocationsData<'v> {
+    values: Vec<Value<'v>>,
+    map: HashMap<RawPointer, usize>,
+    /// Allocations made by `values[i]`, excluding its callees.
+    counts: Vec<AllocCounts>,
+    /// Indices of functions being called, innermost last.
+    stack: Vec<usize>,
+    /// Heap allocation counts when the last call was entered or exited.
+    last: AllocCounts,
+}
+
+unsafe impl<'v> Trace<'v> for AllocationsData<'v> {
+    fn trace(&mut self, tracer: &Tracer<'v>) {
+        self.values.trace(tracer);
+        self.map.clear();
+        for (i, x) in self.values.iter().enumerate() {
+            self.map.insert(x.ptr_value(), i);
+        }
+    }
+}
+
+impl<'v> AllocationsData<'v> {
+    /// Attribute allocations since the last call enter or exit to the current function.
+    fn attribute(&mut self, heap: &Heap) {
+        let now = heap.alloc_counts();
+        if let Some(top) = self.stack.last() {
+            self.counts[*top] += AllocCounts {
+                bytes: now.bytes - self.last.bytes,
+                count: now.count - self.last.count,
+            };
+        }
+        self.last = now;
+    }
+}
+
+impl<'v> AllocationsProfile<'v> {
+    pub(crate) fn new() -> Self {
+        Self(None)
+    }
+
+    pub(crate) fn enable(&mut self, heap: &Heap) {
+        heap.enable_alloc_counts();
+        self.0 = Some(Box::new(AllocationsData {
+            last: heap.alloc_counts(),
+            ..AllocationsData::default()
+        }));
+    }
+
+    #[cold]
+    #[inline(never)]
+    pub(crate) fn record_call_enter(&mut self, function: Value<'v>, heap: &Heap) {
+        if let Some(x) = &mut self.0 {
+            x.attribute(heap);
+            let ind = match x.map.entry(function.ptr_value()) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => {
+                    let res = x.values.len();
+                    x.values.push(function);
+                    x.counts.push(AllocCounts::default());
+                    e.insert(res);
+                    res
+                }
+            };
+            x.stack.push(ind);
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    pub(crate) fn record_call_exit(&mut self, heap: &Heap) {
+        if let Some(x) = &mut self.0 {
+            x.attribute(heap);
+            x.stack.pop();
+        }
+    }
+
+    pub(crate) fn gen(&mut self, heap: &Heap) -> anyhow::Result<ProfileData> {
+        let x = match &mut self.0 {
+            None => return Err(AllocationsProfileError::NotEnabled.into()),
+            Some(x) => x,
+        };
+        x.attribute(heap);
+        let mut data = AllocationsProfileData::default();
+        for (value, counts) in x.values.iter().zip(&x.counts) {
+            let name = if value.is_none() {
+                "(root)".to_owned()
+            } else {
+                value.to_repr()
+            };
+            *data.functions.entry(name).or_default() += *counts;
+        }
+        Ok(ProfileData {
+            profile_mode: ProfileMode::Allocations,
+            profile: ProfileDataImpl::Allocations(data),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::eval::ProfileData;
+    use crate::eval::ProfileMode;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn profile() -> anyhow::Result<ProfileData> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::Allocations)?;
+        let program = r#"
+def no_alloc(x):
+    return x
+def alloc(x):
+    return [x, no_alloc(x)]
+def g():
+    return [alloc(i) for i in range(10)]
+g()
+"#;
+        let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &Globals::standard())?;
+        eval.gen_profile()
+    }
+
+    /// Allocation count and bytes of a function in the CSV.
+    fn row(csv: &str, name: &str) -> (usize, usize) {
+        let prefix = format!("\"{}\",", name);
+        let row = csv.lines().find(|l| l.starts_with(&prefix)).unwrap();
+        let mut cols = row[prefix.len()..].split(',');
+        (
+            cols.next().unwrap().parse().unwrap(),
+            cols.next().unwrap().parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_allocations_profile() -> anyhow::Result<()> {
+        let csv = profile()?.gen()?;
+        assert!(csv.starts_with("Function,Allocations,Bytes\n"), "{}", csv);
+        // One list per call of `alloc`: the list and the array of its elements.
+        let (count, bytes) = row(&csv, "a.star.alloc");
+        assert_eq!(20, count, "{}", csv);
+        assert!(bytes > 0);
+        assert_eq!((0, 0), row(&csv, "a.star.no_alloc"));
+        assert!(row(&csv, "a.star.g").0 > 0, "{}", csv);
+        assert!(row(&csv, "(root)").0 > 0, "{}", csv);
+        Ok(())
+    }
+
+    #[test]
+    fn test_allocations_profile_merge() -> anyhow::Result<()> {
+        let p = profile()?;
+        let merged = ProfileData::merge([&p, &p])?.gen()?;
+        let one = p.gen()?;
+        assert_eq!(
+            (
+                row(&one, "a.star.alloc").0 * 2,
+                row(&one, "a.star.alloc").1 * 2
+            ),
+            row(&merged, "a.star.alloc")
+        );
+        Ok(())
+    }
+}
@@ -24,13 +24,35 @@ use std::ops::AddAssign;
 
 use dupe::Dupe;
 
+use crate::codemap::FileSpan;
+use crate::codemap::ResolvedFileSpan;
+use crate::codemap::ResolvedSpan;
+use crate::collections::SmallMap;
 use crate::eval::bc::opcode::BcOpcode;
+use crate::eval::compiler::def::FrozenDef;
 use crate::eval::runtime::evaluator::EvaluatorError;
+use crate::eval::runtime::frame_span::FrameSpan;
+use crate::eval::runtime::profile::binary::BinaryReader;
+use crate::eval::runtime::profile::binary::BinaryWriter;
+use crate::eval::runtime::profile::binary::ProfileBinaryError;
 use crate::eval::runtime::profile::csv::CsvWriter;
 use crate::eval::runtime::profile::data::ProfileDataImpl;
 use crate::eval::ProfileData;
 use crate::eval::ProfileMode;
 
+/// Opcodes are serialized by name, so profiles stay readable when opcodes are renumbered.
+fn write_opcode(w: &mut BinaryWriter, opcode: BcOpcode) {
+    w.str(&format!("{:?}", opcode));
+}
+
+fn read_opcode(r: &mut BinaryReader) -> anyhow::Result<BcOpcode> {
+    let name = r.str()?;
+    (0..BcOpcode::COUNT as u32)
+        .filter_map(BcOpcode::by_number)
+        .find(|opcode| format!("{:?}", opcode) == name)
+        .ok_or_else(|| ProfileBinaryError::Invalid(format!("opcode `{}`", name)).into())
+}
+
 #[derive(Default, Clone, Dupe, Copy, Debug)]
 struct BcInstrStat {
     count: u64,
@@ -67,6 +89,45 @@ impl<'a> AddAssign<&'a BcInstrPairsStat> for BcInstrPairsStat {
     }
 }
 
+/// Calls from a single call site to a frozen `def`.
+#[derive(Clone, Debug)]
+struct BcCallSiteStat {
+    callee: String,
+    /// Instructions in the callee body.
+    callee_instrs: usize,
+    count: u64,
+}
+
+impl<'a> AddAssign<&'a BcCallSiteStat> for BcCallSiteStat {
+    fn add_assign(&mut self, other: &'a BcCallSiteStat) {
+        self.count += other.count;
+    }
+}
+
+/// Callees with at most this many instructions are reported as
+/// [hot call sites](CallSiteReport).
+const SMALL_DEF_MAX_INSTRS: usize = 16;
+
+/// A frequently executed call to a small frozen `def`,
+/// which might be worth inlining by hand.
+///
+/// Only calls the bytecode compiler resolved to a frozen `def`
+/// (typically a function loaded from another module) are recorded,
+/// which are the same calls the optimizer attempts to inline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallSiteReport {
+    /// The call expression.
+    pub span: ResolvedFileSpan,
+    /// Name of the called function.
+    pub callee: String,
+    /// Number of bytecode instructions in the called function.
+    pub callee_instrs: usize,
+    /// Number of calls made from this call site.
+    pub count: u64,
+    /// Calls from this site as a fraction of all executed instructions.
+    pub frequency: f64,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct BcProfileData {
     by_instr: [BcInstrStat; BcOpcode::COUNT],
@@ -84,6 +145,10 @@ impl<'a> AddAssign<&'a BcProfileData> for BcProfileData {
 pub(crate) struct BcPairsProfileData {
     last: Option<BcOpcode>,
     by_instr: HashMap<[BcOpcode; 2], BcInstrPairsStat>,
+    /// Calls to frozen `def`s recorded while profiling,
+    /// resolved into `call_sites` when the profile is generated.
+    pending_call_sites: HashMap<FileSpan, BcCallSiteStat>,
+    call_sites: SmallMap<ResolvedFileSpan, BcCallSiteStat>,
 }
 
 impl<'a> AddAssign<&'a BcPairsProfileData> for BcPairsProfileData {
@@ -92,6 +157,14 @@ impl<'a> AddAssign<&'a BcPairsProfileData> for BcPairsProfileData {
         for (pair, stat) in &rhs.by_instr {
             *self.by_instr.entry(*pair).or_default() += stat;
         }
+        for (span, stat) in &rhs.call_sites {
+            match self.call_sites.get_mut(span) {
+                Some(lhs) => *lhs += stat,
+                None => {
+                    self.call_sites.insert(span.clone(), stat.clone());
+                }
+            }
+        }
     }
 }
 
@@ -137,6 +210,29 @@ impl BcProfileData {
         csv.finish()
     }
 
+    pub(crate) fn write_binary(&self, w: &mut BinaryWriter) {
+        let used = Vec::from_iter(
+            self.by_instr
+                .iter()
+                .enumerate()
+                .filter(|(_, st)| st.count != 0),
+        );
+        w.usize(used.len());
+        for (i, st) in used {
+            write_opcode(w, BcOpcode::by_number(i as u32).unwrap());
+            w.u64(st.count);
+        }
+    }
+
+    pub(crate) fn read_binary(r: &mut BinaryReader) -> anyhow::Result<BcProfileData> {
+        let mut data = BcProfileData::default();
+        for _ in 0..r.len()? {
+            let opcode = read_opcode(r)?;
+            data.by_instr[opcode as usize].count += r.u64()?;
+        }
+        Ok(data)
+    }
+
     pub(crate) fn merge<'a>(iter: impl IntoIterator<Item = &'a BcProfileData>) -> BcProfileData {
         let mut sum = BcProfileData::default();
         for profile in iter {
@@ -157,6 +253,80 @@ impl BcPairsProfileData {
         self.last = Some(opcode);
     }
 
+    fn record_frozen_def_call(&mut self, span: &FrameSpan, callee: &FrozenDef) {
+        self.pending_call_sites
+            .entry(span.span.to_file_span())
+            .or_insert_with(|| BcCallSiteStat {
+                callee: callee.def_info.name.as_str().to_owned(),
+                callee_instrs: callee.bc().instrs.instr_count(),
+                count: 0,
+            })
+            .count += 1;
+    }
+
+    fn resolve_call_sites(&mut self) {
+        let mut pending: Vec<_> = self
+            .pending_call_sites
+            .drain()
+            .map(|(span, stat)| (span.resolve(), stat))
+            .collect();
+        // Source order, so the report doesn't depend on hash map order.
+        pending.sort_by(|(x, _), (y, _)| {
+            (&x.file, x.span.begin_line, x.span.begin_column).cmp(&(
+                &y.file,
+                y.span.begin_line,
+                y.span.begin_column,
+            ))
+        });
+        for (span, stat) in pending {
+            match self.call_sites.get_mut(&span) {
+                Some(lhs) => *lhs += &stat,
+                None => {
+                    self.call_sites.insert(span, stat);
+                }
+            }
+        }
+    }
+
+    /// Calls to small frozen `def`s, most frequent first.
+    pub(crate) fn hot_call_sites(&self) -> Vec<CallSiteReport> {
+        let count_total = self.by_instr.values().map(|st| st.count).sum::<u64>();
+        let mut reports: Vec<_> = self
+            .call_sites
+            .iter()
+            .filter(|(_, st)| st.callee_instrs <= SMALL_DEF_MAX_INSTRS)
+            .map(|(span, st)| CallSiteReport {
+                span: span.clone(),
+                callee: st.callee.clone(),
+                callee_instrs: st.callee_instrs,
+                count: st.count,
+                frequency: st.count as f64 / count_total.max(1) as f64,
+            })
+            .collect();
+        // Stable, so call sites with equal counts stay in source order.
+        reports.sort_by_key(|r| u64::MAX - r.count);
+        reports
+    }
+
+    pub(crate) fn gen_hot_call_sites_csv(&self) -> String {
+        let mut csv = CsvWriter::new([
+            "Call site",
+            "Callee",
+            "Callee instructions",
+            "Count",
+            "Count / Total",
+        ]);
+        for report in self.hot_call_sites() {
+            csv.write_display(&report.span);
+            csv.write_display(&report.callee);
+            csv.write_value(report.callee_instrs);
+            csv.write_value(report.count);
+            csv.write_display(format!("{:.3}", report.frequency));
+            csv.finish_row();
+        }
+        csv.finish()
+    }
+
     pub(crate) fn gen_csv(&self) -> String {
         let mut by_instr: Vec<_> = self
             .by_instr
@@ -179,6 +349,54 @@ impl BcPairsProfileData {
         csv.finish()
     }
 
+    pub(crate) fn write_binary(&self, w: &mut BinaryWriter) {
+        // Sorted so equal profiles serialize identically.
+        let mut by_instr = Vec::from_iter(&self.by_instr);
+        by_instr.sort_by_key(|(pair, _)| **pair);
+        w.usize(by_instr.len());
+        for ([a, b], st) in by_instr {
+            write_opcode(w, *a);
+            write_opcode(w, *b);
+            w.u64(st.count);
+        }
+        w.usize(self.call_sites.len());
+        for (span, st) in &self.call_sites {
+            w.str(&span.file);
+            w.usize(span.span.begin_line);
+            w.usize(span.span.begin_column);
+            w.usize(span.span.end_line);
+            w.usize(span.span.end_column);
+            w.str(&st.callee);
+            w.usize(st.callee_instrs);
+            w.u64(st.count);
+        }
+    }
+
+    pub(crate) fn read_binary(r: &mut BinaryReader) -> anyhow::Result<BcPairsProfileData> {
+        let mut data = BcPairsProfileData::default();
+        for _ in 0..r.len()? {
+            let pair = [read_opcode(r)?, read_opcode(r)?];
+            data.by_instr.entry(pair).or_default().count += r.u64()?;
+        }
+        for _ in 0..r.len()? {
+            let file = r.str()?.to_owned();
+            let span = ResolvedSpan {
+                begin_line: r.usize()?,
+                begin_column: r.usize()?,
+                end_line: r.usize()?,
+                end_column: r.usize()?,
+            };
+            let stat = BcCallSiteStat {
+                callee: r.str()?.to_owned(),
+                callee_instrs: r.usize()?,
+                count: r.u64()?,
+            };
+            data.call_sites
+                .insert(ResolvedFileSpan { file, span }, stat);
+        }
+        Ok(data)
+    }
+
     pub(crate) fn merge<'a>(
         iter: impl IntoIterator<Item = &'a BcPairsProfileData>,
     ) -> BcPairsProfileData {
@@ -235,10 +453,13 @@ impl BcProfile {
 
     pub(crate) fn gen_bc_pairs_profile(&mut self) -> anyhow::Result<ProfileData> {
         match mem::replace(&mut self.data, BcProfileDataMode::Disabled) {
-            BcProfileDataMode::BcPairs(bc_pairs) => Ok(ProfileData {
-                profile_mode: ProfileMode::BytecodePairs,
-                profile: ProfileDataImpl::BcPairs(*bc_pairs),
-            }),
+            BcProfileDataMode::BcPairs(mut bc_pairs) => {
+                bc_pairs.resolve_call_sites();
+                Ok(ProfileData {
+                    profile_mode: ProfileMode::BytecodePairs,
+                    profile: ProfileDataImpl::BcPairs(*bc_pairs),
+                })
+            }
             _ => Err(EvaluatorError::BcProfilingNotEnabled.into()),
         }
     }
@@ -253,17 +474,33 @@ impl BcProfile {
             }
         }
     }
+
+    /// Whether [`record_frozen_def_call`](BcProfile::record_frozen_def_call) is used.
+    pub(crate) fn records_call_sites(&self) -> bool {
+        matches!(self.data, BcProfileDataMode::BcPairs(..))
+    }
+
+    /// Called from bytecode before a call to a frozen `def`.
+    pub(crate) fn record_frozen_def_call(&mut self, span: &FrameSpan, callee: &FrozenDef) {
+        if let BcProfileDataMode::BcPairs(data) = &mut self.data {
+            data.record_frozen_def_call(span, callee);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use crate::environment::Globals;
     use crate::environment::Module;
     use crate::eval::bc::opcode::BcOpcode;
     use crate::eval::runtime::profile::bc::BcPairsProfileData;
     use crate::eval::runtime::profile::bc::BcProfileData;
     use crate::eval::Evaluator;
+    use crate::eval::ProfileData;
     use crate::eval::ProfileMode;
+    use crate::eval::ReturnFileLoader;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
 
@@ -314,6 +551,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hot_call_sites() -> anyhow::Result<()> {
+        let globals = Globals::standard();
+        let lib = Module::new();
+        Evaluator::new(&lib).eval_module(
+            AstModule::parse(
+                "lib.star",
+                r#"
+def inc(x):
+    return x + 1
+
+def big(x):
+    for _ in range(2):
+        x = x * 2 + 1
+        x = x - 1
+        x = x // 2
+    return [x, x, x][1]
+"#
+                .to_owned(),
+                &Dialect::Standard,
+            )?,
+            &globals,
+        )?;
+        let lib = lib.freeze()?;
+
+        let modules = HashMap::from([("lib.star", &lib)]);
+        let loader = ReturnFileLoader { modules: &modules };
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        eval.enable_profile(&ProfileMode::BytecodePairs)?;
+        eval.eval_module(
+            AstModule::parse(
+                "main.star",
+                r#"
+load("lib.star", "inc", "big")
+def local(x):
+    return x
+for i in range(10):
+    inc(i)
+    local(i)
+    big(i)
+for i in range(3):
+    inc(i)
+"#
+                .to_owned(),
+                &Dialect::Extended,
+            )?,
+            &globals,
+        )?;
+        let profile = eval.gen_profile()?;
+
+        // Only small frozen defs are reported, most frequent first.
+        let reports = profile.hot_call_sites()?;
+        assert_eq!(
+            vec![
+                ("main.star:6:5-11".to_owned(), "inc", 10),
+                ("main.star:10:5-11".to_owned(), "inc", 3),
+            ],
+            reports
+                .iter()
+                .map(|r| (r.span.to_string(), r.callee.as_str(), r.count))
+                .collect::<Vec<_>>()
+        );
+        assert!(reports[0].callee_instrs > 0);
+        assert!(reports[0].frequency > reports[1].frequency);
+
+        let csv = profile.gen_hot_call_sites()?;
+        let mut lines = csv.lines();
+        assert_eq!(
+            Some("Call site,Callee,Callee instructions,Count,Count / Total"),
+            lines.next()
+        );
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("\"main.star:6:5-11\",\"inc\","),
+            "{}",
+            csv
+        );
+
+        // Call sites survive serialization and are summed by merge.
+        let read = ProfileData::deserialize_bin(&profile.serialize_bin()?)?;
+        assert_eq!(reports, read.hot_call_sites()?);
+        let merged = ProfileData::merge([&profile, &read])?.hot_call_sites()?;
+        assert_eq!(20, merged[0].count);
+        assert_eq!(6, merged[1].count);
+
+        assert_eq!(
+            "Hot call sites are only collected by profile mode `bytecode-pairs`, not `statement`",
+            ProfileData::new(ProfileMode::Statement, String::new())
+                .hot_call_sites()
+                .unwrap_err()
+                .to_string()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_bc_profile_data_merge() {
         let bc = BcProfileData::default();
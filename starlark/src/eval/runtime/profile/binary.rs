@@ -0,0 +1,186 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read and write a compact binary encoding of profile data.
+//!
+//! All integers are little-endian, strings and sequences are prefixed
+//! with their length as `u64`.
+
+use std::collections::HashSet;
+use std::str;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+/// Prefix of every serialized profile.
+pub(crate) const MAGIC: &[u8; 8] = b"STARPROF";
+/// Bumped whenever the encoding changes.
+pub(crate) const VERSION: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ProfileBinaryError {
+    #[error("Not a serialized profile")]
+    BadMagic,
+    #[error("Serialized profile version {0} is not supported, expected version {1}")]
+    UnsupportedVersion(u32, u32),
+    #[error("Serialized profile is truncated")]
+    UnexpectedEof,
+    #[error("Serialized profile contains an invalid string")]
+    InvalidUtf8,
+    #[error("Serialized profile contains invalid data: {0}")]
+    Invalid(String),
+    #[error("Serialized profile has {0} trailing bytes")]
+    TrailingData(usize),
+}
+
+#[derive(Default)]
+pub(crate) struct BinaryWriter {
+    buf: Vec<u8>,
+}
+
+impl BinaryWriter {
+    pub(crate) fn u8(&mut self, x: u8) {
+        self.buf.push(x);
+    }
+
+    pub(crate) fn u32(&mut self, x: u32) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, x: u64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub(crate) fn usize(&mut self, x: usize) {
+        self.u64(x as u64);
+    }
+
+    pub(crate) fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub(crate) struct BinaryReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> BinaryReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> BinaryReader<'a> {
+        BinaryReader { data }
+    }
+
+    fn bytes(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if n > self.data.len() {
+            return Err(ProfileBinaryError::UnexpectedEof.into());
+        }
+        let (bytes, rem) = self.data.split_at(n);
+        self.data = rem;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn usize(&mut self) -> anyhow::Result<usize> {
+        let x = self.u64()?;
+        usize::try_from(x).map_err(|_| ProfileBinaryError::Invalid(format!("size {}", x)).into())
+    }
+
+    /// Read a sequence length, checking it against the remaining data
+    /// (each element takes at least one byte), so corrupt input cannot
+    /// cause huge allocations.
+    pub(crate) fn len(&mut self) -> anyhow::Result<usize> {
+        let len = self.usize()?;
+        if len > self.data.len() {
+            return Err(ProfileBinaryError::UnexpectedEof.into());
+        }
+        Ok(len)
+    }
+
+    pub(crate) fn str(&mut self) -> anyhow::Result<&'a str> {
+        let len = self.usize()?;
+        str::from_utf8(self.bytes(len)?).map_err(|_| ProfileBinaryError::InvalidUtf8.into())
+    }
+
+    /// Read a `&'static str`, interning it so that reading many profiles
+    /// does not leak memory for each of them.
+    pub(crate) fn static_str(&mut self) -> anyhow::Result<&'static str> {
+        static INTERNED: Lazy<Mutex<HashSet<&'static str>>> = Lazy::new(Default::default);
+
+        let s = self.str()?;
+        let mut interned = INTERNED.lock().unwrap();
+        match interned.get(s) {
+            Some(s) => Ok(s),
+            None => {
+                let s: &'static str = Box::leak(s.to_owned().into_boxed_str());
+                interned.insert(s);
+                Ok(s)
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> anyhow::Result<()> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(ProfileBinaryError::TrailingData(self.data.len()).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::runtime::profile::binary::BinaryReader;
+    use crate::eval::runtime::profile::binary::BinaryWriter;
+
+    #[test]
+    fn test_round_trip() {
+        let mut w = BinaryWriter::default();
+        w.u8(7);
+        w.u32(1 << 20);
+        w.u64(u64::MAX);
+        w.str("héllo");
+        let data = w.finish();
+
+        let mut r = BinaryReader::new(&data);
+        assert_eq!(7, r.u8().unwrap());
+        assert_eq!(1 << 20, r.u32().unwrap());
+        assert_eq!(u64::MAX, r.u64().unwrap());
+        assert_eq!("héllo", r.static_str().unwrap());
+        r.finish().unwrap();
+
+        let mut r = BinaryReader::new(&data[..data.len() - 1]);
+        r.u8().unwrap();
+        r.u32().unwrap();
+        r.u64().unwrap();
+        assert!(r.str().is_err());
+    }
+}
@@ -134,6 +134,12 @@ impl CsvValue for u64 {
     }
 }
 
+impl CsvValue for i64 {
+    fn format_for_csv(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl CsvValue for i32 {
     fn format_for_csv(&self) -> String {
         self.to_string()
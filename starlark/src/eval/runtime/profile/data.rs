@@ -22,9 +22,17 @@ use anyhow::Context;
 use dupe::Dupe;
 use gazebo::prelude::*;
 
+use crate::eval::runtime::profile::allocations::AllocationsProfileData;
 use crate::eval::runtime::profile::bc::BcPairsProfileData;
 use crate::eval::runtime::profile::bc::BcProfileData;
+use crate::eval::runtime::profile::bc::CallSiteReport;
+use crate::eval::runtime::profile::binary;
+use crate::eval::runtime::profile::binary::BinaryReader;
+use crate::eval::runtime::profile::binary::BinaryWriter;
+use crate::eval::runtime::profile::binary::ProfileBinaryError;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::pprof::PprofBuilder;
+use crate::eval::runtime::profile::retained::RetainedProfileData;
 use crate::eval::ProfileMode;
 use crate::values::AggregateHeapProfileInfo;
 
@@ -34,10 +42,16 @@ enum ProfileDataError {
     ProfileDataNotConsistent,
     #[error("Empty profile list cannot be merged")]
     EmptyProfileList,
-    #[error("Different profile modes in profile")]
-    DifferentProfileModes,
+    #[error("Cannot merge profiles of different modes: `{0}` and `{1}`")]
+    DifferentProfileModes(ProfileMode, ProfileMode),
+    #[error("Profile data for profile mode `{0}` is text, which cannot be merged")]
+    MergeText(ProfileMode),
     #[error("Merge of profile data for profile mode `{0}` is not implemented")]
     MergeNotImplemented(ProfileMode),
+    #[error("Profile mode `{0}` cannot be written as pprof")]
+    PprofNotSupported(ProfileMode),
+    #[error("Hot call sites are only collected by profile mode `bytecode-pairs`, not `{0}`")]
+    HotCallSitesNotSupported(ProfileMode),
 }
 
 #[derive(Clone, Debug)]
@@ -47,6 +61,8 @@ pub(crate) enum ProfileDataImpl {
     AggregateHeapProfileInfo(Box<AggregateHeapProfileInfo>),
     /// Flame graph data is in milliseconds.
     TimeFlameProfile(FlameGraphData),
+    Allocations(AllocationsProfileData),
+    Retained(Box<RetainedProfileData>),
     Other(String),
 }
 
@@ -87,6 +103,28 @@ impl ProfileData {
             (ProfileDataImpl::TimeFlameProfile(_), _) => {
                 Err(ProfileDataError::ProfileDataNotConsistent.into())
             }
+            (ProfileDataImpl::Allocations(data), _) => Ok(data.gen_csv()),
+            (ProfileDataImpl::Retained(data), _) => Ok(data.gen_csv()),
+        }
+    }
+
+    /// Frequently executed calls to small frozen `def`s, most frequent first,
+    /// which hint at functions worth inlining.
+    ///
+    /// Only available for [`ProfileMode::BytecodePairs`].
+    pub fn hot_call_sites(&self) -> anyhow::Result<Vec<CallSiteReport>> {
+        match &self.profile {
+            ProfileDataImpl::BcPairs(bc_pairs) => Ok(bc_pairs.hot_call_sites()),
+            _ => Err(ProfileDataError::HotCallSitesNotSupported(self.profile_mode.dupe()).into()),
+        }
+    }
+
+    /// Like [`gen`](ProfileData::gen), but render [`hot_call_sites`](ProfileData::hot_call_sites)
+    /// as CSV.
+    pub fn gen_hot_call_sites(&self) -> anyhow::Result<String> {
+        match &self.profile {
+            ProfileDataImpl::BcPairs(bc_pairs) => Ok(bc_pairs.gen_hot_call_sites_csv()),
+            _ => Err(ProfileDataError::HotCallSitesNotSupported(self.profile_mode.dupe()).into()),
         }
     }
 
@@ -102,7 +140,119 @@ impl ProfileData {
         Ok(())
     }
 
+    /// Encode as a [pprof](https://github.com/google/pprof) protobuf message.
+    ///
+    /// Only time flame and heap profiles can be encoded. Time samples are in milliseconds,
+    /// heap samples have object count and bytes values and a `type` label.
+    pub fn to_pprof(&self) -> anyhow::Result<Vec<u8>> {
+        let pprof = match (&self.profile, &self.profile_mode) {
+            (ProfileDataImpl::TimeFlameProfile(data), ProfileMode::TimeFlame) => {
+                let mut pprof = PprofBuilder::new(&[("time", "milliseconds")]);
+                data.write_pprof(&mut pprof);
+                pprof
+            }
+            (ProfileDataImpl::AggregateHeapProfileInfo(profile), mode) => {
+                let sample_types = match mode {
+                    ProfileMode::HeapSummaryAllocated | ProfileMode::HeapFlameAllocated => {
+                        [("alloc_objects", "count"), ("alloc_space", "bytes")]
+                    }
+                    ProfileMode::HeapSummaryRetained | ProfileMode::HeapFlameRetained => {
+                        [("retained_objects", "count"), ("retained_space", "bytes")]
+                    }
+                    _ => return Err(ProfileDataError::ProfileDataNotConsistent.into()),
+                };
+                let mut pprof = PprofBuilder::new(&sample_types);
+                profile.write_pprof(&mut pprof);
+                pprof
+            }
+            (_, mode) => return Err(ProfileDataError::PprofNotSupported(mode.dupe()).into()),
+        };
+        Ok(pprof.finish())
+    }
+
+    /// Serialize to a versioned binary format, which, unlike [`gen`](ProfileData::gen),
+    /// keeps all the data, so profiles can be shipped elsewhere, then
+    /// [deserialized](ProfileData::deserialize_bin), [merged](ProfileData::merge) and rendered.
+    pub fn serialize_bin(&self) -> anyhow::Result<Vec<u8>> {
+        let mut w = BinaryWriter::default();
+        for b in binary::MAGIC {
+            w.u8(*b);
+        }
+        w.u32(binary::VERSION);
+        w.str(self.profile_mode.name());
+        match &self.profile {
+            ProfileDataImpl::Bc(bc) => {
+                w.u8(0);
+                bc.write_binary(&mut w);
+            }
+            ProfileDataImpl::BcPairs(bc_pairs) => {
+                w.u8(1);
+                bc_pairs.write_binary(&mut w);
+            }
+            ProfileDataImpl::AggregateHeapProfileInfo(profile) => {
+                w.u8(2);
+                profile.write_binary(&mut w);
+            }
+            ProfileDataImpl::TimeFlameProfile(data) => {
+                w.u8(3);
+                data.write_binary(&mut w);
+            }
+            ProfileDataImpl::Other(profile) => {
+                w.u8(4);
+                w.str(profile);
+            }
+            ProfileDataImpl::Allocations(data) => {
+                w.u8(5);
+                data.write_binary(&mut w);
+            }
+            ProfileDataImpl::Retained(data) => {
+                w.u8(6);
+                data.write_binary(&mut w);
+            }
+        }
+        Ok(w.finish())
+    }
+
+    /// Read profile data written by [`serialize_bin`](ProfileData::serialize_bin).
+    ///
+    /// Fails if the data was written by an incompatible version of this crate.
+    pub fn deserialize_bin(data: &[u8]) -> anyhow::Result<ProfileData> {
+        let mut r = BinaryReader::new(data);
+        for b in binary::MAGIC {
+            if r.u8().ok() != Some(*b) {
+                return Err(ProfileBinaryError::BadMagic.into());
+            }
+        }
+        let version = r.u32()?;
+        if version != binary::VERSION {
+            return Err(ProfileBinaryError::UnsupportedVersion(version, binary::VERSION).into());
+        }
+        let profile_mode: ProfileMode = r.str()?.parse()?;
+        let profile = match r.u8()? {
+            0 => ProfileDataImpl::Bc(Box::new(BcProfileData::read_binary(&mut r)?)),
+            1 => ProfileDataImpl::BcPairs(BcPairsProfileData::read_binary(&mut r)?),
+            2 => ProfileDataImpl::AggregateHeapProfileInfo(Box::new(
+                AggregateHeapProfileInfo::read_binary(&mut r)?,
+            )),
+            3 => ProfileDataImpl::TimeFlameProfile(FlameGraphData::read_binary(&mut r)?),
+            4 => ProfileDataImpl::Other(r.str()?.to_owned()),
+            5 => ProfileDataImpl::Allocations(AllocationsProfileData::read_binary(&mut r)?),
+            6 => ProfileDataImpl::Retained(Box::new(RetainedProfileData::read_binary(&mut r)?)),
+            tag => {
+                return Err(ProfileBinaryError::Invalid(format!("profile kind {}", tag)).into());
+            }
+        };
+        r.finish()?;
+        Ok(ProfileData {
+            profile_mode,
+            profile,
+        })
+    }
+
     /// Merge profiles (aggregate).
+    ///
+    /// All profiles must have the same mode, and profiles which are only available
+    /// as text (e.g. statement profiles) cannot be merged.
     pub fn merge<'a>(
         profiles: impl IntoIterator<Item = &'a ProfileData>,
     ) -> anyhow::Result<ProfileData> {
@@ -113,7 +263,14 @@ impl ProfileData {
         };
         for p in &profiles {
             if p.profile_mode != profile_mode {
-                return Err(ProfileDataError::DifferentProfileModes.into());
+                return Err(ProfileDataError::DifferentProfileModes(
+                    profile_mode,
+                    p.profile_mode.dupe(),
+                )
+                .into());
+            }
+            if let ProfileDataImpl::Other(_) = p.profile {
+                return Err(ProfileDataError::MergeText(profile_mode).into());
             }
         }
         let profile = match &profile_mode {
@@ -152,6 +309,22 @@ impl ProfileData {
                 let profile = FlameGraphData::merge(profiles);
                 ProfileDataImpl::TimeFlameProfile(profile)
             }
+            ProfileMode::Allocations => {
+                let profiles = profiles.try_map(|p| match &p.profile {
+                    ProfileDataImpl::Allocations(data) => Ok(data),
+                    _ => Err(ProfileDataError::ProfileDataNotConsistent),
+                })?;
+                let profile = AllocationsProfileData::merge(profiles);
+                ProfileDataImpl::Allocations(profile)
+            }
+            ProfileMode::Retained => {
+                let profiles = profiles.try_map(|p| match &p.profile {
+                    ProfileDataImpl::Retained(data) => Ok(&**data),
+                    _ => Err(ProfileDataError::ProfileDataNotConsistent),
+                })?;
+                let profile = RetainedProfileData::merge(profiles);
+                ProfileDataImpl::Retained(Box::new(profile))
+            }
             profile_mode => {
                 return Err(ProfileDataError::MergeNotImplemented(profile_mode.dupe()).into());
             }
@@ -167,12 +340,18 @@ impl ProfileData {
 mod tests {
     use dupe::Dupe;
 
+    use crate::environment::Globals;
+    use crate::environment::Module;
     use crate::eval::runtime::profile::bc::BcPairsProfileData;
     use crate::eval::runtime::profile::bc::BcProfileData;
     use crate::eval::runtime::profile::data::ProfileDataImpl;
     use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+    use crate::eval::runtime::profile::pprof::tests::decode_pprof;
+    use crate::eval::Evaluator;
     use crate::eval::ProfileData;
     use crate::eval::ProfileMode;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
     use crate::values::AggregateHeapProfileInfo;
 
     #[test]
@@ -214,6 +393,182 @@ mod tests {
         }
     }
 
+    fn profile_of(mode: &ProfileMode) -> anyhow::Result<ProfileData> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(mode)?;
+        let ast = AstModule::parse(
+            "a.star",
+            "def f(x):\n  return [x + 1]\nf(f(1)[0])".to_owned(),
+            &Dialect::Extended,
+        )?;
+        eval.eval_module(ast, &Globals::standard())?;
+        eval.gen_profile()
+    }
+
+    #[test]
+    fn merge_bc_sums_counts() -> anyhow::Result<()> {
+        fn profile() -> anyhow::Result<ProfileData> {
+            profile_of(&ProfileMode::Bytecode)
+        }
+
+        fn total(csv: &str) -> u64 {
+            let row = csv.lines().find(|l| l.starts_with("\"TOTAL\"")).unwrap();
+            row.split(',').nth(1).unwrap().parse().unwrap()
+        }
+
+        let p1 = profile()?;
+        let p2 = profile()?;
+        let merged = ProfileData::merge(&[p1.clone(), p2.clone()])?;
+        let one = total(&p1.gen()?);
+        assert!(one > 0);
+        assert_eq!(one * 2, total(&merged.gen()?));
+        Ok(())
+    }
+
+    #[test]
+    fn binary_round_trip() -> anyhow::Result<()> {
+        for mode in [
+            ProfileMode::Bytecode,
+            ProfileMode::BytecodePairs,
+            ProfileMode::HeapSummaryAllocated,
+            ProfileMode::HeapFlameAllocated,
+            ProfileMode::TimeFlame,
+            ProfileMode::Statement,
+            ProfileMode::Allocations,
+        ] {
+            let profile = profile_of(&mode)?;
+            let data = profile.serialize_bin()?;
+            let read = ProfileData::deserialize_bin(&data)?;
+            assert_eq!(mode, read.profile_mode);
+            assert_eq!(profile.gen()?, read.gen()?, "{}", mode);
+            assert_eq!(data, read.serialize_bin()?, "{}", mode);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn binary_merge_after_round_trip() -> anyhow::Result<()> {
+        let profile = profile_of(&ProfileMode::HeapSummaryAllocated)?;
+        let read = ProfileData::deserialize_bin(&profile.serialize_bin()?)?;
+        assert_eq!(
+            ProfileData::merge([&profile, &profile])?.gen()?,
+            ProfileData::merge([&read, &profile])?.gen()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn binary_errors() -> anyhow::Result<()> {
+        let data = profile_of(&ProfileMode::Bytecode)?.serialize_bin()?;
+
+        let mut other_version = data.clone();
+        other_version[8] += 1;
+        assert_eq!(
+            "Serialized profile version 3 is not supported, expected version 2",
+            ProfileData::deserialize_bin(&other_version)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Not a serialized profile",
+            ProfileData::deserialize_bin(b"File,Line\n")
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(ProfileData::deserialize_bin(&data[..data.len() - 1]).is_err());
+        let mut trailing = data;
+        trailing.push(0);
+        assert!(ProfileData::deserialize_bin(&trailing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pprof() -> anyhow::Result<()> {
+        let decoded = decode_pprof(&profile_of(&ProfileMode::HeapSummaryAllocated)?.to_pprof()?);
+        assert_eq!(
+            vec![
+                ("alloc_objects".to_owned(), "count".to_owned()),
+                ("alloc_space".to_owned(), "bytes".to_owned())
+            ],
+            decoded.sample_types
+        );
+        // `f` is called twice, and allocates a list each time.
+        let (stack, values, _) = decoded
+            .samples
+            .iter()
+            .find(|(stack, _, labels)| {
+                stack.last().map(|s| s.as_str()) == Some("a.star.f")
+                    && labels.contains(&("type".to_owned(), "list".to_owned()))
+            })
+            .unwrap();
+        assert_eq!(&vec!["None".to_owned(), "a.star.f".to_owned()], stack);
+        assert_eq!(2, values[0]);
+        assert!(values[1] > 0);
+
+        let mut data = FlameGraphData::default();
+        data.root().child("f".into()).add(3);
+        data.root().child("f".into()).child("g".into()).add(5);
+        let profile = ProfileData {
+            profile_mode: ProfileMode::TimeFlame,
+            profile: ProfileDataImpl::TimeFlameProfile(data),
+        };
+        let decoded = decode_pprof(&profile.to_pprof()?);
+        assert_eq!(
+            vec![("time".to_owned(), "milliseconds".to_owned())],
+            decoded.sample_types
+        );
+        assert_eq!(
+            vec![
+                (vec!["f".to_owned()], vec![3], vec![]),
+                (vec!["f".to_owned(), "g".to_owned()], vec![5], vec![]),
+            ],
+            decoded.samples
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pprof_not_supported() -> anyhow::Result<()> {
+        assert_eq!(
+            "Profile mode `bytecode` cannot be written as pprof",
+            profile_of(&ProfileMode::Bytecode)?
+                .to_pprof()
+                .unwrap_err()
+                .to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merge_different_modes() {
+        let bc = ProfileData {
+            profile_mode: ProfileMode::Bytecode,
+            profile: ProfileDataImpl::Bc(Box::new(BcProfileData::default())),
+        };
+        let bc_pairs = ProfileData {
+            profile_mode: ProfileMode::BytecodePairs,
+            profile: ProfileDataImpl::BcPairs(BcPairsProfileData::default()),
+        };
+        assert_eq!(
+            "Cannot merge profiles of different modes: `bytecode` and `bytecode-pairs`",
+            ProfileData::merge([&bc, &bc_pairs])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn merge_text() {
+        let profile = ProfileData::new(ProfileMode::Statement, "File,Line\n".to_owned());
+        assert_eq!(
+            "Profile data for profile mode `statement` is text, which cannot be merged",
+            ProfileData::merge([&profile, &profile])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
     #[test]
     fn merge_time_flame() {
         let profile = ProfileData {
@@ -22,6 +22,9 @@ use std::fmt::Write;
 use dupe::Dupe;
 use starlark_map::small_map::SmallMap;
 
+use crate::eval::runtime::profile::binary::BinaryReader;
+use crate::eval::runtime::profile::binary::BinaryWriter;
+use crate::eval::runtime::profile::pprof::PprofBuilder;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
 
 /// Node in flamegraph tree.
@@ -69,6 +72,45 @@ impl FlameGraphNode {
         }
     }
 
+    fn write_binary(&self, w: &mut BinaryWriter) {
+        match self.value {
+            None => w.u8(0),
+            Some(value) => {
+                w.u8(1);
+                w.u64(value);
+            }
+        }
+        w.usize(self.children.len());
+        for (k, v) in &self.children {
+            w.str(k);
+            v.write_binary(w);
+        }
+    }
+
+    fn read_binary(r: &mut BinaryReader) -> anyhow::Result<FlameGraphNode> {
+        let value = match r.u8()? {
+            0 => None,
+            _ => Some(r.u64()?),
+        };
+        let mut children = SmallMap::new();
+        for _ in 0..r.len()? {
+            let k = ArcStr::from(r.str()?);
+            children.insert(k, FlameGraphNode::read_binary(r)?);
+        }
+        Ok(FlameGraphNode { children, value })
+    }
+
+    fn write_pprof<'a>(&'a self, pprof: &mut PprofBuilder, stack: &mut Vec<&'a str>) {
+        if let Some(value) = self.value {
+            pprof.add_sample(stack.iter().copied(), &[value as i64], &[]);
+        }
+        for (k, v) in &self.children {
+            stack.push(k);
+            v.write_pprof(pprof, stack);
+            stack.pop().unwrap();
+        }
+    }
+
     /// Get or create a child node.
     pub(crate) fn child(&mut self, name: ArcStr) -> &mut FlameGraphNode {
         self.children.entry(name).or_default()
@@ -88,6 +130,23 @@ impl FlameGraphData {
         &mut self.root
     }
 
+    /// Add a sample with a single value for each stack with a value.
+    pub(crate) fn write_pprof(&self, pprof: &mut PprofBuilder) {
+        let mut stack = Vec::new();
+        self.root.write_pprof(pprof, &mut stack);
+        assert!(stack.is_empty());
+    }
+
+    pub(crate) fn write_binary(&self, w: &mut BinaryWriter) {
+        self.root.write_binary(w);
+    }
+
+    pub(crate) fn read_binary(r: &mut BinaryReader) -> anyhow::Result<FlameGraphData> {
+        Ok(FlameGraphData {
+            root: FlameGraphNode::read_binary(r)?,
+        })
+    }
+
     pub(crate) fn merge<'a>(
         graphs: impl IntoIterator<Item = &'a FlameGraphData>,
     ) -> FlameGraphData {
@@ -21,12 +21,16 @@ use std::str::FromStr;
 use allocative::Allocative;
 use dupe::Dupe;
 
+pub(crate) mod allocations;
 pub(crate) mod bc;
+pub(crate) mod binary;
 pub(crate) mod csv;
 pub(crate) mod data;
 pub(crate) mod flamegraph;
 pub(crate) mod heap;
 pub(crate) mod or_instrumentation;
+pub(crate) mod pprof;
+pub(crate) mod retained;
 pub(crate) mod stmt;
 pub(crate) mod time_flame;
 pub(crate) mod typecheck;
@@ -57,8 +61,19 @@ pub enum ProfileMode {
     /// Provide output compatible with
     /// [flamegraph.pl](https://github.com/brendangregg/FlameGraph/blob/master/flamegraph.pl).
     TimeFlame,
+    /// Time spent in each function as a call tree, with inclusive and exclusive time
+    /// of each function per caller, written as indented text.
+    TimeCallTree,
     /// Profile runtime typechecking.
     Typecheck,
+    /// Number of heap allocations and allocated bytes per function, excluding callees.
+    /// Unlike the heap profiles, this mode does not disable garbage collection.
+    Allocations,
+    /// Bytes retained on the heap by each top-level statement, to find what survives
+    /// rather than what is allocated. By default garbage collects before and after
+    /// each top-level statement, which can be disabled with
+    /// [`Evaluator::set_retained_profile_gc`](crate::eval::Evaluator::set_retained_profile_gc).
+    Retained,
 }
 
 impl Display for ProfileMode {
@@ -68,7 +83,7 @@ impl Display for ProfileMode {
 }
 
 impl ProfileMode {
-    fn name(&self) -> &str {
+    pub(crate) fn name(&self) -> &str {
         match self {
             ProfileMode::HeapSummaryAllocated => "heap-summary-allocated",
             ProfileMode::HeapSummaryRetained => "heap-summary-retained",
@@ -79,7 +94,10 @@ impl ProfileMode {
             ProfileMode::Bytecode => "bytecode",
             ProfileMode::BytecodePairs => "bytecode-pairs",
             ProfileMode::TimeFlame => "time-flame",
+            ProfileMode::TimeCallTree => "time-call-tree",
             ProfileMode::Typecheck => "typecheck",
+            ProfileMode::Allocations => "allocations",
+            ProfileMode::Retained => "retained",
         }
     }
 }
@@ -98,7 +116,10 @@ impl FromStr for ProfileMode {
             ProfileMode::Bytecode,
             ProfileMode::BytecodePairs,
             ProfileMode::TimeFlame,
+            ProfileMode::TimeCallTree,
             ProfileMode::Typecheck,
+            ProfileMode::Allocations,
+            ProfileMode::Retained,
         ] {
             if s == mode.name() {
                 return Ok(mode);
@@ -0,0 +1,348 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write profiles in the [pprof](https://github.com/google/pprof/blob/main/proto/profile.proto)
+//! protobuf format.
+//!
+//! Each distinct function name gets one function and one location with the same id.
+
+use starlark_map::small_map::SmallMap;
+
+/// Minimal protobuf encoder, enough to write pprof messages.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    const VARINT: u64 = 0;
+    const LEN: u64 = 2;
+
+    fn varint(&mut self, mut x: u64) {
+        while x >= 0x80 {
+            self.buf.push((x as u8) | 0x80);
+            x >>= 7;
+        }
+        self.buf.push(x as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u64) {
+        self.varint(((field as u64) << 3) | wire_type);
+    }
+
+    fn uint64(&mut self, field: u32, x: u64) {
+        self.key(field, Self::VARINT);
+        self.varint(x);
+    }
+
+    fn int64(&mut self, field: u32, x: i64) {
+        self.uint64(field, x as u64);
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, Self::LEN);
+        self.varint(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn message(&mut self, field: u32, write: impl FnOnce(&mut ProtoWriter)) {
+        let mut w = ProtoWriter::default();
+        write(&mut w);
+        self.bytes(field, &w.buf);
+    }
+
+    fn packed(&mut self, field: u32, xs: impl IntoIterator<Item = u64>) {
+        let mut w = ProtoWriter::default();
+        for x in xs {
+            w.varint(x);
+        }
+        self.bytes(field, &w.buf);
+    }
+}
+
+struct PprofSample {
+    /// Location ids, leaf first.
+    locations: Vec<u64>,
+    values: Vec<i64>,
+    /// Key and value string indices.
+    labels: Vec<(i64, i64)>,
+}
+
+/// Collects samples and writes a pprof `Profile` message.
+pub(crate) struct PprofBuilder {
+    sample_types: Vec<(i64, i64)>,
+    /// String table, the first entry is always the empty string.
+    strings: SmallMap<String, i64>,
+    /// Function name index to function (and location) id.
+    functions: SmallMap<i64, u64>,
+    samples: Vec<PprofSample>,
+}
+
+impl PprofBuilder {
+    /// Create a builder for samples with given `(type, unit)` values.
+    pub(crate) fn new(sample_types: &[(&str, &str)]) -> PprofBuilder {
+        let mut builder = PprofBuilder {
+            sample_types: Vec::new(),
+            strings: SmallMap::new(),
+            functions: SmallMap::new(),
+            samples: Vec::new(),
+        };
+        builder.string("");
+        builder.sample_types = sample_types
+            .iter()
+            .map(|(t, unit)| (builder.string(t), builder.string(unit)))
+            .collect();
+        builder
+    }
+
+    fn string(&mut self, s: &str) -> i64 {
+        if let Some(index) = self.strings.get(s) {
+            return *index;
+        }
+        let index = self.strings.len() as i64;
+        self.strings.insert(s.to_owned(), index);
+        index
+    }
+
+    fn location(&mut self, function: &str) -> u64 {
+        let name = self.string(function);
+        let next_id = self.functions.len() as u64 + 1;
+        *self.functions.entry(name).or_insert(next_id)
+    }
+
+    /// Add a sample. `stack` is root first, `values` match sample types.
+    pub(crate) fn add_sample<'s>(
+        &mut self,
+        stack: impl DoubleEndedIterator<Item = &'s str>,
+        values: &[i64],
+        labels: &[(&str, &str)],
+    ) {
+        assert_eq!(self.sample_types.len(), values.len());
+        let locations = stack.rev().map(|f| self.location(f)).collect();
+        let labels = labels
+            .iter()
+            .map(|(k, v)| (self.string(k), self.string(v)))
+            .collect();
+        self.samples.push(PprofSample {
+            locations,
+            values: values.to_vec(),
+            labels,
+        });
+    }
+
+    /// Encode the `Profile` message.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        let mut w = ProtoWriter::default();
+        for (t, unit) in &self.sample_types {
+            w.message(1, |w| {
+                w.int64(1, *t);
+                w.int64(2, *unit);
+            });
+        }
+        for sample in &self.samples {
+            w.message(2, |w| {
+                w.packed(1, sample.locations.iter().copied());
+                w.packed(2, sample.values.iter().map(|v| *v as u64));
+                for (k, v) in &sample.labels {
+                    w.message(3, |w| {
+                        w.int64(1, *k);
+                        w.int64(2, *v);
+                    });
+                }
+            });
+        }
+        for id in self.functions.values() {
+            w.message(4, |w| {
+                w.uint64(1, *id);
+                w.message(4, |w| w.uint64(1, *id));
+            });
+        }
+        for (name, id) in &self.functions {
+            w.message(5, |w| {
+                w.uint64(1, *id);
+                w.int64(2, *name);
+                w.int64(3, *name);
+            });
+        }
+        for s in self.strings.keys() {
+            w.bytes(6, s.as_bytes());
+        }
+        w.buf
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use crate::eval::runtime::profile::pprof::PprofBuilder;
+
+    /// Decoded protobuf field value.
+    #[derive(Debug, Clone, Copy)]
+    enum Field<'a> {
+        Varint(u64),
+        Len(&'a [u8]),
+    }
+
+    fn varint(data: &mut &[u8]) -> u64 {
+        let mut x = 0;
+        let mut shift = 0;
+        loop {
+            let b = data[0];
+            *data = &data[1..];
+            x |= ((b & 0x7f) as u64) << shift;
+            if b < 0x80 {
+                return x;
+            }
+            shift += 7;
+        }
+    }
+
+    fn fields(mut data: &[u8]) -> Vec<(u64, Field)> {
+        let mut r = Vec::new();
+        while !data.is_empty() {
+            let key = varint(&mut data);
+            let value = match key & 7 {
+                0 => Field::Varint(varint(&mut data)),
+                2 => {
+                    let len = varint(&mut data) as usize;
+                    let (bytes, rem) = data.split_at(len);
+                    data = rem;
+                    Field::Len(bytes)
+                }
+                t => panic!("unexpected wire type {}", t),
+            };
+            r.push((key >> 3, value));
+        }
+        r
+    }
+
+    fn varints(data: &[u8], field: u64) -> Vec<u64> {
+        let mut r = Vec::new();
+        for (f, v) in fields(data) {
+            match (f == field, v) {
+                (true, Field::Varint(x)) => r.push(x),
+                (true, Field::Len(mut packed)) => {
+                    while !packed.is_empty() {
+                        r.push(varint(&mut packed));
+                    }
+                }
+                (false, _) => {}
+            }
+        }
+        r
+    }
+
+    fn messages(data: &[u8], field: u64) -> Vec<&[u8]> {
+        fields(data)
+            .into_iter()
+            .filter_map(|(f, v)| match (f == field, v) {
+                (true, Field::Len(bytes)) => Some(bytes),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// pprof profile decoded and checked for consistency.
+    #[derive(Debug)]
+    pub(crate) struct DecodedPprof {
+        /// `(type, unit)`.
+        pub(crate) sample_types: Vec<(String, String)>,
+        /// Function names root first, values, and labels.
+        pub(crate) samples: Vec<(Vec<String>, Vec<i64>, Vec<(String, String)>)>,
+    }
+
+    /// Decode a pprof `Profile`, panicking if it is malformed.
+    pub(crate) fn decode_pprof(data: &[u8]) -> DecodedPprof {
+        let strings: Vec<String> = messages(data, 6)
+            .into_iter()
+            .map(|s| String::from_utf8(s.to_vec()).unwrap())
+            .collect();
+        assert_eq!("", strings[0], "first string must be empty");
+        let string = |i: u64| strings[i as usize].clone();
+
+        let functions: HashMap<u64, String> = messages(data, 5)
+            .into_iter()
+            .map(|f| (varints(f, 1)[0], string(varints(f, 2)[0])))
+            .collect();
+        let locations: HashMap<u64, String> = messages(data, 4)
+            .into_iter()
+            .map(|l| {
+                let lines = messages(l, 4);
+                assert_eq!(1, lines.len());
+                (
+                    varints(l, 1)[0],
+                    functions[&varints(lines[0], 1)[0]].clone(),
+                )
+            })
+            .collect();
+
+        let sample_types: Vec<(String, String)> = messages(data, 1)
+            .into_iter()
+            .map(|t| (string(varints(t, 1)[0]), string(varints(t, 2)[0])))
+            .collect();
+        let samples = messages(data, 2)
+            .into_iter()
+            .map(|s| {
+                let mut stack: Vec<String> = varints(s, 1)
+                    .into_iter()
+                    .map(|id| locations[&id].clone())
+                    .collect();
+                stack.reverse();
+                let values: Vec<i64> = varints(s, 2).into_iter().map(|v| v as i64).collect();
+                assert_eq!(sample_types.len(), values.len());
+                let labels = messages(s, 3)
+                    .into_iter()
+                    .map(|l| (string(varints(l, 1)[0]), string(varints(l, 2)[0])))
+                    .collect();
+                (stack, values, labels)
+            })
+            .collect();
+        DecodedPprof {
+            sample_types,
+            samples,
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut b = PprofBuilder::new(&[("alloc_objects", "count"), ("alloc_space", "bytes")]);
+        b.add_sample(["f", "g"].into_iter(), &[1, 300], &[("type", "list")]);
+        b.add_sample(["f"].into_iter(), &[2, -1], &[]);
+        b.add_sample([].into_iter(), &[0, 0], &[]);
+        let decoded = decode_pprof(&b.finish());
+        assert_eq!(
+            vec![
+                ("alloc_objects".to_owned(), "count".to_owned()),
+                ("alloc_space".to_owned(), "bytes".to_owned())
+            ],
+            decoded.sample_types
+        );
+        assert_eq!(
+            vec![
+                (
+                    vec!["f".to_owned(), "g".to_owned()],
+                    vec![1, 300],
+                    vec![("type".to_owned(), "list".to_owned())]
+                ),
+                (vec!["f".to_owned()], vec![2, -1], vec![]),
+                (vec![], vec![0, 0], vec![]),
+            ],
+            decoded.samples
+        );
+    }
+}
@@ -0,0 +1,244 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Memory retained on the heap by each top-level statement.
+
+use crate::codemap::FileSpanRef;
+use crate::codemap::ResolvedFileSpan;
+use crate::codemap::ResolvedSpan;
+use crate::collections::SmallMap;
+use crate::eval::runtime::profile::binary::BinaryReader;
+use crate::eval::runtime::profile::binary::BinaryWriter;
+use crate::eval::runtime::profile::csv::CsvWriter;
+use crate::eval::runtime::profile::data::ProfileData;
+use crate::eval::runtime::profile::data::ProfileDataImpl;
+use crate::eval::ProfileMode;
+
+#[derive(Debug, thiserror::Error)]
+enum RetainedProfileError {
+    #[error("Retained memory profiling is not enabled")]
+    NotEnabled,
+}
+
+/// Bytes retained by each top-level statement, in execution order.
+/// Negative if the statement released more memory than it retained.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RetainedProfileData {
+    stmts: Vec<(ResolvedFileSpan, i64)>,
+}
+
+impl RetainedProfileData {
+    pub(crate) fn gen_csv(&self) -> String {
+        let mut csv = CsvWriter::new(["Statement", "Retained(bytes)"]);
+        csv.write_value("TOTAL");
+        csv.write_value(self.stmts.iter().map(|(_, bytes)| bytes).sum::<i64>());
+        csv.finish_row();
+
+        for (span, bytes) in &self.stmts {
+            csv.write_display(format_args!("{}:{}", span.file, span.span.begin_line + 1));
+            csv.write_value(*bytes);
+            csv.finish_row();
+        }
+
+        csv.finish()
+    }
+
+    pub(crate) fn write_binary(&self, w: &mut BinaryWriter) {
+        w.usize(self.stmts.len());
+        for (span, bytes) in &self.stmts {
+            w.str(&span.file);
+            w.usize(span.span.begin_line);
+            w.usize(span.span.begin_column);
+            w.usize(span.span.end_line);
+            w.usize(span.span.end_column);
+            w.u64(*bytes as u64);
+        }
+    }
+
+    pub(crate) fn read_binary(r: &mut BinaryReader) -> anyhow::Result<RetainedProfileData> {
+        let mut stmts = Vec::new();
+        for _ in 0..r.len()? {
+            let file = r.str()?.to_owned();
+            let span = ResolvedSpan {
+                begin_line: r.usize()?,
+                begin_column: r.usize()?,
+                end_line: r.usize()?,
+                end_column: r.usize()?,
+            };
+            let bytes = r.u64()? as i64;
+            stmts.push((ResolvedFileSpan { file, span }, bytes));
+        }
+        Ok(RetainedProfileData { stmts })
+    }
+
+    /// Sum the bytes retained by the same statement, ordered by first execution.
+    pub(crate) fn merge<'a>(
+        profiles: impl IntoIterator<Item = &'a RetainedProfileData>,
+    ) -> RetainedProfileData {
+        let mut stmts: SmallMap<ResolvedFileSpan, i64> = SmallMap::new();
+        for profile in profiles {
+            for (span, bytes) in &profile.stmts {
+                *stmts.entry(span.clone()).or_insert(0) += bytes;
+            }
+        }
+        RetainedProfileData {
+            stmts: stmts.into_iter().collect(),
+        }
+    }
+}
+
+pub(crate) struct RetainedProfile {
+    /// Garbage collect before and after each statement.
+    gc: bool,
+    data: Option<Box<RetainedProfileData>>,
+}
+
+impl RetainedProfile {
+    pub(crate) fn new() -> Self {
+        RetainedProfile {
+            gc: true,
+            data: None,
+        }
+    }
+
+    pub(crate) fn enable(&mut self) {
+        self.data = Some(Box::default());
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.data.is_some()
+    }
+
+    pub(crate) fn set_gc(&mut self, gc: bool) {
+        self.gc = gc;
+    }
+
+    pub(crate) fn gc(&self) -> bool {
+        self.gc
+    }
+
+    pub(crate) fn add(&mut self, span: FileSpanRef, bytes: i64) {
+        if let Some(data) = &mut self.data {
+            data.stmts.push((span.to_file_span().resolve(), bytes));
+        }
+    }
+
+    pub(crate) fn gen(&self) -> anyhow::Result<ProfileData> {
+        match &self.data {
+            Some(data) => Ok(ProfileData {
+                profile_mode: ProfileMode::Retained,
+                profile: ProfileDataImpl::Retained(data.clone()),
+            }),
+            None => Err(RetainedProfileError::NotEnabled.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::runtime::profile::data::ProfileDataImpl;
+    use crate::eval::Evaluator;
+    use crate::eval::ProfileData;
+    use crate::eval::ProfileMode;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    /// Evaluate a program, returning the last value and the bytes retained by each line.
+    fn retained(program: &str, gc: bool) -> anyhow::Result<(String, Vec<i64>)> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::Retained)?;
+        eval.set_retained_profile_gc(gc);
+        let program = AstModule::parse("test.star", program.to_owned(), &Dialect::Extended)?;
+        let res = eval.eval_module(program, &Globals::standard())?.to_repr();
+
+        let profile = eval.gen_profile()?;
+        let csv = profile.gen()?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!("Statement,Retained(bytes)", lines[0]);
+        assert!(lines[1].starts_with("\"TOTAL\","), "{:?}", lines[1]);
+        for (i, line) in lines[2..].iter().enumerate() {
+            let prefix = format!("\"test.star:{}\",", i + 1);
+            assert!(line.starts_with(&prefix), "{:?}", line);
+        }
+
+        // Round trip through the binary format.
+        let profile = ProfileData::deserialize_bin(&profile.serialize_bin()?)?;
+        assert_eq!(csv, profile.gen()?);
+
+        match profile.profile {
+            ProfileDataImpl::Retained(data) => Ok((
+                res,
+                data.stmts.into_iter().map(|(_, bytes)| bytes).collect(),
+            )),
+            _ => panic!("Expected retained profile data"),
+        }
+    }
+
+    #[test]
+    fn test_retained_profile() -> anyhow::Result<()> {
+        let (res, bytes) = retained(
+            r#"small = [1]
+garbage = [list(range(10)) for _ in range(1000)]
+garbage = None
+large = [str(x) for x in range(1000)]
+large[0]"#,
+            true,
+        )?;
+        // The result of the last statement survives the collection.
+        assert_eq!("\"0\"", res);
+        assert!(bytes[0] > 0);
+        // The garbage is released by the statement which drops it.
+        assert!(bytes[1] > bytes[3]);
+        assert_eq!(-bytes[1], bytes[2]);
+        assert!(bytes[3] > 10000);
+        assert_eq!(0, bytes[4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retained_profile_without_gc() -> anyhow::Result<()> {
+        let program = "garbage = list(range(100))\ngarbage = None";
+        let (_, with_gc) = retained(program, true)?;
+        assert!(with_gc[0] > 0);
+        assert!(with_gc[1] < 0);
+        // Without a collection, dropping the garbage does not shrink the heap.
+        let (_, without_gc) = retained(program, false)?;
+        assert!(without_gc[0] > 0);
+        assert_eq!(0, without_gc[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retained_profile_merge() -> anyhow::Result<()> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::Retained)?;
+        let program =
+            AstModule::parse("test.star", "x = [1, 2, 3]".to_owned(), &Dialect::Extended)?;
+        eval.eval_module(program, &Globals::standard())?;
+        let profile = eval.gen_profile()?;
+
+        let merged = ProfileData::merge([&profile, &profile])?;
+        let line = |p: &ProfileData| p.gen().unwrap().lines().nth(2).unwrap().to_owned();
+        let bytes: i64 = line(&profile).split(',').nth(1).unwrap().parse()?;
+        assert_eq!(format!("\"test.star:1\",{}", 2 * bytes), line(&merged));
+        Ok(())
+    }
+}
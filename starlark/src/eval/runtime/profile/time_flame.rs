@@ -17,6 +17,7 @@
 
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fmt::Write;
 use std::slice;
 use std::time::Instant;
 
@@ -83,7 +84,10 @@ unsafe impl<'v> Trace<'v> for FlameData<'v> {
 
 struct Stacks<'a> {
     name: &'a str,
+    /// Time spent in this node, excluding children.
     time: SmallDuration,
+    /// Number of times this node was entered.
+    calls: u64,
     children: HashMap<ValueIndex, Stacks<'a>>,
 }
 
@@ -92,6 +96,7 @@ impl<'a> Stacks<'a> {
         Stacks {
             name,
             time: SmallDuration::default(),
+            calls: 0,
             children: HashMap::new(),
         }
     }
@@ -114,12 +119,14 @@ impl<'a> Stacks<'a> {
             *last_time = *time;
             match frame {
                 Frame::Pop => return,
-                Frame::Push(i) => match self.children.entry(*i) {
-                    Entry::Occupied(mut e) => e.get_mut().add(names, frames, last_time),
-                    Entry::Vacant(e) => e
-                        .insert(Stacks::blank(i.lookup(names).as_str()))
-                        .add(names, frames, last_time),
-                },
+                Frame::Push(i) => {
+                    let child = self
+                        .children
+                        .entry(*i)
+                        .or_insert_with(|| Stacks::blank(i.lookup(names).as_str()));
+                    child.calls += 1;
+                    child.add(names, frames, last_time);
+                }
             }
         }
     }
@@ -140,6 +147,52 @@ impl<'a> Stacks<'a> {
         self.render_with_buffer(data.root());
         data
     }
+
+    /// Time spent in this node, including children.
+    fn inclusive_time(&self) -> SmallDuration {
+        self.time
+            + self
+                .children
+                .values()
+                .map(|x| x.inclusive_time())
+                .sum::<SmallDuration>()
+    }
+
+    fn render_call_tree_with_buffer(&self, depth: usize, out: &mut String) {
+        let mut children = Vec::from_iter(self.children.values().map(|x| (x.inclusive_time(), x)));
+        // Most expensive first, ties broken by name to make the output stable.
+        children.sort_by(|(t1, x1), (t2, x2)| {
+            t2.nanos.cmp(&t1.nanos).then_with(|| x1.name.cmp(x2.name))
+        });
+        for (inclusive, x) in children {
+            writeln!(
+                out,
+                "{:>14.9} {:>14.9} {:>8} {:indent$}{}",
+                inclusive.to_duration().as_secs_f64(),
+                x.time.to_duration().as_secs_f64(),
+                x.calls,
+                "",
+                x.name,
+                indent = depth * 2,
+            )
+            .unwrap();
+            x.render_call_tree_with_buffer(depth + 1, out);
+        }
+    }
+
+    /// Render as an indented tree of functions with their callees,
+    /// with inclusive and exclusive time in seconds.
+    fn render_call_tree(&self) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{:>14} {:>14} {:>8} Function",
+            "Inclusive", "Exclusive", "Calls"
+        )
+        .unwrap();
+        self.render_call_tree_with_buffer(0, &mut out);
+        out
+    }
 }
 
 impl<'v> FlameProfile<'v> {
@@ -184,6 +237,20 @@ impl<'v> FlameProfile<'v> {
         }
     }
 
+    /// Generate the [`ProfileMode::TimeCallTree`] profile from the same records.
+    pub(crate) fn gen_call_tree(&self) -> anyhow::Result<ProfileData> {
+        match &self.0 {
+            None => Err(FlameProfileError::NotEnabled.into()),
+            Some(x) => {
+                let names = x.values.map(|x| x.to_repr());
+                Ok(ProfileData::new(
+                    ProfileMode::TimeCallTree,
+                    Stacks::new(&names, &x.frames).render_call_tree(),
+                ))
+            }
+        }
+    }
+
     fn gen_profile(x: &FlameData) -> ProfileData {
         // Need to write out lines which look like:
         // root;calls1;calls2 1
@@ -195,3 +262,94 @@ impl<'v> FlameProfile<'v> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::runtime::profile::time_flame::Frame;
+    use crate::eval::runtime::profile::time_flame::Stacks;
+    use crate::eval::runtime::profile::time_flame::ValueIndex;
+    use crate::eval::Evaluator;
+    use crate::eval::ProfileMode;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_time_call_tree() -> anyhow::Result<()> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::TimeCallTree)?;
+        let program = r#"
+def helper1():
+    return 1
+def helper2():
+    return 2
+def f():
+    return helper1() + helper2() + helper2()
+f()
+"#;
+        let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &Globals::standard())?;
+        let profile = eval.gen_profile()?.gen()?;
+
+        // Inclusive time of each call covers its exclusive time.
+        for line in profile.lines().skip(1) {
+            let times = Vec::from_iter(
+                line.split_whitespace()
+                    .take(2)
+                    .map(|x| x.parse::<f64>().unwrap()),
+            );
+            assert!(times[0] >= times[1], "{}", line);
+        }
+
+        let mut lines = profile.lines();
+        assert_eq!(
+            "Inclusive      Exclusive    Calls Function",
+            lines.next().unwrap().trim()
+        );
+        // Calls and function names indented by call depth, after the timing columns.
+        // The module itself is recorded as a call to `None`.
+        let mut calls = Vec::from_iter(lines.map(|line| line[30..].trim_start()));
+        calls[2..].sort();
+        assert_eq!(
+            vec![
+                "1 None",
+                "1   a.star.f",
+                "1     a.star.helper1",
+                "2     a.star.helper2"
+            ],
+            calls
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_call_tree_render() {
+        let names = ["f".to_owned(), "helper1".to_owned(), "helper2".to_owned()];
+        let start = Instant::now();
+        let at = |nanos| start + Duration::from_nanos(nanos);
+        let frames = [
+            (Frame::Push(ValueIndex(0)), at(0)),
+            (Frame::Push(ValueIndex(1)), at(1_000_001)),
+            (Frame::Pop, at(3_000_003)),
+            (Frame::Push(ValueIndex(2)), at(4_000_004)),
+            (Frame::Pop, at(9_000_009)),
+            (Frame::Push(ValueIndex(2)), at(10_000_010)),
+            (Frame::Pop, at(11_000_011)),
+            (Frame::Pop, at(12_000_012)),
+        ];
+        // Times are not rounded, and callees are ordered by inclusive time,
+        // most expensive first.
+        assert_eq!(
+            "     Inclusive      Exclusive    Calls Function\n\
+             \x20  0.012000012    0.004000004        1 f\n\
+             \x20  0.006000006    0.006000006        2   helper2\n\
+             \x20  0.002000002    0.002000002        1   helper1\n",
+            Stacks::new(&names, &frames).render_call_tree()
+        );
+    }
+}
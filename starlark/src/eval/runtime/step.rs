@@ -0,0 +1,178 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Single-stepping through bytecode instructions.
+
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+
+use crate::codemap::FileSpan;
+use crate::eval::bc::addr::BcPtrAddr;
+use crate::eval::bc::bytecode::Bc;
+use crate::eval::bc::opcode::BcOpcode;
+use crate::eval::Evaluator;
+
+#[derive(Debug, thiserror::Error)]
+enum StepError {
+    #[error("Instruction budget must be positive")]
+    ZeroBudget,
+    #[error("Evaluation aborted while paused")]
+    Aborted,
+}
+
+/// Position at which evaluation is paused by
+/// [`step_instructions`](Evaluator::step_instructions).
+#[derive(Debug, Clone)]
+pub struct StepPause {
+    opcode: BcOpcode,
+    span: FileSpan,
+    executed: u64,
+}
+
+impl StepPause {
+    /// Name of the instruction to be executed next.
+    pub fn opcode_name(&self) -> String {
+        format!("{:?}", self.opcode)
+    }
+
+    /// Span of the instruction to be executed next.
+    pub fn span(&self) -> &FileSpan {
+        &self.span
+    }
+
+    /// Number of instructions executed since stepping was enabled.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+}
+
+enum StepCommand {
+    Resume,
+    Abort,
+}
+
+/// Controls evaluation paused by [`step_instructions`](Evaluator::step_instructions).
+///
+/// A paused evaluation blocks the thread running it, keeping all of its state,
+/// until the handle resumes or aborts it, so the handle is normally used from
+/// another thread.
+pub struct StepHandle {
+    pauses: Receiver<StepPause>,
+    commands: Sender<StepCommand>,
+}
+
+impl StepHandle {
+    /// Wait until evaluation pauses.
+    ///
+    /// Returns `None` once the evaluator is dropped, so no more pauses can happen.
+    pub fn wait(&self) -> Option<StepPause> {
+        self.pauses.recv().ok()
+    }
+
+    /// Resume paused evaluation, which runs until the next pause.
+    pub fn resume(&self) {
+        // Evaluator is gone, nothing to resume.
+        let _ = self.commands.send(StepCommand::Resume);
+    }
+
+    /// Make paused evaluation fail with an error.
+    pub fn abort(&self) {
+        let _ = self.commands.send(StepCommand::Abort);
+    }
+}
+
+/// Configuration of instruction stepping.
+#[derive(Default)]
+pub(crate) struct StepInstructions {
+    /// Number of instructions to execute between pauses.
+    budget: u64,
+    /// Instructions left until the next pause.
+    remaining: u64,
+    /// Instructions executed so far.
+    executed: u64,
+    /// Channels to the [`StepHandle`].
+    handle: Option<(Sender<StepPause>, Receiver<StepCommand>)>,
+}
+
+impl StepInstructions {
+    pub(crate) fn enable(&mut self, budget: u64) -> anyhow::Result<StepHandle> {
+        if budget == 0 {
+            return Err(StepError::ZeroBudget.into());
+        }
+        let (pause_sender, pause_receiver) = channel();
+        let (command_sender, command_receiver) = channel();
+        *self = StepInstructions {
+            budget,
+            remaining: budget,
+            executed: 0,
+            handle: Some((pause_sender, command_receiver)),
+        };
+        Ok(StepHandle {
+            pauses: pause_receiver,
+            commands: command_sender,
+        })
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// Pause until the handle resumes or aborts evaluation.
+    fn pause(&mut self, opcode: BcOpcode, ip: BcPtrAddr) -> anyhow::Result<()> {
+        let (pauses, commands) = self.handle.as_ref().expect("stepping is enabled");
+        let pause = StepPause {
+            opcode,
+            span: Bc::slow_arg_at_ptr(ip).span.span.to_file_span(),
+            executed: self.executed,
+        };
+        let command = match pauses.send(pause) {
+            Ok(()) => commands.recv(),
+            Err(_) => {
+                // Handle is dropped, nobody can resume us, so stop stepping.
+                self.handle = None;
+                return Ok(());
+            }
+        };
+        match command {
+            Ok(StepCommand::Resume) => Ok(()),
+            Ok(StepCommand::Abort) => Err(StepError::Aborted.into()),
+            Err(_) => {
+                self.handle = None;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Called from bytecode before each instruction when stepping is enabled.
+/// `ip` is the address of the `ProfileBc` instruction preceding the instruction
+/// about to be executed.
+pub(crate) fn step_before_instr(
+    eval: &mut Evaluator,
+    opcode: BcOpcode,
+    ip: BcPtrAddr,
+) -> anyhow::Result<()> {
+    let step = &mut eval.step_instructions;
+    if step.remaining == 0 {
+        step.remaining = step.budget;
+        step.pause(opcode, ip)?;
+    }
+    step.remaining -= 1;
+    step.executed += 1;
+    Ok(())
+}
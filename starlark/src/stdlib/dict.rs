@@ -22,6 +22,8 @@ use std::mem;
 use crate as starlark;
 use crate::environment::MethodsBuilder;
 use crate::hint::unlikely;
+use crate::values::dict::view::alloc_view;
+use crate::values::dict::view::DictViewKind;
 use crate::values::dict::DictMut;
 use crate::values::dict::DictRef;
 use crate::values::none::NoneType;
@@ -95,10 +97,18 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#dict·items
     /// ): get list of (key, value) pairs.
     ///
-    /// `D.items()` returns a new list of key/value pairs, one per element in
+    /// `D.items()` returns a view of the key/value pairs, one per element in
     /// dictionary D, in the same order as they would be returned by a `for`
     /// loop.
     ///
+    /// The view reflects later changes to the dictionary. It supports `len`,
+    /// `in` and iteration, and for compatibility with lists can also be
+    /// indexed, sliced, added to a list and compared with a list.
+    ///
+    /// Compatibility note: these methods used to return lists. A view is not a list:
+    /// its type is `"dict_view"`, it has no list methods such as `append` or `index`,
+    /// and it changes when the dictionary does. Use `list(D.items())` for a list snapshot.
+    ///
     /// Examples:
     ///
     /// ```
@@ -107,17 +117,18 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x.items() == [("one", 1), ("two", 2)]
     /// # "#);
     /// ```
-    #[starlark(speculative_exec_safe, return_type = "[(\"\", \"\")]")]
-    fn items<'v>(this: DictRef<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        Ok(heap.alloc_list_iter(this.iter().map(|(k, v)| heap.alloc((k, v)))))
+    #[starlark(speculative_exec_safe, return_type = "\"dict_view\"")]
+    fn items<'v>(this: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(alloc_view(this, DictViewKind::Items, heap))
     }
 
     /// [dict.keys](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#dict·keys
     /// ): get the list of keys of the dictionary.
     ///
-    /// `D.keys()` returns a new list containing the keys of dictionary D, in
+    /// `D.keys()` returns a view of the keys of dictionary D, in
     /// the same order as they would be returned by a `for` loop.
+    /// The view behaves as described for `items`.
     ///
     /// Examples:
     ///
@@ -127,9 +138,9 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x.keys() == ["one", "two"]
     /// # "#);
     /// ```
-    #[starlark(speculative_exec_safe, return_type = "[\"\"]")]
-    fn keys<'v>(this: DictRef<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        Ok(heap.alloc_list_iter(this.keys()))
+    #[starlark(speculative_exec_safe, return_type = "\"dict_view\"")]
+    fn keys<'v>(this: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(alloc_view(this, DictViewKind::Keys, heap))
     }
 
     /// [dict.pop](
@@ -353,9 +364,9 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#dict·values
     /// ): get the list of values of the dictionary.
     ///
-    /// `D.values()` returns a new list containing the dictionary's values, in
+    /// `D.values()` returns a view of the dictionary's values, in
     /// the same order as they would be returned by a `for` loop over the
-    /// dictionary.
+    /// dictionary. The view behaves as described for `items`.
     ///
     /// Examples:
     ///
@@ -365,9 +376,9 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
     /// x.values() == [1, 2]
     /// # "#);
     /// ```
-    #[starlark(speculative_exec_safe, return_type = "[\"\"]")]
-    fn values<'v>(this: DictRef<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        Ok(heap.alloc_list_iter(this.values()))
+    #[starlark(speculative_exec_safe, return_type = "\"dict_view\"")]
+    fn values<'v>(this: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(alloc_view(this, DictViewKind::Values, heap))
     }
 }
 
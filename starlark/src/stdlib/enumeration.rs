@@ -105,6 +105,38 @@ repr(enum_type) # Check it is finite
         );
     }
 
+    #[test]
+    fn test_enum_in() {
+        assert::pass(
+            r#"
+Color = enum("red", "green")
+Other = enum("red", "blue")
+assert_true(Color("red") in Color)
+assert_true(Color("green") in Color)
+assert_true(Color("red") not in Other)
+assert_true(Other("blue") not in Color)
+assert_true("red" not in Color)
+assert_true(1 not in Color)
+"#,
+        );
+
+        let mut a = Assert::new();
+        a.module(
+            "m",
+            r#"
+Color = enum("red", "green")
+red = Color("red")
+"#,
+        );
+        a.pass(
+            r#"
+load('m', 'Color', 'red')
+assert_true(red in Color)
+assert_true(Color("green") in Color)
+"#,
+        );
+    }
+
     #[test]
     fn test_enum_equality() {
         assert::pass(
@@ -66,20 +66,17 @@ fn unpack_pair<'v>(pair: Value<'v>, heap: &'v Heap) -> anyhow::Result<(Value<'v>
     })?
 }
 
+/// Greatest (or least) element of the iterator, or `None` if it is empty.
 fn min_max_iter<'v>(
     mut it: impl Iterator<Item = Value<'v>>,
     key: Option<Value<'v>>,
     eval: &mut Evaluator<'v, '_>,
     // Select min on true, max on false.
     min: bool,
-) -> anyhow::Result<Value<'v>> {
+) -> anyhow::Result<Option<Value<'v>>> {
     let mut max = match it.next() {
         Some(x) => x,
-        None => {
-            return Err(anyhow::anyhow!(
-                "Argument is an empty iterable, max() expect a non empty iterable"
-            ));
-        }
+        None => return Ok(None),
     };
     let update_max_ordering = if min {
         Ordering::Greater
@@ -105,22 +102,73 @@ fn min_max_iter<'v>(
             }
         }
     };
-    Ok(max)
+    Ok(Some(max))
 }
 
 /// Common implementation of `min` and `max`.
 fn min_max<'v>(
     mut args: Vec<Value<'v>>,
     key: Option<Value<'v>>,
+    default: Option<Value<'v>>,
     eval: &mut Evaluator<'v, '_>,
     // Select min on true, max on false.
     min: bool,
 ) -> anyhow::Result<Value<'v>> {
-    if args.len() == 1 {
-        args.swap_remove(0)
-            .with_iterator(eval.heap(), |it| min_max_iter(it, key, eval, min))?
+    let name = if min { "min" } else { "max" };
+    match args.len() {
+        0 => Err(anyhow::anyhow!(
+            "{}() expected at least 1 argument, got 0",
+            name
+        )),
+        1 => {
+            let res = args
+                .swap_remove(0)
+                .with_iterator(eval.heap(), |it| min_max_iter(it, key, eval, min))??;
+            match (res, default) {
+                (Some(x), _) => Ok(x),
+                (None, Some(default)) => Ok(default),
+                (None, None) => Err(anyhow::anyhow!(
+                    "Argument is an empty iterable, {}() expect a non empty iterable",
+                    name
+                )),
+            }
+        }
+        _ => {
+            if default.is_some() {
+                return Err(anyhow::anyhow!(
+                    "Cannot specify a default for {}() with multiple positional arguments",
+                    name
+                ));
+            }
+            // Non-empty, so there is always a result.
+            Ok(min_max_iter(args.into_iter(), key, eval, min)?.unwrap())
+        }
+    }
+}
+
+/// Class of values which are compared with each other with [`Evaluator::set_total_order`]:
+/// all numbers are one class, other values are classed by type.
+fn total_order_class(x: Value) -> &'static str {
+    if x.unpack_num().is_some() {
+        "number"
     } else {
-        min_max_iter(args.into_iter(), key, eval, min)
+        x.get_type()
+    }
+}
+
+/// Compare values for `sorted` with [`Evaluator::set_total_order`] enabled:
+/// values are ordered by class name first, then compared within the class.
+/// Incomparable values of the same class are ordered by hash.
+fn compare_total<'v>(x: Value<'v>, y: Value<'v>) -> anyhow::Result<Ordering> {
+    match total_order_class(x).cmp(total_order_class(y)) {
+        Ordering::Equal => match x.compare(y) {
+            Ok(ord) => Ok(ord),
+            Err(e) => match (x.get_hash(), y.get_hash()) {
+                (Ok(a), Ok(b)) => Ok(a.get().cmp(&b.get())),
+                _ => Err(e),
+            },
+        },
+        ord => Ok(ord),
     }
 }
 
@@ -365,9 +413,13 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#dir
     /// ): list attributes of a value.
     ///
-    /// `dir(x)` returns a list of the names of the attributes (fields and
+    /// `dir(x)` returns a sorted list of the names of the attributes (fields and
     /// methods) of its operand. The attributes of a value `x` are the names
-    /// `f` such that `x.f` is a valid expression.
+    /// `f` such that `x.f` is a valid expression, and `hasattr(x, f)` is `True`.
+    ///
+    /// `dir()` with no argument returns the sorted names of the variables
+    /// currently in scope: the local variables inside a function, otherwise
+    /// the module variables assigned so far.
     ///
     /// Examples:
     ///
@@ -376,9 +428,22 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// "capitalize" in dir("abc")
     /// # "#);
     /// ```
-    #[starlark(speculative_exec_safe)]
-    fn dir(#[starlark(require = pos)] x: Value) -> anyhow::Result<Vec<String>> {
-        Ok(x.dir_attr())
+    fn dir<'v>(
+        #[starlark(require = pos)] x: Option<Value<'v>>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Vec<String>> {
+        match x {
+            Some(x) => Ok(x.dir_attr()),
+            None => {
+                let mut names: Vec<String> = eval
+                    .local_variables()
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                names.sort();
+                Ok(names)
+            }
+        }
     }
 
     /// [enumerate](
@@ -776,7 +841,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             if let Some(xs) = ListRef::from_value(a) {
                 heap.alloc_list(xs.content())
             } else {
-                a.with_iterator(heap, |it| heap.alloc(AllocList(it)))?
+                a.with_iterator(heap, |it| heap.try_alloc_list_iter(it))??
             }
         } else {
             heap.alloc(AllocList::EMPTY)
@@ -788,27 +853,35 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// ): returns the maximum of a sequence.
     ///
     /// `max(x)` returns the greatest element in the iterable sequence x.
+    /// `max(a, b, ...)` returns the greatest of its positional arguments.
     ///
     /// It is an error if any element does not support ordered comparison,
-    /// or if the sequence is empty.
+    /// or if the sequence is empty and no `default` is given.
     ///
     /// The optional named parameter `key` specifies a function to be applied
-    /// to each element prior to comparison.
+    /// to each element prior to comparison. The optional named parameter
+    /// `default` is returned for an empty iterable, and may only be used
+    /// with a single positional argument.
     ///
     /// ```
     /// # starlark::assert::all_true(r#"
     /// max([3, 1, 4, 1, 5, 9])               == 9
     /// max("two", "three", "four")           == "two"    # the lexicographically greatest
     /// max("two", "three", "four", key=len)  == "three"  # the longest
+    /// max([], default=0)                    == 0
     /// # "#);
+    /// # starlark::assert::fail(r#"
+    /// max([])    # error: empty iterable
+    /// # "#, "empty iterable");
     /// ```
     #[starlark(speculative_exec_safe)]
     fn max<'v>(
         #[starlark(args)] mut args: Vec<Value<'v>>,
         key: Option<Value<'v>>,
+        default: Option<Value<'v>>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        min_max(args, key, eval, false)
+        min_max(args, key, default, eval, false)
     }
 
     /// [min](
@@ -816,24 +889,29 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// ): returns the minimum of a sequence.
     ///
     /// `min(x)` returns the least element in the iterable sequence x.
+    /// `min(a, b, ...)` returns the least of its positional arguments.
     ///
     /// It is an error if any element does not support ordered comparison,
-    /// or if the sequence is empty.
+    /// or if the sequence is empty and no `default` is given.
+    ///
+    /// The named parameters `key` and `default` behave as for `max`.
     ///
     /// ```
     /// # starlark::assert::all_true(r#"
     /// min([3, 1, 4, 1, 5, 9])                 == 1
     /// min("two", "three", "four")             == "four"  # the lexicographically least
     /// min("two", "three", "four", key=len)    == "two"   # the shortest
+    /// min([], default=None)                   == None
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
     fn min<'v>(
         #[starlark(args)] mut args: Vec<Value<'v>>,
         key: Option<Value<'v>>,
+        default: Option<Value<'v>>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        min_max(args, key, eval, true)
+        min_max(args, key, default, eval, true)
     }
 
     /// [ord](
@@ -1020,12 +1098,17 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         };
 
         let mut compare_ok = Ok(());
+        let compare: fn(Value<'v>, Value<'v>) -> anyhow::Result<Ordering> = if eval.total_order {
+            compare_total
+        } else {
+            Value::compare
+        };
 
         it.sort_by(|x: &(Value, Value), y: &(Value, Value)| {
             let ord_or_err = if reverse {
-                x.1.compare(y.1).map(Ordering::reverse)
+                compare(x.1, y.1).map(Ordering::reverse)
             } else {
-                x.1.compare(y.1)
+                compare(x.1, y.1)
             };
             match ord_or_err {
                 Ok(r) => r,
@@ -1179,6 +1262,33 @@ mod tests {
         assert::fail("chr(-1)", "negative");
     }
 
+    #[test]
+    fn test_dir() {
+        assert::pass(
+            r#"
+s = struct(b = 1, a = "x")
+assert_eq(dir(s), ["a", "b"])
+assert_eq(dir("abc"), sorted(dir("abc")))
+assert_true("capitalize" in dir("abc"))
+assert_true("join" in dir(""))
+assert_eq([], [(v, n) for v in [s, "abc", [], {}, 1] for n in dir(v) if not hasattr(v, n)])
+"#,
+        );
+        assert::pass(
+            r#"
+x = 1
+y = 2
+def f(a):
+    b = a
+    return dir()
+names = dir()
+assert_eq(names, ["f", "x", "y"])
+assert_eq(f(1), ["a", "b"])
+z = 3
+"#,
+        );
+    }
+
     #[test]
     fn test_hash() {
         assert::eq("0", "hash('')");
@@ -1222,6 +1332,26 @@ hash(foo)
         assert::fail("int('-2147483649')", "overflow");
     }
 
+    #[test]
+    fn test_min_max() {
+        assert::all_true(
+            r#"
+max(1, 5, 3) == 5
+min(1, 5, 3) == 1
+max(2, 7) == 7
+max([], default=4) == 4
+min((), default=None) == None
+max([1, 2], default=9) == 2
+max(-3, 2, key=lambda x: x * x) == -3
+min([-3, 2], key=lambda x: x * x, default=0) == 2
+"#,
+        );
+        assert::fail("max()", "max() expected at least 1 argument, got 0");
+        assert::fail("min()", "min() expected at least 1 argument, got 0");
+        assert::fail("min([])", "min() expect a non empty iterable");
+        assert::fail("max(1, 2, default=0)", "Cannot specify a default for max()");
+    }
+
     #[test]
     fn test_tuple() {
         assert::eq("(1, 2)", "tuple((1, 2))");
@@ -0,0 +1,137 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Methods for the `int` type.
+
+use num_bigint::BigUint;
+
+use crate as starlark;
+use crate::environment::MethodsBuilder;
+use crate::values::num::Num;
+use crate::values::Value;
+
+/// Absolute value of an int, either small or big.
+enum Magnitude<'v> {
+    Small(u32),
+    Big(&'v BigUint),
+}
+
+impl<'v> Magnitude<'v> {
+    fn of(x: Value<'v>) -> Magnitude<'v> {
+        match x.unpack_num() {
+            Some(Num::Int(x)) => Magnitude::Small(x.unsigned_abs()),
+            Some(Num::BigInt(x)) => Magnitude::Big(x.get().magnitude()),
+            _ => unreachable!("int methods are only defined for int"),
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn int_methods(builder: &mut MethodsBuilder) {
+    /// [int.bit_length](
+    /// https://docs.python.org/3/library/stdtypes.html#int.bit_length
+    /// ): number of bits needed to represent an integer.
+    ///
+    /// `x.bit_length()` returns the number of bits necessary to represent
+    /// the absolute value of `x` in binary, excluding the sign and leading zeros.
+    /// `(0).bit_length()` is `0`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// (0).bit_length() == 0
+    /// (5).bit_length() == 3
+    /// (-5).bit_length() == 3
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn bit_length(this: Value) -> anyhow::Result<i32> {
+        Ok(match Magnitude::of(this) {
+            Magnitude::Small(x) => (u32::BITS - x.leading_zeros()) as i32,
+            Magnitude::Big(x) => i32::try_from(x.bits())?,
+        })
+    }
+
+    /// [int.bit_count](
+    /// https://docs.python.org/3/library/stdtypes.html#int.bit_count
+    /// ): number of ones in the binary representation of an integer.
+    ///
+    /// `x.bit_count()` returns the number of ones in the binary representation
+    /// of the absolute value of `x`, also known as the population count.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// (0).bit_count() == 0
+    /// (13).bit_count() == 3
+    /// (-13).bit_count() == 3
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn bit_count(this: Value) -> anyhow::Result<i32> {
+        Ok(match Magnitude::of(this) {
+            Magnitude::Small(x) => x.count_ones() as i32,
+            Magnitude::Big(x) => i32::try_from(x.count_ones())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_bit_length() {
+        assert::all_true(
+            r#"
+(0).bit_length() == 0
+(1).bit_length() == 1
+(255).bit_length() == 8
+(256).bit_length() == 9
+(-1).bit_length() == 1
+(-256).bit_length() == 9
+(2147483647).bit_length() == 31
+(-2147483648).bit_length() == 32
+(1 << 100).bit_length() == 101
+(-(1 << 100)).bit_length() == 101
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bit_count() {
+        assert::all_true(
+            r#"
+(0).bit_count() == 0
+(1).bit_count() == 1
+(255).bit_count() == 8
+(-255).bit_count() == 8
+(-2147483648).bit_count() == 1
+((1 << 100) - 1).bit_count() == 100
+(-(1 << 100)).bit_count() == 1
+"#,
+        );
+    }
+
+    #[test]
+    fn test_int_methods_on_other_types() {
+        assert::fail("(1.0).bit_length()", "has no attribute `bit_length`");
+        assert::fail("True.bit_count()", "has no attribute `bit_count`");
+    }
+}
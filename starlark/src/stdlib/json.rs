@@ -15,17 +15,23 @@
  * limitations under the License.
  */
 
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 
-use gazebo::prelude::*;
 use num_bigint::BigInt;
+use serde::de::DeserializeSeed;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::Deserializer;
 use thiserror::Error;
 
 use crate as starlark;
 use crate::collections::SmallMap;
 use crate::environment::GlobalsBuilder;
 use crate::values::dict::Dict;
-use crate::values::list::AllocList;
 use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::Heap;
 use crate::values::Value;
@@ -36,37 +42,150 @@ enum JsonError {
     UnrepresentableNumber(String),
 }
 
-fn serde_to_starlark<'v>(x: serde_json::Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-    match x {
-        serde_json::Value::Null => Ok(Value::new_none()),
-        serde_json::Value::Bool(x) => Ok(Value::new_bool(x)),
-        serde_json::Value::Number(x) => {
-            if let Some(x) = x.as_u64() {
-                Ok(heap.alloc(x))
-            } else if let Some(x) = x.as_f64() {
-                Ok(heap.alloc(x))
-            } else if let Ok(x) = BigInt::from_str(&x.to_string()) {
-                Ok(StarlarkBigInt::alloc_bigint(x, heap))
-            } else {
-                Err(JsonError::UnrepresentableNumber(x.to_string()).into())
+/// Number literals of the JSON text `x`, in document order.
+///
+/// `serde_json` decodes integers which don't fit in 64 bits as floats,
+/// so the literals are used to decode them exactly.
+fn json_number_literals(x: &str) -> Vec<&str> {
+    let mut literals = Vec::new();
+    let mut chars = x.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
             }
+            '-' | '0'..='9' => {
+                let mut end = start + 1;
+                while let Some((i, c)) = chars.peek().copied() {
+                    if !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                literals.push(&x[start..end]);
+            }
+            _ => {}
         }
-        serde_json::Value::String(x) => Ok(heap.alloc(x)),
-        serde_json::Value::Array(x) => {
-            Ok(heap.alloc(AllocList(x.into_try_map(|v| serde_to_starlark(v, heap))?)))
-        }
-        serde_json::Value::Object(x) => {
-            let mut mp = SmallMap::with_capacity(x.len());
-            for (k, v) in x {
-                let k = heap.alloc_str(&k).get_hashed_value();
-                let v = serde_to_starlark(v, heap)?;
-                mp.insert_hashed(k, v);
+    }
+    literals
+}
+
+/// Decode JSON into a Starlark value, consuming a number literal for each number.
+#[derive(Clone, Copy)]
+struct JsonToStarlark<'v, 'a> {
+    heap: &'v Heap,
+    numbers: &'a [&'a str],
+    next_number: &'a Cell<usize>,
+}
+
+impl<'v, 'a> JsonToStarlark<'v, 'a> {
+    fn next_number(&self) -> Option<&'a str> {
+        let i = self.next_number.get();
+        self.next_number.set(i + 1);
+        self.numbers.get(i).copied()
+    }
+}
+
+impl<'de, 'v, 'a> DeserializeSeed<'de> for JsonToStarlark<'v, 'a> {
+    type Value = Value<'v>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value<'v>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'v, 'a> Visitor<'de> for JsonToStarlark<'v, 'a> {
+    type Value = Value<'v>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value<'v>, E> {
+        Ok(Value::new_none())
+    }
+
+    fn visit_bool<E>(self, x: bool) -> Result<Value<'v>, E> {
+        Ok(Value::new_bool(x))
+    }
+
+    fn visit_u64<E>(self, x: u64) -> Result<Value<'v>, E> {
+        self.next_number();
+        Ok(self.heap.alloc(x))
+    }
+
+    fn visit_i64<E>(self, x: i64) -> Result<Value<'v>, E> {
+        self.next_number();
+        Ok(self.heap.alloc(x))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, x: f64) -> Result<Value<'v>, E> {
+        // Integers too large for 64 bits stay exact rather than
+        // being rounded to a float.
+        if let Some(literal) = self.next_number() {
+            if !literal.contains(['.', 'e', 'E']) {
+                if let Ok(x) = BigInt::from_str(literal) {
+                    return Ok(StarlarkBigInt::alloc_bigint(x, self.heap));
+                }
             }
-            Ok(heap.alloc(Dict::new(mp)))
         }
+        if x.is_finite() {
+            Ok(self.heap.alloc(x))
+        } else {
+            Err(E::custom(JsonError::UnrepresentableNumber(x.to_string())))
+        }
+    }
+
+    fn visit_str<E>(self, x: &str) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(x))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value<'v>, A::Error> {
+        let mut xs = Vec::new();
+        while let Some(x) = seq.next_element_seed(self)? {
+            xs.push(x);
+        }
+        Ok(self.heap.alloc_list(&xs))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value<'v>, A::Error> {
+        // Keys are sorted, like in `serde_json::Map`.
+        let mut entries = BTreeMap::new();
+        while let Some(k) = map.next_key::<String>()? {
+            let v = map.next_value_seed(self)?;
+            entries.insert(k, v);
+        }
+        let mut mp = SmallMap::with_capacity(entries.len());
+        for (k, v) in entries {
+            mp.insert_hashed(self.heap.alloc_str(&k).get_hashed_value(), v);
+        }
+        Ok(self.heap.alloc(Dict::new(mp)))
     }
 }
 
+fn json_to_starlark<'v>(x: &str, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+    let numbers = json_number_literals(x);
+    let next_number = Cell::new(0);
+    let seed = JsonToStarlark {
+        heap,
+        numbers: &numbers,
+        next_number: &next_number,
+    };
+    let mut deserializer = serde_json::Deserializer::from_str(x);
+    let value = seed.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
 pub(crate) fn json(globals: &mut GlobalsBuilder) {
     #[starlark_module]
     fn json_members(globals: &mut GlobalsBuilder) {
@@ -78,7 +197,7 @@ pub(crate) fn json(globals: &mut GlobalsBuilder) {
             #[starlark(require = pos)] x: &str,
             heap: &'v Heap,
         ) -> anyhow::Result<Value<'v>> {
-            serde_to_starlark(serde_json::from_str(x)?, heap)
+            json_to_starlark(x, heap)
         }
     }
 
@@ -111,5 +230,11 @@ mod tests {
             "123456789123456789123456789",
             "json.decode('123456789123456789123456789')",
         );
+        a.eq("-5", "json.decode('-5')");
+        a.eq("1e30", "json.decode('1e30')");
+        a.eq(
+            "[-123456789123456789123456789, 1.5, {'a': 'x\\\"1', 'b': 123456789123456789123456789}]",
+            r#"json.decode('[-123456789123456789123456789, 1.5, {"b": 123456789123456789123456789, "a": "x\\"1"}]')"#,
+        );
     }
 }
@@ -82,6 +82,34 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
         Ok(NoneType)
     }
 
+    /// [list.count](
+    /// https://docs.python.org/3/library/stdtypes.html#common-sequence-operations
+    /// ): count the occurrences of an element in a list.
+    ///
+    /// `L.count(x)` returns the number of elements of the list L equal to `x`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// [1, 2, 1, 1].count(1) == 3
+    /// [1, 2, 1, 1].count(3) == 0
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn count<'v>(
+        this: &ListRef<'v>,
+        #[starlark(require = pos)] needle: Value<'v>,
+    ) -> anyhow::Result<i32> {
+        let mut count = 0;
+        for x in this.iter() {
+            if x.equals(needle)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
     /// [list.extend](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#list·extend
     /// ): extend a list with another iterable's content.
@@ -113,9 +141,7 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
             // But we can do something smarter to double the elements
             res.double(heap);
         } else {
-            other.with_iterator(heap, |it| {
-                res.extend(it, heap);
-            })?;
+            other.with_iterator(heap, |it| res.try_extend(it, heap))??;
         }
         Ok(NoneType)
     }
@@ -329,6 +355,20 @@ mod tests {
         assert::fail("[True].index(True, 1, 0)", "not found");
     }
 
+    #[test]
+    fn test_count() {
+        assert::all_true(
+            r#"
+[].count(1) == 0
+[1, 2, 3].count(4) == 0
+["a", "b", "a"].count("a") == 2
+[[1], [1], (1,)].count([1]) == 2
+[1, 1.0, True].count(1) == 2
+"#,
+        );
+        assert::is_true("x = [1, 2]\nl = [x, [1, 2], x]\nl.count(x) == 3");
+    }
+
     #[test]
     fn recursive_list() {
         assert::is_true(
@@ -0,0 +1,115 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! The `math` module.
+
+use thiserror::Error;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::num::Num;
+
+#[derive(Debug, Error)]
+enum MathError {
+    #[error("Tolerances must be non-negative, got rel_tol={0}, abs_tol={1}")]
+    NegativeTolerance(f64, f64),
+}
+
+/// Python's `math.isclose` algorithm.
+fn is_close(a: f64, b: f64, rel_tol: f64, abs_tol: f64) -> bool {
+    if a == b {
+        // Equal infinities.
+        return true;
+    }
+    if a.is_infinite() || b.is_infinite() {
+        return false;
+    }
+    // NaN fails all the comparisons.
+    let diff = (b - a).abs();
+    diff <= (rel_tol * b).abs() || diff <= (rel_tol * a).abs() || diff <= abs_tol
+}
+
+pub(crate) fn math(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn math_members(globals: &mut GlobalsBuilder) {
+        /// Whether `a` and `b` are close to each other: their difference is at most
+        /// `rel_tol` relative to the larger of them, or at most `abs_tol`.
+        ///
+        /// Infinities are only close to themselves, and `NaN` is not close to anything.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// math.isclose(0.1 + 0.2, 0.3)
+        /// not math.isclose(1.0, 1.1)
+        /// math.isclose(1.0, 1.1, rel_tol=0.1)
+        /// math.isclose(0.0, 1e-10, abs_tol=1e-9)
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn isclose(
+            #[starlark(require = pos)] a: Num,
+            #[starlark(require = pos)] b: Num,
+            #[starlark(require = named)] rel_tol: Option<Num>,
+            #[starlark(require = named)] abs_tol: Option<Num>,
+        ) -> anyhow::Result<bool> {
+            let rel_tol = rel_tol.map_or(1e-9, |x| x.as_float());
+            let abs_tol = abs_tol.map_or(0.0, |x| x.as_float());
+            if rel_tol < 0.0 || abs_tol < 0.0 {
+                return Err(MathError::NegativeTolerance(rel_tol, abs_tol).into());
+            }
+            Ok(is_close(a.as_float(), b.as_float(), rel_tol, abs_tol))
+        }
+    }
+
+    // Following Python's math module: https://docs.python.org/3/library/math.html
+    globals.struct_("math", math_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_isclose() {
+        assert::all_true(
+            r#"
+math.isclose(1.0, 1.0)
+math.isclose(1, 1.0 + 1e-10)
+math.isclose(1e30, 1e30 + 1e20)
+math.isclose(100, 101, rel_tol=0.01)
+math.isclose(0.0, 1e-10, abs_tol=1e-9)
+math.isclose(123456789123456789123456789, 123456789123456789123456788)
+not math.isclose(1.0, 1.0001)
+not math.isclose(0.0, 1e-10)
+not math.isclose(100, 102, rel_tol=0.01)
+not math.isclose(1, 2, abs_tol=0.5)
+math.isclose(float("inf"), float("inf"))
+math.isclose(float("-inf"), float("-inf"))
+not math.isclose(float("inf"), float("-inf"))
+not math.isclose(float("inf"), 1e308)
+not math.isclose(float("inf"), 1.0, abs_tol=float("inf"))
+not math.isclose(float("nan"), float("nan"))
+not math.isclose(float("nan"), 1.0, abs_tol=float("inf"))
+"#,
+        );
+        assert::fail(
+            "math.isclose(1, 1, rel_tol=-1)",
+            "Tolerances must be non-negative",
+        );
+        assert::fail("math.isclose(1, 1, 0.1)", "extra positional argument");
+        assert::fail("math.isclose(1, '1')", "expected `int or float`");
+    }
+}
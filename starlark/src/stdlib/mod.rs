@@ -27,12 +27,15 @@ pub(crate) mod dict;
 pub(crate) mod enumeration;
 pub(crate) mod extra;
 mod funcs;
+pub(crate) mod int;
 pub(crate) mod json;
 
 pub(crate) mod list;
+pub(crate) mod math;
 pub(crate) mod record;
 pub(crate) mod string;
 pub(crate) mod structs;
+pub(crate) mod tuple;
 pub(crate) mod util;
 
 pub use extra::PrintHandler;
@@ -78,6 +81,8 @@ pub enum LibraryExtension {
     Json,
     /// Add a function `abs()` which will take the absolute value of an int.
     Abs,
+    /// Add a `math` module with functions like `math.isclose(a, b)`.
+    Math,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -99,6 +104,7 @@ impl LibraryExtension {
             Breakpoint,
             Json,
             Abs,
+            Math,
         ]
     }
 
@@ -119,6 +125,7 @@ impl LibraryExtension {
             Breakpoint => breakpoint::global(builder),
             Json => json::json(builder),
             Abs => extra::abs(builder),
+            Math => math::math(builder),
         }
     }
 }
@@ -17,8 +17,6 @@
 
 //! Methods for the `string` type.
 
-use std::cmp;
-
 use gazebo::prelude::*;
 
 use crate as starlark;
@@ -26,6 +24,7 @@ use crate::environment::MethodsBuilder;
 use crate::eval::Arguments;
 use crate::eval::Evaluator;
 use crate::stdlib::string::fast_string::convert_str_indices;
+use crate::values::dict::DictRef;
 use crate::values::none::NoneOr;
 use crate::values::string::fast_string;
 use crate::values::string::interpolation;
@@ -33,40 +32,16 @@ use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::types::string::fast_string::StrIndices;
 use crate::values::types::string::iter::iterate_chars;
 use crate::values::types::string::iter::iterate_codepoints;
+use crate::values::types::string::iter::iterate_lines;
+use crate::values::types::string::iter::iterate_split;
+use crate::values::types::string::iter::Lines;
+use crate::values::types::string::iter::SplitnWhitespace;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::Heap;
 use crate::values::StringValue;
 use crate::values::UnpackValue;
 use crate::values::Value;
 
-// This does not exists in rust, split would cut the string incorrectly and
-// split_whitespace cannot take a n parameter.
-fn splitn_whitespace(s: &str, maxsplit: usize) -> Vec<String> {
-    let mut v = Vec::new();
-    let mut cur = String::new();
-    let mut split = 1;
-    let mut eat_ws = true;
-    for c in s.chars() {
-        if split >= maxsplit && !eat_ws {
-            cur.push(c)
-        } else if c.is_whitespace() {
-            if !cur.is_empty() {
-                v.push(cur);
-                cur = String::new();
-                split += 1;
-                eat_ws = true;
-            }
-        } else {
-            eat_ws = false;
-            cur.push(c)
-        }
-    }
-    if !cur.is_empty() {
-        v.push(cur)
-    }
-    v
-}
-
 fn rsplitn_whitespace(s: &str, maxsplit: usize) -> Vec<String> {
     let mut v = Vec::new();
     let mut cur = String::new();
@@ -94,6 +69,31 @@ fn rsplitn_whitespace(s: &str, maxsplit: usize) -> Vec<String> {
     v
 }
 
+/// Python considers characters in the Unicode "Other" and "Separator"
+/// categories non-printable, except for the ASCII space. Without the Unicode
+/// tables we check control characters, whitespace and the common format characters.
+fn is_printable(c: char) -> bool {
+    if c == ' ' {
+        return true;
+    }
+    !(c.is_control()
+        || c.is_whitespace()
+        || matches!(
+            c,
+            '\u{AD}'
+                | '\u{600}'..='\u{605}'
+                | '\u{61C}'
+                | '\u{180E}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{2066}'..='\u{206F}'
+                | '\u{FEFF}'
+                | '\u{FFF9}'..='\u{FFFB}'
+                | '\u{E000}'..='\u{F8FF}'
+        ))
+}
+
 enum StringOrTuple<'v> {
     String(&'v str),
     Tuple(Vec<&'v str>),
@@ -337,6 +337,9 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// The *field name* may be either a decimal number or a keyword.
     /// A number is interpreted as the index of a positional argument;
     /// a keyword specifies the value of a keyword argument.
+    /// If there is no such keyword argument, but there is exactly one
+    /// positional argument, the keyword names its attribute,
+    /// e.g. `"{x}".format(struct(x = 1))`.
     /// If all the numeric field names form the sequence 0, 1, 2, and so on,
     /// they may be omitted and those values will be implied; however,
     /// the explicit and implicit forms may not be mixed.
@@ -347,8 +350,10 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// the default.
     ///
     /// The *format specifier*, after a colon, specifies field width,
-    /// alignment, padding, and numeric precision.
-    /// Currently it must be empty, but it is reserved for future use.
+    /// alignment, padding, digit grouping (`,` or `_`) and numeric precision,
+    /// using the syntax `[[fill]align][0][width][grouping][.precision][type]`. Width and precision
+    /// may themselves be replacement fields, e.g. `{:{width}}`, but
+    /// these may not be nested any further.
     ///
     /// Examples:
     ///
@@ -359,6 +364,9 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// "a{}b{}c".format(1, 2) == "a1b2c"
     /// "({1}, {0})".format("zero", "one") == "(one, zero)"
     /// "Is {0!r} {0!s}?".format("heterological") == "Is \"heterological\" heterological?"
+    /// "[{:>5}]".format("ab") == "[   ab]"
+    /// "{:.{p}f}".format(3.14159, p=2) == "3.14"
+    /// "{:,}".format(1234567) == "1,234,567"
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
@@ -377,6 +385,40 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         )
     }
 
+    /// [string.format_map](
+    /// https://docs.python.org/3/library/stdtypes.html#str.format_map
+    /// ): format a string using the fields of a dictionary.
+    ///
+    /// `S.format_map(mapping)` is like `S.format(**mapping)`: replacement
+    /// fields are named keys of `mapping`, and positional fields are not allowed.
+    ///
+    /// With `safe = True`, a replacement field whose key is missing from
+    /// `mapping` is kept in the result intact instead of failing.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// "{a}-{b}".format_map({"a": 1, "b": 2}) == "1-2"
+    /// "{a}-{b:>3}".format_map({"a": 1}, safe = True) == "1-{b:>3}"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn format_map<'v>(
+        this: &str,
+        #[starlark(require = pos)] mapping: DictRef<'v>,
+        #[starlark(require = named, default = false)] safe: bool,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StringValue<'v>> {
+        interpolation::format_map(
+            this,
+            &mapping,
+            safe,
+            &mut eval.string_pool,
+            eval.module_env.heap(),
+        )
+    }
+
     /// [string.index](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·index
     /// ): search a substring inside a string, failing on not found.
@@ -502,6 +544,35 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         Ok(true)
     }
 
+    /// [string.isidentifier](
+    /// https://docs.python.org/3/library/stdtypes.html#str.isidentifier
+    /// ): test if a string is a valid identifier.
+    ///
+    /// `S.isidentifier()` reports whether the string S is non-empty, consists
+    /// only of Unicode letters, digits and underscores, and does not start
+    /// with a digit. Keywords are identifiers by this definition.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// "foo_bar2".isidentifier() == True
+    /// "_".isidentifier() == True
+    /// "2x".isidentifier() == False
+    /// "foo-bar".isidentifier() == False
+    /// "".isidentifier() == False
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn isidentifier(this: &str) -> anyhow::Result<bool> {
+        let mut chars = this.chars();
+        match chars.next() {
+            Some(c) if c.is_alphabetic() || c == '_' => {}
+            _ => return Ok(false),
+        }
+        Ok(chars.all(|c| c.is_alphanumeric() || c == '_'))
+    }
+
     /// [string.islower](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·islower
     /// ): test if all letters of a string are lowercase.
@@ -531,6 +602,28 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         Ok(result)
     }
 
+    /// [string.isprintable](
+    /// https://docs.python.org/3/library/stdtypes.html#str.isprintable
+    /// ): test if all characters of a string are printable.
+    ///
+    /// `S.isprintable()` reports whether the string S contains no control,
+    /// format or separator characters other than the ASCII space.
+    /// The empty string is printable.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// "hello, world".isprintable() == True
+    /// "tab\there".isprintable() == False
+    /// "".isprintable() == True
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn isprintable(this: &str) -> anyhow::Result<bool> {
+        Ok(this.chars().all(is_printable))
+    }
+
     /// [string.isspace](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·isspace
     /// ): test if all characters of a string are whitespaces.
@@ -632,6 +725,59 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         Ok(result)
     }
 
+    /// string.iter_split: split a string in substrings lazily.
+    ///
+    /// `S.iter_split([sep [, maxsplit]])` returns an iterable over the same
+    /// substrings as `S.split(sep, maxsplit)`, producing each piece only
+    /// when it is reached, so the pieces of a large string are never all
+    /// allocated at once.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// list("one two  three".iter_split()) == ["one", "two", "three"]
+    /// list("banana".iter_split("n", 1)) == ["ba", "ana"]
+    /// # "#);
+    /// ```
+    #[starlark(return_type = "iter(str.type)")]
+    fn iter_split<'v>(
+        this: StringValue<'v>,
+        #[starlark(require = pos, default = NoneOr::None)] sep: NoneOr<StringValue<'v>>,
+        #[starlark(require = pos, default = NoneOr::None)] maxsplit: NoneOr<i32>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let maxsplit = match maxsplit.into_option() {
+            Some(v) if v >= 0 => Some((v + 1) as usize),
+            _ => None,
+        };
+        Ok(iterate_split(this, sep.into_option(), maxsplit, heap))
+    }
+
+    /// string.iter_splitlines: return the lines of a string lazily.
+    ///
+    /// `S.iter_splitlines([keepends])` returns an iterable over the same
+    /// lines as `S.splitlines(keepends)`, producing each line only when it
+    /// is reached, so `for line in s.iter_splitlines():` does not allocate
+    /// a list of all lines.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// list("one\n\ntwo".iter_splitlines()) == ["one", "", "two"]
+    /// list("one\r\ntwo".iter_splitlines(True)) == ["one\r\n", "two"]
+    /// # "#);
+    /// ```
+    #[starlark(return_type = "iter(str.type)")]
+    fn iter_splitlines<'v>(
+        this: StringValue<'v>,
+        #[starlark(require = pos, default = false)] keepends: bool,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(iterate_lines(this, keepends, heap))
+    }
+
     /// [string.lower](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·lower
     /// ): test if all letters of a string are lowercased.
@@ -692,22 +838,19 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                             Ok(x1)
                         }
                         Some(x2) => {
-                            let s1 = as_str(x1)?;
-                            let s2 = as_str(x2)?;
-                            // guess towards the upper bound, since we throw away over-allocations quickly
-                            // include a buffer (20 bytes)
+                            // Collect the parts first, so the result is allocated once
+                            // with its final length.
                             let n = it.size_hint().0 + 2;
-                            let guess =
-                                (cmp::max(s1.len(), s2.len()) * n) + (this.len() * (n - 1)) + 20;
-                            let mut r = String::with_capacity(guess);
-                            r.push_str(s1);
-                            r.push_str(this);
-                            r.push_str(s2);
+                            let mut parts = Vec::with_capacity(n * 2 - 1);
+                            parts.push(as_str(x1)?);
+                            parts.push(this);
+                            parts.push(as_str(x2)?);
                             for x in it {
-                                r.push_str(this);
-                                r.push_str(as_str(x)?);
+                                parts.push(this);
+                                parts.push(as_str(x)?);
                             }
-                            Ok(heap.alloc(r))
+                            heap.check_string_length(parts.iter().map(|p| p.len()).sum())?;
+                            Ok(heap.alloc_str_concat_many(&parts).to_value())
                         }
                     }
                 }
@@ -720,6 +863,8 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// ): trim leading whitespaces.
     ///
     /// `S.lstrip()` returns a copy of the string S with leading whitespace removed.
+    /// `S.lstrip(chars)` instead removes leading characters contained in `chars`,
+    /// matching Unicode code points; `chars` of `None` means whitespace.
     /// In most cases instead of passing an argument you should use `removeprefix`.
     ///
     /// Examples:
@@ -728,15 +873,16 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// # starlark::assert::all_true(r#"
     /// "  hello  ".lstrip() == "hello  "
     /// "x!hello  ".lstrip("!x ") == "hello  "
+    /// "  hello  ".lstrip(None) == "hello  "
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
     fn lstrip<'v>(
         this: StringValue<'v>,
-        #[starlark(require = pos)] chars: Option<&str>,
+        #[starlark(require = pos, default = NoneOr::None)] chars: NoneOr<&str>,
         heap: &'v Heap,
     ) -> anyhow::Result<StringValue<'v>> {
-        let res = match chars {
+        let res = match chars.into_option() {
             None => this.trim_start(),
             Some(s) => this.trim_start_matches(|c| s.contains(c)),
         };
@@ -1017,6 +1163,8 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// ): trim trailing whitespace.
     ///
     /// `S.rstrip()` returns a copy of the string S with trailing whitespace removed.
+    /// `S.rstrip(chars)` instead removes trailing characters contained in `chars`,
+    /// matching Unicode code points; `chars` of `None` means whitespace.
     /// In most cases instead of passing an argument you should use `removesuffix`.
     ///
     /// Examples:
@@ -1025,15 +1173,16 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// # starlark::assert::all_true(r#"
     /// "  hello  ".rstrip() == "  hello"
     /// "  hello!x".rstrip(" x!") == "  hello"
+    /// "héllo€é".rstrip("é€") == "héllo"
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
     fn rstrip<'v>(
         this: StringValue<'v>,
-        #[starlark(require = pos)] chars: Option<&str>,
+        #[starlark(require = pos, default = NoneOr::None)] chars: NoneOr<&str>,
         heap: &'v Heap,
     ) -> anyhow::Result<StringValue<'v>> {
-        let res = match chars {
+        let res = match chars.into_option() {
             None => this.trim_end(),
             Some(s) => this.trim_end_matches(|c| s.contains(c)),
         };
@@ -1097,7 +1246,9 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         };
         Ok(heap.alloc_list(&match (sep.into_option(), maxsplit) {
             (None, None) => this.split_whitespace().map(|x| heap.alloc(x)).collect(),
-            (None, Some(maxsplit)) => splitn_whitespace(this, maxsplit).map(|x| heap.alloc(x)),
+            (None, Some(maxsplit)) => SplitnWhitespace::new(this, maxsplit)
+                .map(|x| heap.alloc(x))
+                .collect(),
             (Some(sep), None) => {
                 if sep.len() == 1 {
                     // If we are searching for a 1-byte string, we can provide a much faster path.
@@ -1149,31 +1300,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         #[starlark(require = pos, default = false)] keepends: bool,
         heap: &'v Heap,
     ) -> anyhow::Result<Value<'v>> {
-        let mut s = this;
-        let mut lines = Vec::new();
-        loop {
-            if let Some(x) = s.find(|x| x == '\n' || x == '\r') {
-                let y = x;
-                let x = match s.get(y..y + 2) {
-                    Some("\r\n") => y + 2,
-                    _ => y + 1,
-                };
-                if keepends {
-                    lines.push(heap.alloc(s.get(..x).unwrap()))
-                } else {
-                    lines.push(heap.alloc(s.get(..y).unwrap()))
-                }
-                if x == s.len() {
-                    return Ok(heap.alloc_list(&lines));
-                }
-                s = s.get(x..).unwrap();
-            } else {
-                if !s.is_empty() {
-                    lines.push(heap.alloc(s));
-                }
-                return Ok(heap.alloc_list(&lines));
-            }
-        }
+        Ok(heap.alloc_list_iter(Lines::new(this, keepends).map(|x| heap.alloc(x))))
     }
 
     /// [string.startswith](
@@ -1211,6 +1338,8 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     ///
     /// `S.strip()` returns a copy of the string S with leading and trailing
     /// whitespace removed.
+    /// `S.strip(chars)` instead removes leading and trailing characters contained
+    /// in `chars`, matching Unicode code points; `chars` of `None` means whitespace.
     ///
     /// Examples:
     ///
@@ -1218,15 +1347,16 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// # starlark::assert::all_true(r#"
     /// "  hello  ".strip() == "hello"
     /// "xxhello!!".strip("x!") == "hello"
+    /// "\thello\n".strip(None) == "hello"
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
     fn strip<'v>(
         this: StringValue<'v>,
-        #[starlark(require = pos)] chars: Option<&str>,
+        #[starlark(require = pos, default = NoneOr::None)] chars: NoneOr<&str>,
         heap: &'v Heap,
     ) -> anyhow::Result<StringValue<'v>> {
-        let res = match chars {
+        let res = match chars.into_option() {
             None => this.trim(),
             Some(s) => this.trim_matches(|c| s.contains(c)),
         };
@@ -1357,6 +1487,11 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
 #[cfg(test)]
 mod tests {
     use crate::assert;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
 
     #[test]
     fn test_error_codes() {
@@ -1365,6 +1500,66 @@ mod tests {
         assert::fail(r#""bonbon".rindex("on", 2, 5)"#, "not found in");
     }
 
+    #[test]
+    fn test_strip() {
+        assert::all_true(
+            r#"
+" \t hello \n ".strip() == "hello"
+" \t hello \n ".lstrip() == "hello \n "
+" \t hello \n ".rstrip() == " \t hello"
+" hello ".strip(None) == "hello"
+"abcHELLOcba".strip("abc") == "HELLO"
+"abcHELLOcba".lstrip("cba") == "HELLOcba"
+"abcHELLOcba".rstrip("bca") == "abcHELLO"
+" hello ".strip("") == " hello "
+"aaa".strip("a") == ""
+"€é€h€é€".strip("é€") == "h"
+"€é€h€é€".lstrip("€") == "é€h€é€"
+"€é€h€é€".rstrip("€é") == "€é€h"
+"#,
+        );
+        assert::fail(r#""x".strip(1)"#, "Type of parameter");
+    }
+
+    #[test]
+    fn test_isidentifier() {
+        assert::all_true(
+            r#"
+"x".isidentifier()
+"_private".isidentifier()
+"CamelCase_9".isidentifier()
+"def".isidentifier()
+"café".isidentifier()
+not "".isidentifier()
+not "9lives".isidentifier()
+not "a b".isidentifier()
+not "a.b".isidentifier()
+not "a-b".isidentifier()
+not "x\n".isidentifier()
+"#,
+        );
+    }
+
+    #[test]
+    fn test_isprintable() {
+        assert::all_true(
+            r#"
+"".isprintable()
+" ".isprintable()
+"Hello, world! 123 ~".isprintable()
+"café 😀".isprintable()
+not "a\nb".isprintable()
+not "\t".isprintable()
+not "\r".isprintable()
+not "\x00".isprintable()
+not "\x7f".isprintable()
+not "a\u00a0b".isprintable()
+not "zero\u200bwidth".isprintable()
+not "\u2028".isprintable()
+"#,
+        );
+    }
+
     #[test]
     fn test_count() {
         assert::eq("'abc'.count('a', 10, -10)", "0");
@@ -1380,4 +1575,69 @@ mod tests {
         assert::is_true("type('foo'.elems()) != type([])");
         assert::is_true("type('foo'.codepoints()) != type([])");
     }
+
+    #[test]
+    fn test_iter_split() {
+        assert::all_true(
+            r#"
+list("".iter_split()) == "".split()
+list("  a b\tc  ".iter_split()) == "  a b\tc  ".split()
+list("  a b\tc  ".iter_split(None, 1)) == "  a b\tc  ".split(None, 1)
+list("  a b\tc  ".iter_split(None, 0)) == "  a b\tc  ".split(None, 0)
+list("a,,b,c".iter_split(",")) == "a,,b,c".split(",")
+list("a,,b,c".iter_split(",", 2)) == "a,,b,c".split(",", 2)
+list("a::b::c".iter_split("::", -1)) == "a::b::c".split("::", -1)
+list("".iter_split(",")) == [""]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_iter_splitlines() {
+        assert::all_true(
+            r#"
+list("".iter_splitlines()) == "".splitlines()
+list("a\nb\r\nc\rd\n".iter_splitlines()) == "a\nb\r\nc\rd\n".splitlines()
+list("a\nb\r\nc\rd\n".iter_splitlines(True)) == "a\nb\r\nc\rd\n".splitlines(True)
+list("\n\n".iter_splitlines()) == ["", ""]
+"#,
+        );
+        assert::is_true(
+            r#"
+n = 0
+for line in "ab\ncd\n".iter_splitlines():
+    n += len(line)
+n == 4
+"#,
+        );
+    }
+
+    fn allocated_bytes(program: &str) -> usize {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        // Keep everything allocated, so the result reflects the peak.
+        eval.disable_gc();
+        let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        module.heap().allocated_bytes()
+    }
+
+    #[test]
+    fn test_iter_splitlines_does_not_materialize() {
+        let program = |method| {
+            format!(
+                "s = 'xy\\n' * 100000\nn = 0\nfor line in s.{}():\n  n += 1\nif n != 100000:\n  fail(n)",
+                method
+            )
+        };
+        let eager = allocated_bytes(&program("splitlines"));
+        let lazy = allocated_bytes(&program("iter_splitlines"));
+        // The eager form additionally holds a list of all the lines.
+        assert!(
+            lazy + 100000 * 8 <= eager,
+            "lazy: {}, eager: {}",
+            lazy,
+            eager
+        );
+    }
 }
@@ -0,0 +1,72 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Methods for the `tuple` type.
+
+use crate as starlark;
+use crate::environment::MethodsBuilder;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+#[starlark_module]
+pub(crate) fn tuple_methods(builder: &mut MethodsBuilder) {
+    /// [tuple.count](
+    /// https://docs.python.org/3/library/stdtypes.html#common-sequence-operations
+    /// ): count the occurrences of an element in a tuple.
+    ///
+    /// `T.count(x)` returns the number of elements of the tuple T equal to `x`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// (1, 2, 1, 1).count(1) == 3
+    /// (1, 2, 1, 1).count(3) == 0
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn count<'v>(
+        this: &TupleRef<'v>,
+        #[starlark(require = pos)] needle: Value<'v>,
+    ) -> anyhow::Result<i32> {
+        let mut count = 0;
+        for x in this.content() {
+            if x.equals(needle)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_count() {
+        assert::all_true(
+            r#"
+().count(1) == 0
+(1, 2, 3).count(4) == 0
+("a", "b", "a").count("a") == 2
+([1], [1], (1,)).count([1]) == 2
+((1,), [1]).count((1,)) == 1
+"#,
+        );
+    }
+}
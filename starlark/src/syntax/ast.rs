@@ -62,6 +62,7 @@ pub(crate) type Argument = ArgumentP<AstNoPayload>;
 pub(crate) type Parameter = ParameterP<AstNoPayload>;
 pub(crate) type Load = LoadP<AstNoPayload>;
 pub(crate) type Stmt = StmtP<AstNoPayload>;
+pub(crate) type LambdaBody = LambdaBodyP<AstNoPayload>;
 
 // Boxed types used for storing information from the parsing will be used
 // especially for the location of the AST item
@@ -175,10 +176,27 @@ pub(crate) enum AstLiteral {
 #[derive(Debug)]
 pub(crate) struct LambdaP<P: AstPayload> {
     pub(crate) params: Vec<AstParameterP<P>>,
-    pub(crate) body: Box<AstExprP<P>>,
+    pub(crate) body: LambdaBodyP<P>,
     pub(crate) payload: P::DefPayload,
 }
 
+#[derive(Debug)]
+pub(crate) enum LambdaBodyP<P: AstPayload> {
+    /// `lambda x: x + 1`.
+    Expr(Box<AstExprP<P>>),
+    /// `f = def(x):` followed by a block, allowed by `Dialect::enable_def_expr`.
+    Block(Box<AstStmtP<P>>),
+}
+
+impl<P: AstPayload> LambdaBodyP<P> {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            LambdaBodyP::Expr(e) => e.span,
+            LambdaBodyP::Block(s) => s.span,
+        }
+    }
+}
+
 impl<P: AstPayload> LambdaP<P> {
     pub(crate) fn signature_span(&self) -> Span {
         self.params
@@ -187,7 +205,7 @@ impl<P: AstPayload> LambdaP<P> {
             .reduce(|a, b| a.merge(b))
             .unwrap_or(
                 // TODO(nga): this is not correct span.
-                self.body.span,
+                self.body.span(),
             )
     }
 }
@@ -479,13 +497,22 @@ impl Display for Expr {
                 params,
                 body,
                 payload: _,
-            }) => {
-                f.write_str("(lambda ")?;
-                comma_separated_fmt(f, params, |x, f| write!(f, "{}", x.node), false)?;
-                f.write_str(": ")?;
-                write!(f, "{}", body.node)?;
-                f.write_str(")")
-            }
+            }) => match body {
+                LambdaBody::Expr(body) => {
+                    f.write_str("(lambda ")?;
+                    comma_separated_fmt(f, params, |x, f| write!(f, "{}", x.node), false)?;
+                    f.write_str(": ")?;
+                    write!(f, "{}", body.node)?;
+                    f.write_str(")")
+                }
+                LambdaBody::Block(body) => {
+                    f.write_str("(def(")?;
+                    comma_separated_fmt(f, params, |x, f| write!(f, "{}", x.node), false)?;
+                    f.write_str("):\n")?;
+                    body.node.fmt_with_tab(f, "  ".to_owned())?;
+                    f.write_str(")")
+                }
+            },
             Expr::Call(e, args) => {
                 write!(f, "{}(", e.node)?;
                 for (i, x) in args.iter().enumerate() {
@@ -642,7 +669,19 @@ impl Stmt {
                 if let Some(ty) = ty {
                     write!(f, ": {} ", ty.node)?;
                 }
-                writeln!(f, "= {}", r.node)
+                match &r.node {
+                    Expr::Lambda(LambdaP {
+                        params,
+                        body: LambdaBody::Block(body),
+                        payload: _,
+                    }) => {
+                        f.write_str("= def(")?;
+                        comma_separated_fmt(f, params, |x, f| write!(f, "{}", x.node), false)?;
+                        f.write_str("):\n")?;
+                        body.node.fmt_with_tab(f, tab + "  ")
+                    }
+                    r => writeln!(f, "= {}", r),
+                }
             }
             Stmt::AssignModify(l, op, r) => writeln!(f, "{}{}{}{}", tab, l.node, op, r.node),
             Stmt::Statements(v) => {
@@ -31,6 +31,8 @@ pub(crate) enum DialectError {
     Def,
     #[error("`lambda` is not allowed in this dialect")]
     Lambda,
+    #[error("`def` expressions are not allowed in this dialect")]
+    DefExpr,
     #[error("`load` is not allowed in this dialect")]
     Load,
     #[error("* keyword-only-arguments is not allowed in this dialect")]
@@ -78,6 +80,18 @@ pub struct Dialect {
     /// Are `for`, `if` and other statements allowed at the top level.
     /// Only enabled in [`Extended`](Dialect::Extended).
     pub enable_top_level_stmt: bool,
+    /// Are expression statements other than function calls and strings (such as docstrings)
+    /// allowed at the top level, e.g. `x + 1`.
+    /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
+    pub enable_top_level_expr: bool,
+    /// When a call has several `**kwargs` arguments, like `f(**a, **b)`, is a key
+    /// present in more than one of them an error. Otherwise later dictionaries override earlier ones.
+    /// Disabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
+    pub enable_strict_kwargs: bool,
+    /// Are `def` expressions permitted, e.g. `f = def(x):` followed by an indented block,
+    /// defining an anonymous function with a multi-statement body.
+    /// Disabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
+    pub enable_def_expr: bool,
 }
 
 // These are morally enumerations, so give them enumeration-like names
@@ -94,6 +108,9 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
+        enable_top_level_expr: true,
+        enable_strict_kwargs: false,
+        enable_def_expr: false,
     };
 
     /// A superset of [`Standard`](Dialect::Standard), including extra features (types, top-level statements etc).
@@ -106,6 +123,9 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true,
         enable_top_level_stmt: true,
+        enable_top_level_expr: true,
+        enable_strict_kwargs: false,
+        enable_def_expr: false,
     };
 }
 
@@ -126,6 +146,18 @@ impl Dialect {
         }
     }
 
+    pub(crate) fn check_def_expr<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_def_expr {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::DefExpr)
+        }
+    }
+
     pub(crate) fn check_def<T>(
         &self,
         codemap: &CodeMap,
@@ -114,6 +114,34 @@ fn test_top_level_statements() {
     assert_eq!(assert::parse("pass"), "pass\n");
 }
 
+#[test]
+fn test_top_level_for_dialect() {
+    let program = "x = []\nfor i in range(3):\n  x.append(i)\nassert_eq(x, [0, 1, 2])";
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_top_level_stmt = true);
+    a.pass(program);
+
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_top_level_stmt = false);
+    let err = a.fail(program, "`for` cannot be used outside `def`");
+    // The error points at the loop.
+    assert!(err.to_string().contains("assert.bzl:2:1"), "{}", err);
+}
+
+#[test]
+fn test_top_level_expressions() {
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_top_level_expr = false);
+    a.parse_fail("x = 1\n!x + 1!\ny = 2");
+    a.parse_fail("![1, 2]!");
+    assert_eq!(
+        a.parse("\"docstring\"\nf(1)\nx = 2"),
+        "\"docstring\"\nf(1)\nx = 2\n"
+    );
+    assert_eq!(a.parse("def f():\n  x + 1"), "def f():\n  (x + 1)\n");
+    assert_eq!(assert::parse("x + 1"), "(x + 1)\n");
+}
+
 #[test]
 fn test_top_level_def_with_docstring() {
     assert_eq!(
@@ -296,6 +324,44 @@ fn test_op_associativity() {
     assert::fail("0 <= 1 < 2", "Parse error");
 }
 
+#[test]
+fn test_conditional_expression_precedence() {
+    // Binds looser than `or`/`and`/`not`, as in Python.
+    assert_eq!(
+        assert::parse("x or y if c else z"),
+        "((x or y) if c else z)\n"
+    );
+    assert_eq!(
+        assert::parse("x if c or d else y and z"),
+        "(x if (c or d) else (y and z))\n"
+    );
+    assert_eq!(
+        assert::parse("not x if c else y"),
+        "((not x) if c else y)\n"
+    );
+    // Right associative in the `else` branch.
+    assert_eq!(
+        assert::parse("a if b else c if d else e"),
+        "(a if b else (c if d else e))\n"
+    );
+    // A lambda body extends over the whole conditional.
+    assert_eq!(
+        assert::parse("lambda: a if b else c"),
+        "(lambda : (a if b else c))\n"
+    );
+    // Tightens less than a tuple comma.
+    assert_eq!(
+        assert::parse("x = a if b else c, d"),
+        "x = ((a if b else c), d)\n"
+    );
+    // The conditional form is how comprehensions select between values.
+    assert_eq!(
+        assert::parse("[a if x else b for x in y if x or z]"),
+        "[(a if x else b) for x in y if (x or z)]\n"
+    );
+    assert::parse_fail("x if !lambda!: c else d");
+}
+
 #[test]
 fn test_bad_assignment() {
     assert::parse_fail("[!x or y!] = 1");
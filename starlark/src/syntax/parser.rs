@@ -27,10 +27,20 @@ use crate::codemap::FileSpan;
 use crate::codemap::Pos;
 use crate::codemap::Span;
 use crate::collections::SmallMap;
+use crate::docs;
+use crate::docs::DocItem;
+use crate::docs::DocString;
+use crate::docs::DocStringKind;
+use crate::environment::Module;
+use crate::environment::ModuleDocs;
 use crate::errors::Diagnostic;
+use crate::syntax::ast::AstExpr;
 use crate::syntax::ast::AstModule;
+use crate::syntax::ast::AstParameter;
 use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Parameter;
 use crate::syntax::ast::Stmt;
+use crate::syntax::ast::Visibility;
 use crate::syntax::dialect::Dialect;
 use crate::syntax::grammar::StarlarkParser;
 use crate::syntax::lexer::Lexer;
@@ -185,6 +195,75 @@ impl AstModule {
         loads
     }
 
+    /// The documentation for the module and its top level functions, extracted
+    /// from the AST without evaluating it.
+    ///
+    /// The result has the same shape as
+    /// [`FrozenModule::module_documentation`](crate::environment::FrozenModule::module_documentation),
+    /// but only `def` statements at the top-level are included in the members,
+    /// and types and default values are the unevaluated source expressions.
+    pub fn module_documentation(&self) -> ModuleDocs {
+        fn param_docs(param: &AstParameter) -> docs::Param {
+            let typ = |t: &Option<Box<AstExpr>>| {
+                t.as_ref().map(|t| docs::Type {
+                    raw_type: t.to_string(),
+                })
+            };
+            match &param.node {
+                Parameter::Normal(name, t) => docs::Param::Arg {
+                    name: name.0.clone(),
+                    docs: None,
+                    typ: typ(t),
+                    default_value: None,
+                },
+                Parameter::WithDefaultValue(name, t, default) => docs::Param::Arg {
+                    name: name.0.clone(),
+                    docs: None,
+                    typ: typ(t),
+                    default_value: Some(default.to_string()),
+                },
+                Parameter::NoArgs => docs::Param::NoArgs,
+                Parameter::Args(name, t) => docs::Param::Args {
+                    name: format!("*{}", name.0),
+                    docs: None,
+                    typ: typ(t),
+                },
+                Parameter::KwArgs(name, t) => docs::Param::Kwargs {
+                    name: format!("**{}", name.0),
+                    docs: None,
+                    typ: typ(t),
+                },
+            }
+        }
+
+        let module = DocString::extract_raw_starlark_docstring(&self.statement).map(|d| {
+            DocItem::Module(docs::Module {
+                docs: DocString::from_docstring(DocStringKind::Starlark, &d),
+            })
+        });
+
+        let members = self
+            .top_level_statements()
+            .into_iter()
+            .filter_map(|stmt| match &stmt.node {
+                Stmt::Def(def) if Module::default_visibility(&def.name.0) == Visibility::Public => {
+                    let function_docs = docs::Function::from_docstring(
+                        DocStringKind::Starlark,
+                        def.params.iter().map(param_docs).collect(),
+                        def.return_type.as_ref().map(|r| docs::Type {
+                            raw_type: r.to_string(),
+                        }),
+                        DocString::extract_raw_starlark_docstring(&def.body).as_deref(),
+                    );
+                    Some((def.name.0.clone(), Some(DocItem::Function(function_docs))))
+                }
+                _ => None,
+            })
+            .collect();
+
+        ModuleDocs { module, members }
+    }
+
     /// Look up a [`Span`] contained in this module to a [`FileSpan`].
     pub(crate) fn file_span(&self, x: Span) -> FileSpan {
         self.codemap.file_span(x)
@@ -28,6 +28,7 @@ use crate::syntax::ast::ClauseP;
 use crate::syntax::ast::DefP;
 use crate::syntax::ast::ExprP;
 use crate::syntax::ast::ForClauseP;
+use crate::syntax::ast::LambdaBodyP;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::LoadP;
 use crate::syntax::ast::ParameterP;
@@ -150,7 +151,14 @@ impl<A: AstPayload> ExprP<A> {
                 payload,
             }) => ExprP::Lambda(LambdaP {
                 params: params.into_map(|p| p.into_map_payload(f)),
-                body: Box::new(body.into_map_payload(f)),
+                body: match body {
+                    LambdaBodyP::Expr(body) => {
+                        LambdaBodyP::Expr(Box::new(body.into_map_payload(f)))
+                    }
+                    LambdaBodyP::Block(body) => {
+                        LambdaBodyP::Block(Box::new(body.into_map_payload(f)))
+                    }
+                },
                 payload: f.map_def(payload),
             }),
             ExprP::Literal(l) => ExprP::Literal(l),
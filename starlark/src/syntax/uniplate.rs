@@ -30,6 +30,7 @@ use crate::syntax::ast::ClauseP;
 use crate::syntax::ast::DefP;
 use crate::syntax::ast::ExprP;
 use crate::syntax::ast::ForClauseP;
+use crate::syntax::ast::LambdaBodyP;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::ParameterP;
 use crate::syntax::ast::StmtP;
@@ -188,6 +189,16 @@ impl<P: AstPayload> StmtP<P> {
         self.visit_children(|x| pick(x, &mut f))
     }
 
+    pub(crate) fn visit_expr_mut<'a>(&'a mut self, mut f: impl FnMut(&'a mut AstExprP<P>)) {
+        fn pick<'a, P: AstPayload>(x: VisitMut<'a, P>, f: &mut impl FnMut(&'a mut AstExprP<P>)) {
+            match x {
+                VisitMut::Stmt(x) => x.visit_children_mut(|x| pick(x, f)),
+                VisitMut::Expr(x) => f(x),
+            }
+        }
+        self.visit_children_mut(|x| pick(x, &mut f))
+    }
+
     pub(crate) fn visit_stmt_result<E>(
         &self,
         mut f: impl FnMut(&AstStmtP<P>) -> Result<(), E>,
@@ -286,7 +297,10 @@ impl<P: AstPayload> ExprP<P> {
                 payload: _,
             }) => {
                 params.iter().for_each(|x| x.visit_expr(|x| f(x)));
-                f(body);
+                match body {
+                    LambdaBodyP::Expr(body) => f(body),
+                    LambdaBodyP::Block(body) => body.visit_expr(|x| f(x)),
+                }
             }
             ExprP::Literal(_) => {}
             ExprP::Not(x) => f(x),
@@ -348,7 +362,10 @@ impl<P: AstPayload> ExprP<P> {
                 payload: _,
             }) => {
                 params.iter_mut().for_each(|x| x.visit_expr_mut(|x| f(x)));
-                f(body);
+                match body {
+                    LambdaBodyP::Expr(body) => f(body),
+                    LambdaBodyP::Block(body) => body.visit_expr_mut(|x| f(x)),
+                }
             }
             ExprP::Literal(_) => {}
             ExprP::Not(x) => f(x),
@@ -34,11 +34,13 @@ use crate::syntax::ast::AstArgument;
 use crate::syntax::ast::AstAssign;
 use crate::syntax::ast::AstAssignIdent;
 use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
 use crate::syntax::ast::AstParameter;
 use crate::syntax::ast::AstStmt;
 use crate::syntax::ast::AstString;
 use crate::syntax::ast::DefP;
 use crate::syntax::ast::Expr;
+use crate::syntax::ast::LambdaBody;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::Parameter;
 use crate::syntax::ast::Stmt;
@@ -59,6 +61,10 @@ enum ValidateError {
     NoTopLevelIf,
     #[error("`for` cannot be used outside `def` in this dialect")]
     NoTopLevelFor,
+    #[error(
+        "only function calls and strings can be used as statements outside `def` in this dialect"
+    )]
+    NoTopLevelExpr,
     #[error("left-hand-side of assignment must take the form `a`, `a.b` or `a[b]`")]
     InvalidLhs,
     #[error("left-hand-side of modifying assignment cannot be a list or tuple")]
@@ -87,8 +93,6 @@ enum ArgumentDefinitionOrderError {
     RepeatedNamed,
     #[error("Args array after another args or kwargs")]
     ArgsArrayAfterArgsOrKwargs,
-    #[error("Multiple kwargs dictionary in arguments")]
-    MultipleKwargs,
 }
 
 impl Expr {
@@ -103,7 +107,7 @@ impl Expr {
     /// after all positional and named arguments. The spec is silent on whether you are allowed
     /// multiple **kwargs.
     ///
-    /// We allow at most one **kwargs.
+    /// We allow multiple **kwargs, which are merged left to right.
     pub(crate) fn check_call(
         f: AstExpr,
         args: Vec<AstArgument>,
@@ -146,13 +150,7 @@ impl Expr {
                         stage = ArgsStage::Args;
                     }
                 }
-                Argument::KwArgs(_) => {
-                    if stage == ArgsStage::Kwargs {
-                        return err(arg.span, ArgumentDefinitionOrderError::MultipleKwargs);
-                    } else {
-                        stage = ArgsStage::Kwargs;
-                    }
-                }
+                Argument::KwArgs(_) => stage = ArgsStage::Kwargs,
             }
         }
         Ok(Expr::Call(Box::new(f), args))
@@ -251,7 +249,20 @@ impl Expr {
         check_parameters(&params, codemap)?;
         Ok(Expr::Lambda(LambdaP {
             params,
-            body: Box::new(body),
+            body: LambdaBody::Expr(Box::new(body)),
+            payload: (),
+        }))
+    }
+
+    pub(crate) fn check_def_expr(
+        params: Vec<AstParameter>,
+        body: AstStmt,
+        codemap: &CodeMap,
+    ) -> anyhow::Result<Expr> {
+        check_parameters(&params, codemap)?;
+        Ok(Expr::Lambda(LambdaP {
+            params,
+            body: LambdaBody::Block(Box::new(body)),
             payload: (),
         }))
     }
@@ -341,7 +352,8 @@ impl Stmt {
         // Inside a for, we allow continue/break, unless we go beneath a def.
         // Inside a def, we allow return.
         // All load's must occur at the top-level.
-        // At the top-level we only allow for/if when the dialect permits it.
+        // At the top-level we only allow for/if when the dialect permits it,
+        // and expressions other than calls and strings when the dialect permits it.
         fn f(
             codemap: &CodeMap,
             dialect: &Dialect,
@@ -354,6 +366,13 @@ impl Stmt {
 
             match &stmt.node {
                 Stmt::Def(DefP { body, .. }) => f(codemap, dialect, body, false, false, true),
+                Stmt::Assign(_, ty_rhs) => match &ty_rhs.1.node {
+                    Expr::Lambda(LambdaP {
+                        body: LambdaBody::Block(body),
+                        ..
+                    }) => f(codemap, dialect, body, false, false, true),
+                    _ => Ok(()),
+                },
                 Stmt::For(_, over_body) => {
                     let (_, body) = &**over_body;
                     if top_level && !dialect.enable_top_level_stmt {
@@ -371,6 +390,16 @@ impl Stmt {
                         })
                     }
                 }
+                Stmt::Expression(e)
+                    if top_level
+                        && !dialect.enable_top_level_expr
+                        && !matches!(
+                            e.node,
+                            Expr::Call(..) | Expr::Literal(AstLiteral::String(_))
+                        ) =>
+                {
+                    err(ValidateError::NoTopLevelExpr.into())
+                }
                 Stmt::Break if !inside_for => err(ValidateError::BreakOutsideLoop.into()),
                 Stmt::Continue if !inside_for => err(ValidateError::ContinueOutsideLoop.into()),
                 Stmt::Return(_) if !inside_def => err(ValidateError::ReturnOutsideDef.into()),
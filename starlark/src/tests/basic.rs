@@ -193,6 +193,51 @@ xs == ys
     );
 }
 
+#[test]
+fn test_equality_cyclic() {
+    assert::is_true(
+        r#"
+xs = [1]
+xs.append(xs)
+xs == xs
+"#,
+    );
+    assert::is_true(
+        r#"
+xs = [1]
+xs.append(xs)
+ys = [2]
+ys.append(ys)
+xs != ys
+"#,
+    );
+    assert::fail(
+        r#"
+xs = {}
+xs["x"] = [xs]
+ys = {}
+ys["x"] = [ys]
+xs == ys
+"#,
+        "recursion",
+    );
+}
+
+#[test]
+fn test_equality_nan() {
+    assert::all_true(
+        r#"
+float("nan") == float("nan")
+[float("nan")] == [float("nan")]
+(1, float("nan")) == (1, float("nan"))
+{1: float("nan")} == {1: float("nan")}
+struct(x = float("nan")) == struct(x = float("nan"))
+[[float("nan")]] == [[float("nan")]]
+[float("nan")] != [1.0]
+"#,
+    );
+}
+
 #[test]
 fn test_not_in_unhashable() {
     // Note that [] can't be hashed
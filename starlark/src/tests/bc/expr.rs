@@ -28,6 +28,53 @@ fn test_percent_s_one() {
     bc_golden_test("expr_percent_s_one", "def test(x): return '((%s))' % x");
 }
 
+#[test]
+fn test_percent_d_one() {
+    bc_golden_test("expr_percent_d_one", "def test(x): return '((%d))' % x");
+}
+
+#[test]
+fn test_percent_one_fallback() {
+    // Width, several substitutions and non-constant format use generic `%`.
+    bc_golden_test(
+        "expr_percent_one_fallback",
+        "def test(x, f): return ('%5d' % x, '%s-%s' % (x, x), f % x)",
+    );
+}
+
+#[test]
+fn test_percent_d_one_eval() {
+    assert::pass(
+        r#"
+load("assert.star", "assert")
+
+def test(x):
+    return "<%d>" % x
+
+assert.eq("<1>", test(1))
+assert.eq("<-2>", test(-2.5))
+assert.eq("<3>", test((3,)))
+assert.eq("<7>", "<%d>" % 7)
+"#,
+    );
+    assert::fail(
+        r#"
+def test(x):
+    return "<%d>" % x
+test("a")
+"#,
+        "`string`",
+    );
+    assert::fail(
+        r#"
+def test(x):
+    return "<%d>" % x
+test((1, 2))
+"#,
+        "Too many arguments for format string",
+    );
+}
+
 #[test]
 fn test_format_one() {
     bc_golden_test("expr_format_one", "def test(x): return '(({}))'.format(x)");
@@ -49,6 +96,55 @@ assert.eq(("<(1,)>", "<1>"), test((1,)))
     );
 }
 
+#[test]
+fn test_format_n() {
+    bc_golden_test(
+        "expr_format_n",
+        "def test(x, y): return '<{}|{}{{}}>'.format(x, y)",
+    );
+}
+
+#[test]
+fn test_format_n_eval() {
+    assert::pass(
+        r#"
+load("assert.star", "assert")
+
+def test(x, y):
+    return "<{}|{}>".format(x, y)
+
+def explicit(x, y):
+    return "<{1}|{0}>".format(x, y)
+
+def named(x, y):
+    return "<{x}|{}>".format(y, x = x)
+
+assert.eq("<a|(1,)>", test("a", (1,)))
+assert.eq("<{}|b>", "<{{}}|{}>".format("b"))
+assert.eq("<2|1>", explicit(1, 2))
+assert.eq("<1|2>", named(1, 2))
+assert.eq("<1|2>", "<{}|{}>".format(1, 2))
+"#,
+    );
+    // Argument count mismatch falls back to the runtime implementation.
+    assert::eq(
+        r#"
+def test(x, y, z):
+    return "<{}|{}>".format(x, y, z)
+test(1, 2, 3)
+"#,
+        "'<1|2>'",
+    );
+    assert::fail(
+        r#"
+def test(x, y):
+    return "<{}|{}|{}>".format(x, y)
+test(1, 2)
+"#,
+        "Not enough parameters",
+    );
+}
+
 #[test]
 fn test_spec_exec_list() {
     // `list` function is const-evaluated and the resulting list is compiled as list instruction.
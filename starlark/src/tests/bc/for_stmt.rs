@@ -0,0 +1,133 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Test compilation of `for` statements.
+
+use crate::assert;
+use crate::tests::bc::golden::bc_golden_test;
+
+#[test]
+fn test_for_enumerate() {
+    bc_golden_test(
+        "for_enumerate",
+        "def test(xs):\n  for i, x in enumerate(xs):\n    noop(i, x)",
+    );
+}
+
+#[test]
+fn test_for_enumerate_start_is_not_special() {
+    bc_golden_test(
+        "for_enumerate_start",
+        "def test(xs):\n  for i, x in enumerate(xs, 1):\n    noop(i, x)",
+    );
+}
+
+#[test]
+fn test_for_enumerate_semantics() {
+    assert::pass(
+        r#"
+def collect(xs):
+    r = []
+    for i, x in enumerate(xs):
+        r.append((i, x))
+    return r
+
+assert_eq([], collect([]))
+assert_eq([(0, "a"), (1, "b")], collect(["a", "b"]))
+assert_eq([(0, "a"), (1, "b")], collect(("a", "b")))
+assert_eq([(0, "k")], collect({"k": 1}))
+assert_eq([(0, "a"), (1, "b")], collect("ab".elems()))
+assert_eq([(0, 5), (1, 6)], collect(range(5, 7)))
+"#,
+    );
+}
+
+#[test]
+fn test_for_enumerate_mutate() {
+    // `enumerate` takes a snapshot of the list, so it can be mutated in the loop.
+    assert::pass(
+        r#"
+def test():
+    xs = [1, 2, 3]
+    for i, x in enumerate(xs):
+        xs.append(x * 10)
+    return xs
+assert_eq([1, 2, 3, 10, 20, 30], test())
+"#,
+    );
+}
+
+#[test]
+fn test_for_enumerate_control_flow() {
+    assert::pass(
+        r#"
+def find(xs, y):
+    for i, x in enumerate(xs):
+        if x == y:
+            return i
+    return -1
+
+def first_odd_after_skip(xs):
+    r = None
+    for i, x in enumerate(xs):
+        if i == 0:
+            continue
+        if x % 2 == 1:
+            r = (i, x)
+            break
+    return r
+
+assert_eq(2, find(["a", "b", "c"], "c"))
+assert_eq(-1, find(["a"], "c"))
+assert_eq((2, 3), first_odd_after_skip([1, 2, 3, 5]))
+"#,
+    );
+}
+
+#[test]
+fn test_for_enumerate_assign_targets() {
+    assert::pass(
+        r#"
+def test():
+    d = {}
+    captured = []
+    for i, d["x"] in enumerate(["a", "b"]):
+        captured.append(lambda: i)
+    return d, captured[0]()
+
+assert_eq(({"x": "b"}, 1), test())
+
+r = []
+for top_i, top_x in enumerate([True, False]):
+    r.append([top_i, top_x])
+assert_eq([[0, True], [1, False]], r)
+assert_eq([(0, 2), (1, 3)], [(i, x) for i, x in enumerate([2, 3])])
+"#,
+    );
+}
+
+#[test]
+fn test_for_enumerate_errors() {
+    assert::fail(
+        "def test():\n  for i, x in enumerate(1): pass\ntest()",
+        "not supported",
+    );
+    assert::fail(
+        "def test():\n  for i, x, y in enumerate([1]): pass\ntest()",
+        "Unpacked 2 values but expected 3",
+    );
+}
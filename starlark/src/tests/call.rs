@@ -248,6 +248,50 @@ f(1)
     );
 }
 
+#[test]
+fn test_multiple_kwargs() {
+    assert::pass(
+        r#"
+def f(**kwargs):
+    return kwargs
+
+a = {"x": 1, "y": 2}
+b = {"y": 3, "z": 4}
+assert_eq(f(**a, **b), {"x": 1, "y": 3, "z": 4})
+assert_eq(list(f(**a, **b).keys()), ["x", "y", "z"])
+assert_eq(f(**b, **a), {"x": 1, "y": 2, "z": 4})
+assert_eq(f(w=0, **a, **{}, **b), {"w": 0, "x": 1, "y": 3, "z": 4})
+assert_eq(dict(**a, **b), {"x": 1, "y": 3, "z": 4})
+assert_eq(a, {"x": 1, "y": 2})
+
+r = []
+def id(x):
+    r.append(x)
+    return x
+f(**id({"p": 1}), **id({"q": 2}))
+assert_eq(r, [{"p": 1}, {"q": 2}])
+"#,
+    );
+    assert::fail(
+        "def f(**kwargs): pass\nf(**{'x': 1}, **[])",
+        "not a dictionary",
+    );
+    assert::fail(
+        "def f(**kwargs): pass\nf(x = 1, **{'y': 1}, **{'x': 2})",
+        "occurs more than once",
+    );
+
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_strict_kwargs = true);
+    a.pass(
+        "def f(**kwargs): return kwargs\nassert_eq(f(**{'x': 1}, **{'y': 2}), {'x': 1, 'y': 2})",
+    );
+    a.fail(
+        "def f(**kwargs): pass\nf(**{'x': 1, 'y': 2}, **{'y': 3})",
+        "Argument `y` occurs more than once",
+    );
+}
+
 #[test]
 fn test_frame_size() {
     #[starlark_module]
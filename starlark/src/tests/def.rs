@@ -34,6 +34,45 @@ ys = [lambda x: x + y for y in [4,5,6]]
     );
 }
 
+#[test]
+fn test_def_expr() {
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_def_expr = true);
+    a.pass(
+        r#"
+def make_counter(start):
+    step = 2
+    counter = def(n):
+        total = start
+        for _ in range(n):
+            total += step
+        return total
+    return counter
+
+assert_eq(make_counter(1)(3), 7)
+
+fs = {}
+fs["twice"] = def(f, x):
+    def g(y):
+        return f(f(y))
+    return g(x)
+assert_eq(fs["twice"](lambda v: v * 10, 1), 100)
+
+scale = 3
+times = def(x): return x * scale
+assert_eq(times(2), 6)
+"#,
+    );
+    a.fail(
+        "f = def(x):\n    break\n",
+        "`break` cannot be used outside of a `for` loop",
+    );
+    assert::fail(
+        "f = def(x):\n    return x\n",
+        "`def` expressions are not allowed in this dialect",
+    );
+}
+
 #[test]
 fn test_frozen_lambda() {
     let mut a = Assert::new();
@@ -0,0 +1,120 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::UnpackValue;
+
+#[derive(Debug, UnpackValue)]
+enum Color {
+    Red,
+    Green,
+    #[starlark(tag = "navy")]
+    DarkBlue,
+}
+
+#[derive(UnpackValue)]
+enum Shape<'v> {
+    Circle { radius: i32 },
+    Label { text: &'v str },
+}
+
+#[derive(UnpackValue)]
+enum Mixed {
+    None,
+    Some { value: i32 },
+}
+
+#[starlark_module]
+fn unpack_value_functions(globals: &mut GlobalsBuilder) {
+    fn color(c: Color) -> anyhow::Result<String> {
+        Ok(format!("{:?}", c))
+    }
+
+    fn shape<'v>(s: Shape<'v>) -> anyhow::Result<String> {
+        Ok(match s {
+            Shape::Circle { radius } => format!("circle {}", radius),
+            Shape::Label { text } => format!("label {}", text),
+        })
+    }
+
+    fn mixed(m: Mixed) -> anyhow::Result<String> {
+        Ok(match m {
+            Mixed::None => "none".to_owned(),
+            Mixed::Some { value } => format!("some {}", value),
+        })
+    }
+}
+
+#[test]
+fn test_derive_unpack_value_tag() {
+    let mut a = Assert::new();
+    a.globals_add(unpack_value_functions);
+    a.eq("'Red'", "color('red')");
+    a.eq("'Green'", "color('green')");
+    a.eq("'DarkBlue'", "color('navy')");
+    a.eq("'Green'", "color(struct(tag = 'green'))");
+    a.fail(
+        "color('blue')",
+        r#"expected `one of "red", "green", "navy"`"#,
+    );
+    a.fail("color(1)", r#"expected `one of "red", "green", "navy"`"#);
+}
+
+#[test]
+fn test_derive_unpack_value_struct() {
+    let mut a = Assert::new();
+    a.globals_add(unpack_value_functions);
+    a.eq("'circle 3'", "shape(struct(tag = 'circle', radius = 3))");
+    a.eq("'label x'", "shape(struct(tag = 'label', text = 'x'))");
+    a.fail(
+        "shape(struct(tag = 'circle'))",
+        "Type of parameter `s.radius` doesn't match, expected `int.type`, actual `missing`",
+    );
+    a.fail(
+        "shape(struct(tag = 'circle', radius = 'x'))",
+        "Type of parameter `s.radius` doesn't match, expected `int.type`, actual `string`",
+    );
+    a.fail(
+        "shape(struct(tag = 'label', text = 1))",
+        "Type of parameter `s.text` doesn't match, expected `str`, actual `int`",
+    );
+    a.fail("shape('circle')", "one of");
+    a.eq("'none'", "mixed('none')");
+    a.eq("'some 1'", "mixed(struct(tag = 'some', value = 1))");
+    a.fail(
+        "mixed(struct(tag = 'some', value = None))",
+        "Type of parameter `m.value` doesn't match",
+    );
+}
+
+#[test]
+fn test_derive_unpack_value_type_repr() {
+    assert_eq!("str.type", Color::starlark_type_repr());
+    assert_eq!(r#""struct""#, Shape::starlark_type_repr());
+    assert_eq!(r#"[str.type, "struct"]"#, Mixed::starlark_type_repr());
+    assert_eq!(
+        r#"one of struct(tag = "circle", radius = int.type), struct(tag = "label", text = str)"#,
+        Shape::expected()
+    );
+    assert_eq!(
+        r#"one of "none", struct(tag = "some", value = int.type)"#,
+        Mixed::expected()
+    );
+}
@@ -379,3 +379,96 @@ Some extra details can go here,
     assert_eq!(expected_m2, m2_docs);
     assert_eq!(expected_m3, m3_docs);
 }
+
+#[test]
+fn test_module_documentation_serialize() {
+    let docs = assert::pass_module(
+        r#"
+"""Module summary"""
+def f(a: "int", b = 1) -> "string":
+    """
+    Function summary
+
+    Args:
+        a: The docs for a
+
+    Returns:
+        A string
+    """
+    return str(a + b)
+"#,
+    )
+    .module_documentation();
+
+    let json = serde_json::to_value(&docs).unwrap();
+    assert_eq!("module", json["module"]["kind"]);
+    let f = &json["members"]["f"];
+    assert_eq!("function", f["kind"]);
+    assert_eq!("a", f["params"][0]["name"]);
+    assert_eq!("The docs for a", f["params"][0]["docs"]["summary"]);
+    assert_eq!("\"int\"", f["params"][0]["type"]["raw_type"]);
+    assert_eq!("1", f["params"][1]["default_value"]);
+    assert_eq!("A string", f["ret"]["docs"]["summary"]);
+    assert_eq!("\"string\"", f["ret"]["type"]["raw_type"]);
+
+    let roundtrip: ModuleDocs = serde_json::from_value(json).unwrap();
+    assert_eq!(docs, roundtrip);
+}
+
+#[test]
+fn test_ast_module_documentation() {
+    use crate::docs::DocItem;
+    use crate::docs::DocString;
+    use crate::docs::Module;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    let code = r#"
+"""
+Module summary
+
+Module details
+"""
+def f1(a, b: "string", c: "int" = 5, *, d = "x", **kwargs) -> "string":
+    """
+    Function summary
+
+    Args:
+        a: The docs for a
+    """
+    return b
+
+def f2(*args):
+    pass
+
+def _private():
+    """ Not exported """
+    pass
+"#;
+    let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+    let ast_docs = ast.module_documentation();
+
+    assert_eq!(
+        Some(DocItem::Module(Module {
+            docs: DocString::from_docstring(
+                DocStringKind::Starlark,
+                "Module summary\n\nModule details"
+            ),
+        })),
+        ast_docs.module
+    );
+    assert_eq!(2, ast_docs.members.len());
+    match &ast_docs.members["f2"] {
+        Some(DocItem::Function(f)) => assert_eq!(None, f.docs),
+        x => panic!("Expected function docs, got {:?}", x),
+    }
+
+    // Same as the documentation of the evaluated module.
+    let evaluated = assert::pass_module(code).module_documentation();
+    assert_eq!(evaluated, ast_docs);
+
+    let ast = AstModule::parse("a.star", "x = 1\n".to_owned(), &Dialect::Extended).unwrap();
+    let ast_docs = ast.module_documentation();
+    assert_eq!(None, ast_docs.module);
+    assert!(ast_docs.members.is_empty());
+}
@@ -73,6 +73,8 @@ fn test_go() {
             // We added copy, which throws off the assert
             "dir({})[:3]",
             "dir([])[:3]",
+            // We added int methods
+            "dir(1)",
         ],
     ));
     assert.conformance(test_case!("control.star"));
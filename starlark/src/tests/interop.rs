@@ -17,7 +17,9 @@
 
 //! Test starlark-rust embedding.
 
+use std::cell::Cell;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -30,14 +32,20 @@ use crate::any::ProvidesStaticType;
 use crate::assert;
 use crate::assert::Assert;
 use crate::collections::SmallMap;
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
 use crate::environment::Module;
 use crate::eval::Evaluator;
+use crate::eval::FileLoader;
+use crate::eval::LoadCache;
+use crate::eval::ProfileMode;
 use crate::syntax::AstModule;
 use crate::syntax::Dialect;
 use crate::values::any::StarlarkAny;
 use crate::values::none::NoneType;
 use crate::values::Freeze;
+use crate::values::FrozenHeap;
 use crate::values::NoSerialize;
 use crate::values::StarlarkValue;
 use crate::values::Value;
@@ -247,3 +255,162 @@ fun
         .unwrap();
     assert_eq!(v.unpack_str(), Some("(8, \"hello\", 1)"))
 }
+
+#[starlark_module]
+fn native_map_module(builder: &mut GlobalsBuilder) {
+    /// Native function which calls back into Starlark for each element.
+    fn native_map<'v>(
+        f: Value<'v>,
+        xs: Vec<Value<'v>>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Vec<Value<'v>>> {
+        xs.into_iter()
+            .map(|x| eval.eval_function(f, &[x], &[]))
+            .collect()
+    }
+}
+
+#[test]
+fn test_native_function_calls_back_into_starlark() {
+    let mut a = Assert::new();
+    a.globals_add(native_map_module);
+    a.is_true("native_map(lambda x: x * 2, [1, 2, 3]) == [2, 4, 6]");
+    // Nested re-entrant calls.
+    a.is_true(
+        "native_map(lambda xs: native_map(lambda x: -x, xs), [[1], [2, 3]]) == [[-1], [-2, -3]]",
+    );
+    // Errors raised in the callback propagate with the callback in the call stack.
+    a.fail("native_map(lambda x: fail('bad ' + str(x)), [1])", "bad 1");
+    // Call stack depth is restored after callbacks return, so recursion limit is not hit.
+    a.pass("[native_map(lambda x: x, list(range(10))) for _ in range(100)]");
+    a.fail(
+        r#"
+def f(x):
+    return native_map(f, [x])
+f(1)
+"#,
+        "Starlark call stack overflow",
+    );
+}
+
+#[test]
+fn test_native_function_calls_back_into_starlark_profile() -> anyhow::Result<()> {
+    let globals = GlobalsBuilder::extended().with(native_map_module).build();
+    let modu = Module::new();
+    let mut eval = Evaluator::new(&modu);
+    eval.enable_profile(&ProfileMode::HeapFlameAllocated)?;
+    eval.eval_module(
+        AstModule::parse(
+            "a.star",
+            r#"
+def callback(x):
+    return [x]
+native_map(callback, [1, 2, 3])
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )?,
+        &globals,
+    )?;
+    let profile = eval.gen_profile()?.gen()?;
+    assert!(
+        profile.contains("native_map;a.star.callback"),
+        "callback must be recorded as called from `native_map`: {}",
+        profile
+    );
+    Ok(())
+}
+
+#[test]
+fn test_load_cache() {
+    /// Evaluates `lib.star` each time it is loaded.
+    struct CountingLoader {
+        loads: Cell<usize>,
+    }
+
+    impl FileLoader for CountingLoader {
+        fn load(&self, path: &str) -> anyhow::Result<FrozenModule> {
+            assert_eq!("lib.star", path);
+            self.loads.set(self.loads.get() + 1);
+            let module = Module::new();
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse(path, "x = 17".to_owned(), &Dialect::Extended)?;
+            eval.eval_module(ast, &Globals::standard())?;
+            module.freeze()
+        }
+    }
+
+    #[derive(Default)]
+    struct MapLoadCache(RefCell<HashMap<String, FrozenModule>>);
+
+    impl LoadCache for MapLoadCache {
+        fn get(&self, path: &str) -> Option<FrozenModule> {
+            self.0.borrow().get(path).cloned()
+        }
+
+        fn insert(&self, path: &str, module: FrozenModule) {
+            self.0.borrow_mut().insert(path.to_owned(), module);
+        }
+    }
+
+    let loader = CountingLoader {
+        loads: Cell::new(0),
+    };
+    let cache = MapLoadCache::default();
+    for name in ["a.star", "b.star"] {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        eval.set_load_cache(&cache);
+        let ast = AstModule::parse(
+            name,
+            "load('lib.star', 'x')\nload('dir/../lib.star', y = 'x')\nload('./lib.star', w = 'x')\nz = x + y + w"
+                .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        assert_eq!(Some(51), module.get("z").unwrap().unpack_int());
+    }
+    assert_eq!(1, loader.loads.get());
+    assert_eq!(
+        vec!["lib.star"],
+        cache.0.borrow().keys().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_name_resolution_hook() -> anyhow::Result<()> {
+    let calls = Cell::new(0);
+    let hook = |name: &str, heap: &FrozenHeap| {
+        calls.set(calls.get() + 1);
+        name.strip_prefix("virtual_")
+            .map(|suffix| heap.alloc(suffix.to_uppercase()))
+    };
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_name_resolution_hook(&hook);
+    let ast = AstModule::parse(
+        "a.star",
+        "x = virtual_foo + virtual_foo\ny = len(virtual_bar)".to_owned(),
+        &Dialect::Standard,
+    )?;
+    eval.eval_module(ast, &Globals::standard())?;
+    assert_eq!(Some("FOOFOO"), module.get("x").unwrap().unpack_str());
+    assert_eq!(Some(3), module.get("y").unwrap().unpack_int());
+    // Called once per name, not for names found in the globals.
+    assert_eq!(2, calls.get());
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_name_resolution_hook(&hook);
+    let ast = AstModule::parse("a.star", "x = unknown".to_owned(), &Dialect::Standard)?;
+    let err = eval.eval_module(ast, &Globals::standard()).unwrap_err();
+    assert!(
+        err.to_string().contains("Variable `unknown` not found"),
+        "{}",
+        err
+    );
+    Ok(())
+}
@@ -27,7 +27,10 @@ mod freeze_access_value;
 mod go;
 mod interop;
 mod opt;
+mod repr;
 mod runtime;
 mod rustdocs;
+mod slice;
+mod step;
 mod type_annot;
 mod uncategorized;
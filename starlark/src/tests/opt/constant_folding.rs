@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use crate::assert;
 use crate::assert::Assert;
 use crate::tests::bc::golden::bc_golden_test;
 
@@ -41,3 +42,31 @@ fn test_fold_list_add_too_large() {
         );
     }
 }
+
+#[test]
+fn test_fold_module_constants_on_freeze() {
+    bc_golden_test(
+        "constant_folding_module_constants_on_freeze",
+        "def test(): return WIDTH * HEIGHT + len(NAME)\nWIDTH = 3\nHEIGHT = 4\nNAME = 'abc'",
+    );
+}
+
+#[test]
+fn test_fold_module_constants_not_reassigned() {
+    // Globals assigned more than once are not inlined before freeze.
+    assert::pass(
+        r#"
+X = 1
+def f(): return X + 1
+a = f()
+X = 2
+assert_eq(a, 2)
+assert_eq(f(), 3)
+
+if len(str(a)) == 1:
+    Y = 10
+def g(): return Y * 2
+assert_eq(g(), 20)
+"#,
+    );
+}
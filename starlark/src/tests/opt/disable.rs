@@ -0,0 +1,88 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for `Evaluator::enable_optimizations(false)`.
+
+use crate::assert::Assert;
+use crate::eval::compiler::def::FrozenDef;
+
+fn assert(optimizations: bool) -> Assert<'static> {
+    let mut a = Assert::new();
+    a.setup_eval(move |eval| eval.enable_optimizations(optimizations));
+    a
+}
+
+fn test_bc(optimizations: bool, program: &str) -> String {
+    assert(optimizations)
+        .module("a.star", program)
+        .get("test")
+        .unwrap()
+        .downcast::<FrozenDef>()
+        .unwrap()
+        .bc()
+        .dump_debug()
+}
+
+#[test]
+fn test_disabled_optimizations_do_not_fold() {
+    for program in [
+        "def test(): return 1 + 2",
+        "def test(): return -(3)",
+        "def test(): return (1, 2)[0]",
+        "def test(): return 'x' == 'x'",
+        "def test(): return 'foo'.startswith('f')",
+        "def test(): return 'x{}y'.format(1)",
+        "def test(): return len('abc') + 1",
+    ] {
+        let optimized = test_bc(true, program);
+        let unoptimized = test_bc(false, program);
+        assert_ne!(optimized, unoptimized, "{}", program);
+    }
+}
+
+#[test]
+fn test_disabled_optimizations_same_result() {
+    let program = r#"
+N = 10
+def f(x):
+    return x * 2 + N
+
+def test():
+    return [
+        1 + 2,
+        -3,
+        "a" + "b",
+        "foo".startswith("f"),
+        "x{}y".format(1),
+        "%s!" % "a",
+        (1, 2, 3)[1:],
+        [1] + [2],
+        f(3),
+        str(1.5),
+        type(1) == "int",
+        1 if 2 > 1 else 0,
+    ]
+
+result = repr(test())
+"#;
+    let optimized = assert(true).pass_module(program).get("result").unwrap();
+    let unoptimized = assert(false).pass_module(program).get("result").unwrap();
+    assert_eq!(
+        optimized.value().unpack_str().unwrap(),
+        unoptimized.value().unpack_str().unwrap()
+    );
+}
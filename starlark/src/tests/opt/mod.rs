@@ -19,6 +19,7 @@
 
 mod constant_folding;
 mod def_inline;
+mod disable;
 mod if_rand;
 mod speculative_exec;
 mod type_is;
@@ -15,7 +15,16 @@
  * limitations under the License.
  */
 
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 use crate::tests::bc::golden::bc_golden_test;
+use crate::values::function::NativeFunction;
 
 #[test]
 fn test_methods_invoked_speculatively() {
@@ -39,3 +48,89 @@ def test():
 "#,
     );
 }
+
+#[test]
+fn test_speculative_exec_guard() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let mut double = NativeFunction::new_direct(
+        |eval, args| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            let x = args.positional1(eval.heap())?;
+            x.add(x, eval.heap())
+        },
+        "double".to_owned(),
+    );
+    // Only ints are folded, so calls with strings are counted at runtime.
+    double.set_speculative_exec_guard(|args| {
+        args.positional_args()
+            .iter()
+            .all(|x| x.unpack_int().is_some())
+    });
+    let mut globals = GlobalsBuilder::standard();
+    globals.set("double", double);
+    let globals = globals.build();
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse(
+        "a.star",
+        r#"
+def f():
+    return double(21)
+def g():
+    return double("ab")
+res = [f(), f(), f(), g(), g(), g()]
+"#
+        .to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    eval.eval_module(ast, &globals).unwrap();
+
+    assert_eq!(
+        "[42, 42, 42, \"abab\", \"abab\", \"abab\"]",
+        module.get("res").unwrap().to_repr()
+    );
+    // Once when compiling `f`, and on every call of `g`.
+    assert_eq!(4, CALLS.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_speculative_exec_mutates_args() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let mut touch = NativeFunction::new_direct(
+        |eval, args| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            args.positional1(eval.heap())
+        },
+        "touch".to_owned(),
+    );
+    // Declaring mutation takes precedence over both the flag and the guard.
+    touch.speculative_exec_safe = true;
+    touch.set_speculative_exec_guard(|_| true);
+    touch.set_mutates_args();
+    let mut globals = GlobalsBuilder::standard();
+    globals.set("touch", touch);
+    let globals = globals.build();
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse(
+        "a.star",
+        r#"
+def f():
+    return touch(1)
+res = [f(), f(), f()]
+"#
+        .to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    eval.eval_module(ast, &globals).unwrap();
+
+    assert_eq!("[1, 1, 1]", module.get("res").unwrap().to_repr());
+    // Never called at compile time.
+    assert_eq!(3, CALLS.load(Ordering::SeqCst));
+}
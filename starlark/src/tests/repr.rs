@@ -0,0 +1,98 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Check that `repr` output parses back to an equal value.
+
+use crate::assert;
+
+/// Evaluate `expr`, then evaluate its `repr` and check it produces an equal value.
+fn assert_repr_round_trip(expr: &str) {
+    let repr = assert::pass(&format!("repr({})", expr));
+    let repr = repr.unpack_str().unwrap();
+    assert::is_true(&format!("({}) == ({})", repr, expr));
+}
+
+#[test]
+fn test_repr_round_trip() {
+    for expr in [
+        "None",
+        "True",
+        "False",
+        "0",
+        "-17",
+        "1 << 100",
+        "1.5",
+        "-0.25",
+        "1e300",
+        "''",
+        "'abc'",
+        r#"'a"b\'c'"#,
+        r"'\n\t\r\\'",
+        r"'\x00\x01\x7f'",
+        "'\u{1F600}'",
+        "()",
+        "(1,)",
+        "((1,),)",
+        "(1, 'x', (2,))",
+        "[]",
+        "[1, [2, (3,)]]",
+        "{}",
+        "{1: (2,), 'x': [3]}",
+        "{(1,): -1e-07}",
+    ] {
+        assert_repr_round_trip(expr);
+    }
+}
+
+#[test]
+fn test_repr_float_shortest_round_trip() {
+    for expr in [
+        "0.1",
+        "0.1 + 0.2",
+        "1 / 3",
+        "2 / 3",
+        "1.1 * 1.1",
+        "123456789.123",
+        "1e22",
+        "1e-7 / 3",
+        "-0.0",
+        "5e-324",
+        "1.7976931348623157e308",
+    ] {
+        assert_repr_round_trip(expr);
+    }
+    assert::eq("repr(0.1)", "'0.1'");
+    assert::eq("repr(0.1 + 0.2)", "'0.30000000000000004'");
+    assert::eq("repr(1 / 3)", "'0.3333333333333333'");
+    assert::eq("repr(123456789.123)", "'1.23456789123e+08'");
+    assert::eq("str(1e-7 / 3)", "'3.3333333333333334e-08'");
+}
+
+#[test]
+fn test_repr_single_element_tuple() {
+    assert::eq("repr((1,))", "'(1,)'");
+    assert::eq("repr(('x',))", "'(\"x\",)'");
+    assert::eq("repr([(1,)])", "'[(1,)]'");
+}
+
+#[test]
+fn test_repr_special_floats() {
+    // Non-finite floats are printed as in the Go implementation, so they do not round-trip.
+    assert::eq("repr(float('+inf'))", "'+inf'");
+    assert::eq("repr(float('-inf'))", "'-inf'");
+    assert::eq("repr((float('nan'),))", "'(nan,)'");
+}
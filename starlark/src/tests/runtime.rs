@@ -17,22 +17,39 @@
 
 //! Test of runtime.
 
+use std::cell::Cell;
+use std::cell::RefCell;
 use std::fmt::Write;
 use std::mem;
+use std::rc::Rc;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
 
 use derive_more::Display;
+use dupe::Dupe;
 use once_cell::sync::Lazy;
 
 use crate as starlark;
 use crate::assert;
 use crate::assert::Assert;
+use crate::codemap::FileSpan;
+use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
+use crate::errors::Diagnostic;
+use crate::eval::CallEvent;
 use crate::eval::Evaluator;
+use crate::eval::RecursionLimitExceeded;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 use crate::values::any::StarlarkAny;
 use crate::values::FrozenHeap;
 use crate::values::Heap;
+use crate::values::HeapLimitExceeded;
+use crate::values::StringLengthExceeded;
+use crate::values::Value;
 
 #[test]
 fn test_garbage_collect() {
@@ -229,3 +246,541 @@ fn test_display_debug() {
     assert_eq!(format!("{:?}", v), "FrozenValue(\"test\")");
     assert_eq!(format!("{:#?}", v), "FrozenValue(\n    \"test\",\n)");
 }
+
+#[test]
+fn test_heap_limit() {
+    #[starlark_module]
+    fn globals(builder: &mut GlobalsBuilder) {
+        /// Call `f` with a heap limit, returning `None` if it fails.
+        fn call_limited<'v>(
+            f: Value<'v>,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<Value<'v>> {
+            match eval.with_heap_limit(10_000, |eval| eval.eval_function(f, &[], &[])) {
+                Ok(v) => Ok(v),
+                Err(e) => {
+                    let err = match e.downcast_ref::<Diagnostic>() {
+                        Some(diag) => &diag.message,
+                        None => &e,
+                    };
+                    assert!(err.downcast_ref::<HeapLimitExceeded>().is_some(), "{}", e);
+                    Ok(Value::new_none())
+                }
+            }
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(globals);
+    a.pass(
+        r#"
+def small():
+    return [1, 2, 3]
+def big():
+    return [x for x in range(10000)]
+assert_eq(call_limited(small), [1, 2, 3])
+assert_eq(call_limited(big), None)
+# The limit is gone once the callback returns.
+assert_eq(len(big()), 10000)
+assert_eq(call_limited(lambda: call_limited(big)), None)
+# Rejected before the memory is requested.
+assert_eq(call_limited(lambda: "x" * 1000000000), None)
+assert_eq(call_limited(lambda: list(range(1000000000))), None)
+"#,
+    );
+}
+
+#[test]
+fn test_max_heap_bytes() {
+    fn eval_limited(code: &str) -> anyhow::Result<()> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_max_heap_bytes(1_000_000);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &Globals::standard())?;
+        Ok(())
+    }
+
+    fn assert_limit_exceeded(code: &str) {
+        let err = eval_limited(code).unwrap_err();
+        let err = match err.downcast_ref::<Diagnostic>() {
+            Some(diag) => &diag.message,
+            None => &err,
+        };
+        let err = err
+            .downcast_ref::<HeapLimitExceeded>()
+            .unwrap_or_else(|| panic!("{}: {}", code, err));
+        assert_eq!(1_000_000, err.limit);
+    }
+
+    eval_limited("x = [str(i) for i in range(1000)]").unwrap();
+    // Rejected before the list is built.
+    assert_limit_exceeded("x = [0] * 1000000000");
+    assert_limit_exceeded("x = 'abc' * 1000000000");
+    assert_limit_exceeded("x = (1, 2) * 1000000000");
+    assert_limit_exceeded("x = list(range(1000000000))");
+    assert_limit_exceeded("x = []\nx.extend(range(1000000000))");
+    // Checked while looping, without any calls.
+    assert_limit_exceeded(
+        r#"
+x = []
+for i in range(1000000000):
+    x.append(str(i))
+"#,
+    );
+    // Checked after calls.
+    assert_limit_exceeded(
+        r#"
+def f():
+    return [str(i) for i in range(100000)]
+x = f()
+"#,
+    );
+}
+
+#[test]
+fn test_max_string_length() {
+    fn eval_limited(code: &str) -> anyhow::Result<String> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_max_string_length(1000);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        Ok(eval.eval_module(ast, &Globals::standard())?.to_str())
+    }
+
+    fn assert_limit_exceeded(code: &str) {
+        let err = eval_limited(code).unwrap_err();
+        let err = match err.downcast_ref::<Diagnostic>() {
+            Some(diag) => &diag.message,
+            None => &err,
+        };
+        let err = err
+            .downcast_ref::<StringLengthExceeded>()
+            .unwrap_or_else(|| panic!("{}: {}", code, err));
+        assert_eq!(1000, err.limit);
+        assert!(err.length > 1000, "{}: {}", code, err);
+    }
+
+    assert_eq!(
+        1000,
+        eval_limited("len('a' * 1000)")
+            .unwrap()
+            .parse::<usize>()
+            .unwrap()
+    );
+    assert_limit_exceeded("'a' * 1000000000000");
+    assert_limit_exceeded("'a' * 1001");
+    assert_limit_exceeded("s = 'a' * 600\ns + s");
+    assert_limit_exceeded("s = 'a' * 600\ns += s");
+    assert_limit_exceeded("''.join(['a' * 600, 'b' * 600])");
+    assert_limit_exceeded("s = 'a' * 600\n'{}{}'.format(s, s)");
+    assert_limit_exceeded("s = 'a' * 600\n'{0}{0}'.format(s)");
+    assert_limit_exceeded("s = 'a' * 600\n'{}-'.format(s) * 2");
+    assert_limit_exceeded("s = 'a' * 600\n'%s%s' % (s, s)");
+    assert_limit_exceeded("s = 'a' * 1000\n'x%s' % s");
+    assert_limit_exceeded("s = ['a'] * 999\n'%s' % (s,)");
+    assert_limit_exceeded("s = 'a' * 999\n'%r' % s");
+    assert_limit_exceeded("s = 'a' * 1000\n'x{}'.format(s)");
+    assert_limit_exceeded("s = ['a'] * 999\n'x{}'.format(s)");
+    assert_limit_exceeded("def f(s):\n    return 'x{}y{}'.format(s, s)\nf('a' * 600)");
+    // Rejected before padding or formatting to the requested width or precision.
+    assert_limit_exceeded("'{:1000000000000}'.format('a')");
+    assert_limit_exceeded("'{:>1000000000000}'.format(1)");
+    assert_limit_exceeded("'{!r:^1000000000000}'.format([])");
+    assert_limit_exceeded("'{:.1000000000000f}'.format(1.5)");
+    assert_eq!(
+        "abc",
+        eval_limited("'{:.1000000000000}'.format('abc')").unwrap()
+    );
+    assert_limit_exceeded("s = 'a' * 600\n'%r%s' % (s, [s])");
+    // Fails at runtime, not during constant folding.
+    assert_limit_exceeded("def f():\n    return '{}{}'.format('a' * 600, 'b' * 600)\nf()");
+}
+
+#[test]
+fn test_call_stack_depth_limit() {
+    let program = r#"
+def is_even(n):
+    return True if n == 0 else is_odd(n - 1)
+
+def is_odd(n):
+    return False if n == 0 else is_even(n - 1)
+"#;
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+
+    let call = |eval: &mut Evaluator, code: &str| {
+        let ast = AstModule::parse("b.star", code.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &Globals::standard())
+            .map(|v| v.to_repr())
+    };
+
+    // The default limit is hit well before the native stack overflows.
+    let err = call(&mut eval, "is_even(55)").unwrap_err();
+    let diag = err.downcast_ref::<Diagnostic>().unwrap();
+    let limit = diag
+        .message
+        .downcast_ref::<RecursionLimitExceeded>()
+        .unwrap_or_else(|| panic!("{}", err));
+    assert_eq!(50, limit.limit);
+    // The error points at the call which exceeded the limit.
+    let span = diag.span.as_ref().unwrap();
+    assert_eq!("a.star", span.filename());
+    assert!(span.source_span().starts_with("is_"), "{}", span);
+    assert!(!diag.call_stack.is_empty());
+
+    // The stack unwound cleanly, so the evaluator can be reused.
+    assert_eq!("True", call(&mut eval, "is_even(4)").unwrap());
+
+    eval.set_call_stack_depth_limit(60);
+    assert_eq!("False", call(&mut eval, "is_even(55)").unwrap());
+    eval.set_call_stack_depth_limit(10);
+    assert!(call(&mut eval, "is_even(10)").is_err());
+    assert_eq!("True", call(&mut eval, "is_even(6)").unwrap());
+}
+
+#[test]
+fn test_total_order() {
+    fn eval_sorted(total_order: bool, code: &str) -> anyhow::Result<String> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_total_order(total_order);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        Ok(eval.eval_module(ast, &Globals::extended())?.to_repr())
+    }
+
+    let values = "[2, 'b', None, 1, 'a', (1,), True]";
+    let mixed = format!("sorted({})", values);
+    assert!(eval_sorted(false, &mixed).is_err());
+    // Grouped by type name, comparable values in their usual order.
+    assert_eq!(
+        "[None, True, 1, 2, \"a\", \"b\", (1,)]",
+        eval_sorted(true, &mixed).unwrap()
+    );
+    assert_eq!(
+        "[(1,), \"b\", \"a\", 2, 1, True, None]",
+        eval_sorted(true, &format!("sorted({}, reverse=True)", values)).unwrap()
+    );
+    // Numbers of all types are ordered together, so the order is transitive.
+    assert_eq!(
+        "[len, 0.5, 1, 2.5, 3, \"a\"]",
+        eval_sorted(true, "sorted([2.5, 'a', 1, len, 3, 0.5])").unwrap()
+    );
+    assert_eq!(
+        eval_sorted(true, "sorted([1, 2.5, len])").unwrap(),
+        eval_sorted(true, "sorted([len, 2.5, 1])").unwrap()
+    );
+    // Incomparable values of the same type are ordered by hash, so consistently.
+    let members = "E = enum('x', 'y')\na = E('x')\nb = E('y')\n";
+    assert!(eval_sorted(false, &format!("{}sorted([a, b])", members)).is_err());
+    assert_eq!(
+        eval_sorted(true, &format!("{}sorted([a, b])", members)).unwrap(),
+        eval_sorted(true, &format!("{}sorted([b, a])", members)).unwrap()
+    );
+    // Equality is unchanged.
+    assert_eq!("False", eval_sorted(true, "1 == '1'").unwrap());
+    // Values which are neither comparable nor hashable still fail.
+    assert!(eval_sorted(true, "sorted([{}, {}])").is_err());
+}
+
+#[test]
+fn test_value_provenance() {
+    let program = r#"
+def make(port):
+    return struct(port = port, name = "p%d" % port)
+
+config = struct(
+    server = make(80),
+    items = [1],
+)
+f = lambda: None
+"#;
+    let eval_module = |track: bool| {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.track_value_provenance(track);
+            let ast =
+                AstModule::parse("config.star", program.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::extended()).unwrap();
+        }
+        module.freeze().unwrap()
+    };
+    let line = |span: FileSpan| {
+        assert_eq!("config.star", span.filename());
+        span.resolve_span().begin_line + 1
+    };
+
+    let module = eval_module(true);
+    let config = module.get("config").unwrap();
+    let config = config.value();
+    let heap = Heap::new();
+    assert_eq!(5, line(config.provenance().unwrap()));
+    let server = config.get_attr("server", &heap).unwrap().unwrap();
+    assert_eq!(3, line(server.provenance().unwrap()));
+    // Allocated by `make` itself, outside of any call it makes.
+    let name = server.get_attr("name", &heap).unwrap().unwrap();
+    assert_eq!(6, line(name.provenance().unwrap()));
+    let items = config.get_attr("items", &heap).unwrap().unwrap();
+    assert_eq!(7, line(items.provenance().unwrap()));
+    assert_eq!(
+        2,
+        line(module.get("make").unwrap().value().provenance().unwrap())
+    );
+    assert_eq!(
+        9,
+        line(module.get("f").unwrap().value().provenance().unwrap())
+    );
+
+    // Values are only tracked when enabled, functions always are.
+    let module = eval_module(false);
+    assert_eq!(None, module.get("config").unwrap().value().provenance());
+    assert!(module.get("make").unwrap().value().provenance().is_some());
+}
+
+#[test]
+fn test_call_timeout_per_function() {
+    // Every reading of the clock advances it by `step`,
+    // so a native call takes `step` plus twice `step` per native call it makes.
+    let run = |code: &str, step: u64| {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_call_timeout_per_function(Duration::from_millis(100));
+        let now = Cell::new(Instant::now());
+        eval.native_call_clock = Some(Box::new(move || {
+            now.set(now.get() + Duration::from_millis(step));
+            now.get()
+        }));
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &Globals::standard())
+            .map(|v| v.to_repr())
+    };
+
+    // Many fast calls exceed the limit in total, but each is within it.
+    assert_eq!(
+        "\"199\"",
+        run("[str(i) for i in range(200)][-1]", 40).unwrap()
+    );
+    // Starlark functions are not timed.
+    assert_eq!("1", run("def f():\n    return 1\nf()", 200).unwrap());
+    // Natives calling back into Starlark are timed including the callback.
+    assert_eq!(
+        "[1, 2]",
+        run("sorted([2, 1], key = lambda x: x)", 40).unwrap()
+    );
+
+    let err = run("def f(x):\n    str(x)\nf(1)", 200).unwrap_err();
+    let diag = err.downcast_ref::<Diagnostic>().unwrap();
+    assert_eq!(
+        "Native function `str` call took 200ms, limit is 100ms",
+        diag.message.to_string()
+    );
+    assert_eq!("str(x)", diag.span.as_ref().unwrap().source_span());
+    // Methods are timed too.
+    let err = run("def f(x):\n    x.upper()\nf('a')", 200).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Native function `upper` call took 200ms"),
+        "{}",
+        err
+    );
+    // Each callback is within the limit, but not the `sorted` call as a whole.
+    let err = run("sorted([2, 1], key = lambda x: str(x))", 40).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Native function `sorted` call took 200ms"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_call_observer() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_call_observer(Box::new({
+        let events = events.dupe();
+        move |event: CallEvent| {
+            let span = event
+                .span()
+                .map_or(String::new(), |s| s.source_span().to_owned());
+            events
+                .borrow_mut()
+                .push(format!("{:?} {} {}", event.kind(), event.name(), span));
+        }
+    }));
+    let code = "def f(x):\n    return sorted(x)\nf([1])";
+    let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    assert_eq!(
+        vec![
+            "Enter f f([1])",
+            "Enter sorted sorted(x)",
+            "Exit sorted sorted(x)",
+            "Exit f f([1])",
+        ],
+        *events.borrow()
+    );
+
+    eval.clear_call_observer();
+    let ast = AstModule::parse("b.star", "len([])".to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    assert_eq!(4, events.borrow().len());
+}
+
+#[test]
+fn test_value_provenance_literals() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.track_value_provenance(true);
+    let code = r#"
+def mk():
+    return {"k": [1, 2]}
+x = struct(a = mk(), b = [3])
+garbage = [[i] for i in range(100)]
+"#;
+    let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::extended()).unwrap();
+    // Provenance survives values being moved by GC.
+    unsafe { eval.garbage_collect() };
+
+    let x = module.get("x").unwrap();
+    let origin = |v: Value| v.provenance().map(|s| s.source_span().to_owned());
+    assert_eq!(Some("struct(a = mk(), b = [3])".to_owned()), origin(x));
+    let a = x.get_attr("a", module.heap()).unwrap().unwrap();
+    assert_eq!(Some(r#"{"k": [1, 2]}"#.to_owned()), origin(a));
+    let b = x.get_attr("b", module.heap()).unwrap().unwrap();
+    assert_eq!(Some("[3]".to_owned()), origin(b));
+    assert_eq!(3, b.provenance().unwrap().resolve_span().begin_line);
+    assert_eq!(None, origin(Value::new_int(1)));
+}
+
+#[test]
+fn test_trace_allocations_to() {
+    #[derive(Clone, Dupe, Default)]
+    struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buf = SharedBuf::default();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.trace_allocations_to(Box::new(buf.dupe()));
+    let code = "def f(x):\n    return x * 2.5\ny = f(1.5)\nz = f(2.5)";
+    let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    eval.stop_tracing_allocations().unwrap();
+
+    let log = String::from_utf8(buf.0.borrow().clone()).unwrap();
+    let floats: Vec<&str> = log.lines().filter(|l| l.starts_with("float\t")).collect();
+    // A float is a vtable pointer followed by an `f64`.
+    let size = mem::size_of::<usize>() + mem::size_of::<f64>();
+    assert_eq!(
+        vec![
+            format!("float\t{}\ta.star:3:5-11", size),
+            format!("float\t{}\ta.star:4:5-11", size),
+        ],
+        floats
+    );
+
+    // Nothing is written after tracing is stopped.
+    let len = buf.0.borrow().len();
+    let ast = AstModule::parse("b.star", code.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    assert_eq!(len, buf.0.borrow().len());
+}
+
+#[test]
+fn test_canonical_dict_iteration() {
+    let code = r#"
+a = {"b": 1, "c": 2, "a": 3}
+b = {"a": 3, "c": 2, "b": 1}
+def render(d):
+    return ",".join([k + "=" + str(d[k]) for k in d])
+res_a = render(a)
+res_b = render(b)
+listed = list(a)
+keys = a.keys()
+same = a == b
+"#;
+    let run = |canonical: bool| {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_canonical_dict_iteration(canonical);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        let get = |name: &str| module.get(name).unwrap().to_str();
+        (
+            get("res_a"),
+            get("res_b"),
+            get("listed"),
+            get("keys"),
+            get("same"),
+        )
+    };
+
+    assert_eq!(
+        (
+            "a=3,b=1,c=2".to_owned(),
+            "a=3,b=1,c=2".to_owned(),
+            "[\"a\", \"b\", \"c\"]".to_owned(),
+            "[\"b\", \"c\", \"a\"]".to_owned(),
+            "True".to_owned(),
+        ),
+        run(true)
+    );
+    assert_eq!(
+        (
+            "b=1,c=2,a=3".to_owned(),
+            "a=3,c=2,b=1".to_owned(),
+            "[\"b\", \"c\", \"a\"]".to_owned(),
+            "[\"b\", \"c\", \"a\"]".to_owned(),
+            "True".to_owned(),
+        ),
+        run(false)
+    );
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_canonical_dict_iteration(true);
+    let ast = AstModule::parse(
+        "a.star",
+        "list({1: 1, 'x': 2})".to_owned(),
+        &Dialect::Extended,
+    )
+    .unwrap();
+    let err = eval.eval_module(ast, &Globals::standard()).unwrap_err();
+    assert!(err.to_string().contains("`cmp()` not supported"), "{}", err);
+}
+
+#[test]
+fn test_canonical_dict_iteration_mutate() {
+    for code in [
+        "d = {1: 1, 2: 2}\nfor k in d:\n    d.pop(k)",
+        "d = {1: 1, 2: 2}\ndef pop(k):\n    return d.pop(k)\nsorted(d, key = pop)",
+    ] {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_canonical_dict_iteration(true);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+        let err = eval.eval_module(ast, &Globals::standard()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("dict changed size during iteration"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Slicing of strings, lists and tuples, checked against a reference implementation.
+
+use crate::assert;
+
+/// Computes the indices selected by `xs[start:stop:step]`
+/// following the Python rules for omitted and out-of-range bounds.
+const REFERENCE: &str = r#"
+def normalize(i, n, default, lower, upper):
+    if i == None:
+        return default
+    if i < 0:
+        return max(i + n, lower)
+    return min(i, upper)
+
+def indices(n, start, stop, step):
+    step = 1 if step == None else step
+    lower, upper = (0, n) if step > 0 else (-1, n - 1)
+    start = normalize(start, n, upper if step < 0 else lower, lower, upper)
+    stop = normalize(stop, n, lower if step < 0 else upper, lower, upper)
+    return range(start, stop, step)
+
+BOUNDS = [None, -100, -8, -7, -3, -1, 0, 1, 3, 6, 7, 100]
+STEPS = [None, 1, 2, 3, 100, -1, -2, -3, -100]
+
+def check(s):
+    xs = list(s.elems())
+    t = tuple(xs)
+    n = len(xs)
+    for start in BOUNDS:
+        for stop in BOUNDS:
+            for step in STEPS:
+                idx = indices(n, start, stop, step)
+                expected = [xs[i] for i in idx]
+                assert_eq(s[start:stop:step], "".join(expected))
+                assert_eq(xs[start:stop:step], expected)
+                assert_eq(t[start:stop:step], tuple(expected))
+"#;
+
+#[test]
+fn test_slice_matrix() {
+    assert::pass(&format!("{}\ncheck('abcdefg')", REFERENCE));
+}
+
+#[test]
+fn test_slice_matrix_short() {
+    assert::pass(&format!("{}\ncheck('')\ncheck('a')", REFERENCE));
+}
+
+#[test]
+fn test_slice_matrix_non_ascii() {
+    assert::pass(&format!("{}\ncheck('aé€b😀cd')", REFERENCE));
+}
+
+#[test]
+fn test_slice_negative_step() {
+    assert::eq("'hello'[::-1]", "'olleh'");
+    assert::eq("(1, 2, 3)[::-1]", "(3, 2, 1)");
+    assert::eq("[0, 1, 2, 3, 4, 5, 6][5:1:-2]", "[5, 3]");
+    assert::eq("'abcdef'[5:1:-2]", "'fd'");
+    assert::eq("(0, 1, 2, 3, 4, 5)[5:1:-2]", "(5, 3)");
+    assert::eq("[1, 2, 3][1:2:-1]", "[]");
+    assert::eq("'abc'[-100::-1]", "''");
+    assert::eq("(1, 2, 3)[100::-1]", "(3, 2, 1)");
+    // `None` is the same as an omitted bound.
+    assert::eq("'abc'[None:None:-1]", "'cba'");
+    assert::eq("'abc'[None:2]", "'ab'");
+}
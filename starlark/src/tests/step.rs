@@ -0,0 +1,153 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::thread;
+
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::eval::StepHandle;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+const PROGRAM: &str = "\
+def f(x):
+  return x * 2
+y = f(1) + f(2)
+";
+
+/// Evaluate `PROGRAM` on another thread, pausing every `n` instructions,
+/// and pass the handle controlling it to `control`.
+/// Return whether evaluation succeeded.
+fn step_program(n: u64, control: impl FnOnce(&StepHandle)) -> bool {
+    let (handle, ok) = thread::scope(|s| {
+        let (handle_sender, handle_receiver) = std::sync::mpsc::channel();
+        let evaluation = s.spawn(move || {
+            let module = Module::new();
+            let globals = Globals::standard();
+            let mut eval = Evaluator::new(&module);
+            handle_sender
+                .send(eval.step_instructions(n).unwrap())
+                .unwrap();
+            let ast = AstModule::parse("a.star", PROGRAM.to_owned(), &Dialect::Extended).unwrap();
+            let ok = eval.eval_module(ast, &globals).is_ok();
+            drop(eval);
+            if ok {
+                assert_eq!(6, module.get("y").unwrap().unpack_int().unwrap());
+            } else {
+                assert!(module.get("y").is_none());
+            }
+            ok
+        });
+        let handle = handle_receiver.recv().unwrap();
+        control(&handle);
+        (handle, evaluation.join().unwrap())
+    });
+    // Evaluator is dropped, no more pauses.
+    assert!(handle.wait().is_none());
+    ok
+}
+
+/// Resume after every pause, return `executed` at each pause.
+fn resume_all(n: u64) -> Vec<u64> {
+    let mut pauses = Vec::new();
+    let ok = step_program(n, |handle| {
+        while let Some(pause) = handle.wait() {
+            assert!(!pause.opcode_name().is_empty());
+            assert_eq!("a.star", pause.span().filename());
+            pauses.push(pause.executed());
+            handle.resume();
+        }
+    });
+    assert!(ok);
+    pauses
+}
+
+#[test]
+fn test_step_instructions_one_by_one() {
+    let pauses = resume_all(1);
+    assert!(pauses.len() > 5);
+    let expected: Vec<u64> = (1..=pauses.len() as u64).collect();
+    assert_eq!(expected, pauses);
+}
+
+#[test]
+fn test_step_instructions_budget() {
+    let executed = resume_all(1).len() as u64 + 1;
+    let pauses = resume_all(3);
+    assert_eq!((executed - 1) / 3, pauses.len() as u64);
+    assert!(
+        pauses
+            .iter()
+            .enumerate()
+            .all(|(i, e)| *e == 3 * (i as u64 + 1))
+    );
+}
+
+#[test]
+fn test_step_instructions_resume() {
+    let ok = step_program(1, |handle| {
+        let first = handle.wait().unwrap();
+        handle.resume();
+        let second = handle.wait().unwrap();
+        assert_eq!(first.executed() + 1, second.executed());
+        handle.resume();
+        while handle.wait().is_some() {
+            handle.resume();
+        }
+    });
+    assert!(ok);
+}
+
+#[test]
+fn test_step_instructions_abort() {
+    let ok = step_program(2, |handle| {
+        while let Some(pause) = handle.wait() {
+            if pause.executed() >= 4 {
+                handle.abort();
+            } else {
+                handle.resume();
+            }
+        }
+    });
+    assert!(!ok);
+}
+
+#[test]
+fn test_step_instructions_handle_dropped() {
+    let module = Module::new();
+    let globals = Globals::standard();
+    let mut eval = Evaluator::new(&module);
+    drop(eval.step_instructions(1).unwrap());
+    // Nobody can resume evaluation, so it runs without pausing.
+    let ast = AstModule::parse("a.star", PROGRAM.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &globals).unwrap();
+    assert_eq!(6, module.get("y").unwrap().unpack_int().unwrap());
+}
+
+#[test]
+fn test_step_instructions_zero_budget() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    assert!(
+        eval.step_instructions(0)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("must be positive")
+    );
+}
@@ -15,22 +15,26 @@
  * limitations under the License.
  */
 
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::fmt::Write;
 
 use allocative::Allocative;
 use derive_more::Display;
+use dupe::Dupe;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
 use crate::assert;
 use crate::assert::Assert;
 use crate::collections::SmallMap;
+use crate::environment::FrozenModule;
 use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
 use crate::environment::Module;
 use crate::errors::Diagnostic;
 use crate::eval::Evaluator;
+use crate::eval::FileLoader;
 use crate::syntax::AstModule;
 use crate::syntax::Dialect;
 use crate::values::Freeze;
@@ -41,6 +45,7 @@ use crate::values::StarlarkValue;
 use crate::values::Trace;
 use crate::values::UnpackValue;
 use crate::values::Value;
+use crate::values::ValueError;
 
 #[test]
 fn alias_test() {
@@ -283,6 +288,49 @@ xs[1] += 1
     );
 }
 
+#[test]
+fn test_bool() {
+    // `bool()` uses `to_bool` of custom types.
+    #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+    #[display(fmt = "truthy({})", _0)]
+    struct Truthy(bool);
+    starlark_simple_value!(Truthy);
+
+    impl<'v> StarlarkValue<'v> for Truthy {
+        starlark_type!("truthy");
+
+        fn to_bool(&self) -> bool {
+            self.0
+        }
+    }
+
+    #[starlark_module]
+    fn module(build: &mut GlobalsBuilder) {
+        fn truthy(x: bool) -> anyhow::Result<Truthy> {
+            Ok(Truthy(x))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(module);
+    a.all_true(
+        r#"
+bool() == False
+bool(truthy(True)) == True
+bool(truthy(False)) == False
+bool(0.0) == False
+bool(0.5) == True
+bool(range(0)) == False
+bool(range(2)) == True
+bool(bool) == True
+(1 if truthy(False) else 2) == 2
+not truthy(False)
+"#,
+    );
+    a.fail("bool(1, 2)", "Wrong number of positional");
+    a.fail("bool(x = 1)", "extra named");
+}
+
 #[test]
 fn test_radd() {
     // We want select append to always produce a select, much like the
@@ -352,6 +400,153 @@ assert_eq(repr(s2), "$[2, 1, 3]")
     );
 }
 
+#[test]
+fn test_contained_in() {
+    // A decimal digit, which can be tested for membership in an int.
+    #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+    #[display(fmt = "digit({})", _0)]
+    struct Digit(u32);
+    starlark_simple_value!(Digit);
+
+    impl<'v> StarlarkValue<'v> for Digit {
+        starlark_type!("digit");
+
+        fn contained_in(&self, collection: Value<'v>) -> Option<anyhow::Result<bool>> {
+            let n = collection.unpack_int()?;
+            let digit = char::from_digit(self.0, 10).unwrap();
+            Some(Ok(n.to_string().contains(digit)))
+        }
+    }
+
+    #[starlark_module]
+    fn module(builder: &mut GlobalsBuilder) {
+        fn digit(x: u32) -> anyhow::Result<Digit> {
+            Ok(Digit(x))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(module);
+    a.pass(
+        r#"
+assert_true(digit(3) in 1234)
+assert_true(digit(5) not in 1234)
+# Lists support `in`, so the element is not asked.
+assert_true(digit(3) not in [3])
+"#,
+    );
+    a.fail(
+        "digit(3) in 1.5",
+        "Argument of type `float` is not iterable",
+    );
+    a.fail("1 in 1234", "Argument of type `int` is not iterable");
+}
+
+#[test]
+fn test_in_not_iterable() {
+    assert::fail("1 in 2", "Argument of type `int` is not iterable");
+    assert::fail("\"a\" in 5", "Argument of type `int` is not iterable");
+    assert::fail("1 in None", "Argument of type `NoneType` is not iterable");
+    assert::fail("1 not in True", "Argument of type `bool` is not iterable");
+    // Iterable, but does not support `in`.
+    assert::fail(
+        "1 in 'abc'.elems()",
+        "Operation `in` not supported for types `int` and `iterator`",
+    );
+}
+
+#[test]
+fn test_reflected_bit_operations() {
+    // A set of flags, which can be combined with plain ints on either side.
+    #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+    #[display(fmt = "flags({})", _0)]
+    struct Flags(u32);
+    starlark_simple_value!(Flags);
+
+    fn bits(x: Value) -> Option<u32> {
+        match Flags::from_value(x) {
+            Some(x) => Some(x.0),
+            None => Some(x.unpack_int()? as u32),
+        }
+    }
+
+    fn op<'v>(
+        x: u32,
+        y: Value<'v>,
+        f: fn(u32, u32) -> u32,
+        heap: &'v Heap,
+    ) -> Option<anyhow::Result<Value<'v>>> {
+        Some(Ok(heap.alloc(Flags(f(x, bits(y)?)))))
+    }
+
+    impl<'v> StarlarkValue<'v> for Flags {
+        starlark_type!("flags");
+
+        fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+            op(self.0, other, |x, y| x & y, heap)
+                .unwrap_or_else(|| ValueError::unsupported_with(self, "&", other))
+        }
+
+        fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+            op(self.0, other, |x, y| x | y, heap)
+                .unwrap_or_else(|| ValueError::unsupported_with(self, "|", other))
+        }
+
+        fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+            op(self.0, other, |x, y| x ^ y, heap)
+                .unwrap_or_else(|| ValueError::unsupported_with(self, "^", other))
+        }
+
+        fn rbit_and(&self, lhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+            op(self.0, lhs, |x, y| y & x, heap)
+        }
+
+        fn rbit_or(&self, lhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+            op(self.0, lhs, |x, y| y | x, heap)
+        }
+
+        fn rbit_xor(&self, lhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+            op(self.0, lhs, |x, y| y ^ x, heap)
+        }
+    }
+
+    #[starlark_module]
+    fn module(builder: &mut GlobalsBuilder) {
+        fn flags(x: i32) -> anyhow::Result<Flags> {
+            Ok(Flags(x as u32))
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(module);
+    a.module("consts", "READ = 1\nWRITE = 2\nEXEC = 4");
+    a.pass(
+        r#"
+load("consts", "READ", "WRITE", "EXEC")
+r = flags(READ)
+assert_eq(repr(r | WRITE), "flags(3)")
+assert_eq(repr(WRITE | r), "flags(3)")
+assert_eq(repr(r & 3), "flags(1)")
+assert_eq(repr(3 & r), "flags(1)")
+assert_eq(repr(r ^ 3), "flags(2)")
+assert_eq(repr(3 ^ r), "flags(2)")
+assert_eq(repr(r | flags(EXEC)), "flags(5)")
+x = EXEC
+x |= r
+assert_eq(repr(x), "flags(5)")
+assert_eq(READ | WRITE, 3)
+"#,
+    );
+    a.fail(
+        "'a' & flags(1)",
+        "Operation `&` not supported for types `string` and `flags`",
+    );
+    a.fail(
+        "flags(1) | 'a'",
+        "Operation `|` not supported for types `flags` and `string`",
+    );
+}
+
 #[test]
 fn test_compound_assignment() {
     assert::pass(
@@ -498,6 +693,66 @@ fn test_load_reexport() {
     );
 }
 
+#[test]
+fn test_load_rename_checked_before_execution() {
+    let mut a = Assert::new();
+    a.module("a", "x = 1\n_y = 2");
+    // The bad load is reported before the preceding `fail` gets a chance to run.
+    let err = a.fail("fail('too early')\nload('a', new='missing')", "missing");
+    assert!(!err.to_string().contains("too early"));
+    assert!(err.to_string().contains("new='missing'"));
+    a.fail(
+        "fail('too early')\nload('a', y='_y')",
+        "Cannot import private symbol `_y`",
+    );
+    a.pass("load('a', new='x')\nassert_eq(new, 1)");
+}
+
+#[test]
+fn test_load_module_requested_once() -> anyhow::Result<()> {
+    struct CountingLoader {
+        module: FrozenModule,
+        loads: Cell<usize>,
+    }
+
+    impl FileLoader for CountingLoader {
+        fn load(&self, path: &str) -> anyhow::Result<FrozenModule> {
+            self.loads.set(self.loads.get() + 1);
+            match path {
+                "a" => Ok(self.module.dupe()),
+                _ => Err(anyhow::anyhow!("Cannot find module `{}`", path)),
+            }
+        }
+    }
+
+    let a = Module::new();
+    a.set("x", Value::new_int(1));
+    let loader = CountingLoader {
+        module: a.freeze()?,
+        loads: Cell::new(0),
+    };
+    let eval = |program: &str| {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        let ast = AstModule::parse("b.star", program.to_owned(), &Dialect::Standard)?;
+        eval.eval_module(ast, &Globals::standard()).map(|_| ())
+    };
+
+    eval("load('a', 'x')\nx + 1")?;
+    assert_eq!(1, loader.loads.get());
+
+    loader.loads.set(0);
+    let err = eval("fail('too early')\nload('b', 'y')").unwrap_err();
+    assert!(
+        err.to_string().contains("Cannot find module `b`"),
+        "{}",
+        err
+    );
+    assert_eq!(1, loader.loads.get());
+    Ok(())
+}
+
 #[test]
 fn test_module_visibility_preserved_by_evaluator() -> anyhow::Result<()> {
     // Make sure that when we use a module in the evaluator, the entering / exiting the
@@ -65,6 +65,7 @@ fn unique_identifiers<'f>(
         scope_data,
         &mut cst,
         FrozenRef::new(Globals::empty()),
+        None,
         codemap,
         &Dialect::Extended,
     );
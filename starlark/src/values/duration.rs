@@ -0,0 +1,202 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Duration helpers for configuration values.
+//!
+//! A duration is written either as an int number of seconds (`30`),
+//! or as a string of number/unit pairs (`"30s"`, `"1h30m"`, `"500ms"`).
+//! Supported units are `d`, `h`, `m`, `s`, `ms`, `us` and `ns`.
+
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::values::Value;
+use crate::values::ValueError;
+
+#[derive(Debug, thiserror::Error)]
+enum DurationError {
+    #[error("Empty duration string")]
+    Empty,
+    #[error("Expected a number at position {0} in duration `{1}`")]
+    ExpectedNumber(usize, String),
+    #[error("Missing unit at position {0} in duration `{1}`")]
+    MissingUnit(usize, String),
+    #[error(
+        "Unknown duration unit `{0}` at position {1} in duration `{2}`, expected one of `d`, `h`, `m`, `s`, `ms`, `us`, `ns`"
+    )]
+    UnknownUnit(String, usize, String),
+    #[error("Duration must not be negative, got `{0}`")]
+    Negative(i32),
+    #[error("Duration `{0}` is too large")]
+    Overflow(String),
+}
+
+/// A duration parsed by [`parse_duration`], split into whole seconds and nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ParsedDuration {
+    /// Whole seconds.
+    pub seconds: u64,
+    /// Nanoseconds, always less than `1_000_000_000`.
+    pub nanos: u32,
+}
+
+impl ParsedDuration {
+    /// Convert to a [`Duration`].
+    pub fn to_duration(self) -> Duration {
+        Duration::new(self.seconds, self.nanos)
+    }
+
+    /// Interpret as an offset from the Unix epoch.
+    pub fn to_system_time(self) -> SystemTime {
+        UNIX_EPOCH + self.to_duration()
+    }
+}
+
+impl From<ParsedDuration> for Duration {
+    fn from(d: ParsedDuration) -> Duration {
+        d.to_duration()
+    }
+}
+
+/// Parse a duration given either as an int number of seconds or as a string like `"1h30m"`.
+pub fn parse_duration(value: Value) -> anyhow::Result<ParsedDuration> {
+    if let Some(seconds) = value.unpack_int() {
+        if seconds < 0 {
+            return Err(DurationError::Negative(seconds).into());
+        }
+        Ok(ParsedDuration {
+            seconds: seconds as u64,
+            nanos: 0,
+        })
+    } else if let Some(s) = value.unpack_str() {
+        parse_duration_str(s)
+    } else {
+        Err(ValueError::IncorrectParameterTypeWithExpected(
+            "int or str".to_owned(),
+            value.get_type().to_owned(),
+        )
+        .into())
+    }
+}
+
+/// Parse a duration string like `"1h30m"` or `"500ms"`.
+/// Errors report the byte position of the offending character.
+pub fn parse_duration_str(s: &str) -> anyhow::Result<ParsedDuration> {
+    if s.is_empty() {
+        return Err(DurationError::Empty.into());
+    }
+    let overflow = || DurationError::Overflow(s.to_owned());
+    let bytes = s.as_bytes();
+    let mut total: u128 = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let number_start = pos;
+        while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        if pos == number_start {
+            return Err(DurationError::ExpectedNumber(number_start, s.to_owned()).into());
+        }
+        let number: u128 = s[number_start..pos].parse().map_err(|_| overflow())?;
+        let unit_start = pos;
+        while pos < bytes.len() && bytes[pos].is_ascii_alphabetic() {
+            pos += 1;
+        }
+        let nanos_per_unit: u128 = match &s[unit_start..pos] {
+            "d" => 86_400_000_000_000,
+            "h" => 3_600_000_000_000,
+            "m" => 60_000_000_000,
+            "s" => 1_000_000_000,
+            "ms" => 1_000_000,
+            "us" => 1_000,
+            "ns" => 1,
+            "" => return Err(DurationError::MissingUnit(unit_start, s.to_owned()).into()),
+            unit => {
+                return Err(
+                    DurationError::UnknownUnit(unit.to_owned(), unit_start, s.to_owned()).into(),
+                );
+            }
+        };
+        total = number
+            .checked_mul(nanos_per_unit)
+            .and_then(|n| total.checked_add(n))
+            .ok_or_else(overflow)?;
+    }
+    Ok(ParsedDuration {
+        seconds: u64::try_from(total / 1_000_000_000).map_err(|_| overflow())?,
+        nanos: (total % 1_000_000_000) as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::UNIX_EPOCH;
+
+    use crate::values::duration::parse_duration;
+    use crate::values::duration::parse_duration_str;
+    use crate::values::duration::ParsedDuration;
+    use crate::values::Heap;
+
+    #[test]
+    fn test_parse_duration() {
+        let heap = Heap::new();
+        assert_eq!(
+            ParsedDuration {
+                seconds: 5400,
+                nanos: 0
+            },
+            parse_duration(heap.alloc("1h30m")).unwrap()
+        );
+        assert_eq!(
+            ParsedDuration {
+                seconds: 0,
+                nanos: 500_000_000
+            },
+            parse_duration(heap.alloc("500ms")).unwrap()
+        );
+        assert_eq!(
+            ParsedDuration {
+                seconds: 30,
+                nanos: 0
+            },
+            parse_duration(heap.alloc(30)).unwrap()
+        );
+        assert_eq!(
+            Duration::from_millis(1500),
+            parse_duration_str("1s500ms").unwrap().to_duration()
+        );
+        assert_eq!(
+            UNIX_EPOCH + Duration::from_secs(86400),
+            parse_duration_str("1d").unwrap().to_system_time()
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_errors() {
+        let heap = Heap::new();
+        let err = |s: &str| parse_duration_str(s).unwrap_err().to_string();
+        assert_eq!("Empty duration string", err(""));
+        assert!(err("1h30").contains("Missing unit at position 4"));
+        assert!(err("10x").contains("Unknown duration unit `x` at position 2"));
+        assert!(err("1h m").contains("Expected a number at position 2"));
+        assert!(err("99999999999999999999d").contains("too large"));
+        assert!(parse_duration(heap.alloc(-1)).is_err());
+        assert!(parse_duration(heap.alloc(1.5)).is_err());
+    }
+}
@@ -72,6 +72,8 @@ pub(crate) enum ControlError {
     NotHashableValue(String),
     #[error("Too many recursion levels")]
     TooManyRecursionLevel,
+    #[error("Argument of type `{0}` is not iterable")]
+    NotIterable(String),
 }
 
 impl ValueError {
@@ -96,6 +98,22 @@ impl ValueError {
         }
     }
 
+    /// Error is [`OperationNotSupported`](ValueError::OperationNotSupported),
+    /// [`OperationNotSupportedBinary`](ValueError::OperationNotSupportedBinary)
+    /// or [`NotIterable`](ControlError::NotIterable).
+    pub(crate) fn is_unsupported(e: &anyhow::Error) -> bool {
+        matches!(
+            e.downcast_ref::<ValueError>(),
+            Some(
+                ValueError::OperationNotSupported { .. }
+                    | ValueError::OperationNotSupportedBinary { .. }
+            )
+        ) || matches!(
+            e.downcast_ref::<ControlError>(),
+            Some(ControlError::NotIterable(..))
+        )
+    }
+
     /// Helper to create an [`OperationNotSupported`](ValueError::OperationNotSupported) error.
     #[cold]
     pub fn unsupported<'v, T, V: StarlarkValue<'v> + ?Sized>(
@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+//! Index helpers for implementing sequence types.
+
 use crate::values::Value;
 use crate::values::ValueError;
 
@@ -80,6 +82,16 @@ pub(crate) fn convert_index(v: Value, len: i32) -> anyhow::Result<i32> {
     }
 }
 
+/// Resolve an index into a sequence of length `len` the way builtin lists do,
+/// for use in [`at`](crate::values::StarlarkValue::at)
+/// and [`set_at`](crate::values::StarlarkValue::set_at) implementations.
+///
+/// Negative indices count from the end. Fails if the index is not an int
+/// or is out of range, with the same errors as builtin lists.
+pub fn resolve_index(index: Value, len: i32) -> anyhow::Result<usize> {
+    Ok(convert_index(index, len)? as usize)
+}
+
 /// Parse indices for slicing.
 ///
 /// Takes the object length and 3 optional values and returns `(i32, i32,
@@ -193,6 +205,41 @@ mod tests {
         assert!(convert_index(Value::new_int(-8), 7).is_err()); // -8 + 7 = -1 < 0
     }
 
+    #[test]
+    fn test_resolve_index() {
+        let heap = Heap::new();
+        assert_eq!(2, resolve_index(Value::new_int(-1), 3).unwrap());
+        assert_eq!(0, resolve_index(Value::new_int(0), 3).unwrap());
+        assert_eq!(
+            "Index `3` is out of bound",
+            resolve_index(Value::new_int(3), 3).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Index `-1` is out of bound",
+            resolve_index(Value::new_int(-4), 3)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Index `-1` is out of bound",
+            resolve_index(Value::new_int(-1), 0)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "Type of parameters mismatch, expected `int`, actual `string`",
+            resolve_index(heap.alloc("a"), 3).unwrap_err().to_string()
+        );
+
+        // Same messages as builtin lists.
+        crate::assert::fail("[1, 2, 3][3]", "Index `3` is out of bound");
+        crate::assert::fail("[][-1]", "Index `-1` is out of bound");
+        crate::assert::fail(
+            "[1, 2, 3]['a']",
+            "Type of parameters mismatch, expected `int`, actual `string`",
+        );
+    }
+
     #[test]
     fn test_apply_slice() {
         let s = &[0, 1, 2, 3, 4, 5, 6];
@@ -24,6 +24,7 @@ use std::mem;
 use allocative::Allocative;
 use derive_more::Display;
 use gazebo::cast;
+use gazebo::coerce::coerce;
 use gazebo::prelude::*;
 use serde::Serialize;
 use serde::Serializer;
@@ -61,12 +62,16 @@ use crate::values::types::tuple::value::Tuple;
 use crate::values::ComplexValue;
 use crate::values::Freezer;
 use crate::values::FrozenValue;
+use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::Trace;
 use crate::values::Tracer;
 use crate::values::Value;
 use crate::values::ValueTyped;
 
+/// Strings up to this length are deduplicated when frozen.
+const MAX_INTERNED_STRING_LEN: usize = 64;
+
 const fn alloc_static<M, T>(mode: M, value: T) -> AValueRepr<AValueImpl<M, T>>
 where
     AValueImpl<M, T>: AValue<'static>,
@@ -128,6 +133,8 @@ impl ValueEmptyArray {
 enum AValueError {
     #[error("Value of type `{0}` cannot be frozen")]
     CannotBeFrozen(&'static str),
+    #[error("Value of type `{0}` cannot be shallow copied")]
+    CannotBeShallowCopied(&'static str),
 }
 
 /// Sized counterpart of [`AValueDyn`].
@@ -170,6 +177,11 @@ pub(crate) trait AValue<'v>: StarlarkValueDyn<'v> + Sized {
 
     unsafe fn heap_copy(me: *mut AValueRepr<Self>, tracer: &Tracer<'v>) -> Value<'v>;
 
+    /// Allocate a copy of this value in `heap`, sharing any values it refers to.
+    fn shallow_copy(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Err(AValueError::CannotBeShallowCopied(Self::StarlarkValue::TYPE).into())
+    }
+
     fn get_hash(&self) -> anyhow::Result<StarlarkHashValue> {
         let mut hasher = StarlarkHasher::new();
         self.write_hash(&mut hasher)?;
@@ -305,6 +317,10 @@ impl<'v, T: StarlarkValueBasic<'v>> AValue<'v> for AValueImpl<Basic, T> {
         unreachable!("Basic types don't appear in the heap")
     }
 
+    fn shallow_copy(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unreachable!("Basic values are copied by `Value::shallow_copy_to`")
+    }
+
     fn get_hash(&self) -> anyhow::Result<StarlarkHashValue> {
         Ok(self.1.get_hash())
     }
@@ -334,6 +350,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkFloat> {
         Self::heap_copy_impl(me, tracer, |_v, _tracer| {})
     }
 
+    fn shallow_copy(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_float(self.1))
+    }
+
     fn get_hash(&self) -> anyhow::Result<StarlarkHashValue> {
         Ok(Num::from(self.1.0).get_hash())
     }
@@ -366,7 +386,12 @@ impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkStr> {
         );
 
         let s = (*me).payload.1.as_str();
-        let fv = freezer.alloc(s);
+        // Long strings are rarely equal, so not worth hashing to deduplicate.
+        let fv = if s.len() <= MAX_INTERNED_STRING_LEN {
+            freezer.alloc_str_intern(s).to_frozen_value()
+        } else {
+            freezer.alloc(s)
+        };
         debug_assert!(fv.is_str());
         AValueHeader::overwrite_with_forward::<Self>(me, ForwardPtr::new(fv.0.raw().ptr_value()));
         Ok(fv)
@@ -388,6 +413,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkStr> {
         v
     }
 
+    fn shallow_copy(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_str(self.1.as_str()).to_value())
+    }
+
     fn get_hash(&self) -> anyhow::Result<StarlarkHashValue> {
         Ok(self.1.get_hash())
     }
@@ -418,6 +447,21 @@ impl<'v> AValue<'v> for AValueImpl<Direct, Tuple<'v>> {
         AValueForward::assert_does_not_overwrite_extra::<Self>();
         let content = (*me).payload.1.content();
 
+        if freezer.tuple_interner.borrow_mut().can_intern(content) {
+            // Such tuples cannot be cyclic, so we can freeze the content before
+            // allocating the tuple, and reuse an equal tuple if we have one.
+            let frozen_values = content.try_map(|v| freezer.freeze(*v))?;
+            let fv = freezer
+                .tuple_interner
+                .borrow_mut()
+                .intern(&frozen_values, || freezer.heap.alloc_tuple(&frozen_values));
+            AValueHeader::overwrite_with_forward::<Self>(
+                me,
+                ForwardPtr::new(fv.0.raw().ptr_value()),
+            );
+            return Ok(fv);
+        }
+
         let (fv, r, extra) =
             freezer.reserve_with_extra::<AValueImpl<Direct, FrozenTuple>>(content.len());
         AValueHeader::overwrite_with_forward::<Self>(me, ForwardPtr::new(fv.0.raw().ptr_value()));
@@ -454,6 +498,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, Tuple<'v>> {
         maybe_uninit_write_slice(extra, content);
         v
     }
+
+    fn shallow_copy(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_tuple(self.1.content()))
+    }
 }
 
 impl<'v> AValue<'v> for AValueImpl<Direct, FrozenTuple> {
@@ -479,6 +527,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, FrozenTuple> {
     unsafe fn heap_copy(_me: *mut AValueRepr<Self>, _tracer: &Tracer<'v>) -> Value<'v> {
         panic!("shouldn't be copying frozen values");
     }
+
+    fn shallow_copy(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_tuple(coerce(self.1.content())))
+    }
 }
 
 impl<'v> AValue<'v> for AValueImpl<Direct, ListGen<ListData<'v>>> {
@@ -526,6 +578,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, ListGen<ListData<'v>>> {
     unsafe fn heap_copy(me: *mut AValueRepr<Self>, tracer: &Tracer<'v>) -> Value<'v> {
         Self::heap_copy_impl(me, tracer, Trace::trace)
     }
+
+    fn shallow_copy(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_list(self.1.0.content()))
+    }
 }
 
 impl<'v> AValue<'v> for AValueImpl<Direct, ListGen<FrozenListData>> {
@@ -551,6 +607,10 @@ impl<'v> AValue<'v> for AValueImpl<Direct, ListGen<FrozenListData>> {
     unsafe fn heap_copy(_me: *mut AValueRepr<Self>, _tracer: &Tracer<'v>) -> Value<'v> {
         panic!("shouldn't be copying frozen values");
     }
+
+    fn shallow_copy(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_list(coerce(self.1.0.content())))
+    }
 }
 
 impl<'v> AValue<'v> for AValueImpl<Direct, Array<'v>> {
@@ -0,0 +1,83 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Streaming log of heap allocations.
+
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+
+/// Flush the sink after this many entries, so that the log can be followed
+/// while evaluation is still running.
+const FLUSH_EVERY: usize = 4096;
+
+/// Writes one line per allocation, `type\tsize\tspan`, to a sink.
+/// The span is the location of the innermost function call being evaluated,
+/// or `-` outside of any call.
+pub(crate) struct AllocLog {
+    writer: BufWriter<Box<dyn Write>>,
+    /// Spans of the calls being evaluated, innermost last.
+    spans: Vec<String>,
+    /// Entries written since the last flush.
+    unflushed: usize,
+    /// First write error, after which nothing more is written.
+    error: Option<io::Error>,
+}
+
+impl AllocLog {
+    pub(crate) fn new(sink: Box<dyn Write>) -> AllocLog {
+        AllocLog {
+            writer: BufWriter::new(sink),
+            spans: Vec::new(),
+            unflushed: 0,
+            error: None,
+        }
+    }
+
+    pub(crate) fn call_enter(&mut self, span: String) {
+        self.spans.push(span);
+    }
+
+    pub(crate) fn call_exit(&mut self) {
+        // Tracing may have been enabled in the middle of a call.
+        self.spans.pop();
+    }
+
+    pub(crate) fn alloc(&mut self, typ: &str, size: usize) {
+        if self.error.is_some() {
+            return;
+        }
+        let span = self.spans.last().map_or("-", |s| s.as_str());
+        let mut res = writeln!(self.writer, "{}\t{}\t{}", typ, size, span);
+        self.unflushed += 1;
+        if res.is_ok() && self.unflushed >= FLUSH_EVERY {
+            self.unflushed = 0;
+            res = self.writer.flush();
+        }
+        if let Err(e) = res {
+            self.error = Some(e);
+        }
+    }
+
+    /// Flush the remaining entries, returning the first write error if any.
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()
+    }
+}
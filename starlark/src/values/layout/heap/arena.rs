@@ -27,6 +27,8 @@
 //! item it replaced.
 
 use std::alloc::Layout;
+use std::cell::Cell;
+use std::cell::RefCell;
 use std::cmp;
 use std::collections::HashMap;
 use std::marker::PhantomData;
@@ -47,6 +49,7 @@ use crate::collections::StarlarkHashValue;
 use crate::values::layout::avalue::starlark_str;
 use crate::values::layout::avalue::AValue;
 use crate::values::layout::avalue::BlackHole;
+use crate::values::layout::heap::alloc_log::AllocLog;
 use crate::values::layout::heap::call_enter_exit::CallEnter;
 use crate::values::layout::heap::call_enter_exit::CallExit;
 use crate::values::layout::heap::call_enter_exit::NeedsDrop;
@@ -54,12 +57,14 @@ use crate::values::layout::heap::call_enter_exit::NoDrop;
 use crate::values::layout::heap::heap_type::HeapKind;
 use crate::values::layout::heap::profile::alloc_counts::AllocCounts;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
+use crate::values::layout::heap::provenance::Provenance;
 use crate::values::layout::heap::repr::AValueForward;
 use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueOrForward;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::vtable::AValueVTable;
 use crate::values::string::StarlarkStr;
+use crate::values::StarlarkValue;
 use crate::values::Value;
 use crate::values::ValueLike;
 
@@ -82,6 +87,21 @@ pub(crate) struct Arena {
     non_drop: Bump,
     /// Arena for things which might need dropping (e.g. Vec, with memory on heap)
     drop: Bump,
+    /// If set, flag allocations which take `allocated_bytes` above this.
+    max_bytes: Cell<Option<usize>>,
+    /// Allocated bytes when `max_bytes` was first exceeded.
+    max_bytes_exceeded: Cell<Option<usize>>,
+    /// If set, allocations are counted in `alloc_counts`.
+    count_allocs: Cell<bool>,
+    /// Allocations made while `count_allocs` is set.
+    alloc_counts: Cell<AllocCounts>,
+    /// Whether `count_allocs` is set or `alloc_log` or `provenance` is present,
+    /// the only flag checked on every allocation.
+    track_allocs: Cell<bool>,
+    /// If set, every allocation is written to this log.
+    alloc_log: RefCell<Option<Box<AllocLog>>>,
+    /// If set, allocations remember the span they were made at.
+    provenance: RefCell<Option<Box<Provenance>>>,
 }
 
 /// Reservation is morally a Reservation<T>, but we treat is as an
@@ -140,6 +160,32 @@ impl<'c> Iterator for ChunkIter<'c> {
 }
 
 impl Arena {
+    /// Start counting allocations, see [`alloc_counts`](Arena::alloc_counts).
+    pub(crate) fn enable_alloc_counts(&self) {
+        self.count_allocs.set(true);
+        self.update_track_allocs();
+    }
+
+    /// Allocations made since [`enable_alloc_counts`](Arena::enable_alloc_counts).
+    pub(crate) fn alloc_counts(&self) -> AllocCounts {
+        self.alloc_counts.get()
+    }
+
+    /// Continue counting allocations from `other`, which this arena replaces after GC.
+    pub(crate) fn take_alloc_counts_from(&self, other: &Arena) {
+        self.count_allocs.set(other.count_allocs.get());
+        self.alloc_counts.set(other.alloc_counts.get());
+        self.update_track_allocs();
+    }
+
+    fn update_track_allocs(&self) {
+        self.track_allocs.set(
+            self.count_allocs.get()
+                || self.alloc_log.borrow().is_some()
+                || self.provenance.borrow().is_some(),
+        );
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.allocated_bytes() == 0
     }
@@ -156,7 +202,108 @@ impl Arena {
         self.drop.chunk_capacity() + self.non_drop.chunk_capacity()
     }
 
+    /// Arena with its first chunks preallocated to hold `bytes` in total,
+    /// split evenly between values which need dropping and those which don't.
+    pub(crate) fn with_capacity(bytes: usize) -> Arena {
+        Arena {
+            non_drop: Bump::with_capacity(bytes / 2),
+            drop: Bump::with_capacity(bytes - bytes / 2),
+            max_bytes: Cell::new(None),
+            max_bytes_exceeded: Cell::new(None),
+            count_allocs: Cell::new(false),
+            alloc_counts: Cell::new(AllocCounts::default()),
+            track_allocs: Cell::new(false),
+            alloc_log: RefCell::new(None),
+            provenance: RefCell::new(None),
+        }
+    }
+
+    /// Arena with the same byte limit as this one, used as a GC target.
+    pub(crate) fn new_with_same_limit(&self) -> Arena {
+        let arena = Arena::default();
+        arena.max_bytes.set(self.max_bytes.get());
+        arena.max_bytes_exceeded.set(self.max_bytes_exceeded.get());
+        arena
+    }
+
+    pub(crate) fn set_max_bytes(&self, max_bytes: Option<usize>) {
+        self.max_bytes.set(max_bytes);
+        self.max_bytes_exceeded.set(None);
+    }
+
+    pub(crate) fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes.get()
+    }
+
+    /// Allocated bytes, if an allocation took them over `max_bytes`.
+    pub(crate) fn max_bytes_exceeded(&self) -> Option<usize> {
+        self.max_bytes_exceeded.get()
+    }
+
+    /// Start or stop logging allocations, returning the previous log.
+    pub(crate) fn set_alloc_log(&self, log: Option<Box<AllocLog>>) -> Option<Box<AllocLog>> {
+        let old = self.alloc_log.replace(log);
+        self.update_track_allocs();
+        old
+    }
+
+    /// Access the allocation log, if set.
+    pub(crate) fn with_alloc_log(&self, f: impl FnOnce(&mut AllocLog)) {
+        if let Some(log) = self.alloc_log.borrow_mut().as_mut() {
+            f(log);
+        }
+    }
+
+    /// Start or stop tracking where allocations are made, returning the previous tracking.
+    pub(crate) fn set_provenance(
+        &self,
+        provenance: Option<Box<Provenance>>,
+    ) -> Option<Box<Provenance>> {
+        let old = self.provenance.replace(provenance);
+        self.update_track_allocs();
+        old
+    }
+
+    /// Access the provenance tracking, if set.
+    pub(crate) fn with_provenance(&self, f: impl FnOnce(&mut Provenance)) {
+        if let Some(provenance) = self.provenance.borrow_mut().as_mut() {
+            f(provenance);
+        }
+    }
+
+    /// Count, log and track an allocation, only called when `track_allocs` is set.
+    #[cold]
+    #[inline(never)]
+    fn track_alloc(&self, typ: &str, size: usize, addr: usize) {
+        if self.count_allocs.get() {
+            self.alloc_counts.set(
+                self.alloc_counts.get()
+                    + AllocCounts {
+                        bytes: size,
+                        count: 1,
+                    },
+            );
+        }
+        self.with_alloc_log(|log| log.alloc(typ, size));
+        self.with_provenance(|provenance| provenance.alloc(addr));
+    }
+
+    /// Only called after a new chunk was allocated,
+    /// so the limit costs nothing for allocations fitting in the current chunk.
+    #[cold]
+    #[inline(never)]
+    fn check_max_bytes(&self) {
+        if let Some(max_bytes) = self.max_bytes.get() {
+            // Do not count the unfilled part of the new chunk.
+            let allocated = self.allocated_bytes() - self.available_bytes();
+            if allocated > max_bytes && self.max_bytes_exceeded.get().is_none() {
+                self.max_bytes_exceeded.set(Some(allocated));
+            }
+        }
+    }
+
     fn alloc_uninit<'v, 'v2: 'v, T: AValue<'v2>>(
+        &self,
         bump: &'v Bump,
         extra_len: usize,
     ) -> (
@@ -178,7 +325,14 @@ impl Arena {
         );
         debug_assert!(size % AValueHeader::ALIGN == 0);
         let layout = Layout::from_size_align(size, mem::align_of::<AValueHeader>()).unwrap();
+        let new_chunk = self.max_bytes.get().is_some() && size > bump.chunk_capacity();
         let p = bump.alloc_layout(layout).as_ptr();
+        if new_chunk {
+            self.check_max_bytes();
+        }
+        if self.track_allocs.get() {
+            self.track_alloc(<T::StarlarkValue as StarlarkValue>::TYPE, size, p as usize);
+        }
         unsafe {
             let repr = &mut *(p as *mut MaybeUninit<AValueRepr<T>>);
             let extra = slice::from_raw_parts_mut(
@@ -209,7 +363,7 @@ impl Arena {
         // it returns `false` from `is_str`.
         assert!(!T::IS_STR);
 
-        let (p, extra) = Self::alloc_uninit::<T>(self.bump_for_type::<T>(), extra_len);
+        let (p, extra) = self.alloc_uninit::<T>(self.bump_for_type::<T>(), extra_len);
         // If we don't have a vtable we can't skip over missing elements to drop,
         // so very important to put in a current vtable
         // We always alloc at least one pointer worth of space, so can write in a one-ST blackhole
@@ -244,7 +398,7 @@ impl Arena {
     ) -> &'v AValueRepr<T> {
         debug_assert!(x.extra_len() == 0);
         let bump = self.bump_for_type::<T>();
-        let (p, extra) = Self::alloc_uninit::<T>(bump, 0);
+        let (p, extra) = self.alloc_uninit::<T>(bump, 0);
         debug_assert!(extra.is_empty());
         p.write(AValueRepr {
             header: AValueHeader::new::<T>(),
@@ -260,7 +414,7 @@ impl Arena {
         x: T,
     ) -> (*mut AValueRepr<T>, &'v mut [MaybeUninit<T::ExtraElem>]) {
         let bump = self.bump_for_type::<T>();
-        let (p, extra) = Self::alloc_uninit::<T>(bump, x.extra_len());
+        let (p, extra) = self.alloc_uninit::<T>(bump, x.extra_len());
         let p = p.write(AValueRepr {
             header: AValueHeader::new::<T>(),
             payload: x,
@@ -392,7 +546,7 @@ impl Arena {
     }
 
     // Iterate over the values in the both bumps in any order
-    fn for_each_unordered<'a>(&'a self, mut f: impl FnMut(&'a AValueHeader)) {
+    pub(crate) fn for_each_unordered<'a>(&'a self, mut f: impl FnMut(&'a AValueHeader)) {
         for bump in [&self.drop, &self.non_drop] {
             Self::for_each_unordered_in_bump(bump, &mut f);
         }
@@ -431,6 +585,8 @@ impl Arena {
 
 impl Drop for Arena {
     fn drop(&mut self) {
+        // Forget the spans before the memory can be reused by another arena.
+        self.provenance.get_mut().take();
         self.for_each_drop_unordered(|x| {
             // Safe to convert to *mut because we are the only owner
             let value = x.payload_ptr() as *mut ();
@@ -441,7 +597,17 @@ impl Drop for Arena {
 
 impl Allocative for Arena {
     fn visit<'a, 'b: 'a>(&self, visitor: &'a mut allocative::Visitor<'b>) {
-        let Arena { drop, non_drop } = self;
+        let Arena {
+            drop,
+            non_drop,
+            max_bytes: _,
+            max_bytes_exceeded: _,
+            count_allocs: _,
+            alloc_counts: _,
+            track_allocs: _,
+            alloc_log: _,
+            provenance: _,
+        } = self;
 
         fn visit_bump<'a, 'b: 'a>(bump: &Bump, visitor: &'a mut Visitor<'b>) {
             let mut visitor =
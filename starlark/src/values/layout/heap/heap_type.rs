@@ -42,10 +42,12 @@ use gazebo::cast;
 use once_cell::sync::Lazy;
 use starlark_map::small_set::SmallSet;
 
+use crate::codemap::FileSpan;
 use crate::collections::maybe_uninit_backport::maybe_uninit_write_slice;
 use crate::collections::maybe_uninit_backport::maybe_uninit_write_slice_cloned;
 use crate::collections::Hashed;
 use crate::collections::StarlarkHashValue;
+use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::FrozenDef;
 use crate::values::any::StarlarkAny;
 use crate::values::array::Array;
@@ -63,6 +65,7 @@ use crate::values::layout::avalue::AValue;
 use crate::values::layout::avalue::VALUE_EMPTY_ARRAY;
 use crate::values::layout::avalue::VALUE_EMPTY_FROZEN_LIST;
 use crate::values::layout::avalue::VALUE_EMPTY_TUPLE;
+use crate::values::layout::heap::alloc_log::AllocLog;
 use crate::values::layout::heap::arena::Arena;
 use crate::values::layout::heap::arena::ArenaVisitor;
 use crate::values::layout::heap::arena::Reservation;
@@ -72,21 +75,27 @@ use crate::values::layout::heap::call_enter_exit::NeedsDrop;
 use crate::values::layout::heap::call_enter_exit::NoDrop;
 use crate::values::layout::heap::fast_cell::FastCell;
 use crate::values::layout::heap::maybe_uninit_slice_util::maybe_uninit_write_from_exact_size_iter;
+use crate::values::layout::heap::profile::alloc_counts::AllocCounts;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
+use crate::values::layout::heap::repr::AValueOrForward;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::static_string::constant_string;
 use crate::values::layout::typed::string::StringValueLike;
 use crate::values::layout::value::FrozenValue;
 use crate::values::layout::value::Value;
+use crate::values::layout::vtable::AValueDyn;
 use crate::values::string::intern::interner::FrozenStringInterner;
 use crate::values::string::StarlarkStr;
 use crate::values::types::float::StarlarkFloat;
+use crate::values::types::string::intern::shared::InternedStrings;
+use crate::values::types::tuple::intern::FrozenTupleInterner;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::ComplexValue;
 use crate::values::FrozenRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValueTyped;
+use crate::values::SharedStringInterner;
 use crate::values::StarlarkValue;
 use crate::values::StringValue;
 use crate::values::Trace;
@@ -106,6 +115,10 @@ pub struct Heap {
     /// Peak memory seen when a garbage collection takes place (may be lower than currently allocated)
     peak_allocated: Cell<usize>,
     arena: FastCell<Arena>,
+    /// If set, maximum length in bytes of strings built by string operations.
+    max_string_length: Cell<Option<usize>>,
+    /// Iterate dicts in sorted key order instead of insertion order.
+    canonical_dict_iteration: Cell<bool>,
 }
 
 impl Debug for Heap {
@@ -119,6 +132,29 @@ impl Debug for Heap {
     }
 }
 
+/// Error returned when evaluation allocates more on a [`Heap`] than the limit
+/// set with [`Evaluator::set_max_heap_bytes`](crate::eval::Evaluator::set_max_heap_bytes)
+/// or [`Evaluator::with_heap_limit`](crate::eval::Evaluator::with_heap_limit).
+#[derive(Debug, Clone, Dupe, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Heap size limit exceeded: {allocated} bytes allocated, limit is {limit} bytes")]
+pub struct HeapLimitExceeded {
+    /// Bytes the heap had allocated, or was about to allocate.
+    pub allocated: usize,
+    /// The configured limit.
+    pub limit: usize,
+}
+
+/// Error returned when a string operation would build a string longer than the limit
+/// set with [`Evaluator::set_max_string_length`](crate::eval::Evaluator::set_max_string_length).
+#[derive(Debug, Clone, Dupe, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("String length limit exceeded: result would be {length} bytes, limit is {limit} bytes")]
+pub struct StringLengthExceeded {
+    /// Length in bytes of the string which would have been built.
+    pub length: usize,
+    /// The configured limit.
+    pub limit: usize,
+}
+
 /// A heap on which [`FrozenValue`]s can be allocated.
 /// Can be kept alive by a [`FrozenHeapRef`].
 #[derive(Default)]
@@ -501,6 +537,19 @@ pub struct Freezer {
     pub(crate) heap: FrozenHeap,
     /// Defs frozen by this freezer.
     pub(crate) frozen_defs: RefCell<Vec<FrozenRef<'static, FrozenDef>>>,
+    /// Structurally equal constant tuples frozen by this freezer share one allocation.
+    pub(crate) tuple_interner: RefCell<FrozenTupleInterner>,
+    /// Interner shared with other freezers, if any,
+    /// and the strings it held when freezing started.
+    string_interner: Option<(SharedStringInterner, Arc<InternedStrings>)>,
+    /// Strings allocated by this freezer, to be added to the shared interner.
+    interned_strings: RefCell<Vec<FrozenStringValue>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum FreezerError {
+    #[error("Cannot freeze `def` outside of module freeze")]
+    DefOutsideModule,
 }
 
 impl Freezer {
@@ -508,11 +557,51 @@ impl Freezer {
         Freezer {
             heap,
             frozen_defs: RefCell::new(Vec::new()),
+            tuple_interner: RefCell::new(FrozenTupleInterner::default()),
+            string_interner: None,
+            interned_strings: RefCell::new(Vec::new()),
         }
     }
 
+    /// Reuse strings frozen by other freezers sharing the same interner.
+    /// Strings frozen by this freezer are added to the interner
+    /// once freezing is finished.
+    pub fn with_string_interner(mut self, interner: SharedStringInterner) -> Self {
+        let snapshot = interner.snapshot();
+        self.string_interner = Some((interner, snapshot));
+        self
+    }
+
     pub(crate) fn into_ref(self) -> FrozenHeapRef {
-        self.heap.into_ref()
+        let Freezer {
+            heap,
+            string_interner,
+            interned_strings,
+            ..
+        } = self;
+        let heap = heap.into_ref();
+        if let Some((string_interner, snapshot)) = string_interner {
+            // Release the snapshot so the interner can be updated in place.
+            drop(snapshot);
+            string_interner.insert_all(interned_strings.into_inner());
+        }
+        heap
+    }
+
+    /// Allocate a string while freezing, reusing an equal string
+    /// from the shared interner if there is one.
+    pub(crate) fn alloc_str_intern(&self, s: &str) -> FrozenStringValue {
+        let interned = match &self.string_interner {
+            Some((_, interned)) if constant_string(s).is_none() => interned,
+            _ => return self.heap.alloc_str_intern(s),
+        };
+        if let Some((fv, heap)) = interned.get(Hashed::new(s)) {
+            self.heap.add_reference(heap);
+            return fv;
+        }
+        let fv = self.heap.alloc_str_intern(s);
+        self.interned_strings.borrow_mut().push(fv);
+        fv
     }
 
     /// Allocate a new value while freezing. Usually not a great idea.
@@ -543,16 +632,53 @@ impl Freezer {
 
     /// Freeze a nested value while freezing yourself.
     pub fn freeze(&self, value: Value) -> anyhow::Result<FrozenValue> {
+        match Self::frozen_or_unfrozen(value) {
+            Either::Left(x) => Ok(x),
+            Either::Right(v) => unsafe { v.heap_freeze(self) },
+        }
+    }
+
+    /// Freeze `value` and everything reachable from it,
+    /// used by [`Module::freeze_value`](crate::environment::Module::freeze_value).
+    ///
+    /// Caller must guarantee that no other value of `heap` is used afterwards:
+    /// `heap` is compacted to the values reachable from `value`,
+    /// which are then moved out of it.
+    pub(crate) unsafe fn freeze_value<'v>(
+        &self,
+        heap: &'v Heap,
+        mut value: Value<'v>,
+    ) -> anyhow::Result<FrozenValue> {
+        heap.garbage_collect(|tracer| tracer.trace(&mut value));
+        // Check before freezing anything, so on error the heap is left intact.
+        if heap.contains_def() {
+            return Err(FreezerError::DefOutsideModule.into());
+        }
+        self.freeze(value)
+    }
+
+    /// Value if it is frozen, or if it was already replaced with a forward
+    /// to its frozen version. Otherwise, the value which needs to be frozen.
+    fn frozen_or_unfrozen(value: Value) -> Either<FrozenValue, AValueDyn<'_>> {
         // Case 1: We have our value encoded in our pointer
         if let Some(x) = value.unpack_frozen() {
-            return Ok(x);
+            return Either::Left(x);
         }
 
         // Case 2: We have already been replaced with a forwarding, or need to freeze
         let value = value.0.unpack_ptr().unwrap();
         match value.unpack_overwrite() {
-            Either::Left(x) => Ok(unsafe { x.unpack_frozen_value() }),
-            Either::Right(v) => unsafe { v.heap_freeze(self) },
+            Either::Left(x) => Either::Left(unsafe { x.unpack_frozen_value() }),
+            Either::Right(v) => Either::Right(v),
+        }
+    }
+
+    /// Resolve a value which may have already been frozen by this freezer:
+    /// unlike unfrozen values, such values cannot be inspected directly.
+    pub(crate) fn resolve_forward(value: Value) -> Value {
+        match Self::frozen_or_unfrozen(value) {
+            Either::Left(x) => x.to_value(),
+            Either::Right(_) => value,
         }
     }
 
@@ -570,6 +696,20 @@ impl Heap {
         Self::default()
     }
 
+    /// Create a new [`Heap`] with room for about `bytes` of values preallocated,
+    /// avoiding repeated growth when an evaluation is known to allocate a lot.
+    ///
+    /// This is only a performance hint: the heap still grows beyond it as needed,
+    /// and the preallocation is not kept after a garbage collection.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Heap {
+            peak_allocated: Cell::new(0),
+            arena: FastCell::new(Arena::with_capacity(bytes)),
+            max_string_length: Cell::new(None),
+            canonical_dict_iteration: Cell::new(false),
+        }
+    }
+
     /// Number of bytes allocated on this heap, not including any memory
     /// allocated outside of the starlark heap.
     pub fn allocated_bytes(&self) -> usize {
@@ -587,6 +727,97 @@ impl Heap {
         self.arena.borrow().available_bytes()
     }
 
+    /// Bytes actually used on this heap, excluding unfilled chunk capacity.
+    pub(crate) fn filled_bytes(&self) -> usize {
+        let arena = self.arena.borrow();
+        arena.allocated_bytes() - arena.available_bytes()
+    }
+
+    /// Limit [`filled_bytes`](Heap::filled_bytes). Allocations are not refused,
+    /// but once the limit is exceeded, [`check_max_bytes`](Heap::check_max_bytes) fails.
+    pub(crate) fn set_max_bytes(&self, max_bytes: Option<usize>) {
+        self.arena.borrow().set_max_bytes(max_bytes);
+    }
+
+    pub(crate) fn max_bytes(&self) -> Option<usize> {
+        self.arena.borrow().max_bytes()
+    }
+
+    /// Run `f` with the heap allowed to grow by at most `bytes` bytes,
+    /// restoring the previous limit afterwards.
+    pub(crate) fn with_max_bytes<R>(
+        &self,
+        bytes: usize,
+        f: impl FnOnce() -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
+        let limit = self.filled_bytes().saturating_add(bytes);
+        let old_limit = self.max_bytes();
+        self.set_max_bytes(Some(old_limit.map_or(limit, |old| old.min(limit))));
+        let res = f().and_then(|r| {
+            self.check_max_bytes()?;
+            Ok(r)
+        });
+        self.set_max_bytes(old_limit);
+        res
+    }
+
+    /// Fail if an allocation (including a GC copy) has exceeded the limit
+    /// set by [`set_max_bytes`](Heap::set_max_bytes).
+    pub(crate) fn check_max_bytes(&self) -> anyhow::Result<()> {
+        let arena = self.arena.borrow();
+        match (arena.max_bytes_exceeded(), arena.max_bytes()) {
+            (Some(allocated), Some(limit)) => Err(HeapLimitExceeded { allocated, limit }.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail before building a value of `bytes` bytes which would exceed
+    /// the limit set by [`set_max_bytes`](Heap::set_max_bytes), so that
+    /// huge values are rejected before the memory is requested.
+    #[inline]
+    pub(crate) fn check_alloc_bytes(&self, bytes: usize) -> anyhow::Result<()> {
+        match self.arena.borrow().max_bytes() {
+            None => Ok(()),
+            Some(limit) => {
+                let allocated = self.filled_bytes().saturating_add(bytes);
+                if allocated > limit {
+                    Err(HeapLimitExceeded { allocated, limit }.into())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Limit the length of strings built by string operations such as
+    /// concatenation, repetition, `join` and formatting.
+    pub(crate) fn set_max_string_length(&self, max_length: Option<usize>) {
+        self.max_string_length.set(max_length);
+    }
+
+    /// Make iteration over dicts allocated on this heap or any other heap
+    /// visit keys in sorted order, while this heap is used for evaluation.
+    pub(crate) fn set_canonical_dict_iteration(&self, enable: bool) {
+        self.canonical_dict_iteration.set(enable);
+    }
+
+    /// Whether dicts are iterated in sorted key order,
+    /// see [`set_canonical_dict_iteration`](Heap::set_canonical_dict_iteration).
+    #[inline]
+    pub(crate) fn canonical_dict_iteration(&self) -> bool {
+        self.canonical_dict_iteration.get()
+    }
+
+    /// Fail before building a string of `length` bytes which would exceed
+    /// the limit set by [`set_max_string_length`](Heap::set_max_string_length).
+    #[inline]
+    pub(crate) fn check_string_length(&self, length: usize) -> anyhow::Result<()> {
+        match self.max_string_length.get() {
+            Some(limit) if length > limit => Err(StringLengthExceeded { length, limit }.into()),
+            _ => Ok(()),
+        }
+    }
+
     fn alloc_raw<'v, 'v2: 'v2>(&'v self, x: impl AValue<'v2, ExtraElem = ()>) -> Value<'v> {
         let arena = self.arena.borrow();
         let v: &AValueRepr<_> = arena.alloc(x);
@@ -668,6 +899,26 @@ impl Heap {
         }
     }
 
+    /// Allocate a string on the heap, based on any number of concatenated strings.
+    /// The result is written in place, without building an intermediate [`String`].
+    pub fn alloc_str_concat_many<'v>(&'v self, parts: &[&str]) -> StringValue<'v> {
+        match parts {
+            [] => self.alloc_str(""),
+            [x] => self.alloc_str(x),
+            [x, y] => self.alloc_str_concat(x, y),
+            [x, y, z] => self.alloc_str_concat3(x, y, z),
+            _ => {
+                let len = parts.iter().map(|x| x.len()).sum();
+                self.alloc_str_init(len, |mut dest| unsafe {
+                    for x in parts {
+                        copy_nonoverlapping(x.as_ptr(), dest, x.len());
+                        dest = dest.add(x.len());
+                    }
+                })
+            }
+        }
+    }
+
     /// Allocate a tuple with the given elements.
     pub(crate) fn alloc_tuple<'v>(&'v self, elems: &[Value<'v>]) -> Value<'v> {
         if elems.is_empty() {
@@ -736,6 +987,18 @@ impl Heap {
         list.to_value()
     }
 
+    /// Allocate a list with the given elements, failing instead of growing it
+    /// beyond the limit set by [`set_max_bytes`](Heap::set_max_bytes).
+    pub(crate) fn try_alloc_list_iter<'v>(
+        &'v self,
+        elems: impl IntoIterator<Item = Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        let array = self.alloc_array(0);
+        let list = self.alloc_raw_typed(list_avalue(array));
+        list.0.try_extend(elems, self)?;
+        Ok(list.to_value())
+    }
+
     /// Allocate a list by concatenating two slices.
     pub(crate) fn alloc_list_concat<'v>(&'v self, a: &[Value<'v>], b: &[Value<'v>]) -> Value<'v> {
         let array = self.alloc_array(a.len() + b.len());
@@ -817,6 +1080,15 @@ impl Heap {
         (*self.arena.get_mut()).visit_arena(HeapKind::Unfrozen, forward_heap_kind, v)
     }
 
+    /// Whether any `def` is allocated on this heap.
+    fn contains_def(&self) -> bool {
+        let mut res = false;
+        self.arena.borrow().for_each_unordered(|x| {
+            res |= x.unpack().downcast_ref::<Def>().is_some();
+        });
+        res
+    }
+
     /// Garbage collect any values that are unused. This function is _unsafe_ in
     /// the sense that any `Value<'v>` not returned by `Tracer` _will become
     /// invalid_. Furthermore, any references to values, e.g `&'v str` will
@@ -827,17 +1099,43 @@ impl Heap {
         self.garbage_collect_internal(f)
     }
 
+    /// Compact the heap: move all the values reachable from `roots` into
+    /// contiguous storage, and release all the memory used by other values
+    /// back to the allocator. Useful after building and discarding large
+    /// temporary structures.
+    ///
+    /// The cost is proportional to the size of the live values,
+    /// which are all copied, and in addition the destructors of dead
+    /// values which have them are run.
+    ///
+    /// This function is _unsafe_: `roots` must trace every live value on
+    /// this heap, and any `Value<'v>` not updated by the [`Tracer`]
+    /// _will become invalid_, as will any references into values
+    /// (e.g. `&'v str`). Values on a heap owned by an [`Evaluator`](crate::eval::Evaluator)
+    /// should be compacted with [`Evaluator::garbage_collect`](crate::eval::Evaluator::garbage_collect).
+    pub unsafe fn compact<'v>(&'v self, roots: impl FnOnce(&Tracer<'v>)) {
+        self.garbage_collect(roots)
+    }
+
     unsafe fn garbage_collect_internal<'v>(&'v self, f: impl FnOnce(&Tracer<'v>)) {
         // Must rewrite all Value's so they point at the new heap.
         // Take the arena out of the heap to make sure nobody allocates in it,
         // but hold the reference until the GC is done.
-        let _arena = self.arena.take();
+        let old_arena = self.arena.take();
 
         let tracer = Tracer::<'v> {
-            arena: Arena::default(),
+            arena: old_arena.new_with_same_limit(),
             phantom: PhantomData,
         };
         f(&tracer);
+        // Copying live values is not an allocation, so counts continue from the old arena.
+        tracer.arena.take_alloc_counts_from(&old_arena);
+        // Copying live values is not an allocation, so the log continues in the new arena.
+        tracer.arena.set_alloc_log(old_arena.set_alloc_log(None));
+        if let Some(mut provenance) = old_arena.set_provenance(None) {
+            provenance.relocate();
+            tracer.arena.set_provenance(Some(provenance));
+        }
         self.arena.set(tracer.arena);
     }
 
@@ -846,6 +1144,73 @@ impl Heap {
         self.arena.borrow().allocated_summary()
     }
 
+    /// Start counting allocations on this heap,
+    /// used by [`ProfileMode::Allocations`](crate::eval::ProfileMode::Allocations).
+    pub(crate) fn enable_alloc_counts(&self) {
+        self.arena.borrow().enable_alloc_counts();
+    }
+
+    /// Allocations made since [`enable_alloc_counts`](Heap::enable_alloc_counts).
+    pub(crate) fn alloc_counts(&self) -> AllocCounts {
+        self.arena.borrow().alloc_counts()
+    }
+
+    /// Start or stop logging allocations, returning the previous log.
+    pub(crate) fn set_alloc_log(&self, log: Option<Box<AllocLog>>) -> Option<Box<AllocLog>> {
+        self.arena.borrow().set_alloc_log(log)
+    }
+
+    /// Start or stop recording the span of the call which made each allocation,
+    /// see [`Evaluator::track_value_provenance`](crate::eval::Evaluator::track_value_provenance).
+    pub(crate) fn track_provenance(&self, enable: bool) {
+        let arena = self.arena.borrow();
+        let old = arena.set_provenance(None);
+        if enable {
+            arena.set_provenance(Some(old.unwrap_or_default()));
+        }
+    }
+
+    /// Attribute further allocations to the call at `span`,
+    /// if allocations are logged or their provenance is tracked.
+    pub(crate) fn call_enter(&self, span: Option<FileSpan>) {
+        let arena = self.arena.borrow();
+        arena.with_alloc_log(|log| {
+            log.call_enter(
+                span.as_ref()
+                    .map_or_else(|| "-".to_owned(), |s| s.to_string()),
+            )
+        });
+        arena.with_provenance(|provenance| provenance.call_enter(span));
+    }
+
+    pub(crate) fn call_exit(&self) {
+        let arena = self.arena.borrow();
+        arena.with_alloc_log(|log| log.call_exit());
+        arena.with_provenance(|provenance| provenance.call_exit());
+    }
+
+    /// Attribute `value` to `span` instead of the call it was allocated in,
+    /// if provenance is tracked.
+    pub(crate) fn set_provenance<'v>(&'v self, value: Value<'v>, span: FileSpan) {
+        if let Some(header) = value.0.unpack_ptr() {
+            let addr = header as *const AValueOrForward as usize;
+            self.arena
+                .borrow()
+                .with_provenance(|provenance| provenance.set(addr, span));
+        }
+    }
+
+    /// Hand the provenance of the values frozen into `frozen` over to it.
+    /// Must be called after freezing, while this heap is still alive.
+    pub(crate) fn freeze_provenance(&self, frozen: &FrozenHeap) {
+        if let Some(mut provenance) = self.arena.borrow().set_provenance(None) {
+            provenance.relocate();
+            let mut frozen_provenance = frozen.arena.set_provenance(None).unwrap_or_default();
+            frozen_provenance.append(&mut provenance);
+            frozen.arena.set_provenance(Some(frozen_provenance));
+        }
+    }
+
     pub(crate) fn record_call_enter<'v>(&'v self, function: Value<'v>) {
         let time = Instant::now();
         assert!(mem::needs_drop::<CallEnter<NeedsDrop>>());
@@ -952,3 +1317,160 @@ where
     FrozenHeapRef: Send + Sync,
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::list::ListRef;
+    use crate::values::FrozenHeap;
+    use crate::values::Heap;
+    use crate::values::HeapLimitExceeded;
+    use crate::values::Value;
+
+    #[test]
+    fn test_compact() {
+        let heap = Heap::new();
+        let mut root = heap.alloc(vec![heap.alloc_str("live").to_value()]);
+
+        let garbage: Vec<Value> = (0..10000).map(|i| heap.alloc(i.to_string())).collect();
+        heap.alloc(garbage);
+        let before = heap.allocated_bytes();
+        assert!(before > 100000, "{}", before);
+
+        unsafe { heap.compact(|tracer| tracer.trace(&mut root)) };
+
+        let after = heap.allocated_bytes();
+        assert!(after < 1000, "{}", after);
+        assert!(heap.peak_allocated_bytes() >= before);
+        let root = ListRef::from_value(root).unwrap();
+        assert_eq!(1, root.len());
+        assert_eq!(Some("live"), root[0].unpack_str());
+    }
+
+    #[test]
+    fn test_allocated_summary_top_by_bytes() {
+        let heap = Heap::new();
+        for i in 0..10 {
+            heap.alloc(vec![
+                heap.alloc(format!("x{}", i)),
+                heap.alloc(format!("y{}", i)),
+            ]);
+        }
+        heap.alloc(vec![Value::new_none()]);
+        let summary = heap.allocated_summary();
+        let top = summary.top_by_bytes(1);
+        assert_eq!(1, top.len());
+        assert_eq!(summary.summary()[&top[0].0], top[0].1);
+        let all = summary.top_by_bytes(usize::MAX);
+        assert_eq!(summary.summary().len(), all.len());
+        assert!(all.windows(2).all(|w| w[0].1.1 >= w[1].1.1));
+        assert_eq!(20, summary.summary()["string"].0, "{:?}", summary.summary());
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let heap = Heap::with_capacity(1 << 20);
+        let before = heap.allocated_bytes();
+        assert!(before >= 1 << 20, "{}", before);
+        let xs: Vec<Value> = (0..1000).map(|i| heap.alloc(i.to_string())).collect();
+        heap.alloc(xs);
+        // Everything fits in the preallocated chunks.
+        assert_eq!(before, heap.allocated_bytes());
+    }
+
+    #[test]
+    fn test_max_bytes_gc_copy() {
+        let heap = Heap::new();
+        let live: Vec<Value> = (0..10000).map(|i| heap.alloc(i.to_string())).collect();
+        let mut root = heap.alloc(live);
+        let limit = (heap.allocated_bytes() - heap.available_bytes()) / 2;
+
+        // Setting the limit doesn't fail by itself, only further allocations do.
+        heap.set_max_bytes(Some(limit));
+        heap.check_max_bytes().unwrap();
+
+        // Copying the live values exceeds the limit.
+        unsafe { heap.compact(|tracer| tracer.trace(&mut root)) };
+        let err = heap.check_max_bytes().unwrap_err();
+        let err = err.downcast_ref::<HeapLimitExceeded>().unwrap();
+        assert_eq!(limit, err.limit);
+        assert!(err.allocated > limit);
+        assert_eq!(10000, ListRef::from_value(root).unwrap().len());
+
+        assert!(heap.check_alloc_bytes(limit * 2).is_err());
+        heap.set_max_bytes(None);
+        heap.check_max_bytes().unwrap();
+        heap.check_alloc_bytes(limit * 2).unwrap();
+    }
+
+    #[test]
+    fn test_alloc_str_concat_many() {
+        let heap = Heap::new();
+        let cases: &[&[&str]] = &[
+            &[],
+            &["x"],
+            &["", ""],
+            &["a", "", "b"],
+            &["a", "bc", "", "déf", "g"],
+        ];
+        for parts in cases {
+            let s = heap.alloc_str_concat_many(parts);
+            assert_eq!(parts.concat(), s.as_str());
+            assert_eq!(heap.alloc_str(&parts.concat()).get_hash(), s.get_hash());
+        }
+    }
+
+    fn eval_and_freeze_value(code: &str) -> anyhow::Result<Vec<String>> {
+        let heap = FrozenHeap::new();
+        let frozen = {
+            let module = Module::new();
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("x.star", code.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            drop(eval);
+            module.freeze_value("x", &heap)?
+        };
+        let x = frozen.to_value();
+        let x = ListRef::from_value(x).unwrap();
+        let res = x.iter().map(|v| v.to_repr()).collect();
+        // Shared and cyclic structure is frozen once.
+        assert!(x[0].ptr_eq(x[1]));
+        assert!(x[2].ptr_eq(frozen.to_value()));
+        Ok(res)
+    }
+
+    #[test]
+    fn test_freeze_value() {
+        assert_eq!(
+            vec![
+                "[1, \"s\"]",
+                "[1, \"s\"]",
+                "[[1, \"s\"], [1, \"s\"], [...]]"
+            ],
+            eval_and_freeze_value(
+                r#"
+shared = [1, "s"]
+x = [shared, shared]
+x.append(x)
+"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_freeze_value_def() {
+        let err = eval_and_freeze_value(
+            r#"
+def f(): pass
+x = [f]
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Cannot freeze `def`"), "{}", err);
+    }
+}
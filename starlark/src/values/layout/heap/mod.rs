@@ -17,10 +17,12 @@
 
 //! Starlark heap implementation.
 
+pub(crate) mod alloc_log;
 pub(crate) mod arena;
 pub(crate) mod call_enter_exit;
 mod fast_cell;
 pub(crate) mod heap_type;
 pub(crate) mod maybe_uninit_slice_util;
 pub(crate) mod profile;
+pub(crate) mod provenance;
 pub(crate) mod repr;
@@ -29,12 +29,17 @@ use std::time::Instant;
 use allocative::Allocative;
 use dupe::Dupe;
 use either::Either;
+use serde_json::json;
 use starlark_map::small_map::SmallMap;
 
+use crate::eval::runtime::profile::binary::BinaryReader;
+use crate::eval::runtime::profile::binary::BinaryWriter;
+use crate::eval::runtime::profile::binary::ProfileBinaryError;
 use crate::eval::runtime::profile::data::ProfileDataImpl;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
 use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
+use crate::eval::runtime::profile::pprof::PprofBuilder;
 use crate::eval::runtime::small_duration::SmallDuration;
 use crate::eval::ProfileData;
 use crate::values::layout::heap::arena::ArenaVisitor;
@@ -219,6 +224,48 @@ pub(crate) struct StackFrame {
 }
 
 impl StackFrame {
+    fn write_binary(&self, w: &mut BinaryWriter) {
+        w.usize(self.callees.len());
+        for (id, callee) in &self.callees {
+            w.usize(id.0);
+            callee.write_binary(w);
+        }
+        w.usize(self.allocs.summary.len());
+        for (t, counts) in &self.allocs.summary {
+            w.str(t);
+            w.usize(counts.bytes);
+            w.usize(counts.count);
+        }
+        w.u64(self.time_x2.nanos);
+        w.u32(self.calls_x2);
+    }
+
+    fn read_binary(r: &mut BinaryReader, strings: &StringIndex) -> anyhow::Result<StackFrame> {
+        let mut callees = SmallMap::new();
+        for _ in 0..r.len()? {
+            let id = r.usize()?;
+            if id >= strings.len() {
+                return Err(ProfileBinaryError::Invalid(format!("string id {}", id)).into());
+            }
+            callees.insert(StringId(id), StackFrame::read_binary(r, strings)?);
+        }
+        let mut allocs = HeapSummary::default();
+        for _ in 0..r.len()? {
+            let t = r.static_str()?;
+            let bytes = r.usize()?;
+            let count = r.usize()?;
+            allocs.add(t, AllocCounts { bytes, count });
+        }
+        let time_x2 = SmallDuration { nanos: r.u64()? };
+        let calls_x2 = r.u32()?;
+        Ok(StackFrame {
+            callees,
+            allocs,
+            time_x2,
+            calls_x2,
+        })
+    }
+
     fn merge_callees<'a>(
         frames: &'a [StackFrameWithContext<'a>],
         strings: &mut StringIndex,
@@ -288,6 +335,53 @@ impl<'c> StackFrameWithContext<'c> {
             frame.write_flame_graph(child_node);
         }
     }
+
+    /// Add a sample for each type allocated in this frame and its callees.
+    fn write_pprof(&self, pprof: &mut PprofBuilder, stack: &mut Vec<&'c str>) {
+        for (t, v) in &self.frame.allocs.summary {
+            pprof.add_sample(
+                stack.iter().copied(),
+                &[v.count as i64, v.bytes as i64],
+                &[("type", t)],
+            );
+        }
+        for (id, frame) in self.callees() {
+            stack.push(id.as_str());
+            frame.write_pprof(pprof, stack);
+            stack.pop().unwrap();
+        }
+    }
+
+    /// Allocations in this stack frame and all its callees.
+    fn total_allocs(&self) -> AllocCounts {
+        self.callees()
+            .fold(self.frame.allocs.total(), |total, (_, frame)| {
+                total + frame.total_allocs()
+            })
+    }
+
+    /// Convert this stack frame's data to a JSON tree node.
+    fn to_json(&self, function: &str) -> serde_json::Value {
+        let total = self.total_allocs();
+        let types: Vec<_> = self
+            .frame
+            .allocs
+            .summary
+            .iter()
+            .map(|(t, v)| json!({"type": t, "bytes": v.bytes, "count": v.count}))
+            .collect();
+        let callees: Vec<_> = self
+            .callees()
+            .map(|(id, frame)| frame.to_json(id.as_str()))
+            .collect();
+        json!({
+            "function": function,
+            "bytes": total.bytes,
+            "count": total.count,
+            "types": types,
+            "callees": callees,
+        })
+    }
 }
 
 /// `Clone` wrapper.
@@ -390,6 +484,31 @@ impl AggregateHeapProfileInfo {
         }
     }
 
+    pub(crate) fn write_binary(&self, w: &mut BinaryWriter) {
+        w.usize(self.strings.len());
+        for i in 0..self.strings.len() {
+            w.str(self.strings.get(StringId(i)));
+        }
+        self.root.write_binary(w);
+        w.usize(self.unused_capacity.get());
+    }
+
+    pub(crate) fn read_binary(r: &mut BinaryReader) -> anyhow::Result<AggregateHeapProfileInfo> {
+        let mut strings = StringIndex::default();
+        for i in 0..r.len()? {
+            if strings.index(r.str()?) != StringId(i) {
+                return Err(ProfileBinaryError::Invalid("duplicate string".to_owned()).into());
+            }
+        }
+        let root = StackFrame::read_binary(r, &strings)?;
+        let unused_capacity = UnusedCapacity::new(r.usize()?);
+        Ok(AggregateHeapProfileInfo {
+            strings,
+            root,
+            unused_capacity,
+        })
+    }
+
     /// Write this out recursively to a file.
     pub fn gen_flame_graph(&self) -> String {
         let mut data = FlameGraphData::default();
@@ -400,10 +519,26 @@ impl AggregateHeapProfileInfo {
         data.write()
     }
 
+    /// Add samples with object count and bytes values, labelled with the type.
+    pub(crate) fn write_pprof(&self, pprof: &mut PprofBuilder) {
+        let mut stack = Vec::new();
+        self.root().write_pprof(pprof, &mut stack);
+        assert!(stack.is_empty());
+    }
+
     /// Write per-function summary in CSV format.
     pub fn gen_summary_csv(&self) -> String {
         HeapSummaryByFunction::init(self).gen_csv()
     }
+
+    /// Write the call tree with memory by type in JSON format, e.g. for treemap visualization.
+    ///
+    /// Each node is `{"function", "bytes", "count", "types", "callees"}`, where `bytes` and `count`
+    /// include callees, `types` lists `{"type", "bytes", "count"}` allocated in the function itself,
+    /// and `callees` contains nodes of the same shape.
+    pub fn gen_summary_json(&self) -> String {
+        serde_json::to_string(&self.root().to_json("root")).unwrap()
+    }
 }
 
 #[derive(Debug, Allocative)]
@@ -42,6 +42,18 @@ impl HeapSummary {
             .collect()
     }
 
+    /// The `n` types using the most bytes, as (type, (count, total size)),
+    /// largest first.
+    pub fn top_by_bytes(&self, n: usize) -> Vec<(String, (usize, usize))> {
+        let mut types: Vec<_> = self.summary.iter().collect();
+        types.sort_by(|(ak, av), (bk, bv)| bv.bytes.cmp(&av.bytes).then_with(|| ak.cmp(bk)));
+        types
+            .into_iter()
+            .take(n)
+            .map(|(k, v)| ((*k).to_owned(), (v.count, v.bytes)))
+            .collect()
+    }
+
     pub(crate) fn total(&self) -> AllocCounts {
         self.summary.values().sum()
     }
@@ -44,6 +44,10 @@ impl StringIndex {
         StringId(self.strings.len() - 1)
     }
 
+    pub(crate) fn len(&self) -> usize {
+        self.strings.len()
+    }
+
     pub(crate) fn get(&self, id: StringId) -> &ArcStr {
         self.strings.get_index(id.0).expect("invalid string id")
     }
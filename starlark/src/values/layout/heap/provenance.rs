@@ -0,0 +1,119 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Spans where heap values were allocated, for
+//! [`Evaluator::track_value_provenance`](crate::eval::Evaluator::track_value_provenance).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use either::Either;
+use once_cell::sync::Lazy;
+
+use crate::codemap::FileSpan;
+use crate::values::layout::heap::repr::AValueOrForward;
+
+/// Span of every tracked allocation, keyed by the address of its header.
+///
+/// Values do not know their heap, so the table is shared by all heaps.
+/// Each entry is owned by the [`Provenance`] of the arena holding the allocation,
+/// which removes it before the memory can be reused.
+static SPANS: Lazy<Mutex<HashMap<usize, FileSpan>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Span where the value at `header` was allocated, if it was tracked.
+pub(crate) fn lookup(header: &AValueOrForward) -> Option<FileSpan> {
+    let spans = SPANS.lock().unwrap();
+    spans
+        .get(&(header as *const AValueOrForward as usize))
+        .cloned()
+}
+
+/// Provenance of the allocations of one arena.
+#[derive(Default)]
+pub(crate) struct Provenance {
+    /// Spans of the calls being evaluated, innermost last.
+    /// Calls without a span inherit the span of their caller.
+    calls: Vec<Option<FileSpan>>,
+    /// Addresses of the allocations with an entry in [`SPANS`].
+    addrs: Vec<usize>,
+}
+
+impl Provenance {
+    pub(crate) fn call_enter(&mut self, span: Option<FileSpan>) {
+        let span = span.or_else(|| self.calls.last().cloned().flatten());
+        self.calls.push(span);
+    }
+
+    pub(crate) fn call_exit(&mut self) {
+        // Tracking may have been enabled in the middle of a call.
+        self.calls.pop();
+    }
+
+    /// Attribute a new allocation to the innermost call being evaluated.
+    pub(crate) fn alloc(&mut self, addr: usize) {
+        if let Some(Some(span)) = self.calls.last() {
+            self.set(addr, span.clone());
+        }
+    }
+
+    /// Attribute the allocation at `addr` to `span`,
+    /// replacing the span it was allocated at.
+    pub(crate) fn set(&mut self, addr: usize, span: FileSpan) {
+        if SPANS.lock().unwrap().insert(addr, span).is_none() {
+            self.addrs.push(addr);
+        }
+    }
+
+    /// Follow the forward pointers left by GC or freezing,
+    /// forgetting the allocations which were not moved.
+    ///
+    /// The arena holding the old allocations must still be alive.
+    pub(crate) fn relocate(&mut self) {
+        let mut spans = SPANS.lock().unwrap();
+        for addr in &mut self.addrs {
+            let span = spans.remove(addr);
+            // Safe because the arena holding `addr` is alive.
+            let header = unsafe { &*(*addr as *const AValueOrForward) };
+            *addr = match (header.unpack(), span) {
+                (Either::Right(forward), Some(span)) => {
+                    let new_addr = forward.forward_ptr().untagged();
+                    spans.insert(new_addr, span);
+                    new_addr
+                }
+                _ => 0,
+            };
+        }
+        self.addrs.retain(|addr| *addr != 0);
+    }
+
+    /// Take over the allocations of `other`, after they were relocated into this arena.
+    pub(crate) fn append(&mut self, other: &mut Provenance) {
+        self.addrs.append(&mut other.addrs);
+    }
+}
+
+impl Drop for Provenance {
+    fn drop(&mut self) {
+        if self.addrs.is_empty() {
+            return;
+        }
+        let mut spans = SPANS.lock().unwrap();
+        for addr in &self.addrs {
+            spans.remove(addr);
+        }
+    }
+}
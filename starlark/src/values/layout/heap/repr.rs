@@ -29,6 +29,7 @@ use crate::any::AnyLifetime;
 use crate::values::layout::avalue::AValue;
 use crate::values::layout::heap::arena::MIN_ALLOC;
 use crate::values::layout::heap::heap_type::HeapKind;
+use crate::values::layout::pointer::TAG_STR;
 use crate::values::layout::vtable::AValueDyn;
 use crate::values::layout::vtable::AValueVTable;
 use crate::values::FrozenValue;
@@ -79,6 +80,11 @@ impl ForwardPtr {
         ForwardPtr(ptr)
     }
 
+    /// Address of the moved object, without the str tag.
+    pub(crate) fn untagged(self) -> usize {
+        self.0 & !TAG_STR
+    }
+
     /// It's caller responsibility to ensure that forward pointer points to a frozen value.
     pub(crate) unsafe fn unpack_frozen_value(self) -> FrozenValue {
         FrozenValue::new_ptr_usize_with_str_tag(self.0)
@@ -122,7 +122,7 @@ assert_eq_size!(Option<FrozenPointer<'static>>, usize);
 const TAG_BITS: usize = 0b111;
 
 const TAG_INT: usize = 0b010;
-const TAG_STR: usize = 0b100;
+pub(crate) const TAG_STR: usize = 0b100;
 // Pointer to an object, which is not frozen.
 // Note, an object can be changed from unfrozen to frozen, not vice versa.
 const TAG_UNFROZEN: usize = 0b001;
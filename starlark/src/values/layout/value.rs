@@ -34,6 +34,9 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
 
 use allocative::Allocative;
 use dupe::Clone_;
@@ -53,6 +56,7 @@ use starlark_map::Equivalent;
 use crate as starlark;
 use crate::any::AnyLifetime;
 use crate::any::ProvidesStaticType;
+use crate::codemap::FileSpan;
 use crate::collections::Hashed;
 use crate::collections::StarlarkHashValue;
 use crate::collections::StarlarkHasher;
@@ -66,6 +70,7 @@ use crate::eval::Evaluator;
 use crate::eval::ParametersSpec;
 use crate::sealed::Sealed;
 use crate::values::demand::request_value_impl;
+use crate::values::dict::DictRef;
 use crate::values::dict::FrozenDictRef;
 use crate::values::enumeration::EnumType;
 use crate::values::enumeration::FrozenEnumValue;
@@ -80,6 +85,7 @@ use crate::values::layout::avalue::StarlarkStrAValue;
 use crate::values::layout::avalue::VALUE_FALSE;
 use crate::values::layout::avalue::VALUE_NONE;
 use crate::values::layout::avalue::VALUE_TRUE;
+use crate::values::layout::heap::provenance;
 use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::pointer::FrozenPointer;
@@ -88,6 +94,7 @@ use crate::values::layout::pointer::RawPointer;
 use crate::values::layout::static_string::VALUE_EMPTY_STRING;
 use crate::values::layout::typed::string::StringValueLike;
 use crate::values::layout::vtable::AValueDyn;
+use crate::values::list::ListRef;
 use crate::values::num::Num;
 use crate::values::range::Range;
 use crate::values::record::FrozenRecord;
@@ -97,8 +104,13 @@ use crate::values::recursive_repr_or_json_guard::repr_stack_push;
 use crate::values::stack_guard;
 use crate::values::string::StarlarkStr;
 use crate::values::structs::value::FrozenStruct;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
 use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::types::bool::BOOL_TYPE;
+use crate::values::types::int::INT_TYPE;
 use crate::values::types::list::value::FrozenListData;
+use crate::values::types::string::STRING_TYPE;
 use crate::values::types::tuple::value::FrozenTuple;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::types::unbound::MaybeUnboundValue;
@@ -292,6 +304,17 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// Is this value frozen, and therefore immutable.
+    ///
+    /// Values allocated on a [`FrozenHeap`](crate::values::FrozenHeap), as well as
+    /// values stored inline in the pointer (e.g. small ints, `None`, `True`)
+    /// are frozen. Values allocated on a [`Heap`] are not frozen,
+    /// even if they are of an immutable type.
+    #[inline]
+    pub fn is_frozen(self) -> bool {
+        !self.0.is_unfrozen()
+    }
+
     #[inline]
     unsafe fn unpack_frozen_unchecked(self) -> FrozenValue {
         debug_assert!(!self.0.is_unfrozen());
@@ -399,6 +422,45 @@ impl<'v> Value<'v> {
         self.unpack_starlark_str().map(|s| s.as_str())
     }
 
+    /// Obtain the underlying integer if it is an `int` which fits in an `i32`.
+    /// Same as [`unpack_int`](Value::unpack_int), named to pair with
+    /// [`require_i32`](Value::require_i32).
+    #[inline]
+    pub fn unpack_i32(self) -> Option<i32> {
+        self.unpack_int()
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn require_error(self, expected: &str) -> anyhow::Error {
+        ValueError::IncorrectParameterTypeWithExpected(
+            expected.to_owned(),
+            self.get_type().to_owned(),
+        )
+        .into()
+    }
+
+    /// Like [`unpack_i32`](Value::unpack_i32), but fail with an error naming
+    /// the actual type if the value is not an `int` in range.
+    pub fn require_i32(self) -> anyhow::Result<i32> {
+        self.unpack_i32()
+            .ok_or_else(|| self.require_error(INT_TYPE))
+    }
+
+    /// Like [`unpack_str`](Value::unpack_str), but fail with an error naming
+    /// the actual type if the value is not a string.
+    pub fn require_str(self) -> anyhow::Result<&'v str> {
+        self.unpack_str()
+            .ok_or_else(|| self.require_error(STRING_TYPE))
+    }
+
+    /// Like [`unpack_bool`](Value::unpack_bool), but fail with an error naming
+    /// the actual type if the value is not a boolean.
+    pub fn require_bool(self) -> anyhow::Result<bool> {
+        self.unpack_bool()
+            .ok_or_else(|| self.require_error(BOOL_TYPE))
+    }
+
     /// Get a pointer to a [`AValue`].
     pub(crate) fn get_ref(self) -> AValueDyn<'v> {
         unsafe {
@@ -457,6 +519,19 @@ impl<'v> Value<'v> {
         ValueIdentity::new(self)
     }
 
+    /// Hash of this [`Value`] derived from its pointer, consistent with [`Value::ptr_eq`]:
+    /// values that are `ptr_eq` have the same `ptr_hash`. Like [`Value::identity`], this is
+    /// only stable until the next GC.
+    ///
+    /// This is unrelated to the Starlark hash of the value (see [`Value::get_hashed`]),
+    /// and is intended for identity-keyed maps in embedders.
+    #[inline]
+    pub fn ptr_hash(self) -> u64 {
+        let mut hasher = StarlarkHasher::new();
+        self.ptr_value().hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Get the underlying pointer.
     /// Should be done sparingly as it slightly breaks the abstraction.
     /// Most useful as a hash key based on pointer.
@@ -472,6 +547,19 @@ impl<'v> Value<'v> {
         self.get_ref().get_type()
     }
 
+    /// Allocate a copy of this value in `heap`. Unlike a deep copy, the values
+    /// this value refers to (e.g. the elements of a list) are shared, not copied.
+    ///
+    /// Supported for strings, floats, lists and tuples; `None`, booleans and
+    /// small ints are returned unchanged. Other types return an error.
+    pub fn shallow_copy_to(self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        if self.is_none() || self.unpack_bool().is_some() || self.unpack_int().is_some() {
+            Ok(self)
+        } else {
+            self.get_ref().shallow_copy_to(heap)
+        }
+    }
+
     /// `bool(x)`.
     pub fn to_bool(self) -> bool {
         // Fast path for the common case
@@ -492,6 +580,22 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// Convert to an `i32` without losing information, unlike `int(x)` which truncates floats.
+    ///
+    /// Accepts the same values as [`to_int`](Value::to_int), and also floats which are
+    /// whole numbers within the range of `i32`. Fails for any other float, e.g. `3.5`.
+    pub fn to_int_lossless(self) -> anyhow::Result<i32> {
+        match self.unpack_num() {
+            Some(Num::Float(f)) => Num::Float(f).as_int().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Cannot convert float `{}` to int without losing precision",
+                    self.to_repr()
+                )
+            }),
+            _ => self.to_int(),
+        }
+    }
+
     /// `x[index]`.
     pub fn at(self, index: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         self.get_ref().at(index, heap)
@@ -514,8 +618,17 @@ impl<'v> Value<'v> {
     }
 
     /// `other in x`.
+    ///
+    /// Asks `x` first, and if `x` does not support `in` for `other`,
+    /// asks `other` with [`contained_in`](StarlarkValue::contained_in).
     pub fn is_in(self, other: Value<'v>) -> anyhow::Result<bool> {
-        self.get_ref().is_in(other)
+        match self.get_ref().is_in(other) {
+            Err(e) if ValueError::is_unsupported(&e) => match other.get_ref().contained_in(self) {
+                Some(r) => r,
+                None => Err(e),
+            },
+            r => r,
+        }
     }
 
     /// `+x`.
@@ -554,18 +667,49 @@ impl<'v> Value<'v> {
     }
 
     /// `x & other`.
+    ///
+    /// Asks `x` first, and if `x` does not support `&` with `other`,
+    /// asks `other` with [`rbit_and`](StarlarkValue::rbit_and).
     pub fn bit_and(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        self.get_ref().bit_and(other, heap)
+        match self.get_ref().bit_and(other, heap) {
+            Err(e) if ValueError::is_unsupported(&e) => {
+                match other.get_ref().rbit_and(self, heap) {
+                    Some(r) => r,
+                    None => Err(e),
+                }
+            }
+            r => r,
+        }
     }
 
     /// `x | other`.
+    ///
+    /// Asks `x` first, and if `x` does not support `|` with `other`,
+    /// asks `other` with [`rbit_or`](StarlarkValue::rbit_or).
     pub fn bit_or(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        self.get_ref().bit_or(other, heap)
+        match self.get_ref().bit_or(other, heap) {
+            Err(e) if ValueError::is_unsupported(&e) => match other.get_ref().rbit_or(self, heap) {
+                Some(r) => r,
+                None => Err(e),
+            },
+            r => r,
+        }
     }
 
     /// `x ^ other`.
+    ///
+    /// Asks `x` first, and if `x` does not support `^` with `other`,
+    /// asks `other` with [`rbit_xor`](StarlarkValue::rbit_xor).
     pub fn bit_xor(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        self.get_ref().bit_xor(other, heap)
+        match self.get_ref().bit_xor(other, heap) {
+            Err(e) if ValueError::is_unsupported(&e) => {
+                match other.get_ref().rbit_xor(self, heap) {
+                    Some(r) => r,
+                    None => Err(e),
+                }
+            }
+            r => r,
+        }
     }
 
     /// `~x`.
@@ -639,6 +783,13 @@ impl<'v> Value<'v> {
         self.get_ref().get_type_starlark_repr()
     }
 
+    /// Does this value match a named type, as used in runtime type checks of
+    /// annotations, e.g. `"string"` or a record type name. Values always match
+    /// their own [`get_type`](Value::get_type), and some also match their supertypes.
+    pub fn matches_type(self, ty: &str) -> bool {
+        self.get_ref().matches_type(ty)
+    }
+
     /// Add two [`Value`]s together. Will first try using [`radd`](StarlarkValue::radd),
     /// before falling back to [`add`](StarlarkValue::add).
     pub fn add(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
@@ -660,6 +811,7 @@ impl<'v> Value<'v> {
                 } else if rs.is_empty() {
                     return Ok(self);
                 } else {
+                    heap.check_string_length(ls.len() + rs.len())?;
                     return Ok(heap.alloc_str_concat(ls, rs).to_value());
                 }
             }
@@ -706,6 +858,74 @@ impl<'v> Value<'v> {
         serde_json::to_string(&self).map_err(|e| anyhow::anyhow!(e))
     }
 
+    /// Convert the value to JSON spread over multiple lines,
+    /// with nested elements indented by `indent` spaces.
+    ///
+    /// Return an error if the value or any contained value does not support conversion to JSON.
+    pub fn to_json_pretty(self, indent: usize) -> anyhow::Result<String> {
+        let indent = " ".repeat(indent);
+        let mut json = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(
+            &mut json,
+            serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes()),
+        );
+        self.serialize(&mut serializer)
+            .map_err(|e| anyhow::anyhow!(e))?;
+        // serde_json only writes valid UTF-8.
+        Ok(String::from_utf8(json)?)
+    }
+
+    /// Estimate the length in bytes of the JSON produced by [`to_json`](Value::to_json)
+    /// without serializing the value, for example to reject a value which
+    /// is too large before writing it to a size-limited sink.
+    ///
+    /// Lists, tuples, dicts and structs are walked recursively,
+    /// strings count their bytes ignoring escapes, and other values count their `repr`.
+    /// A value which contains itself is counted once.
+    pub fn approx_json_size(self) -> usize {
+        // Separators: brackets, plus a comma between elements.
+        fn container(len: usize, elements: usize) -> usize {
+            2 + len.saturating_sub(1) + elements
+        }
+
+        let _guard = match json_stack_push(self) {
+            Ok(guard) => guard,
+            Err(..) => return 0,
+        };
+        if let Some(s) = self.unpack_str() {
+            s.len() + 2
+        } else if self.is_none() {
+            "null".len()
+        } else if let Some(list) = ListRef::from_value(self) {
+            container(list.len(), list.iter().map(Value::approx_json_size).sum())
+        } else if let Some(tuple) = TupleRef::from_value(self) {
+            container(tuple.len(), tuple.iter().map(Value::approx_json_size).sum())
+        } else if let Some(dict) = DictRef::from_value(self) {
+            let entries = dict
+                .iter()
+                .map(|(k, v)| k.approx_json_size() + 1 + v.approx_json_size())
+                .sum();
+            container(dict.len(), entries)
+        } else if let Some(s) = StructRef::from_value(self) {
+            let fields = s
+                .iter()
+                .map(|(k, v)| k.len() + 3 + v.approx_json_size())
+                .sum();
+            container(s.iter().len(), fields)
+        } else {
+            self.to_repr().len()
+        }
+    }
+
+    /// Write the value as JSON to a writer, without buffering the whole output,
+    /// so large values can be written with bounded memory.
+    ///
+    /// Return an error if the value or any contained value does not support conversion
+    /// to JSON, or on an IO error. Output written before the error is not rolled back.
+    pub fn write_json(self, writer: impl io::Write) -> anyhow::Result<()> {
+        serde_json::to_writer(writer, &self).map_err(|e| anyhow::anyhow!(e))
+    }
+
     /// Forwards to [`StarlarkValue::set_attr`].
     pub fn set_attr(self, attribute: &str, alloc_value: Value<'v>) -> anyhow::Result<()> {
         self.get_ref().set_attr(attribute, alloc_value)
@@ -760,6 +980,9 @@ impl<'v> Value<'v> {
 
     /// Are two values equal. If the values are of different types it will
     /// return [`false`]. It will only error if there is excessive recursion.
+    ///
+    /// Comparing distinct cyclic containers fails with a recursion error.
+    /// Floats compare by total order, so NaN equals NaN, inside containers too.
     #[inline]
     pub fn equals(self, other: Value<'v>) -> anyhow::Result<bool> {
         ValueLike::equals(self, other)
@@ -845,6 +1068,18 @@ impl<'v> Value<'v> {
     pub fn request_value<T: AnyLifetime<'v>>(self) -> Option<T> {
         request_value_impl(self)
     }
+
+    /// Where the value was defined, if known.
+    ///
+    /// Functions defined in Starlark report their definition.
+    /// Other values report the innermost call being evaluated when they were allocated,
+    /// if [`Evaluator::track_value_provenance`](crate::eval::Evaluator::track_value_provenance)
+    /// was enabled, including after their module is frozen.
+    /// Types can report their own with [`StarlarkValue::provide`].
+    pub fn provenance(self) -> Option<FileSpan> {
+        self.request_value::<FileSpan>()
+            .or_else(|| provenance::lookup(self.0.unpack_ptr()?))
+    }
 }
 
 impl FrozenValue {
@@ -993,6 +1228,18 @@ impl FrozenValue {
         }
     }
 
+    /// Per-call check whether `invoke` can be called speculatively,
+    /// see [`NativeFunction::set_speculative_exec_guard`].
+    pub(crate) fn speculative_exec_guard(self) -> Option<fn(&Arguments) -> bool> {
+        FrozenValueTyped::<NativeFunction>::new(self)?.speculative_exec_guard
+    }
+
+    /// Does `invoke` mutate its arguments?
+    /// See [`NativeFunction::set_mutates_args`].
+    pub(crate) fn mutates_args(self) -> bool {
+        FrozenValueTyped::<NativeFunction>::new(self).map_or(false, |v| v.mutates_args)
+    }
+
     /// `self == b` is `ptr_eq`.
     pub(crate) fn eq_is_ptr_eq(self) -> bool {
         // Note `int` is not `ptr_eq` because `int` can be equal to `float`.
@@ -1004,6 +1251,12 @@ impl FrozenValue {
             || matches!(Tuple::from_value(self.to_value()), Some(t) if t.len() == 0)
     }
 
+    /// Downcast to a typed frozen value, which can be used without downcasting again.
+    #[inline]
+    pub fn downcast<T: StarlarkValue<'static>>(self) -> Option<FrozenValueTyped<'static, T>> {
+        FrozenValueTyped::new(self)
+    }
+
     /// Downcast to given type.
     #[inline]
     pub fn downcast_frozen_ref<T: StarlarkValue<'static>>(self) -> Option<FrozenRef<'static, T>> {
@@ -1130,6 +1383,16 @@ pub trait ValueLike<'v>:
     /// Get a reference to underlying data or [`None`]
     /// if contained object has different type than requested.
     fn downcast_ref<T: StarlarkValue<'v>>(self) -> Option<&'v T>;
+
+    /// Like [`downcast_ref`](ValueLike::downcast_ref), but fail with an error
+    /// naming both the expected [`TYPE`](StarlarkValue::TYPE) and the actual type.
+    #[inline]
+    fn downcast_ref_err<T: StarlarkValue<'v>>(self) -> anyhow::Result<&'v T> {
+        match self.downcast_ref() {
+            Some(v) => Ok(v),
+            None => Err(self.to_value().require_error(T::TYPE)),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -1240,13 +1503,215 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::hash::Hash;
+    use std::hash::Hasher;
+    use std::io;
+
+    use allocative::Allocative;
+    use derive_more::Display;
+
+    use crate as starlark;
+    use crate::any::ProvidesStaticType;
+    use crate::assert;
+    use crate::collections::StarlarkHasher;
+    use crate::values::dict::AllocDict;
+    use crate::values::list::AllocList;
     use crate::values::none::NoneType;
     use crate::values::string::StarlarkStr;
     use crate::values::types::int::PointerI32;
+    use crate::values::Freezer;
+    use crate::values::FrozenHeap;
+    use crate::values::FrozenValue;
     use crate::values::Heap;
+    use crate::values::NoSerialize;
+    use crate::values::StarlarkValue;
     use crate::values::Value;
     use crate::values::ValueLike;
 
+    #[test]
+    fn test_write_json() {
+        let value = assert::pass("{'a': [1, 2.5, None], 'b': struct(c = ('d', True))}");
+        let mut json = Vec::new();
+        value.value().write_json(&mut json).unwrap();
+        assert_eq!(
+            value.value().to_json().unwrap(),
+            String::from_utf8(json).unwrap()
+        );
+
+        let value = assert::pass("x = [1]\nx.append({'x': x})\nx");
+        let err = value.value().write_json(io::sink()).unwrap_err();
+        assert!(err.to_string().contains("Cycle detected"), "{}", err);
+    }
+
+    #[test]
+    fn test_to_json_pretty() {
+        let value = assert::pass("{'a': [1, {}], 'b': struct(c = ('d', True))}");
+        assert_eq!(
+            r#"{"a":[1,{}],"b":{"c":["d",true]}}"#,
+            value.value().to_json().unwrap()
+        );
+        assert_eq!(
+            r#"{
+  "a": [
+    1,
+    {}
+  ],
+  "b": {
+    "c": [
+      "d",
+      true
+    ]
+  }
+}"#,
+            value.value().to_json_pretty(2).unwrap()
+        );
+        assert_eq!(
+            "[\n1\n]",
+            assert::pass("[1]").value().to_json_pretty(0).unwrap()
+        );
+
+        let value = assert::pass("[len]");
+        let err = value.value().to_json_pretty(2).unwrap_err();
+        assert!(err.to_string().contains("`function`"), "{}", err);
+    }
+
+    #[test]
+    fn test_approx_json_size() {
+        for expr in [
+            "None",
+            "[]",
+            "{}",
+            "'abc'",
+            "[1, 2.5, True, None]",
+            "{'a': [1, 'b'], 'c': struct(d = (1, 'e'), f = {})}",
+            "[{'key': 'value ' * 10, 'n': 12345}] * 100",
+        ] {
+            let value = assert::pass(expr);
+            let exact = value.value().to_json().unwrap().len();
+            assert_eq!(exact, value.value().approx_json_size(), "{}", expr);
+        }
+
+        // Escapes are not counted.
+        let value = assert::pass(r#"["He said \"hi\"\n"] * 10"#);
+        let exact = value.value().to_json().unwrap().len();
+        let approx = value.value().approx_json_size();
+        assert!(
+            approx < exact && approx * 5 >= exact * 4,
+            "{} {}",
+            approx,
+            exact
+        );
+
+        // Cycles terminate.
+        let value = assert::pass("x = [1]\nx.append({'x': x})\nx");
+        assert_eq!("[1,{\"x\":}]".len(), value.value().approx_json_size());
+    }
+
+    #[test]
+    fn test_type_starlark_repr_matches() {
+        for expr in [
+            "1",
+            "2.5",
+            "'s'",
+            "None",
+            "True",
+            "[1]",
+            "(1,)",
+            "{1: 2}",
+            "range(3)",
+            "lambda: 1",
+        ] {
+            let value = assert::pass(expr);
+            let value = value.value();
+            assert!(value.matches_type(value.get_type()), "{}", expr);
+            let repr = value.get_type_starlark_repr();
+            assert::pass(&format!("def f(x: {}):\n    pass\nf({})", repr, expr));
+        }
+        assert!(!Value::new_int(1).matches_type("string"));
+        assert::fail(
+            &format!(
+                "def f(x: {}):\n    pass\nf('s')",
+                Value::new_int(1).get_type_starlark_repr()
+            ),
+            "does not match the type annotation",
+        );
+    }
+
+    #[test]
+    fn test_shallow_copy_to() {
+        let frozen = FrozenHeap::new();
+        let elem = frozen.alloc("element");
+        let values = [
+            frozen.alloc("a longer string").to_value(),
+            frozen.alloc(2.5).to_value(),
+            frozen
+                .alloc_tuple(&[elem, FrozenValue::new_int(3)])
+                .to_value(),
+            frozen.alloc(AllocList([elem])).to_value(),
+            Value::new_int(7),
+            Value::new_none(),
+            Value::new_bool(false),
+        ];
+
+        let heap = Heap::new();
+        for v in values {
+            let copy = v.shallow_copy_to(&heap).unwrap();
+            assert!(copy.equals(v).unwrap(), "{}", v);
+            assert_eq!(v.get_type(), copy.get_type());
+        }
+
+        // Containers are new values, but share their elements.
+        let list = values[3].shallow_copy_to(&heap).unwrap();
+        assert!(!list.is_frozen());
+        assert!(!list.ptr_eq(values[3]));
+        assert!(
+            list.at(Value::new_int(0), &heap)
+                .unwrap()
+                .ptr_eq(elem.to_value())
+        );
+
+        let dict = heap.alloc(AllocDict([("x", 1)]));
+        assert_eq!(
+            "Value of type `dict` cannot be shallow copied",
+            dict.shallow_copy_to(&heap).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_unpack_and_require() {
+        let heap = Heap::new();
+        let int = Value::new_int(17);
+        let big = heap.alloc(1i64 << 40);
+        let string = heap.alloc("hello");
+        let boolean = Value::new_bool(true);
+        let none = Value::new_none();
+
+        assert_eq!(Some(17), int.unpack_i32());
+        assert_eq!(None, big.unpack_i32());
+        assert_eq!(None, string.unpack_i32());
+        assert_eq!(None, boolean.unpack_i32());
+        assert_eq!(Some("hello"), string.unpack_str());
+        assert_eq!(None, int.unpack_str());
+        assert_eq!(Some(true), boolean.unpack_bool());
+        assert_eq!(None, int.unpack_bool());
+
+        assert_eq!(17, int.require_i32().unwrap());
+        assert_eq!("hello", string.require_str().unwrap());
+        assert!(boolean.require_bool().unwrap());
+        assert_eq!(
+            "Type of parameters mismatch, expected `int`, actual `string`",
+            string.require_i32().unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Type of parameters mismatch, expected `string`, actual `NoneType`",
+            none.require_str().unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Type of parameters mismatch, expected `bool`, actual `int`",
+            int.require_bool().unwrap_err().to_string()
+        );
+    }
+
     #[test]
     fn test_downcast_ref() {
         let heap = Heap::new();
@@ -1269,4 +1734,118 @@ mod tests {
         assert_eq!(17, integer.downcast_ref::<PointerI32>().unwrap().get());
         assert!(none.downcast_ref::<PointerI32>().is_none());
     }
+
+    #[test]
+    fn test_downcast_ref_err() {
+        let heap = Heap::new();
+        let string = heap.alloc_str("asd").to_value();
+        assert_eq!(
+            "asd",
+            string.downcast_ref_err::<StarlarkStr>().unwrap().as_str()
+        );
+        assert_eq!(
+            "Type of parameters mismatch, expected `NoneType`, actual `string`",
+            string
+                .downcast_ref_err::<NoneType>()
+                .unwrap_err()
+                .to_string()
+        );
+
+        let frozen_heap = FrozenHeap::new();
+        let frozen = frozen_heap.alloc("frozen");
+        assert_eq!(
+            "frozen",
+            frozen.downcast_ref_err::<StarlarkStr>().unwrap().as_str()
+        );
+        assert_eq!(
+            "Type of parameters mismatch, expected `int`, actual `string`",
+            frozen
+                .downcast_ref_err::<PointerI32>()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_frozen_downcast() {
+        #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+        #[display(fmt = "Magic({})", _0)]
+        struct Magic(i32);
+        starlark_simple_value!(Magic);
+        impl<'v> StarlarkValue<'v> for Magic {
+            starlark_type!("magic");
+        }
+
+        let heap = FrozenHeap::new();
+        let magic = heap.alloc_simple(Magic(17));
+        let typed = magic.downcast::<Magic>().unwrap();
+        assert_eq!(17, typed.as_ref().0);
+        assert_eq!(magic, typed.to_frozen_value());
+
+        assert!(magic.downcast::<StarlarkStr>().is_none());
+        assert!(heap.alloc("magic").downcast::<Magic>().is_none());
+    }
+
+    #[test]
+    fn test_is_frozen() {
+        let heap = Heap::new();
+        let list = heap.alloc(vec![1, 2]);
+        let string = heap.alloc_str("abc").to_value();
+        assert!(!list.is_frozen());
+        assert!(!string.is_frozen());
+        assert!(Value::new_int(17).is_frozen());
+        assert!(Value::new_none().is_frozen());
+        assert!(Value::new_bool(true).is_frozen());
+
+        let freezer = Freezer::new(FrozenHeap::new());
+        let frozen_list = freezer.freeze(list).unwrap().to_value();
+        let frozen_string = freezer.freeze(string).unwrap().to_value();
+        assert!(frozen_list.is_frozen());
+        assert!(frozen_string.is_frozen());
+    }
+
+    #[test]
+    fn test_to_int_lossless() {
+        let heap = Heap::new();
+        assert_eq!(3, heap.alloc(3.0).to_int_lossless().unwrap());
+        assert_eq!(-7, heap.alloc(-7.0).to_int_lossless().unwrap());
+        assert_eq!(0, heap.alloc(-0.0).to_int_lossless().unwrap());
+        assert_eq!(5, Value::new_int(5).to_int_lossless().unwrap());
+        assert_eq!(1, Value::new_bool(true).to_int_lossless().unwrap());
+        assert!(
+            heap.alloc(3.5)
+                .to_int_lossless()
+                .unwrap_err()
+                .to_string()
+                .contains("without losing precision")
+        );
+        assert!(heap.alloc(f64::NAN).to_int_lossless().is_err());
+        assert!(heap.alloc(1e10).to_int_lossless().is_err());
+        assert!(heap.alloc("3").to_int_lossless().is_err());
+        // The `int` builtin still truncates.
+        assert::eq("3", "int(3.5)");
+    }
+
+    #[test]
+    fn test_ptr_hash() {
+        let heap = Heap::new();
+        let list = heap.alloc(vec![1, 2]);
+        let copy = list;
+        assert!(list.ptr_eq(copy));
+        assert_eq!(list.ptr_hash(), copy.ptr_hash());
+        assert_eq!(Value::new_none().ptr_hash(), Value::new_none().ptr_hash());
+        assert_eq!(Value::new_int(3).ptr_hash(), Value::new_int(3).ptr_hash());
+
+        // The hash is the hash of the pointer.
+        let mut hasher = StarlarkHasher::new();
+        list.ptr_value().hash(&mut hasher);
+        assert_eq!(hasher.finish(), list.ptr_hash());
+
+        // Equal but distinct values hash by their own pointers.
+        let other = heap.alloc(vec![1, 2]);
+        assert!(!list.ptr_eq(other));
+        assert!(list.equals(other).unwrap());
+        assert_ne!(list.ptr_hash(), other.ptr_hash());
+        assert_ne!(Value::new_int(3).ptr_hash(), Value::new_int(4).ptr_hash());
+    }
 }
@@ -96,6 +96,7 @@ pub(crate) struct AValueVTable {
     memory_size: fn(*const ()) -> usize,
     heap_freeze: fn(*mut (), &Freezer) -> anyhow::Result<FrozenValue>,
     heap_copy: for<'v> fn(*mut (), &Tracer<'v>) -> Value<'v>,
+    shallow_copy: for<'v> fn(*const (), &'v Heap) -> anyhow::Result<Value<'v>>,
 
     // `StarlarkValue` supertraits.
     display: unsafe fn(*const ()) -> *const dyn Display,
@@ -128,6 +129,7 @@ impl AValueVTable {
 
             heap_freeze: |_, _| panic!("BlackHole"),
             heap_copy: |_, _| panic!("BlackHole"),
+            shallow_copy: |_, _| panic!("BlackHole"),
             get_hash: |_| panic!("BlackHole"),
             type_name: "BlackHole",
             type_as_allocative_key: BLACKHOLE_ALLOCATIVE_KEY,
@@ -171,6 +173,11 @@ impl AValueVTable {
                 let value = T::heap_copy(p, transmute!(&Tracer, &Tracer, tracer));
                 transmute!(Value, Value, value)
             },
+            shallow_copy: |p, heap| unsafe {
+                let p = &*(p as *const T);
+                let value = T::shallow_copy(p, transmute!(&Heap, &Heap, heap));
+                transmute!(anyhow::Result<Value>, anyhow::Result<Value>, value)
+            },
             static_type_of_value:
                 GetTypeId::<<T::StarlarkValue as ProvidesStaticType>::StaticType>::TYPE_ID,
             get_hash: |p| unsafe {
@@ -277,6 +284,13 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.heap_copy)(self.value as *const _ as *mut (), tracer)
     }
 
+    /// Allocate a copy of this value in `heap`. Values this value refers to
+    /// are shared rather than copied. Fails for types which don't support it.
+    #[inline]
+    pub(crate) fn shallow_copy_to(self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        (self.vtable.shallow_copy)(self.value as *const (), heap)
+    }
+
     #[inline]
     pub(crate) fn documentation(self) -> Option<DocItem> {
         (self.vtable.starlark_value.documentation)(StarlarkValueRawPtr::new(self.value))
@@ -297,6 +311,11 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.starlark_value.is_in)(StarlarkValueRawPtr::new(self.value), collection)
     }
 
+    #[inline]
+    pub(crate) fn contained_in(self, collection: Value<'v>) -> Option<anyhow::Result<bool>> {
+        (self.vtable.starlark_value.contained_in)(StarlarkValueRawPtr::new(self.value), collection)
+    }
+
     #[inline]
     pub(crate) fn slice(
         self,
@@ -353,6 +372,33 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.starlark_value.bit_xor)(StarlarkValueRawPtr::new(self.value), other, heap)
     }
 
+    #[inline]
+    pub(crate) fn rbit_and(
+        self,
+        other: Value<'v>,
+        heap: &'v Heap,
+    ) -> Option<anyhow::Result<Value<'v>>> {
+        (self.vtable.starlark_value.rbit_and)(StarlarkValueRawPtr::new(self.value), other, heap)
+    }
+
+    #[inline]
+    pub(crate) fn rbit_or(
+        self,
+        other: Value<'v>,
+        heap: &'v Heap,
+    ) -> Option<anyhow::Result<Value<'v>>> {
+        (self.vtable.starlark_value.rbit_or)(StarlarkValueRawPtr::new(self.value), other, heap)
+    }
+
+    #[inline]
+    pub(crate) fn rbit_xor(
+        self,
+        other: Value<'v>,
+        heap: &'v Heap,
+    ) -> Option<anyhow::Result<Value<'v>>> {
+        (self.vtable.starlark_value.rbit_xor)(StarlarkValueRawPtr::new(self.value), other, heap)
+    }
+
     #[inline]
     pub(crate) fn bit_not(self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         (self.vtable.starlark_value.bit_not)(StarlarkValueRawPtr::new(self.value), heap)
@@ -36,6 +36,7 @@ pub use starlark_derive::Freeze;
 pub use starlark_derive::NoSerialize;
 pub use starlark_derive::StarlarkAttrs;
 pub use starlark_derive::Trace;
+pub use starlark_derive::UnpackValue;
 
 pub use crate::any::AnyLifetime;
 pub use crate::any::ProvidesStaticType;
@@ -49,6 +50,8 @@ pub use crate::values::layout::heap::heap_type::Freezer;
 pub use crate::values::layout::heap::heap_type::FrozenHeap;
 pub use crate::values::layout::heap::heap_type::FrozenHeapRef;
 pub use crate::values::layout::heap::heap_type::Heap;
+pub use crate::values::layout::heap::heap_type::HeapLimitExceeded;
+pub use crate::values::layout::heap::heap_type::StringLengthExceeded;
 pub use crate::values::layout::heap::heap_type::Tracer;
 pub use crate::values::layout::heap::profile::aggregated::AggregateHeapProfileInfo;
 pub use crate::values::layout::identity::ValueIdentity;
@@ -81,6 +84,7 @@ pub use crate::values::types::range;
 pub use crate::values::types::record;
 pub use crate::values::types::regex;
 pub use crate::values::types::string;
+pub use crate::values::types::string::intern::shared::SharedStringInterner;
 pub use crate::values::types::structs;
 pub use crate::values::types::tuple;
 pub use crate::values::unpack::UnpackValue;
@@ -90,10 +94,11 @@ mod alloc_value;
 pub(crate) mod basic;
 mod comparison;
 pub(crate) mod demand;
+pub mod duration;
 pub(crate) mod error;
 mod freeze;
 pub(crate) mod frozen_ref;
-mod index;
+pub mod index;
 pub(crate) mod iter;
 pub(crate) mod layout;
 pub(crate) mod num;
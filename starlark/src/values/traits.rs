@@ -501,8 +501,24 @@ pub trait StarlarkValue<'v>:
     /// ('z' in 'abc') == False
     /// # "#);
     /// ```
-    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
-        ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+    ///
+    /// The default implementation reports that the current value is not iterable,
+    /// so iterable types which do not support `in` should override it to return
+    /// an operation not supported error instead.
+    fn is_in(&self, _other: Value<'v>) -> anyhow::Result<bool> {
+        Err(ControlError::NotIterable(Self::TYPE.to_owned()).into())
+    }
+
+    /// Tell whether the current value is in `collection`, for collections whose
+    /// [`is_in`](StarlarkValue::is_in) does not support this value.
+    /// Should return [`None`] to fall through to the `is_in` error.
+    ///
+    /// `x in collection` calls `is_in` of the collection first, and this function
+    /// only if that fails with an operation not supported error. So this function
+    /// cannot change the result for builtin containers like lists or dicts,
+    /// which compare elements with [`equals`](StarlarkValue::equals).
+    fn contained_in(&self, _collection: Value<'v>) -> Option<anyhow::Result<bool>> {
+        None
     }
 
     /// Apply the `+` unary operator to the current value.
@@ -674,6 +690,29 @@ pub trait StarlarkValue<'v>:
         ValueError::unsupported_with(self, "^", other)
     }
 
+    /// Bitwise `&` with the arguments the other way around, for left operands whose
+    /// [`bit_and`](StarlarkValue::bit_and) does not support this value.
+    /// Should return [`None`] to fall through to the `bit_and` error.
+    ///
+    /// `x & y` calls `bit_and` of `x` first, and this function of `y` only if that
+    /// fails with an operation not supported error, so e.g. `1 & y` can be implemented
+    /// by the type of `y`.
+    fn rbit_and(&self, _lhs: Value<'v>, _heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        None
+    }
+
+    /// Bitwise `|` with the arguments the other way around,
+    /// see [`rbit_and`](StarlarkValue::rbit_and).
+    fn rbit_or(&self, _lhs: Value<'v>, _heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        None
+    }
+
+    /// Bitwise `^` with the arguments the other way around,
+    /// see [`rbit_and`](StarlarkValue::rbit_and).
+    fn rbit_xor(&self, _lhs: Value<'v>, _heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        None
+    }
+
     /// Bitwise `~` operator.
     fn bit_not(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         ValueError::unsupported(self, "~")
@@ -26,6 +26,7 @@ use std::ops::Not;
 use allocative::Allocative;
 use num_bigint::BigInt;
 use num_bigint::Sign;
+use num_traits::cast::FromPrimitive;
 use num_traits::cast::ToPrimitive;
 use num_traits::Signed;
 use num_traits::Zero;
@@ -34,7 +35,9 @@ use serde::Serialize;
 use crate as starlark;
 use crate::any::ProvidesStaticType;
 use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
 use crate::values::float::StarlarkFloat;
+use crate::values::int::int_methods;
 use crate::values::num::Num;
 use crate::values::FrozenHeap;
 use crate::values::FrozenValue;
@@ -120,6 +123,32 @@ impl StarlarkBigInt {
         Self::cmp_small_big(b, a).reverse()
     }
 
+    /// Compare exactly, without rounding the big integer to a float,
+    /// so that mixed int and float values are totally ordered.
+    /// NaN is greater than any number, like in float comparison.
+    pub(crate) fn cmp_big_float(a: &StarlarkBigInt, b: f64) -> Ordering {
+        if b.is_nan() {
+            Ordering::Less
+        } else if b.is_infinite() {
+            if b > 0.0 {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        } else {
+            let floor = b.floor();
+            // Integral finite floats are exactly representable as big integers.
+            match a.value.cmp(&BigInt::from_f64(floor).unwrap()) {
+                Ordering::Equal if floor != b => Ordering::Less,
+                ord => ord,
+            }
+        }
+    }
+
+    pub(crate) fn cmp_float_big(a: f64, b: &StarlarkBigInt) -> Ordering {
+        Self::cmp_big_float(b, a).reverse()
+    }
+
     fn signum(b: &BigInt) -> i32 {
         match b.sign() {
             Sign::Plus => 1,
@@ -188,6 +217,10 @@ impl Serialize for StarlarkBigInt {
 impl<'v> StarlarkValue<'v> for StarlarkBigInt {
     starlark_type!("int");
 
+    fn get_methods() -> Option<&'static Methods> {
+        int_methods()
+    }
+
     fn to_bool(&self) -> bool {
         // `StarlarkBigInt` is non-zero.
         true
@@ -212,7 +245,7 @@ impl<'v> StarlarkValue<'v> for StarlarkBigInt {
                 Ok(false)
             }
             Some(Num::BigInt(other)) => Ok(self == other),
-            Some(Num::Float(f)) => Ok(self.to_f64() == f),
+            Some(Num::Float(f)) => Ok(StarlarkBigInt::cmp_big_float(self, f) == Ordering::Equal),
         }
     }
 
@@ -221,7 +254,7 @@ impl<'v> StarlarkValue<'v> for StarlarkBigInt {
             None => ValueError::unsupported_with(self, "compare", other),
             Some(Num::BigInt(b)) => Ok(self.value.cmp(&b.value)),
             Some(Num::Int(i)) => Ok(StarlarkBigInt::cmp_big_small(self, i)),
-            Some(Num::Float(f)) => Ok(StarlarkFloat::compare_impl(self.to_f64(), f)),
+            Some(Num::Float(f)) => Ok(StarlarkBigInt::cmp_big_float(self, f)),
         }
     }
 
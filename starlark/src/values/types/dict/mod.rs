@@ -21,6 +21,7 @@ mod alloc;
 mod of;
 mod refs;
 pub(crate) mod value;
+pub(crate) mod view;
 
 pub use crate::values::dict::alloc::AllocDict;
 pub use crate::values::dict::of::DictOf;
@@ -24,6 +24,7 @@ use gazebo::cell::ARef;
 use gazebo::coerce::coerce;
 
 use crate::values::dict::value::DictGen;
+use crate::values::dict::value::DictMutationDuringIteration;
 use crate::values::dict::value::FrozenDictData;
 use crate::values::dict::Dict;
 use crate::values::type_repr::StarlarkTypeRepr;
@@ -88,7 +89,7 @@ impl<'v> DictMut<'v> {
             None => Err(error(x)),
             Some(ptr) => match ptr.0.try_borrow_mut() {
                 Ok(x) => Ok(DictMut { aref: x }),
-                Err(_) => Err(ValueError::MutationDuringIteration.into()),
+                Err(_) => Err(DictMutationDuringIteration.into()),
             },
         }
     }
@@ -19,6 +19,7 @@ use std::any::TypeId;
 use std::cell::Ref;
 use std::cell::RefCell;
 use std::cell::RefMut;
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -63,6 +64,11 @@ use crate::values::UnpackValue;
 use crate::values::Value;
 use crate::values::ValueLike;
 
+/// Error when a dict is mutated while it is being iterated over.
+#[derive(Debug, thiserror::Error)]
+#[error("dict changed size during iteration")]
+pub(crate) struct DictMutationDuringIteration;
+
 #[derive(
     Clone,
     Default,
@@ -210,6 +216,11 @@ impl<'v> Dict<'v> {
         self.content.values().copied()
     }
 
+    /// The key/value pair at the given position in iteration order.
+    pub(crate) fn get_index(&self, index: usize) -> Option<(Value<'v>, Value<'v>)> {
+        self.content.get_index(index).map(|(k, v)| (*k, *v))
+    }
+
     /// Get the value associated with a particular key. Will be [`Err`] if the key is not hashable,
     /// and otherwise [`Some`] if the key exists in the dictionary and [`None`] otherwise.
     pub fn get(&self, key: Value<'v>) -> anyhow::Result<Option<Value<'v>>> {
@@ -314,7 +325,7 @@ impl<'v> DictLike<'v> for RefCell<Dict<'v>> {
                 xs.content.insert_hashed(index, alloc_value);
                 Ok(())
             }
-            Err(_) => Err(ValueError::MutationDuringIteration.into()),
+            Err(_) => Err(DictMutationDuringIteration.into()),
         }
     }
 }
@@ -334,6 +345,21 @@ pub(crate) fn dict_methods() -> Option<&'static Methods> {
     RES.methods(crate::stdlib::dict::dict_methods)
 }
 
+/// Keys sorted by Starlark comparison, for canonical iteration.
+fn sorted_keys<'v>(content: &SmallMap<Value<'v>, Value<'v>>) -> anyhow::Result<Vec<Value<'v>>> {
+    let mut keys: Vec<Value<'v>> = content.keys().copied().collect();
+    let mut compare_ok = Ok(());
+    keys.sort_by(|x, y| match x.compare(*y) {
+        Ok(r) => r,
+        Err(e) => {
+            compare_ok = Err(e);
+            Ordering::Equal // does not matter
+        }
+    });
+    compare_ok?;
+    Ok(keys)
+}
+
 impl<'v, T: DictLike<'v> + 'v> StarlarkValue<'v> for DictGen<T>
 where
     Self: ProvidesStaticType,
@@ -395,11 +421,17 @@ where
 
     fn iterate<'a>(
         &'a self,
-        _heap: &'v Heap,
+        heap: &'v Heap,
     ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
     where
         'v: 'a,
     {
+        if heap.canonical_dict_iteration() {
+            // Keep the content borrowed, so the dict cannot be mutated during iteration.
+            let content = self.0.content();
+            let keys = sorted_keys(&content)?;
+            return Ok(Box::new(ARefIterator::new(content, |_| keys.into_iter())));
+        }
         Ok(Box::new(ARefIterator::new(self.0.content(), |x| {
             x.keys().copied()
         })))
@@ -407,9 +439,12 @@ where
 
     fn with_iterator(
         &self,
-        _heap: &'v Heap,
+        heap: &'v Heap,
         f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
     ) -> anyhow::Result<()> {
+        if heap.canonical_dict_iteration() {
+            return f(&mut sorted_keys(&self.0.content())?.into_iter());
+        }
         f(&mut self.0.content().keys().copied())
     }
 
@@ -487,6 +522,49 @@ b1 and b2 and b3
         );
     }
 
+    #[test]
+    fn test_mutate_dict_during_iteration() {
+        for (iter, mutate) in [
+            ("d", "d.pop(k)"),
+            ("d", "d[k + 10] = 1"),
+            ("d", "d.clear()"),
+            ("d.keys()", "d.pop(k)"),
+            ("d.values()", "d.popitem()"),
+            ("d.items()", "d.update({4: 4})"),
+            ("d", "d.setdefault(5, 5)"),
+        ] {
+            assert::fail(
+                &format!(
+                    r#"
+def f():
+    d = {{1: 1, 2: 2, 3: 3}}
+    for k in {}:
+        {}
+f()
+"#,
+                    iter, mutate
+                ),
+                "dict changed size during iteration",
+            );
+        }
+        assert::fail(
+            "d = {1: 1}\n[d.pop(k) for k in d]",
+            "dict changed size during iteration",
+        );
+        // The dict can be mutated once the iteration is over.
+        assert::pass(
+            r#"
+d = {1: 1, 2: 2}
+for k in d:
+    pass
+d.pop(1)
+for k in list(d):
+    d.pop(k)
+assert_eq(d, {})
+"#,
+        );
+    }
+
     #[test]
     fn test_get_str() -> anyhow::Result<()> {
         let heap = Heap::new();
@@ -0,0 +1,375 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Views over the keys, values or items of a dictionary,
+//! as returned by `keys()`, `values()` and `items()`.
+//!
+//! A view reads the dictionary each time it is used, so it reflects
+//! any mutation made after it was created. For compatibility with the
+//! Starlark specification, where these methods return lists, views can
+//! also be indexed, sliced, concatenated with lists and compared to lists.
+//! They are not lists though: list methods such as `append` are not available,
+//! and `type()` returns `"dict_view"`.
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use gazebo::coerce::Coerce;
+use gazebo::display::display_container;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::values::dict::DictRef;
+use crate::values::index::apply_slice;
+use crate::values::index::convert_index;
+use crate::values::iter::ARefIterator;
+use crate::values::list::ListRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Which part of the entries of a dictionary a view produces.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Trace, Allocative)]
+pub(crate) enum DictViewKind {
+    Keys,
+    Values,
+    Items,
+}
+
+#[derive(Debug, Trace, Coerce, Freeze, ProvidesStaticType, Allocative)]
+#[repr(C)]
+pub(crate) struct DictViewGen<V> {
+    dict: V,
+    #[freeze(identity)]
+    kind: DictViewKind,
+}
+
+starlark_complex_value!(pub(crate) DictView);
+
+/// Allocate a view of `dict`, which must be a dictionary.
+pub(crate) fn alloc_view<'v>(dict: Value<'v>, kind: DictViewKind, heap: &'v Heap) -> Value<'v> {
+    heap.alloc(DictView { dict, kind })
+}
+
+impl<'v, V: ValueLike<'v>> DictViewGen<V> {
+    fn dict(&self) -> DictRef<'v> {
+        DictRef::from_value(self.dict.to_value()).expect("view over a dict")
+    }
+
+    /// The elements the view currently produces.
+    fn content(&self, heap: &'v Heap) -> Vec<Value<'v>> {
+        let dict = self.dict();
+        match self.kind {
+            DictViewKind::Keys => dict.keys().collect(),
+            DictViewKind::Values => dict.values().collect(),
+            DictViewKind::Items => dict.iter().map(|(k, v)| heap.alloc((k, v))).collect(),
+        }
+    }
+}
+
+/// The dictionary and kind of a dict view, or `None` if the value is not one.
+fn as_view<'v>(x: Value<'v>) -> Option<(DictRef<'v>, DictViewKind)> {
+    DictView::from_value(x).map(|view| (view.dict(), view.kind))
+}
+
+/// The current elements of a dict view, or `None` if the value is not one.
+pub(crate) fn dict_view_content<'v>(x: Value<'v>, heap: &'v Heap) -> Option<Vec<Value<'v>>> {
+    DictView::from_value(x).map(|view| view.content(heap))
+}
+
+/// The element of a dictionary entry, unless the view produces items
+/// (which would need allocating).
+fn project<'v>(kind: DictViewKind, (k, v): (Value<'v>, Value<'v>)) -> Option<Value<'v>> {
+    match kind {
+        DictViewKind::Keys => Some(k),
+        DictViewKind::Values => Some(v),
+        DictViewKind::Items => None,
+    }
+}
+
+/// Does a dictionary entry, as an item `(k, v)`, equal `y`.
+fn item_equals<'v>((k, v): (Value<'v>, Value<'v>), y: Value<'v>) -> anyhow::Result<bool> {
+    match TupleRef::from_value(y) {
+        Some(t) if t.len() == 2 => Ok(k.equals(t.content()[0])? && v.equals(t.content()[1])?),
+        _ => Ok(false),
+    }
+}
+
+fn entry_equals<'v>(
+    kind: DictViewKind,
+    entry: (Value<'v>, Value<'v>),
+    y: Value<'v>,
+) -> anyhow::Result<bool> {
+    match project(kind, entry) {
+        Some(x) => x.equals(y),
+        None => item_equals(entry, y),
+    }
+}
+
+/// Compare a dict view with the elements of a list,
+/// or `None` if the value is not a dict view.
+pub(crate) fn dict_view_equals_slice<'v>(
+    view: Value<'v>,
+    ys: &[Value<'v>],
+) -> Option<anyhow::Result<bool>> {
+    let (dict, kind) = as_view(view)?;
+    Some(entries_equal_slice(&dict, kind, ys))
+}
+
+fn entries_equal_slice<'v>(
+    dict: &DictRef<'v>,
+    kind: DictViewKind,
+    ys: &[Value<'v>],
+) -> anyhow::Result<bool> {
+    if dict.len() != ys.len() {
+        return Ok(false);
+    }
+    for (entry, y) in dict.iter().zip(ys) {
+        if !entry_equals(kind, entry, *y)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+impl<'v, V: ValueLike<'v>> Serialize for DictViewGen<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let dict = self.dict();
+        match self.kind {
+            DictViewKind::Keys => serializer.collect_seq(dict.keys()),
+            DictViewKind::Values => serializer.collect_seq(dict.values()),
+            DictViewKind::Items => serializer.collect_seq(dict.iter()),
+        }
+    }
+}
+
+impl<'v, V: ValueLike<'v>> Display for DictViewGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dict = self.dict();
+        match self.kind {
+            DictViewKind::Keys => display_container(f, "[", "]", dict.keys()),
+            DictViewKind::Values => display_container(f, "[", "]", dict.values()),
+            DictViewKind::Items => display_container(
+                f,
+                "[",
+                "]",
+                dict.iter().map(|(k, v)| format!("({}, {})", k, v)),
+            ),
+        }
+    }
+}
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for DictViewGen<V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!("dict_view");
+
+    fn to_bool(&self) -> bool {
+        !self.dict().is_empty()
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.dict().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        let dict = self.dict();
+        match self.kind {
+            DictViewKind::Keys => Ok(dict.get_hashed(other.get_hashed()?).is_some()),
+            DictViewKind::Values => {
+                for x in dict.values() {
+                    if x.equals(other)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            DictViewKind::Items => match TupleRef::from_value(other) {
+                Some(t) if t.len() == 2 => match dict.get(t.content()[0])? {
+                    Some(v) => v.equals(t.content()[1]),
+                    None => Ok(false),
+                },
+                _ => Ok(false),
+            },
+        }
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        let aref = self.dict().aref;
+        Ok(match self.kind {
+            DictViewKind::Keys => Box::new(ARefIterator::new(aref, |x| x.keys())),
+            DictViewKind::Values => Box::new(ARefIterator::new(aref, |x| x.values())),
+            DictViewKind::Items => Box::new(ARefIterator::new(aref, move |x| {
+                x.iter().map(move |(k, v)| heap.alloc((k, v)))
+            })),
+        })
+    }
+
+    fn at(&self, index: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let dict = self.dict();
+        let i = convert_index(index, dict.len() as i32)? as usize;
+        let entry = dict.get_index(i).expect("index checked by convert_index");
+        Ok(match project(self.kind, entry) {
+            Some(x) => x,
+            None => heap.alloc(entry),
+        })
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let content = self.content(heap);
+        let res = apply_slice(&content, start, stop, stride)?;
+        Ok(heap.alloc_list(&res))
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        if let Some(ys) = ListRef::from_value(other) {
+            return entries_equal_slice(&self.dict(), self.kind, ys.content());
+        }
+        let (ys, ykind) = match as_view(other) {
+            Some(y) => y,
+            None => return Ok(false),
+        };
+        let xs = self.dict();
+        if xs.len() != ys.len() {
+            return Ok(false);
+        }
+        for (x, y) in xs.iter().zip(ys.iter()) {
+            let eq = match (project(self.kind, x), project(ykind, y)) {
+                (Some(x), Some(y)) => x.equals(y)?,
+                (Some(x), None) => item_equals(y, x)?,
+                (None, Some(y)) => item_equals(x, y)?,
+                (None, None) => x.0.equals(y.0)? && x.1.equals(y.1)?,
+            };
+            if !eq {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn add(&self, other: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        let ys = match ListRef::from_value(other) {
+            Some(ys) => ys.content().to_vec(),
+            None => dict_view_content(other, heap)?,
+        };
+        Some(Ok(heap.alloc_list_concat(&self.content(heap), &ys)))
+    }
+
+    fn radd(&self, lhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        let xs = ListRef::from_value(lhs)?;
+        Some(Ok(heap.alloc_list_concat(xs.content(), &self.content(heap))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_view_reflects_mutation() {
+        assert::is_true(
+            r#"
+d = {"a": 1}
+keys = d.keys()
+values = d.values()
+items = d.items()
+d["b"] = 2
+keys == ["a", "b"] and values == [1, 2] and items == [("a", 1), ("b", 2)] and len(keys) == 2
+"#,
+        );
+        assert::is_true(
+            r#"
+d = {}
+keys = d.keys()
+before = bool(keys)
+d[1] = 2
+not before and bool(keys) and 1 in keys and list(keys) == [1]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_view_in() {
+        assert::all_true(
+            r#"
+"a" in {"a": 1}.keys()
+"b" not in {"a": 1}.keys()
+1 in {"a": 1}.values()
+"a" not in {"a": 1}.values()
+("a", 1) in {"a": 1}.items()
+("a", 2) not in {"a": 1}.items()
+"a" not in {"a": 1}.items()
+"#,
+        );
+    }
+
+    #[test]
+    fn test_view_list_compatibility() {
+        assert::all_true(
+            r#"
+{"a": 1, "b": 2}.keys()[1] == "b"
+{"a": 1, "b": 2}.items()[-1] == ("b", 2)
+{"a": 1, "b": 2}.values()[1:] == [2]
+{"a": 1}.keys() + ["x"] == ["a", "x"]
+["x"] + {"a": 1}.keys() == ["x", "a"]
+{"a": 1}.keys() + {"b": 2}.values() == ["a", 2]
+[("a", 1)] == {"a": 1}.items()
+{"a": 1}.keys() == {"a": 2}.keys()
+{"a": 1}.items() != {"a": 2}.items()
+{"a": 1}.keys() != ("a",)
+sorted({"b": 1, "a": 2}.keys()) == ["a", "b"]
+dict({"a": 1}.items()) == {"a": 1}
+str({"a": 1}.items()) == '[("a", 1)]'
+type({}.keys()) == "dict_view"
+json.encode({"a": 1}.items()) == '[["a",1]]'
+"#,
+        );
+        assert::fail("{'a': 1}.keys().append('b')", "has no attribute `append`");
+        assert::fail("{'a': 1}.keys()[1]", "out of bound");
+    }
+
+    #[test]
+    fn test_frozen_view() {
+        let mut a = assert::Assert::new();
+        a.module("m", "d = {'a': 1}\nk = d.keys()");
+        a.is_true("load('m', 'k')\nk == ['a'] and 'a' in k");
+        a.fail(
+            "d = {'a': 1}\nfor k in d.keys():\n  d.pop(k)",
+            "dict changed size during iteration",
+        );
+    }
+}
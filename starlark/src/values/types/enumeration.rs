@@ -32,6 +32,7 @@
 //! assert_eq(Colors[0], val)
 //! assert_eq(Colors.type, "Colors")
 //! assert_eq([v.value for v in Colors], ["Red", "Green", "Blue"])
+//! assert_true(val in Colors)
 //! # "#);
 //! ```
 use std::cell::RefCell;
@@ -238,6 +239,17 @@ where
         f(&mut self.elements.values().map(|x| x.to_value()))
     }
 
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        // Only members of this enum are in it, not their underlying values.
+        let Some(member) = EnumValue::from_value(other) else {
+            return Ok(false);
+        };
+        match self.elements.get_index(member.index as usize) {
+            Some((_, v)) => v.to_value().equals(other),
+            None => Ok(false),
+        }
+    }
+
     fn get_methods() -> Option<&'static Methods> {
         static RES: MethodsStatic = MethodsStatic::new();
         RES.methods(enum_type_methods)
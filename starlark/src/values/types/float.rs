@@ -33,6 +33,7 @@ use crate::collections::StarlarkHasher;
 use crate::private::Private;
 use crate::values::num::Num;
 use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::FrozenHeap;
@@ -133,16 +134,14 @@ pub(crate) fn write_compact<W: fmt::Write>(
     if !f.is_finite() {
         write_non_finite(output, f)
     } else {
-        let abs = f.abs();
-        let exponent = if f == 0.0 {
-            0
-        } else {
-            abs.log10().floor() as i32
-        };
+        // `{:e}` produces the shortest mantissa which parses back to the same float.
+        let shortest = format!("{:e}", f);
+        let (mantissa, exponent) = shortest.split_once('e').unwrap();
+        let exponent: i32 = exponent.parse().unwrap();
 
         if exponent.abs() >= WRITE_PRECISION as i32 {
-            // use scientific notation if exponent is outside of our precision (but strip 0s)
-            write_scientific(output, f, exponent_char, true)
+            // use scientific notation if exponent is outside of our precision
+            write!(output, "{}{}{:+03}", mantissa, exponent_char, exponent)
         } else if f.fract() == 0.0 {
             // make sure there's a fractional part even if the number doesn't have it
             output.write_fmt(format_args!("{:.1}", f))
@@ -331,10 +330,10 @@ impl<'v> StarlarkValue<'v> for StarlarkFloat {
     }
 
     fn compare(&self, other: Value) -> anyhow::Result<Ordering> {
-        if let Some(other_float) = other.unpack_num().map(|n| n.as_float()) {
-            Ok(StarlarkFloat::compare_impl(self.0, other_float))
-        } else {
-            ValueError::unsupported_with(self, "==", other)
+        match other.unpack_num() {
+            Some(Num::BigInt(b)) => Ok(StarlarkBigInt::cmp_float_big(self.0, b)),
+            Some(other) => Ok(StarlarkFloat::compare_impl(self.0, other.as_float())),
+            None => ValueError::unsupported_with(self, "==", other),
         }
     }
 }
@@ -413,6 +412,11 @@ mod tests {
         assert_eq!(compact(1.23e45), "1.23e+45");
         assert_eq!(compact(-3.14e-145), "-3.14e-145");
         assert_eq!(compact(1e300), "1e+300");
+        assert_eq!(compact(1234567.891), "1.234567891e+06");
+        assert_eq!(compact(-1.0000000000000002e-7), "-1.0000000000000002e-07");
+        assert_eq!(compact(999999.9999999999), "999999.9999999999");
+        assert_eq!(compact(5e-324), "5e-324");
+        assert_eq!(compact(f64::MAX), "1.7976931348623157e+308");
     }
 
     #[test]
@@ -468,4 +472,28 @@ float("+inf") < float("nan")
             "[float('-inf'), -1e+300, -1.0, -1, -1e-300, -1e-300, 0, 0.0, -0.0, 1e-300, 1e-300, 1.0, 1, 1e+300, float('+inf'), float('nan')]",
         );
     }
+
+    #[test]
+    fn test_comparisons_mixed_int_float() {
+        assert::all_true(
+            r#"
+sorted([3, 2.5, 1, -1.5, 0]) == [-1.5, 0, 1, 2.5, 3]
+sorted([3, 1.0, 2, 0.5], reverse = True) == [3, 2, 1.0, 0.5]
+9007199254740993 > 9007199254740992.0
+9007199254740992.0 < 9007199254740993
+9007199254740993 != 9007199254740992.0
+9007199254740992 == 9007199254740992.0
+100000000000000000000000000001 > 1e29
+-100000000000000000000000000001 < -1e29
+100000000000000000000000000000.5 != 100000000000000000000000000000
+1e400 > 100000000000000000000000000001
+float("-inf") < -100000000000000000000000000001
+float("nan") > 100000000000000000000000000001
+"#,
+        );
+        assert::eq(
+            "sorted([9007199254740993, 9007199254740992.0, 9007199254740991, 9007199254740994.0, 9007199254740992])",
+            "[9007199254740991, 9007199254740992.0, 9007199254740992, 9007199254740993, 9007199254740994.0]",
+        );
+    }
 }
@@ -159,6 +159,14 @@ pub struct NativeFunction {
     pub(crate) typ: Option<FrozenValue>,
     /// Safe to evaluate speculatively.
     pub(crate) speculative_exec_safe: bool,
+    /// Safe to evaluate speculatively with the given arguments,
+    /// consulted when `speculative_exec_safe` is false.
+    #[derivative(Debug = "ignore")]
+    #[allocative(skip)]
+    pub(crate) speculative_exec_guard: Option<fn(&Arguments) -> bool>,
+    /// Mutates its arguments, so never evaluated speculatively,
+    /// regardless of `speculative_exec_safe` and `speculative_exec_guard`.
+    pub(crate) mutates_args: bool,
     #[derivative(Debug = "ignore")]
     pub(crate) raw_docs: Option<NativeCallableRawDocs>,
 }
@@ -185,6 +193,8 @@ impl NativeFunction {
             name,
             typ: None,
             speculative_exec_safe: false,
+            speculative_exec_guard: None,
+            mutates_args: false,
             raw_docs: None,
         }
     }
@@ -212,6 +222,22 @@ impl NativeFunction {
     pub fn set_type(&mut self, typ: FrozenValue) {
         self.typ = Some(typ)
     }
+
+    /// Allow the compiler to call this function when all the arguments are constants
+    /// and `guard` returns `true` for them, folding the call into its result.
+    /// Only needed for functions which are pure for some arguments only,
+    /// otherwise use `#[starlark(speculative_exec_safe)]`.
+    pub fn set_speculative_exec_guard(&mut self, guard: fn(&Arguments) -> bool) {
+        self.speculative_exec_guard = Some(guard);
+    }
+
+    /// Declare that this function mutates its arguments (e.g. appends to a list argument).
+    /// Such calls are never folded by the compiler: this takes precedence over
+    /// `#[starlark(speculative_exec_safe)]` and [`set_speculative_exec_guard`](NativeFunction::set_speculative_exec_guard),
+    /// because folding would mutate a constant shared by every evaluation of the call.
+    pub fn set_mutates_args(&mut self) {
+        self.mutates_args = true;
+    }
 }
 
 impl<'v> AllocValue<'v> for NativeFunction {
@@ -230,7 +256,7 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        self.function.invoke(eval, args)
+        eval.with_native_call_timeout(&self.name, |eval| self.function.invoke(eval, args))
     }
 
     fn get_attr(&self, attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
@@ -289,7 +315,7 @@ impl<'v> StarlarkValue<'v> for NativeMethod {
         eval: &mut Evaluator<'v, '_>,
         _: Private,
     ) -> anyhow::Result<Value<'v>> {
-        self.function.invoke(eval, this, args)
+        eval.with_native_call_timeout(&self.name, |eval| self.function.invoke(eval, this, args))
     }
 
     fn documentation(&self) -> Option<DocItem> {
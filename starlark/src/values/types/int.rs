@@ -42,6 +42,8 @@ use crate::any::AnyLifetime;
 use crate::any::ProvidesStaticType;
 use crate::collections::StarlarkHashValue;
 use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
 use crate::private::Private;
 use crate::values::basic::StarlarkValueBasic;
 use crate::values::error::ValueError;
@@ -136,10 +138,19 @@ impl Display for PointerI32 {
     }
 }
 
+pub(crate) fn int_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::int::int_methods)
+}
+
 /// Define the int type
 impl<'v> StarlarkValue<'v> for PointerI32 {
     starlark_type!(INT_TYPE);
 
+    fn get_methods() -> Option<&'static Methods> {
+        int_methods()
+    }
+
     fn get_type_starlark_repr() -> String {
         "int.type".to_owned()
     }
@@ -55,7 +55,7 @@ impl KnownMethod {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        self.imp.invoke(eval, this, args)
+        eval.with_native_call_timeout(&self.method.name, |eval| self.imp.invoke(eval, this, args))
     }
 }
 
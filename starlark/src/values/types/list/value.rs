@@ -23,6 +23,8 @@ use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::mem;
+use std::ptr;
 use std::slice;
 
 use allocative::Allocative;
@@ -41,9 +43,11 @@ use crate::private::Private;
 use crate::values::array::Array;
 use crate::values::comparison::compare_slice;
 use crate::values::comparison::equals_slice;
+use crate::values::dict::view::dict_view_content;
+use crate::values::dict::view::dict_view_equals_slice;
 use crate::values::error::ValueError;
 use crate::values::index::apply_slice;
-use crate::values::index::convert_index;
+use crate::values::index::resolve_index;
 use crate::values::list::ListRef;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::AllocFrozenValue;
@@ -148,16 +152,19 @@ impl<'v> ListData<'v> {
         Ok(())
     }
 
-    #[cold]
-    #[inline(never)]
-    fn reserve_additional_slow(&self, additional: usize, heap: &'v Heap) {
+    fn new_capacity(&self, additional: usize) -> usize {
         let new_cap = cmp::max(self.len() + additional, self.len() * 2);
         // Size of `Array` is 2 words and size of `List` is one word,
         // so allocating at least 4 words would not be too large waste.
         // Note `Vec` allocates 4 by default.
         // Also note `Array` removes extra capacity on GC.
-        let new_cap = cmp::max(new_cap, 4);
+        cmp::max(new_cap, 4)
+    }
 
+    #[cold]
+    #[inline(never)]
+    fn reserve_additional_slow(&self, additional: usize, heap: &'v Heap) {
+        let new_cap = self.new_capacity(additional);
         let new_array = heap.alloc_array(new_cap);
         new_array.extend_from_slice(self.content());
         self.content.set(new_array);
@@ -203,6 +210,38 @@ impl<'v> ListData<'v> {
         }
     }
 
+    /// Like [`reserve_additional`](ListData::reserve_additional), but fail instead
+    /// of growing beyond the heap size limit.
+    fn try_reserve_additional(&self, additional: usize, heap: &'v Heap) -> anyhow::Result<()> {
+        if self.content.get().as_ref().remaining_capacity() >= additional {
+            return Ok(());
+        }
+        let new_cap = self.new_capacity(additional);
+        heap.check_alloc_bytes(new_cap.saturating_mul(mem::size_of::<Value>()))?;
+        self.reserve_additional_slow(additional, heap);
+        Ok(())
+    }
+
+    /// Like [`extend`](ListData::extend), but fail instead of growing
+    /// beyond the heap size limit, before the memory is requested.
+    pub(crate) fn try_extend<I: IntoIterator<Item = Value<'v>>>(
+        &self,
+        iter: I,
+        heap: &'v Heap,
+    ) -> anyhow::Result<()> {
+        if heap.max_bytes().is_none() {
+            self.extend(iter, heap);
+            return Ok(());
+        }
+        let iter = iter.into_iter();
+        self.try_reserve_additional(iter.size_hint().0, heap)?;
+        for item in iter {
+            self.try_reserve_additional(1, heap)?;
+            self.content.get().push(item);
+        }
+        Ok(())
+    }
+
     pub(crate) fn push(&self, value: Value<'v>, heap: &'v Heap) {
         self.reserve_additional(1, heap);
         self.content.get().push(value);
@@ -440,8 +479,19 @@ where
 
     fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
         match ListRef::from_value(other) {
-            None => Ok(false),
-            Some(other) => equals_slice(self.0.content(), &other.content, |x, y| x.equals(*y)),
+            None => dict_view_equals_slice(other, self.0.content()).unwrap_or(Ok(false)),
+            Some(other) => {
+                let xs = self.0.content();
+                let ys = other.content();
+                if xs.len() != ys.len() {
+                    return Ok(false);
+                }
+                // Lists sharing the same backing storage are trivially equal.
+                if ptr::eq(xs.as_ptr(), ys.as_ptr()) {
+                    return Ok(true);
+                }
+                equals_slice(xs, ys, |x, y| x.equals(*y))
+            }
         }
     }
 
@@ -453,7 +503,7 @@ where
     }
 
     fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        let i = convert_index(index, self.0.content().len() as i32)? as usize;
+        let i = resolve_index(index, self.0.content().len() as i32)?;
         Ok(self.0.content()[i])
     }
 
@@ -501,13 +551,22 @@ where
     }
 
     fn add(&self, other: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
-        ListRef::from_value(other)
-            .map(|other| Ok(heap.alloc_list_concat(self.0.content(), other.content())))
+        match ListRef::from_value(other) {
+            Some(other) => Some(Ok(heap.alloc_list_concat(self.0.content(), other.content()))),
+            None => dict_view_content(other, heap)
+                .map(|other| Ok(heap.alloc_list_concat(self.0.content(), &other))),
+        }
     }
 
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = Vec::with_capacity(self.0.content().len() * cmp::max(0, l) as usize);
+        let len = self
+            .0
+            .content()
+            .len()
+            .saturating_mul(cmp::max(0, l) as usize);
+        heap.check_alloc_bytes(len.saturating_mul(mem::size_of::<Value>()))?;
+        let mut result = Vec::with_capacity(len);
         for _ in 0..l {
             result.extend(self.0.content().iter());
         }
@@ -515,7 +574,7 @@ where
     }
 
     fn set_at(&self, index: Value<'v>, alloc_value: Value<'v>) -> anyhow::Result<()> {
-        let i = convert_index(index, self.0.content().len() as i32)? as usize;
+        let i = resolve_index(index, self.0.content().len() as i32)?;
         self.0.set_at(i, alloc_value)
     }
 }
@@ -573,6 +632,30 @@ v == [1, 1, [2, 3]]
         );
     }
 
+    #[test]
+    fn test_add_list_and_tuple() {
+        assert::all_true(
+            r#"
+[1] + [2, 3] == [1, 2, 3]
+(1,) + (2, 3) == (1, 2, 3)
+[] + [] == []
+() + () == ()
+"#,
+        );
+        assert::fail(
+            "[1] + (2,)",
+            "Operation `+` not supported for types `list` and `tuple`",
+        );
+        assert::fail(
+            "(1,) + [2]",
+            "Operation `+` not supported for types `tuple` and `list`",
+        );
+        assert::fail(
+            "def f(x, y): return x + y\nf([], ())",
+            "Operation `+` not supported for types `list` and `tuple`",
+        );
+    }
+
     #[test]
     fn test_value_alias() {
         assert::is_true(
@@ -585,6 +668,23 @@ v1 == [1, 2, 4] and v2 == [1, 2, 4]
         );
     }
 
+    #[test]
+    fn test_equals() {
+        assert::is_true(
+            r#"
+x = [1, 2, 3]
+x == x and x == list(x) and x != x + [4] and [x] == [x]
+"#,
+        );
+        assert::all_true(
+            r#"
+[] == []
+[1, 2] != [1, 3]
+[1, 2] != [1, 2, 3]
+"#,
+        );
+    }
+
     #[test]
     fn test_mutating_imports() {
         let mut a = Assert::new();
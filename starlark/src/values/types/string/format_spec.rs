@@ -0,0 +1,726 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Format specifier (the part after `:` in `"{:>10.3f}"`) used by `str.format`.
+//! Based on <https://docs.python.org/3/library/string.html#format-specification-mini-language>
+
+use std::fmt::Binary;
+use std::fmt::Display;
+use std::fmt::LowerHex;
+use std::fmt::Octal;
+use std::fmt::UpperHex;
+use std::fmt::Write;
+use std::iter;
+
+use dupe::Dupe;
+use num_bigint::Sign;
+use thiserror::Error;
+
+use crate::values::num::Num;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(Debug, Error)]
+enum FormatSpecError {
+    #[error("Invalid format specifier `{0}`")]
+    Invalid(String),
+    #[error("Unknown format code '{0}' for value of type `{1}`")]
+    UnknownFormatCode(char, &'static str),
+    #[error("Precision not allowed in integer format specifier")]
+    PrecisionNotAllowedForInt,
+    #[error("'=' alignment not allowed in string format specifier")]
+    SignAwareAlignmentNotAllowedForStr,
+    #[error("Cannot specify '{0}' with '{1}'")]
+    GroupingNotAllowed(char, char),
+    #[error("Sign not allowed in string format specifier")]
+    SignNotAllowedForStr,
+    #[error("Sign not allowed with integer format specifier 'c'")]
+    SignNotAllowedForChar,
+    #[error("Format code 'c' argument `{0}` is not a valid Unicode codepoint")]
+    InvalidCodepoint(String),
+}
+
+/// Alignment of the formatted value within the field width.
+#[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq)]
+enum Align {
+    /// `<`.
+    Left,
+    /// `>`.
+    Right,
+    /// `^`.
+    Center,
+    /// `=`: padding is placed after the sign, but before the digits.
+    AfterSign,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Align> {
+        match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            '=' => Some(Align::AfterSign),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed format specifier: `[[fill]align][sign][0][width][grouping][.precision][type]`.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct FormatSpec {
+    fill: Option<char>,
+    align: Option<Align>,
+    /// `+`, `-` or ` `: how to show the sign of non-negative numbers.
+    sign: Option<char>,
+    /// `0` flag: pad numbers with zeros after the sign.
+    zero: bool,
+    width: usize,
+    /// `,` or `_`: separator inserted between groups of digits.
+    grouping: Option<char>,
+    precision: Option<usize>,
+    ty: Option<char>,
+}
+
+/// Split leading ASCII digits.
+fn split_digits(s: &str) -> (&str, &str) {
+    let n = s.bytes().take_while(|b| b.is_ascii_digit()).count();
+    s.split_at(n)
+}
+
+impl FormatSpec {
+    pub(crate) fn parse(spec: &str) -> anyhow::Result<FormatSpec> {
+        let invalid = || FormatSpecError::Invalid(spec.to_owned());
+
+        let mut res = FormatSpec::default();
+        let mut rem = spec;
+
+        let mut chars = rem.chars();
+        match (chars.next(), chars.next().and_then(Align::from_char)) {
+            (Some(fill), Some(align)) => {
+                res.fill = Some(fill);
+                res.align = Some(align);
+                rem = chars.as_str();
+            }
+            (Some(c), None) => {
+                if let Some(align) = Align::from_char(c) {
+                    res.align = Some(align);
+                    rem = &rem[1..];
+                }
+            }
+            (None, _) => {}
+        }
+
+        if let Some(c @ ('+' | '-' | ' ')) = rem.chars().next() {
+            res.sign = Some(c);
+            rem = &rem[1..];
+        }
+
+        if let Some(r) = rem.strip_prefix('0') {
+            res.zero = true;
+            rem = r;
+        }
+
+        let (width, r) = split_digits(rem);
+        if !width.is_empty() {
+            res.width = width.parse().map_err(|_| invalid())?;
+        }
+        rem = r;
+
+        if let Some(r) = rem.strip_prefix(',').or_else(|| rem.strip_prefix('_')) {
+            res.grouping = Some(rem.as_bytes()[0] as char);
+            rem = r;
+        }
+
+        if let Some(r) = rem.strip_prefix('.') {
+            let (precision, r) = split_digits(r);
+            if precision.is_empty() {
+                return Err(invalid().into());
+            }
+            res.precision = Some(precision.parse().map_err(|_| invalid())?);
+            rem = r;
+        }
+
+        let mut chars = rem.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => {}
+            (Some(c), None) if c.is_ascii_alphabetic() || c == '%' => res.ty = Some(c),
+            _ => return Err(invalid().into()),
+        }
+
+        Ok(res)
+    }
+
+    /// Length a value may be formatted to before the result can be checked:
+    /// the padded width, or for numbers the precision if larger.
+    pub(crate) fn reserved_len(&self, is_num: bool) -> usize {
+        match self.precision {
+            Some(precision) if is_num => self.width.max(precision),
+            _ => self.width,
+        }
+    }
+
+    /// Format a value according to this specifier.
+    pub(crate) fn format_value(&self, value: Value, out: &mut String) -> anyhow::Result<()> {
+        if let Some(s) = value.unpack_str() {
+            return self.format_str(s, out);
+        }
+        match value.unpack_num() {
+            Some(Num::Int(i)) => self.format_int(i < 0, i.unsigned_abs(), value, out),
+            Some(Num::BigInt(b)) => self.format_int(
+                b.get().sign() == Sign::Minus,
+                b.get().magnitude(),
+                value,
+                out,
+            ),
+            Some(Num::Float(f)) => self.format_float(f, value, out),
+            None => {
+                // Other values are formatted as their `str`.
+                let mut s = String::new();
+                value.collect_str(&mut s);
+                self.format_str(&s, out)
+            }
+        }
+    }
+
+    /// Format a string according to this specifier.
+    pub(crate) fn format_str(&self, s: &str, out: &mut String) -> anyhow::Result<()> {
+        match self.ty {
+            None | Some('s') => {}
+            Some(c) => return Err(FormatSpecError::UnknownFormatCode(c, "string").into()),
+        }
+        if self.align == Some(Align::AfterSign) {
+            return Err(FormatSpecError::SignAwareAlignmentNotAllowedForStr.into());
+        }
+        if self.sign.is_some() {
+            return Err(FormatSpecError::SignNotAllowedForStr.into());
+        }
+        if let Some(grouping) = self.grouping {
+            return Err(FormatSpecError::GroupingNotAllowed(grouping, 's').into());
+        }
+        let s = match self.precision {
+            Some(precision) => match s.char_indices().nth(precision) {
+                Some((i, _)) => &s[..i],
+                None => s,
+            },
+            None => s,
+        };
+        self.pad("", s, Align::Left, out);
+        Ok(())
+    }
+
+    fn format_int<T>(
+        &self,
+        negative: bool,
+        abs: T,
+        value: Value,
+        out: &mut String,
+    ) -> anyhow::Result<()>
+    where
+        T: Display + Binary + Octal + LowerHex + UpperHex,
+    {
+        let (body, group) = match self.ty {
+            Some('e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%') => {
+                let f = value.unpack_num().unwrap().as_float();
+                return self.format_float(f, value, out);
+            }
+            _ if self.precision.is_some() => {
+                return Err(FormatSpecError::PrecisionNotAllowedForInt.into());
+            }
+            Some('c') => return self.format_char(value, out),
+            None | Some('d') => (abs.to_string(), 3),
+            Some('b') => (format!("{:b}", abs), 4),
+            Some('o') => (format!("{:o}", abs), 4),
+            Some('x') => (format!("{:x}", abs), 4),
+            Some('X') => (format!("{:X}", abs), 4),
+            Some(c) => {
+                return Err(FormatSpecError::UnknownFormatCode(c, value.get_type()).into());
+            }
+        };
+        if let (Some(','), 4, Some(ty)) = (self.grouping, group, self.ty) {
+            // Like Python, only `_` groups digits of binary, octal and hex numbers.
+            return Err(FormatSpecError::GroupingNotAllowed(',', ty).into());
+        }
+        self.pad_grouped_number(negative, &body, "", group, out);
+        Ok(())
+    }
+
+    /// Format an integer as the character with that codepoint, like Python `{:c}`.
+    fn format_char(&self, value: Value, out: &mut String) -> anyhow::Result<()> {
+        if self.sign.is_some() {
+            return Err(FormatSpecError::SignNotAllowedForChar.into());
+        }
+        if let Some(grouping) = self.grouping {
+            return Err(FormatSpecError::GroupingNotAllowed(grouping, 'c').into());
+        }
+        let c = value
+            .unpack_num()
+            .and_then(|n| n.as_int())
+            .and_then(|i| u32::try_from(i).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| FormatSpecError::InvalidCodepoint(value.to_repr()))?;
+        self.pad("", c.encode_utf8(&mut [0; 4]), Align::Right, out);
+        Ok(())
+    }
+
+    fn format_float(&self, f: f64, value: Value, out: &mut String) -> anyhow::Result<()> {
+        let negative = f.is_sign_negative() && !f.is_nan();
+        let abs = f.abs();
+        let body = if !abs.is_finite() {
+            let body = if abs.is_nan() { "nan" } else { "inf" };
+            match self.ty {
+                Some('E' | 'F' | 'G') => body.to_uppercase(),
+                Some('%') => format!("{}%", body),
+                _ => body.to_owned(),
+            }
+        } else {
+            match self.ty {
+                Some('f' | 'F') => format!("{:.*}", self.precision.unwrap_or(6), abs),
+                Some(c @ ('e' | 'E')) => format_exponent(abs, self.precision.unwrap_or(6), c),
+                Some(c @ ('g' | 'G')) => format_general(
+                    abs,
+                    self.precision.unwrap_or(6),
+                    c.to_ascii_lowercase() != c,
+                    false,
+                ),
+                Some('%') => format!("{:.*}%", self.precision.unwrap_or(6), abs * 100.0),
+                None => match self.precision {
+                    Some(precision) => format_general(abs, precision, false, true),
+                    None => {
+                        let mut s = String::new();
+                        value.collect_str(&mut s);
+                        s.trim_start_matches('-').to_owned()
+                    }
+                },
+                Some(c) => {
+                    return Err(FormatSpecError::UnknownFormatCode(c, value.get_type()).into());
+                }
+            }
+        };
+        if abs.is_finite() {
+            let digits = body.bytes().take_while(|b| b.is_ascii_digit()).count();
+            let (digits, rest) = body.split_at(digits);
+            self.pad_grouped_number(negative, digits, rest, 3, out);
+        } else {
+            self.pad_number(negative, &body, out);
+        }
+        Ok(())
+    }
+
+    /// Sign to write before a number.
+    fn sign(&self, negative: bool) -> &'static str {
+        match (negative, self.sign) {
+            (true, _) => "-",
+            (false, Some('+')) => "+",
+            (false, Some(' ')) => " ",
+            (false, _) => "",
+        }
+    }
+
+    fn pad_number(&self, negative: bool, body: &str, out: &mut String) {
+        self.pad(self.sign(negative), body, Align::Right, out);
+    }
+
+    /// Write a number made of integer part `digits` followed by `rest`,
+    /// separating groups of `group` digits if grouping is requested.
+    ///
+    /// As in Python, padding zeros from the `0` flag are grouped too, e.g. `0,001,234`.
+    fn pad_grouped_number(
+        &self,
+        negative: bool,
+        digits: &str,
+        rest: &str,
+        group: usize,
+        out: &mut String,
+    ) {
+        let sep = match self.grouping {
+            Some(sep) => sep,
+            None => return self.pad_number(negative, &format!("{}{}", digits, rest), out),
+        };
+        let min_len = if self.zero && self.fill.is_none() && self.align.is_none() {
+            self.width
+                .saturating_sub(self.sign(negative).len() + rest.chars().count())
+        } else {
+            0
+        };
+        let mut len = digits.len();
+        while len + (len.max(1) - 1) / group < min_len {
+            len += 1;
+        }
+        let mut body = String::new();
+        for (i, c) in iter::repeat('0')
+            .take(len - digits.len())
+            .chain(digits.chars())
+            .enumerate()
+        {
+            if i != 0 && (len - i) % group == 0 {
+                body.push(sep);
+            }
+            body.push(c);
+        }
+        body.push_str(rest);
+        self.pad_number(negative, &body, out);
+    }
+
+    /// Write `sign` and `body` padded to the width.
+    fn pad(&self, sign: &str, body: &str, default_align: Align, out: &mut String) {
+        let fill = self.fill.unwrap_or(if self.zero { '0' } else { ' ' });
+        let align = match self.align {
+            Some(align) => align,
+            None if self.zero && default_align != Align::Left => Align::AfterSign,
+            None => default_align,
+        };
+        let len = sign.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+        let (before, after) = match align {
+            Align::Left => (0, padding),
+            Align::Right | Align::AfterSign => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        if align == Align::AfterSign {
+            out.push_str(sign);
+            out.extend((0..before).map(|_| fill));
+        } else {
+            out.extend((0..before).map(|_| fill));
+            out.push_str(sign);
+        }
+        out.push_str(body);
+        out.extend((0..after).map(|_| fill));
+    }
+}
+
+/// Split exponent from Rust `{:e}` output.
+fn split_exponent(s: &str) -> (&str, i32) {
+    let (mantissa, exponent) = s.split_once('e').unwrap();
+    (mantissa, exponent.parse().unwrap())
+}
+
+/// Write exponent like Python does, e.g. `e+05`.
+fn write_exponent(out: &mut String, exponent_char: char, exponent: i32) {
+    write!(out, "{}{:+03}", exponent_char, exponent).unwrap();
+}
+
+/// Format non-negative finite float in `e` format.
+fn format_exponent(abs: f64, precision: usize, exponent_char: char) -> String {
+    let s = format!("{:.*e}", precision, abs);
+    let (mantissa, exponent) = split_exponent(&s);
+    let mut res = mantissa.to_owned();
+    write_exponent(&mut res, exponent_char, exponent);
+    res
+}
+
+/// Format non-negative finite float in `g` format.
+///
+/// With `add_dot_0`, used when no type code is given, fixed-point notation
+/// keeps at least one digit after the decimal point, e.g. `2.0` rather than `2`,
+/// and like in Python, exponent notation is used from one digit earlier.
+fn format_general(abs: f64, precision: usize, upper: bool, add_dot_0: bool) -> String {
+    fn strip_zeros(s: &str) -> &str {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            s
+        }
+    }
+
+    let precision = precision.max(1);
+    let s = format!("{:.*e}", precision - 1, abs);
+    let (mantissa, exponent) = split_exponent(&s);
+    let fixed_limit = if add_dot_0 { precision - 1 } else { precision };
+    if -4 <= exponent && exponent < fixed_limit as i32 {
+        let s = format!("{:.*}", (precision as i32 - 1 - exponent) as usize, abs);
+        let mut res = strip_zeros(&s).to_owned();
+        if add_dot_0 && !res.contains('.') {
+            res.push_str(".0");
+        }
+        res
+    } else {
+        let mut res = strip_zeros(mantissa).to_owned();
+        write_exponent(&mut res, if upper { 'E' } else { 'e' }, exponent);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::values::types::string::format_spec::Align;
+    use crate::values::types::string::format_spec::FormatSpec;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(FormatSpec::default(), FormatSpec::parse("").unwrap());
+        assert_eq!(
+            FormatSpec {
+                fill: Some('*'),
+                align: Some(Align::Center),
+                sign: None,
+                zero: false,
+                width: 10,
+                grouping: None,
+                precision: Some(3),
+                ty: Some('f'),
+            },
+            FormatSpec::parse("*^10.3f").unwrap()
+        );
+        assert_eq!(
+            FormatSpec {
+                zero: true,
+                width: 5,
+                ..FormatSpec::default()
+            },
+            FormatSpec::parse("05").unwrap()
+        );
+        assert_eq!(
+            FormatSpec {
+                fill: Some('<'),
+                align: Some(Align::Left),
+                ..FormatSpec::default()
+            },
+            FormatSpec::parse("<<").unwrap()
+        );
+        assert_eq!(
+            FormatSpec {
+                zero: true,
+                width: 8,
+                grouping: Some(','),
+                precision: Some(2),
+                ty: Some('f'),
+                ..FormatSpec::default()
+            },
+            FormatSpec::parse("08,.2f").unwrap()
+        );
+        assert_eq!(
+            FormatSpec {
+                sign: Some(' '),
+                zero: true,
+                width: 6,
+                ..FormatSpec::default()
+            },
+            FormatSpec::parse(" 06").unwrap()
+        );
+        assert_eq!(
+            FormatSpec {
+                fill: Some('+'),
+                align: Some(Align::Right),
+                sign: Some('+'),
+                ..FormatSpec::default()
+            },
+            FormatSpec::parse("+>+").unwrap()
+        );
+        assert!(FormatSpec::parse("10.").is_err());
+        assert!(FormatSpec::parse("+-").is_err());
+        assert!(FormatSpec::parse(",_").is_err());
+        assert!(FormatSpec::parse("ff").is_err());
+        assert!(FormatSpec::parse("10:").is_err());
+    }
+
+    #[test]
+    fn test_format_str() {
+        assert::all_true(
+            r#"
+"{:5}".format("ab") == "ab   "
+"{:>5}".format("ab") == "   ab"
+"{:^6}".format("ab") == "  ab  "
+"{:*^5}".format("ab") == "*ab**"
+"{:.2}".format("abc") == "ab"
+"{:>5.1s}".format("abc") == "    a"
+"{:3}".format("abcde") == "abcde"
+"{!r:>6}".format("ab") == "  \"ab\""
+"{:>5}".format([1]) == "  [1]"
+"{:>6}".format(None) == "  None"
+"#,
+        );
+        assert::fail(r#""{:d}".format("a")"#, "Unknown format code 'd'");
+        assert::fail(r#""{:=5}".format("a")"#, "'=' alignment not allowed");
+    }
+
+    #[test]
+    fn test_format_int() {
+        assert::all_true(
+            r#"
+"{:5}".format(42) == "   42"
+"{:<5}".format(42) == "42   "
+"{:05}".format(-42) == "-0042"
+"{:=5}".format(-42) == "-  42"
+"{:d}".format(-7) == "-7"
+"{:b}".format(5) == "101"
+"{:o}".format(8) == "10"
+"{:x}".format(255) == "ff"
+"{:X}".format(-255) == "-FF"
+"{:x}".format(1 << 100) == "10000000000000000000000000"
+"{:f}".format(3) == "3.000000"
+"#,
+        );
+        assert::fail(r#""{:s}".format(1)"#, "Unknown format code 's'");
+        assert::fail(r#""{:.2d}".format(1)"#, "Precision not allowed");
+    }
+
+    #[test]
+    fn test_format_float() {
+        assert::all_true(
+            r#"
+"{:f}".format(1.5) == "1.500000"
+"{:.2f}".format(3.14159) == "3.14"
+"{:8.3f}".format(-3.14159) == "  -3.142"
+"{:08.3f}".format(-3.14159) == "-003.142"
+"{:.0f}".format(2.5) == "2"
+"{:e}".format(12345.678) == "1.234568e+04"
+"{:.2E}".format(0.000123) == "1.23E-04"
+"{:g}".format(0.0001) == "0.0001"
+"{:g}".format(0.00001) == "1e-05"
+"{:g}".format(1234567.0) == "1.23457e+06"
+"{:.3g}".format(3.14159) == "3.14"
+"{:.1%}".format(0.256) == "25.6%"
+"{:6}".format(1.5) == "   1.5"
+"{:.3}".format(2.0) == "2.0"
+"{:.3}".format(2.5) == "2.5"
+"{:.4}".format(123.0) == "123.0"
+"{:.3}".format(123.0) == "1.23e+02"
+"{:.1}".format(2.5) == "2e+00"
+"{:.3}".format(0.0001) == "0.0001"
+"{:f}".format(float("-inf")) == "-inf"
+"{:F}".format(float("nan")) == "NAN"
+"#,
+        );
+        assert::fail(r#""{:d}".format(1.5)"#, "Unknown format code 'd'");
+    }
+
+    #[test]
+    fn test_format_grouping() {
+        assert::all_true(
+            r#"
+"{:,}".format(1234567) == "1,234,567"
+"{:_}".format(1234567) == "1_234_567"
+"{:,}".format(123) == "123"
+"{:,}".format(0) == "0"
+"{:,}".format(-1234567) == "-1,234,567"
+"{:,d}".format(1000) == "1,000"
+"{:,}".format(1 << 70) == "1,180,591,620,717,411,303,424"
+"{:_x}".format(0xdeadbeef) == "dead_beef"
+"{:_b}".format(10) == "1010"
+"{:_b}".format(31) == "1_1111"
+"{:,.2f}".format(1234567.891) == "1,234,567.89"
+"{:_.1f}".format(-1234.56) == "-1_234.6"
+"{:,}".format(1234.5) == "1,234.5"
+"{:,.0f}".format(999999.9) == "1,000,000"
+"{:,e}".format(1234.5) == "1.234500e+03"
+"{:,f}".format(float("inf")) == "inf"
+"{:>10,}".format(12345) == "    12,345"
+"{:*<10,}".format(-12345) == "-12,345***"
+"{:08,}".format(1234) == "0,001,234"
+"{:09,}".format(-1234) == "-0,001,234"
+"{:010,.2f}".format(1234.5) == "001,234.50"
+"{:06_x}".format(255) == "0_00ff"
+"#,
+        );
+        assert::fail(r#""{:,x}".format(255)"#, "Cannot specify ',' with 'x'");
+        assert::fail(r#""{:,}".format("a")"#, "Cannot specify ',' with 's'");
+    }
+
+    #[test]
+    fn test_format_grouping_precision() {
+        // Only the integer part is grouped, after rounding to the precision.
+        assert::all_true(
+            r#"
+"{:,.2f}".format(1234567.891) == "1,234,567.89"
+"{:,.2f}".format(-1234567.891) == "-1,234,567.89"
+"{:,.2f}".format(999.999) == "1,000.00"
+"{:,.2f}".format(0.5) == "0.50"
+"{:,.2f}".format(-0.001) == "-0.00"
+"{:,.4f}".format(1234.56789) == "1,234.5679"
+"{:,.0f}".format(1234567.5) == "1,234,568"
+"{:,.0f}".format(-1234567.4) == "-1,234,567"
+"{:,.0f}".format(123.0) == "123"
+"{:+,.2f}".format(1234.5) == "+1,234.50"
+"{:>14,.2f}".format(-1234.5) == "     -1,234.50"
+"{:,.1%}".format(12345.0) == "1,234,500.0%"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_format_char() {
+        assert::all_true(
+            r#"
+"{:c}".format(65) == "A"
+"{:c}".format(0x1F63F) == "😿"
+"{:3c}".format(97) == "  a"
+"{:<3c}|".format(97) == "a  |"
+"{0:c}{0:d}".format(66) == "B66"
+"#,
+        );
+        assert::fail(
+            r#""{:c}".format(-1)"#,
+            "argument `-1` is not a valid Unicode codepoint",
+        );
+        assert::fail(
+            r#""{:c}".format(0x110000)"#,
+            "argument `1114112` is not a valid Unicode codepoint",
+        );
+        assert::fail(
+            r#""{:c}".format(0xD800)"#,
+            "argument `55296` is not a valid Unicode codepoint",
+        );
+        assert::fail(
+            r#""{:c}".format(1 << 70)"#,
+            "is not a valid Unicode codepoint",
+        );
+        assert::fail(r#""{:c}".format("a")"#, "Unknown format code 'c'");
+        assert::fail(r#""{:,c}".format(65)"#, "Cannot specify ',' with 'c'");
+    }
+
+    #[test]
+    fn test_format_sign() {
+        assert::all_true(
+            r#"
+"{:+}".format(5) == "+5"
+"{:+}".format(-5) == "-5"
+"{:-}".format(5) == "5"
+"{:-}".format(-5) == "-5"
+"{: }".format(5) == " 5"
+"{: }".format(-5) == "-5"
+"{:+}".format(0) == "+0"
+"{:+}".format(1 << 70) == "+1180591620717411303424"
+"{:+x}".format(255) == "+ff"
+"{:+.1f}".format(2.5) == "+2.5"
+"{:+.1f}".format(-2.5) == "-2.5"
+"{:-.1f}".format(2.5) == "2.5"
+"{:-.1f}".format(-2.5) == "-2.5"
+"{: .1f}".format(2.5) == " 2.5"
+"{: .1f}".format(-2.5) == "-2.5"
+"{:+}".format(0.0) == "+0.0"
+"{:+}".format(-0.0) == "-0.0"
+"{:+}".format(float("inf")) == "+inf"
+"{: }".format(float("nan")) == " nan"
+"{:+05}".format(42) == "+0042"
+"{: 05}".format(42) == " 0042"
+"{:>+6}".format(5) == "    +5"
+"{:=+6}".format(5) == "+    5"
+"{:+,}".format(1234) == "+1,234"
+"{:+08,}".format(1234) == "+001,234"
+"#,
+        );
+        assert::fail(
+            r#""{:+}".format("a")"#,
+            "Sign not allowed in string format specifier",
+        );
+        assert::fail(
+            r#""{:+c}".format(65)"#,
+            "Sign not allowed with integer format specifier 'c'",
+        );
+    }
+}
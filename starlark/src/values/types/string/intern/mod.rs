@@ -16,3 +16,4 @@
  */
 
 pub(crate) mod interner;
+pub(crate) mod shared;
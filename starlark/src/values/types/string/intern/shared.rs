@@ -0,0 +1,110 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! String interner shared between several freezes.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+use hashbrown::raw::RawTable;
+
+use crate::collections::Hashed;
+use crate::values::FrozenHeap;
+use crate::values::FrozenHeapRef;
+use crate::values::FrozenStringValue;
+
+/// Interner for frozen strings shared by several [`Freezer`](crate::values::Freezer) runs.
+///
+/// When modules are frozen with the same interner, a string which was already
+/// interned by an earlier module is reused instead of being copied again, and the
+/// new module keeps the interned string alive.
+/// The interner stores its own copy of each string, in a heap shared by the strings
+/// added by one freeze, so it does not keep alive the heaps of the modules.
+///
+/// Cloning the interner is cheap, clones share the same storage.
+#[derive(Default, Clone, Dupe)]
+pub struct SharedStringInterner {
+    strings: Arc<Mutex<Arc<InternedStrings>>>,
+}
+
+/// Snapshot of the strings in a [`SharedStringInterner`],
+/// read by a freezer without locking.
+#[derive(Default, Clone)]
+pub(crate) struct InternedStrings {
+    map: RawTable<(FrozenStringValue, FrozenHeapRef)>,
+}
+
+impl InternedStrings {
+    /// Interned string equal to `s`, and the heap holding it.
+    pub(crate) fn get(&self, s: Hashed<&str>) -> Option<(FrozenStringValue, &FrozenHeapRef)> {
+        self.map
+            .get(s.hash().promote(), |(x, _)| s == x.get_hashed_str())
+            .map(|(x, heap)| (*x, heap))
+    }
+}
+
+impl SharedStringInterner {
+    /// Create an empty interner.
+    pub fn new() -> SharedStringInterner {
+        SharedStringInterner::default()
+    }
+
+    /// Number of strings in the interner.
+    pub fn len(&self) -> usize {
+        self.strings.lock().unwrap().map.len()
+    }
+
+    /// Interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Strings interned so far, for the lookups of one freeze.
+    pub(crate) fn snapshot(&self) -> Arc<InternedStrings> {
+        self.strings.lock().unwrap().dupe()
+    }
+
+    /// Copy the strings frozen by one freeze into the interner.
+    /// Strings already present are kept.
+    pub(crate) fn insert_all(&self, strings: Vec<FrozenStringValue>) {
+        if strings.is_empty() {
+            return;
+        }
+        let mut guard = self.strings.lock().unwrap();
+        // Copies the table only if a freeze is still reading a snapshot.
+        let interned = Arc::make_mut(&mut guard);
+        let heap = FrozenHeap::new();
+        let added: Vec<FrozenStringValue> = strings
+            .into_iter()
+            .filter(|s| interned.get(s.get_hashed_str()).is_none())
+            .map(|s| heap.alloc_str_intern(s.as_str()))
+            .collect();
+        let heap = heap.into_ref();
+        for s in added {
+            let hashed = s.get_hashed_str();
+            // The same string may have been frozen more than once.
+            if interned.get(hashed).is_none() {
+                interned
+                    .map
+                    .insert(hashed.hash().promote(), (s, heap.dupe()), |(x, _)| {
+                        x.get_hash().promote()
+                    });
+            }
+        }
+    }
+}
@@ -19,6 +19,7 @@
 //! Based on <https://docs.python.org/3/library/stdtypes.html#printf-style-string-formatting>
 
 use std::fmt::Write;
+use std::iter;
 use std::mem;
 use std::str::FromStr;
 
@@ -31,7 +32,9 @@ use crate::values::dict::Dict;
 use crate::values::float;
 use crate::values::num;
 use crate::values::num::Num;
+use crate::values::types::string::format_spec::FormatSpec;
 use crate::values::types::tuple::value::Tuple;
+use crate::values::FrozenStringValue;
 use crate::values::Heap;
 use crate::values::StringValue;
 use crate::values::UnpackValue;
@@ -50,7 +53,14 @@ enum StringInterpolationError {
     NotEnoughParameters,
 }
 
-pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
+/// Append `piece` to `out`, unless the result would exceed the heap string length limit.
+fn push_str_checked(out: &mut String, piece: &str, heap: &Heap) -> anyhow::Result<()> {
+    heap.check_string_length(out.len() + piece.len())?;
+    out.push_str(piece);
+    Ok(())
+}
+
+pub(crate) fn percent(format: &str, value: Value, heap: &Heap) -> anyhow::Result<String> {
     // For performance reasons, we treat format as a list of bytes
     // (which is fine, the only thing we care about are '%' and ASCII digits).
     // As a result, we accumulate into a Vec<u8>, which we know at any point
@@ -87,24 +97,18 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
                     b's' => {
                         let arg = next_value()?;
                         match arg.unpack_str() {
-                            None => arg.collect_repr(out),
-                            Some(s) => out.push_str(s),
-                        }
-                    }
-                    b'r' => next_value()?.collect_repr(out),
-                    b'd' => {
-                        let value = next_value()?;
-                        if let Some(num::Num::Float(v)) = value.unpack_num() {
-                            match num::Num::Float(v.trunc()).as_int() {
-                                None => {
-                                    return ValueError::unsupported(&float::StarlarkFloat(v), "%d");
-                                }
-                                Some(v) => write!(out, "{}", v).unwrap(),
+                            None => {
+                                arg.collect_repr(out);
+                                heap.check_string_length(out.len())?;
                             }
-                        } else {
-                            write!(out, "{}", value.to_int()?).unwrap()
+                            Some(s) => push_str_checked(out, s, heap)?,
                         }
                     }
+                    b'r' => {
+                        next_value()?.collect_repr(out);
+                        heap.check_string_length(out.len())?;
+                    }
+                    b'd' => percent_d(next_value()?, out)?,
                     b'o' => {
                         let v = next_value()?.to_int()?;
                         write!(
@@ -170,10 +174,27 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
     if values.next().is_some() {
         Err(StringInterpolationError::TooManyParameters.into())
     } else {
+        heap.check_string_length(res.len())?;
         Ok(unsafe { String::from_utf8_unchecked(res) })
     }
 }
 
+/// Write `value` formatted with `%d`.
+fn percent_d(value: Value, out: &mut String) -> anyhow::Result<()> {
+    if let Some(num::Num::Float(v)) = value.unpack_num() {
+        match num::Num::Float(v.trunc()).as_int() {
+            None => ValueError::unsupported(&float::StarlarkFloat(v), "%d"),
+            Some(v) => {
+                write!(out, "{}", v).unwrap();
+                Ok(())
+            }
+        }
+    } else {
+        write!(out, "{}", value.to_int()?).unwrap();
+        Ok(())
+    }
+}
+
 /// Try parse `"aaa{}bbb"` and return `("aaa", "bbb")`.
 pub(crate) fn parse_format_one(s: &str) -> Option<(String, String)> {
     let mut parser = FormatParser {
@@ -201,20 +222,42 @@ pub(crate) fn parse_format_one(s: &str) -> Option<(String, String)> {
     Some((before, after))
 }
 
-/// Try parse `"aaa%sbbb"` and return `("aaa", "bbb")`.
-pub(crate) fn parse_percent_s_one(format: &str) -> Option<(String, String)> {
+/// Try parse `"aaa{}bbb{}ccc"` and return `["aaa", "bbb", "ccc"]`,
+/// one more chunk than there are captures.
+/// Returns `None` if any capture has an index, a name or a format spec.
+pub(crate) fn parse_format_n(s: &str) -> Option<Vec<String>> {
+    let mut parser = FormatParser {
+        format_str: s,
+        rem_input: s,
+    };
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    loop {
+        match parser.next().ok()? {
+            Some(FormatToken::Text(text)) => chunk.push_str(text),
+            Some(FormatToken::Capture("")) => chunks.push(mem::take(&mut chunk)),
+            Some(FormatToken::Capture(_)) => return None,
+            None => break,
+        }
+    }
+    chunks.push(chunk);
+    Some(chunks)
+}
+
+/// Try parse `"aaa%sbbb"` or `"aaa%dbbb"` and return `("aaa", 's' or 'd', "bbb")`.
+pub(crate) fn parse_percent_one(format: &str) -> Option<(String, char, String)> {
     let mut before = String::with_capacity(format.len());
     let mut chars = format.chars();
-    loop {
+    let conversion = loop {
         match chars.next()? {
             '%' => match chars.next()? {
                 '%' => before.push('%'),
-                's' => break,
+                c @ ('s' | 'd') => break c,
                 _ => return None,
             },
             c => before.push(c),
         }
-    }
+    };
     let mut after = String::with_capacity(format.len() - before.len());
     loop {
         match chars.next() {
@@ -226,7 +269,7 @@ pub(crate) fn parse_percent_s_one(format: &str) -> Option<(String, String)> {
             None => break,
         }
     }
-    Some((before, after))
+    Some((before, conversion, after))
 }
 
 /// Evaluate `"<before>{}<after>".format(arg)`.
@@ -235,17 +278,56 @@ pub(crate) fn format_one<'v>(
     arg: Value<'v>,
     after: &str,
     heap: &'v Heap,
-) -> StringValue<'v> {
+) -> anyhow::Result<StringValue<'v>> {
     match StringValue::new(arg) {
-        Some(arg) => heap.alloc_str_concat3(before, &arg, after),
+        Some(arg) => {
+            heap.check_string_length(before.len() + arg.len() + after.len())?;
+            Ok(heap.alloc_str_concat3(before, &arg, after))
+        }
         None => {
             let mut result = String::with_capacity(before.len() + after.len() + 10);
             result.push_str(before);
             arg.collect_repr(&mut result);
             result.push_str(after);
-            heap.alloc_str(&result)
+            heap.check_string_length(result.len())?;
+            Ok(heap.alloc_str(&result))
+        }
+    }
+}
+
+/// Evaluate `"<c0>{}<c1>{}...<cn>".format(a0, a1, ...)`, with one more chunk than arguments.
+pub(crate) fn format_n<'v>(
+    chunks: &[FrozenStringValue],
+    args: &[Value<'v>],
+    heap: &'v Heap,
+) -> anyhow::Result<StringValue<'v>> {
+    debug_assert!(chunks.len() == args.len() + 1);
+    let mut parts: Vec<&str> = Vec::with_capacity(chunks.len() + args.len());
+    let mut reprs = String::new();
+    let mut repr_ends = Vec::new();
+    // Strings are concatenated without copying, other values are rendered into `reprs` first.
+    for arg in args {
+        if StringValue::new(*arg).is_none() {
+            arg.collect_repr(&mut reprs);
+            repr_ends.push(reprs.len());
+        }
+    }
+    let mut repr_start = 0;
+    let mut repr_ends = repr_ends.into_iter();
+    for (chunk, arg) in chunks.iter().zip(args) {
+        parts.push(chunk.as_str());
+        match arg.unpack_str() {
+            Some(arg) => parts.push(arg),
+            None => {
+                let end = repr_ends.next().unwrap();
+                parts.push(&reprs[repr_start..end]);
+                repr_start = end;
+            }
         }
     }
+    parts.push(chunks.last().unwrap().as_str());
+    heap.check_string_length(parts.iter().map(|p| p.len()).sum())?;
+    Ok(heap.alloc_str_concat_many(&parts))
 }
 
 /// Evaluate `"<before>%s<after>" % arg`.
@@ -256,7 +338,10 @@ pub(crate) fn percent_s_one<'v>(
     heap: &'v Heap,
 ) -> anyhow::Result<StringValue<'v>> {
     Ok(match StringValue::new(arg) {
-        Some(arg) => heap.alloc_str_concat3(before, &arg, after),
+        Some(arg) => {
+            heap.check_string_length(before.len() + arg.len() + after.len())?;
+            heap.alloc_str_concat3(before, &arg, after)
+        }
         None => {
             let one = match Tuple::from_value(arg) {
                 Some(tuple) => match tuple.content() {
@@ -266,21 +351,51 @@ pub(crate) fn percent_s_one<'v>(
                 },
                 None => arg,
             };
-            format_one(before, one, after, heap)
+            format_one(before, one, after, heap)?
         }
     })
 }
 
+/// Evaluate `"<before>%d<after>" % arg`.
+pub(crate) fn percent_d_one<'v>(
+    before: &str,
+    arg: Value<'v>,
+    after: &str,
+    heap: &'v Heap,
+) -> anyhow::Result<StringValue<'v>> {
+    let one = match Tuple::from_value(arg) {
+        Some(tuple) => match tuple.content() {
+            [] => return Err(StringInterpolationError::NotEnoughParameters.into()),
+            [value] => *value,
+            [_, _, ..] => return Err(StringInterpolationError::TooManyParameters.into()),
+        },
+        None => arg,
+    };
+    let mut result = String::with_capacity(before.len() + after.len() + 10);
+    result.push_str(before);
+    percent_d(one, &mut result)?;
+    result.push_str(after);
+    heap.check_string_length(result.len())?;
+    Ok(heap.alloc_str(&result))
+}
+
 /// The format string can either have explicit indices,
 /// or grab things sequentially, but not both.
 /// FormatArgs knows which we are doing and keeps them in mind.
 struct FormatArgs<'v, T: Iterator<Item = Value<'v>>> {
     // Initially we have the iterator set and the args empty.
-    // If we ever ask by index, we decant the iterator into args.
+    // If we ever ask by index, or need to know the number of arguments,
+    // we decant the iterator into args.
     iterator: T,
     args: Vec<Value<'v>>,
     by_index: bool,
     by_order: bool,
+    /// Number of arguments taken in order.
+    next: usize,
+    /// The first argument taken in order before decanting, if any.
+    first: Option<Value<'v>>,
+    /// `Some(n)` if decanted after `n` arguments were taken in order.
+    decanted: Option<usize>,
 }
 
 impl<'v, T: Iterator<Item = Value<'v>>> FormatArgs<'v, T> {
@@ -290,6 +405,20 @@ impl<'v, T: Iterator<Item = Value<'v>>> FormatArgs<'v, T> {
             args: Vec::new(),
             by_index: false,
             by_order: false,
+            next: 0,
+            first: None,
+            decanted: None,
+        }
+    }
+
+    fn decant(&mut self) -> usize {
+        match self.decanted {
+            Some(taken) => taken,
+            None => {
+                self.args.extend(&mut self.iterator);
+                self.decanted = Some(self.next);
+                self.next
+            }
         }
     }
 
@@ -300,9 +429,19 @@ impl<'v, T: Iterator<Item = Value<'v>>> FormatArgs<'v, T> {
             ))
         } else {
             self.by_order = true;
-            match self.iterator.next() {
+            let next = match self.decanted {
+                None => self.iterator.next(),
+                Some(taken) => self.args.get(self.next - taken).copied(),
+            };
+            match next {
                 None => Err(anyhow::anyhow!("Not enough parameters in format string")),
-                Some(x) => Ok(x),
+                Some(x) => {
+                    if self.next == 0 {
+                        self.first = Some(x);
+                    }
+                    self.next += 1;
+                    Ok(x)
+                }
             }
         }
     }
@@ -313,16 +452,23 @@ impl<'v, T: Iterator<Item = Value<'v>>> FormatArgs<'v, T> {
                 "Cannot mix manual field specification and automatic field numbering in format string",
             ))
         } else {
-            if !self.by_index {
-                self.args.extend(&mut self.iterator);
-                self.by_index = true;
-            }
+            self.by_index = true;
+            self.decant();
             match self.args.get(index) {
                 None => Err(ValueError::IndexOutOfBound(index as i32).into()),
                 Some(v) => Ok(*v),
             }
         }
     }
+
+    /// The positional argument, if there is exactly one.
+    fn single(&mut self) -> Option<Value<'v>> {
+        match (self.decant(), self.args.as_slice()) {
+            (0, [x]) => Some(*x),
+            (1, []) => self.first,
+            _ => None,
+        }
+    }
 }
 
 /// Parser for `.format()` arguments.
@@ -361,13 +507,30 @@ impl<'a> FormatParser<'a> {
                         return Ok(Some(FormatToken::Text("{")));
                     }
                     i = 1;
+                    // Format specifier may contain one level of nested replacement fields,
+                    // e.g. `{:{width}}`.
+                    let mut nested = false;
                     while i < self.rem_input.len() {
                         match self.rem_input.as_bytes()[i] {
+                            b'}' if nested => {
+                                nested = false;
+                                i += 1;
+                            }
                             b'}' => {
                                 let capture = &self.rem_input[1..i];
                                 self.rem_input = &self.rem_input[i + 1..];
                                 return Ok(Some(FormatToken::Capture(capture)));
                             }
+                            b'{' if nested => {
+                                return Err(anyhow::anyhow!(
+                                    "Max format specifier nesting depth exceeded in format string `{}`",
+                                    self.format_str
+                                ));
+                            }
+                            b'{' if self.rem_input[1..i].contains(':') => {
+                                nested = true;
+                                i += 1;
+                            }
                             b'{' => {
                                 break;
                             }
@@ -409,6 +572,29 @@ pub(crate) fn format<'v>(
     kwargs: Dict<'v>,
     string_pool: &mut StringPool,
     heap: &'v Heap,
+) -> anyhow::Result<StringValue<'v>> {
+    format_impl(this, args, &kwargs, false, string_pool, heap)
+}
+
+/// `str.format_map`: like `format`, but takes named fields from the mapping.
+/// With `safe`, replacement fields naming missing keys are preserved intact.
+pub(crate) fn format_map<'v>(
+    this: &str,
+    mapping: &Dict<'v>,
+    safe: bool,
+    string_pool: &mut StringPool,
+    heap: &'v Heap,
+) -> anyhow::Result<StringValue<'v>> {
+    format_impl(this, iter::empty(), mapping, safe, string_pool, heap)
+}
+
+fn format_impl<'v>(
+    this: &str,
+    args: impl Iterator<Item = Value<'v>>,
+    kwargs: &Dict<'v>,
+    safe: bool,
+    string_pool: &mut StringPool,
+    heap: &'v Heap,
 ) -> anyhow::Result<StringValue<'v>> {
     let mut parser = FormatParser {
         format_str: this,
@@ -418,9 +604,25 @@ pub(crate) fn format<'v>(
     let mut args = FormatArgs::new(args);
     while let Some(token) = parser.next()? {
         match token {
-            FormatToken::Text(text) => result.push_str(text),
+            FormatToken::Text(text) => push_str_checked(&mut result, text, heap)?,
             FormatToken::Capture(capture) => {
-                format_capture(capture, &mut args, &kwargs, &mut result)?
+                let len = result.len();
+                match format_capture(capture, &mut args, kwargs, heap, &mut result) {
+                    Ok(()) => {}
+                    Err(e)
+                        if safe
+                            && matches!(
+                                e.downcast_ref::<ValueError>(),
+                                Some(ValueError::KeyNotFound(_))
+                            ) =>
+                    {
+                        result.truncate(len);
+                        result.push('{');
+                        result.push_str(capture);
+                        result.push('}');
+                    }
+                    Err(e) => return Err(e),
+                }
             }
         }
     }
@@ -432,22 +634,25 @@ pub(crate) fn format<'v>(
 fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
     capture: &str,
     args: &mut FormatArgs<'v, T>,
-    kwargs: &Dict,
+    kwargs: &Dict<'v>,
+    heap: &'v Heap,
     result: &mut String,
 ) -> anyhow::Result<()> {
-    let (n, conv) = {
-        if let Some((n, conv)) = capture.split_once('!') {
-            (n, conv)
-        } else {
-            (capture, "s")
-        }
+    let (field, spec) = match capture.split_once(':') {
+        Some((field, spec)) => (field, spec),
+        None => (capture, ""),
+    };
+    let (n, conv) = match field.split_once('!') {
+        Some((n, conv)) => (n, Some(conv)),
+        None => (field, None),
     };
     let conv_s = |x: Value, result: &mut String| x.collect_str(result);
     let conv_r = |x: Value, result: &mut String| x.collect_repr(result);
-    let conv: &dyn Fn(Value, &mut String) = match conv {
-        "s" => &conv_s,
-        "r" => &conv_r,
-        c => {
+    let conv: Option<&dyn Fn(Value, &mut String)> = match conv {
+        None => None,
+        Some("s") => Some(&conv_s),
+        Some("r") => Some(&conv_r),
+        Some(c) => {
             return Err(anyhow::anyhow!(
                 concat!(
                     "'{}' is not a valid format string specifier, only ",
@@ -457,13 +662,51 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
             ));
         }
     };
+    let value = format_field(n, args, kwargs, heap)?;
+    if spec.is_empty() {
+        return match (conv, value.unpack_str()) {
+            (None, Some(s)) => push_str_checked(result, s, heap),
+            _ => {
+                conv.unwrap_or(&conv_s)(value, result);
+                heap.check_string_length(result.len())
+            }
+        };
+    }
+    let spec = if spec.contains('{') {
+        FormatSpec::parse(&format_nested_spec(spec, args, kwargs, heap)?)?
+    } else {
+        FormatSpec::parse(spec)?
+    };
+    // Width and precision can be arbitrarily large, so check them before formatting.
+    match conv {
+        Some(conv) => {
+            let mut s = String::new();
+            conv(value, &mut s);
+            heap.check_string_length(result.len() + spec.reserved_len(false))?;
+            spec.format_str(&s, result)?;
+        }
+        None => {
+            heap.check_string_length(
+                result.len() + spec.reserved_len(value.unpack_num().is_some()),
+            )?;
+            spec.format_value(value, result)?;
+        }
+    }
+    heap.check_string_length(result.len())
+}
+
+/// Resolve the value of the replacement field name.
+fn format_field<'v, T: Iterator<Item = Value<'v>>>(
+    n: &str,
+    args: &mut FormatArgs<'v, T>,
+    kwargs: &Dict<'v>,
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
     if n.is_empty() {
-        conv(args.next_ordered()?, result);
-        Ok(())
+        args.next_ordered()
     } else if n.chars().all(|c| c.is_ascii_digit()) {
         let i = usize::from_str(n).unwrap();
-        conv(args.by_index(i)?, result);
-        Ok(())
+        args.by_index(i)
     } else {
         if let Some(x) = n.chars().find(|c| match c {
             '.' | ',' | '[' | ']' => true,
@@ -474,16 +717,41 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
                 x
             ));
         }
-        match kwargs.get_str(n) {
-            None => Err(ValueError::KeyNotFound(n.to_owned()).into()),
-            Some(v) => {
-                conv(v, result);
-                Ok(())
+        if let Some(v) = kwargs.get_str(n) {
+            return Ok(v);
+        }
+        // With a single positional argument, fall back to its attribute,
+        // e.g. `"{x}".format(struct(x = 1))`. Methods are not fields.
+        if let Some(v) = args.single() {
+            if let Some(v) = v.get_ref().get_attr(n, heap) {
+                return Ok(v);
             }
         }
+        Err(ValueError::KeyNotFound(n.to_owned()).into())
     }
 }
 
+/// Substitute nested replacement fields in the format specifier,
+/// e.g. `{w}` in `{:{w}}`.
+fn format_nested_spec<'v, T: Iterator<Item = Value<'v>>>(
+    spec: &str,
+    args: &mut FormatArgs<'v, T>,
+    kwargs: &Dict<'v>,
+    heap: &'v Heap,
+) -> anyhow::Result<String> {
+    let mut res = String::with_capacity(spec.len());
+    let mut rem = spec;
+    while let Some((before, after)) = rem.split_once('{') {
+        // Parser guarantees nested fields are closed.
+        let (n, after) = after.split_once('}').unwrap();
+        res.push_str(before);
+        format_field(n, args, kwargs, heap)?.collect_str(&mut res);
+        rem = after;
+    }
+    res.push_str(rem);
+    Ok(res)
+}
+
 #[cfg(test)]
 mod tests {
     use gazebo::coerce::coerce;
@@ -491,15 +759,17 @@ mod tests {
     use super::*;
     use crate::assert;
     use crate::collections::SmallMap;
+    use crate::values::FrozenHeap;
     use crate::values::Heap;
 
     fn format_capture_for_test<'v, T: Iterator<Item = Value<'v>>>(
         capture: &str,
         args: &mut FormatArgs<'v, T>,
-        kwargs: &Dict,
+        kwargs: &Dict<'v>,
+        heap: &'v Heap,
     ) -> anyhow::Result<String> {
         let mut result = String::new();
-        super::format_capture(capture, args, kwargs, &mut result)?;
+        super::format_capture(capture, args, kwargs, heap, &mut result)?;
         Ok(result)
     }
 
@@ -515,32 +785,32 @@ mod tests {
         kwargs.insert_hashed(heap.alloc_str("c").get_hashed(), heap.alloc("z"));
         let kwargs = Dict::new(coerce(kwargs));
         assert_eq!(
-            format_capture_for_test("", &mut args, &kwargs).unwrap(),
+            format_capture_for_test("", &mut args, &kwargs, &heap).unwrap(),
             "1"
         );
         assert_eq!(
-            format_capture_for_test("!s", &mut args, &kwargs).unwrap(),
+            format_capture_for_test("!s", &mut args, &kwargs, &heap).unwrap(),
             "2"
         );
         assert_eq!(
-            format_capture_for_test("!r", &mut args, &kwargs).unwrap(),
+            format_capture_for_test("!r", &mut args, &kwargs, &heap).unwrap(),
             "\"3\""
         );
         assert_eq!(
-            format_capture_for_test("a!r", &mut args, &kwargs).unwrap(),
+            format_capture_for_test("a!r", &mut args, &kwargs, &heap).unwrap(),
             "\"x\""
         );
         assert_eq!(
-            format_capture_for_test("a!s", &mut args, &kwargs).unwrap(),
+            format_capture_for_test("a!s", &mut args, &kwargs, &heap).unwrap(),
             "x"
         );
-        assert!(format_capture_for_test("1", &mut args, &kwargs).is_err());
+        assert!(format_capture_for_test("1", &mut args, &kwargs, &heap).is_err());
         let mut args = FormatArgs::new(original_args.iter().copied());
         assert_eq!(
-            format_capture_for_test("1", &mut args, &kwargs).unwrap(),
+            format_capture_for_test("1", &mut args, &kwargs, &heap).unwrap(),
             "2"
         );
-        assert!(format_capture_for_test("", &mut args, &kwargs).is_err());
+        assert!(format_capture_for_test("", &mut args, &kwargs, &heap).is_err());
     }
 
     #[test]
@@ -548,6 +818,178 @@ mod tests {
         assert::eq("'a{x}b{y}c{}'.format(1, x=2, y=3)", "'a2b3c1'")
     }
 
+    #[test]
+    fn test_format_repeated_index() {
+        assert::all_true(
+            r#"
+"{0}{0}".format("a") == "aa"
+"{0}{0}{0}".format(1) == "111"
+"{1}{0}{1}".format("a", "b") == "bab"
+"{0}{1}{0}{1}".format("a", "b") == "abab"
+"{0!r}{0}".format("a") == '"a"a'
+"{0:>3}{0:<3}|".format("a") == "  aa  |"
+"{0}{x}{0}".format("a", x = "b") == "aba"
+"#,
+        );
+        assert::eq(
+            r#"
+def f(a, b):
+    return "{0}{0}".format(a, b) + "{1}{0}{1}".format(a, b)
+f("a", "b")
+"#,
+            "'aabab'",
+        );
+        assert::fail(r#""{0}{}".format("a", "b")"#, "Cannot mix");
+        assert::fail(r#""{}{0}".format("a", "b")"#, "Cannot mix");
+    }
+
+    #[test]
+    fn test_format_containers() {
+        assert::all_true(
+            r#"
+"{}".format([1, "a"]) == '[1, "a"]'
+"{}".format({"a": [1], 2: None}) == '{"a": [1], 2: None}'
+"{}".format(("a",)) == '("a",)'
+"{} {}".format([], {}) == "[] {}"
+"{!s}".format(["a"]) == str(["a"])
+"{!r}".format(["a"]) == repr(["a"])
+"{x}".format(x = {"k": "v"}) == str({"k": "v"})
+"{:>8}".format(["a"]) == '   ["a"]'
+"#,
+        );
+        assert::eq("x = [1]\nx.append(x)\n'{}'.format(x)", "'[1, [...]]'");
+    }
+
+    #[test]
+    fn test_format_singletons() {
+        assert::all_true(
+            r#"
+"{}".format(None) == "None"
+"{}".format(True) == "True"
+"{}".format(False) == "False"
+"{} {} {}".format(None, True, False) == str(None) + " " + str(True) + " " + str(False)
+"{!s} {!r}".format(True, False) == "True False"
+"{!r}".format(None) == "None"
+"{x}".format(x = None) == "None"
+"{:>6}".format(True) == "  True"
+"{:<6}|".format(None) == "None  |"
+"{:^7}".format(False) == " False "
+"[{}]".format([None, True, False]) == "[[None, True, False]]"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_format_nested_spec() {
+        assert::all_true(
+            r#"
+"{:{w}}".format("x", w=3) == "x  "
+"{:>{}}".format("x", 3) == "  x"
+"{0:>{1}}".format("x", 3) == "  x"
+"{:.{p}f}".format(3.14159, p=2) == "3.14"
+"{:{w}.{p}f}".format(3.14159, w=6, p=1) == "   3.1"
+"{x:{fill}^{w}}".format(x="ab", fill="*", w=6) == "**ab**"
+"#,
+        );
+        assert::fail(r#""{:{w}}".format("x")"#, "was not found");
+        assert::fail(r#""{:{{w}}}".format("x", w=2)"#, "nesting depth exceeded");
+        assert::fail(r#""{:{}}".format("x")"#, "Not enough parameters");
+    }
+
+    #[test]
+    fn test_format_brace_escapes() {
+        assert::all_true(
+            r#"
+"{{}}".format() == "{}"
+"a{{b}}c".format() == "a{b}c"
+"{{{{}}}}".format() == "{{}}"
+"{{{}}}".format(1) == "{1}"
+"{}}}".format(1) == "1}"
+"{{{}".format(1) == "{1"
+"}}{}{{".format(1) == "}1{"
+"{{{0}}}".format(1) == "{1}"
+"{{{x}}}".format(x = 1) == "{1}"
+"{{{!r}}}".format("x") == "{\"x\"}"
+"{!r}}}".format("x") == "\"x\"}"
+"{{{:>3}}}".format(1) == "{  1}"
+"{{{:{}}}}".format(1, 3) == "{  1}"
+"{{{0:{1}}}}".format(1, 3) == "{  1}"
+"{:{w}}}}".format(1, w = 3) == "  1}"
+"{{{{{}}}}}".format(1) == "{{1}}"
+"{{}}{}{{}}{}".format(1, 2) == "{}1{}2"
+"{{x}} {x}".format_map({"x": 1}) == "{x} 1"
+"#,
+        );
+        // Same through the compiled fast paths for one and many plain placeholders.
+        assert::pass(
+            r#"
+def one(x):
+    return "{{{}}}".format(x)
+def many(x, y):
+    return "}}{}{{{}}}".format(x, y)
+assert_eq("{1}", one(1))
+assert_eq("}1{2}", many(1, 2))
+"#,
+        );
+        assert::fail(r#""{}}".format(1)"#, "Standalone '}'");
+        assert::fail(r#""{{{}".format()"#, "Not enough parameters");
+        assert::fail(r#""{{{".format()"#, "Unmatched '{'");
+        assert::fail(r#""{:{<5}".format(1)"#, "Unmatched '{'");
+        assert::fail(r#""{:}<5}".format(1)"#, "Standalone '}'");
+        assert::fail(r#""{:{{}}}".format(1)"#, "nesting depth exceeded");
+    }
+
+    #[test]
+    fn test_format_attr_fallback() {
+        assert::all_true(
+            r#"
+"{x}-{y}".format(struct(x = 1, y = "a")) == "1-a"
+"{x}".format(struct(x = 1), x = 2) == "2"
+"{} {x}".format(struct(x = 1)) == "struct(x=1) 1"
+"{x} {}".format(struct(x = 1)) == "1 struct(x=1)"
+"{x} {0}".format(struct(x = 1)) == "1 struct(x=1)"
+"{x:>3}".format(struct(x = 1)) == "  1"
+"#,
+        );
+        assert::fail(r#""{y}".format(struct(x = 1))"#, "Key `y` was not found");
+        assert::fail(
+            r#""{x}".format(struct(x = 1), struct(x = 2))"#,
+            "Key `x` was not found",
+        );
+        assert::fail(r#""{upper}".format("abc")"#, "Key `upper` was not found");
+    }
+
+    #[test]
+    fn test_format_map() {
+        assert::all_true(
+            r#"
+"{a}{b}".format_map({"a": 1, "b": "x"}) == "1x"
+"{a!r:>5}".format_map({"a": "x"}) == "  \"x\""
+"{{a}} {a}".format_map({"a": 1}) == "{a} 1"
+"#,
+        );
+        assert::fail(r#""{a}{b}".format_map({"a": 1})"#, "Key `b` was not found");
+        assert::fail(r#""{}".format_map({"a": 1})"#, "Not enough parameters");
+    }
+
+    #[test]
+    fn test_format_map_safe() {
+        assert::all_true(
+            r#"
+"{a}{b}".format_map({"a": 1}, safe = True) == "1{b}"
+"{b!r:>5} {a}".format_map({"a": 1}, safe = True) == "{b!r:>5} 1"
+"{a:{w}}|".format_map({"a": 1}, safe = True) == "{a:{w}}|"
+"{{b}} {b}".format_map({}, safe = True) == "{b} {b}"
+"{a}".format_map({"a": 1}, safe = False) == "1"
+"#,
+        );
+        assert::fail(r#""{b}".format_map({}, safe = False)"#, "was not found");
+        assert::fail(
+            r#""{}".format_map({}, safe = True)"#,
+            "Not enough parameters",
+        );
+    }
+
     #[test]
     fn test_parse_format_one() {
         assert_eq!(
@@ -564,18 +1006,70 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_percent_s_one() {
+    fn test_parse_format_n() {
+        assert_eq!(Some(vec!["abc".to_owned()]), parse_format_n("abc"));
         assert_eq!(
-            Some(("abc".to_owned(), "def".to_owned())),
-            parse_percent_s_one("abc%sdef")
+            Some(vec!["a".to_owned(), "".to_owned(), "b{".to_owned()]),
+            parse_format_n("a{}{}b{{")
+        );
+        assert_eq!(
+            Some(vec!["}".to_owned(), "x".to_owned(), "".to_owned()]),
+            parse_format_n("}}{}x{}")
+        );
+        assert_eq!(None, parse_format_n("a{"));
+        assert_eq!(None, parse_format_n("{}{0}"));
+        assert_eq!(None, parse_format_n("{}{x}"));
+        assert_eq!(None, parse_format_n("{:>3}"));
+    }
+
+    #[test]
+    fn test_format_n() {
+        let heap = Heap::new();
+        let frozen_heap = FrozenHeap::new();
+        let chunks = ["<", "|", "|", ">"].map(|c| frozen_heap.alloc_str(c));
+        let args = [
+            heap.alloc_str("s").to_value(),
+            Value::new_int(1),
+            heap.alloc((2, "x")),
+        ];
+        assert_eq!(
+            "<s|1|(2, \"x\")>",
+            format_n(&chunks, &args, &heap).unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn test_parse_percent_one() {
+        assert_eq!(
+            Some(("abc".to_owned(), 's', "def".to_owned())),
+            parse_percent_one("abc%sdef")
+        );
+        assert_eq!(
+            Some(("a%b".to_owned(), 's', "c%d%".to_owned())),
+            parse_percent_one("a%%b%sc%%d%%")
         );
         assert_eq!(
-            Some(("a%b".to_owned(), "c%d%".to_owned())),
-            parse_percent_s_one("a%%b%sc%%d%%")
+            Some(("<".to_owned(), 'd', ">".to_owned())),
+            parse_percent_one("<%d>")
         );
-        assert_eq!(None, parse_percent_s_one("a%"));
-        assert_eq!(None, parse_percent_s_one("a%s%"));
-        assert_eq!(None, parse_percent_s_one("a%s%s"));
-        assert_eq!(None, parse_percent_s_one("%d"));
+        assert_eq!(None, parse_percent_one("a%"));
+        assert_eq!(None, parse_percent_one("a%s%"));
+        assert_eq!(None, parse_percent_one("a%s%s"));
+        assert_eq!(None, parse_percent_one("%s%d"));
+        assert_eq!(None, parse_percent_one("%r"));
+        assert_eq!(None, parse_percent_one("%5d"));
+        assert_eq!(None, parse_percent_one("%.2s"));
+    }
+
+    #[test]
+    fn test_percent_d_one() {
+        let heap = Heap::new();
+        let d = |arg| percent_d_one("<", arg, ">", &heap).map(|s| s.as_str().to_owned());
+        assert_eq!("<17>", d(Value::new_int(17)).unwrap());
+        assert_eq!("<-2>", d(heap.alloc(-2.7)).unwrap());
+        assert_eq!("<3>", d(heap.alloc((3,))).unwrap());
+        assert!(d(heap.alloc("x")).is_err());
+        assert!(d(heap.alloc((1, 2))).is_err());
+        assert!(d(heap.alloc_tuple(&[])).is_err());
     }
 }
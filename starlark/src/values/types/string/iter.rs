@@ -28,6 +28,7 @@ use crate::values::StarlarkValue;
 use crate::values::StringValue;
 use crate::values::StringValueLike;
 use crate::values::Value;
+use crate::values::ValueError;
 use crate::values::ValueLike;
 
 /// An opaque iterator over a string, produced by elems/codepoints
@@ -68,6 +69,10 @@ where
 {
     starlark_type!("iterator");
 
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+    }
+
     fn iterate<'a>(
         &'a self,
         heap: &'v Heap,
@@ -96,3 +101,200 @@ where
         }
     }
 }
+
+/// Lines of a string, split at `\n`, `\r\n` or `\r`, as produced by `splitlines`.
+pub(crate) struct Lines<'a> {
+    rest: &'a str,
+    keepends: bool,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(s: &'a str, keepends: bool) -> Lines<'a> {
+        Lines { rest: s, keepends }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let s = self.rest;
+        match s.find(|x| x == '\n' || x == '\r') {
+            Some(y) => {
+                let x = match s.get(y..y + 2) {
+                    Some("\r\n") => y + 2,
+                    _ => y + 1,
+                };
+                self.rest = &s[x..];
+                Some(if self.keepends { &s[..x] } else { &s[..y] })
+            }
+            None => {
+                self.rest = "";
+                Some(s)
+            }
+        }
+    }
+}
+
+/// Split at runs of whitespace into at most `maxsplit` pieces,
+/// the last of which keeps any trailing whitespace.
+/// Does not exist in Rust: `split` would cut the string incorrectly and
+/// `split_whitespace` cannot take a limit.
+pub(crate) struct SplitnWhitespace<'a> {
+    rest: &'a str,
+    remaining: usize,
+}
+
+impl<'a> SplitnWhitespace<'a> {
+    pub(crate) fn new(s: &'a str, maxsplit: usize) -> SplitnWhitespace<'a> {
+        SplitnWhitespace {
+            rest: s,
+            remaining: maxsplit,
+        }
+    }
+}
+
+impl<'a> Iterator for SplitnWhitespace<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let s = self.rest.trim_start();
+        if s.is_empty() || self.remaining == 0 {
+            self.rest = "";
+            return None;
+        }
+        self.remaining -= 1;
+        match s.find(char::is_whitespace) {
+            Some(i) if self.remaining != 0 => {
+                self.rest = &s[i..];
+                Some(&s[..i])
+            }
+            _ => {
+                self.rest = "";
+                Some(s)
+            }
+        }
+    }
+}
+
+/// The pieces of `split`, where `maxsplit` is the maximum number of pieces.
+pub(crate) fn split<'a>(
+    s: &'a str,
+    sep: Option<&'a str>,
+    maxsplit: Option<usize>,
+) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+    match (sep, maxsplit) {
+        (None, None) => Box::new(s.split_whitespace()),
+        (None, Some(maxsplit)) => Box::new(SplitnWhitespace::new(s, maxsplit)),
+        (Some(sep), None) => Box::new(s.split(sep)),
+        (Some(sep), Some(maxsplit)) => Box::new(s.splitn(maxsplit, sep)),
+    }
+}
+
+/// An opaque iterator over the pieces of a string, produced by `iter_split`.
+#[derive(
+    Debug,
+    Trace,
+    Coerce,
+    Display,
+    Freeze,
+    NoSerialize,
+    ProvidesStaticType,
+    Allocative
+)]
+#[display(fmt = "iterator")]
+#[repr(C)]
+struct StringSplitIteratorGen<'v, V: ValueLike<'v>> {
+    string: V::String,
+    sep: Option<V::String>,
+    maxsplit: Option<usize>,
+}
+
+/// An opaque iterator over the lines of a string, produced by `iter_splitlines`.
+#[derive(
+    Debug,
+    Trace,
+    Coerce,
+    Display,
+    Freeze,
+    NoSerialize,
+    ProvidesStaticType,
+    Allocative
+)]
+#[display(fmt = "iterator")]
+#[repr(C)]
+struct StringLinesIteratorGen<'v, V: ValueLike<'v>> {
+    string: V::String,
+    keepends: bool,
+}
+
+pub(crate) fn iterate_split<'v>(
+    string: StringValue<'v>,
+    sep: Option<StringValue<'v>>,
+    maxsplit: Option<usize>,
+    heap: &'v Heap,
+) -> Value<'v> {
+    heap.alloc_complex(StringSplitIteratorGen::<'v, Value<'v>> {
+        string,
+        sep,
+        maxsplit,
+    })
+}
+
+pub(crate) fn iterate_lines<'v>(
+    string: StringValue<'v>,
+    keepends: bool,
+    heap: &'v Heap,
+) -> Value<'v> {
+    heap.alloc_complex(StringLinesIteratorGen::<'v, Value<'v>> { string, keepends })
+}
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for StringSplitIteratorGen<'v, V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!("iterator");
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        let sep = self.sep.as_ref().map(|x| x.as_str());
+        Ok(Box::new(
+            split(self.string.as_str(), sep, self.maxsplit).map(move |x| heap.alloc(x)),
+        ))
+    }
+}
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for StringLinesIteratorGen<'v, V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!("iterator");
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        Ok(Box::new(
+            Lines::new(self.string.as_str(), self.keepends).map(move |x| heap.alloc(x)),
+        ))
+    }
+}
@@ -47,6 +47,7 @@ use crate::environment::MethodsStatic;
 use crate::private::Private;
 use crate::values::index::apply_slice;
 use crate::values::string::repr::string_repr;
+use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::types::none::NoneOr;
 use crate::values::types::string::fast_string::StrIndices;
 use crate::values::Heap;
@@ -54,9 +55,11 @@ use crate::values::StarlarkValue;
 use crate::values::UnpackValue;
 use crate::values::Value;
 use crate::values::ValueError;
+use crate::values::ValueLike;
 
 mod alloc_unpack;
 pub(crate) mod fast_string;
+mod format_spec;
 pub(crate) mod intern;
 pub(crate) mod interpolation;
 pub(crate) mod iter;
@@ -338,6 +341,7 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
         fn start_stop_to_none_or(v: Option<Value>) -> anyhow::Result<NoneOr<i32>> {
             match v {
                 None => Ok(NoneOr::None),
+                Some(v) if v.is_none() => Ok(NoneOr::None),
                 Some(v) => Ok(NoneOr::Other(v.to_int()?)),
             }
         }
@@ -354,6 +358,8 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
         if let Some(other_str) = other.unpack_str() {
             if self.is_empty() {
                 Some(Ok(other))
+            } else if let Err(e) = heap.check_string_length(self.len() + other_str.len()) {
+                Some(Err(e))
             } else {
                 Some(Ok(heap.alloc_str_concat(self, other_str).to_value()))
             }
@@ -363,8 +369,15 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
     }
 
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        if let Some(b) = other.downcast_ref::<StarlarkBigInt>() {
+            // Too long to build, but report it as such when the length is limited.
+            heap.check_string_length((self.len() as f64 * b.to_f64().max(0.0)) as usize)?;
+        }
         let l = i32::unpack_param(other)?;
-        let mut result = String::with_capacity(self.len() * cmp::max(0, l) as usize);
+        let len = self.len().saturating_mul(cmp::max(0, l) as usize);
+        heap.check_string_length(len)?;
+        heap.check_alloc_bytes(len)?;
+        let mut result = String::with_capacity(len);
         for _i in 0..l {
             result.push_str(self)
         }
@@ -372,7 +385,7 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
     }
 
     fn percent(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        Ok(heap.alloc(interpolation::percent(self, other)?))
+        Ok(heap.alloc(interpolation::percent(self, other, heap)?))
     }
 }
 
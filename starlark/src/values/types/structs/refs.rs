@@ -39,6 +39,13 @@ impl<'v> StructRef<'v> {
     pub fn iter(&self) -> impl ExactSizeIterator<Item = (StringValue<'v>, Value<'v>)> + '_ {
         self.0.iter()
     }
+
+    /// Get a struct field by name.
+    pub fn get(&self, name: &str) -> Option<Value<'v>> {
+        self.0
+            .iter()
+            .find_map(|(k, v)| if k.as_str() == name { Some(v) } else { None })
+    }
 }
 
 impl<'v> StarlarkTypeRepr for StructRef<'v> {
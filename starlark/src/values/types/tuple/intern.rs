@@ -0,0 +1,213 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Interner for frozen tuples, used to deduplicate constant tuples when freezing.
+
+use std::collections::HashMap;
+
+use gazebo::coerce::coerce;
+use hashbrown::raw::RawTable;
+
+use crate::collections::StarlarkHashValue;
+use crate::collections::StarlarkHasher;
+use crate::values::types::tuple::value::Tuple;
+use crate::values::Freezer;
+use crate::values::FrozenValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Deduplicate structurally equal frozen tuples.
+///
+/// Only tuples made of `None`, `bool`, `int`, `str` and other such tuples are
+/// considered: these are deeply immutable, cannot form cycles, and for them
+/// equality implies the values are indistinguishable (this is not true for
+/// floats, e.g. `0.0 == -0.0`, or `1 == 1.0`).
+#[derive(Default)]
+pub(crate) struct FrozenTupleInterner {
+    map: RawTable<FrozenValue>,
+    /// Results of [`can_intern`](FrozenTupleInterner::can_intern) for nested tuples,
+    /// keyed by the address of their content, so that each tuple is inspected once
+    /// no matter how deep it is nested.
+    checked: HashMap<usize, bool>,
+}
+
+impl FrozenTupleInterner {
+    /// Can a tuple with given content be deduplicated.
+    pub(crate) fn can_intern<'v>(&mut self, content: &[Value<'v>]) -> bool {
+        match self.checked.get(&(content.as_ptr() as usize)) {
+            Some(r) => *r,
+            None => content.iter().all(|x| self.can_intern_elem(*x)),
+        }
+    }
+
+    fn can_intern_elem(&mut self, x: Value) -> bool {
+        let x = Freezer::resolve_forward(x);
+        if x.is_none() || x.unpack_bool().is_some() || x.unpack_int().is_some() || x.is_str() {
+            true
+        } else if let Some(t) = Tuple::from_value(x) {
+            let content = t.content();
+            let r = self.can_intern(content);
+            self.checked.insert(content.as_ptr() as usize, r);
+            r
+        } else {
+            false
+        }
+    }
+
+    fn hash(content: &[Value]) -> StarlarkHashValue {
+        let mut hasher = StarlarkHasher::new();
+        for x in content {
+            x.write_hash(&mut hasher)
+                .expect("interned tuple elements are hashable");
+        }
+        hasher.finish_small()
+    }
+
+    fn elem_eq<'v>(x: Value<'v>, y: Value<'v>) -> bool {
+        if x.ptr_eq(y) {
+            return true;
+        }
+        match (Tuple::from_value(x), Tuple::from_value(y)) {
+            (Some(x), Some(y)) => Self::content_eq(x.content(), y.content()),
+            (None, None) => x.get_type() == y.get_type() && x.equals(y).unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    fn content_eq<'v>(x: &[Value<'v>], y: &[Value<'v>]) -> bool {
+        x.len() == y.len() && x.iter().zip(y).all(|(x, y)| Self::elem_eq(*x, *y))
+    }
+
+    fn tuple_content(x: FrozenValue) -> &'static [Value<'static>] {
+        Tuple::from_value(x.to_value())
+            .expect("interned value is a tuple")
+            .content()
+    }
+
+    /// Find a tuple with equal content, or allocate a new one.
+    /// Content must satisfy [`FrozenTupleInterner::can_intern`].
+    pub(crate) fn intern(
+        &mut self,
+        content: &[FrozenValue],
+        alloc: impl FnOnce() -> FrozenValue,
+    ) -> FrozenValue {
+        let content: &[Value] = coerce(content);
+        let hash = Self::hash(content);
+        match self.map.get(hash.promote(), |x| {
+            Self::content_eq(Self::tuple_content(*x), content)
+        }) {
+            Some(tuple) => *tuple,
+            None => {
+                let tuple = alloc();
+                self.map.insert(hash.promote(), tuple, |x| {
+                    Self::hash(Self::tuple_content(*x)).promote()
+                });
+                tuple
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::Freezer;
+    use crate::values::FrozenHeap;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    /// Freeze two tuples with the same freezer, return true if they share allocation.
+    fn freeze_pair(content: impl for<'v> Fn(&'v Heap) -> Vec<Value<'v>>) -> bool {
+        let heap = Heap::new();
+        let freezer = Freezer::new(FrozenHeap::new());
+        let x = freezer.freeze(heap.alloc_tuple(&content(&heap))).unwrap();
+        let y = freezer.freeze(heap.alloc_tuple(&content(&heap))).unwrap();
+        assert!(x.to_value().equals(y.to_value()).unwrap());
+        x.to_value().ptr_eq(y.to_value())
+    }
+
+    #[test]
+    fn test_intern() {
+        assert!(freeze_pair(|heap| vec![
+            Value::new_none(),
+            Value::new_bool(true),
+            heap.alloc_tuple(&[Value::new_int(1), heap.alloc_str("abc").to_value()]),
+        ]));
+        assert!(!freeze_pair(|heap| vec![heap.alloc(1.5)]));
+        assert!(!freeze_pair(|heap| vec![heap.alloc(Vec::<i32>::new())]));
+        assert!(!freeze_pair(|heap| vec![
+            heap.alloc_tuple(&[heap.alloc(Vec::<i32>::new())])
+        ]));
+    }
+
+    #[test]
+    fn test_intern_strings() {
+        let heap = Heap::new();
+        let freezer = Freezer::new(FrozenHeap::new());
+        let freeze = |s: &str| {
+            freezer
+                .freeze(heap.alloc_str(s).to_value())
+                .unwrap()
+                .to_value()
+        };
+        assert!(freeze("abc").ptr_eq(freeze("abc")));
+        let long = "x".repeat(1000);
+        assert!(!freeze(&long).ptr_eq(freeze(&long)));
+    }
+
+    #[test]
+    fn test_intern_distinguishes_equal_values_of_different_types() {
+        let heap = Heap::new();
+        let freezer = Freezer::new(FrozenHeap::new());
+        let x = freezer
+            .freeze(heap.alloc_tuple(&[Value::new_int(1)]))
+            .unwrap();
+        let y = freezer
+            .freeze(heap.alloc_tuple(&[Value::new_bool(true)]))
+            .unwrap();
+        let z = freezer
+            .freeze(heap.alloc_tuple(&[heap.alloc(1.0)]))
+            .unwrap();
+        assert!(!x.to_value().ptr_eq(y.to_value()));
+        assert!(!x.to_value().ptr_eq(z.to_value()));
+    }
+
+    #[test]
+    fn test_intern_module() -> anyhow::Result<()> {
+        fn frozen_heap_bytes(program: &str) -> anyhow::Result<usize> {
+            let module = Module::new();
+            let mut eval = Evaluator::new(&module);
+            eval.eval_module(
+                AstModule::parse("a.star", program.to_owned(), &Dialect::Standard)?,
+                &Globals::standard(),
+            )?;
+            let frozen = module.freeze()?;
+            Ok(frozen.frozen_heap().allocated_bytes())
+        }
+
+        // Same number of tuples with and without duplicates.
+        let equal = frozen_heap_bytes("xs = [(1, 'abc', (2,)) for i in range(1000)]")?;
+        let distinct = frozen_heap_bytes("xs = [(i, 'abc', (2,)) for i in range(1000)]")?;
+        // The list of 1000 pointers dominates the deduplicated heap.
+        assert!(equal * 4 < distinct, "{} vs {}", equal, distinct);
+        Ok(())
+    }
+}
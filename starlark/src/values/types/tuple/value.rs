@@ -15,11 +15,13 @@
  * limitations under the License.
  */
 
+use std::cmp;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::mem;
 use std::slice;
 
 use allocative::Allocative;
@@ -32,6 +34,8 @@ use serde::Serialize;
 use crate as starlark;
 use crate::any::ProvidesStaticType;
 use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
 use crate::private::Private;
 use crate::values::comparison::compare_slice;
 use crate::values::comparison::equals_slice;
@@ -133,12 +137,21 @@ impl<'v, V: ValueLike<'v>> TupleGen<V> {
     }
 }
 
+pub(crate) fn tuple_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::tuple::tuple_methods)
+}
+
 impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for TupleGen<V>
 where
     Self: ProvidesStaticType,
 {
     starlark_type!(Tuple::TYPE);
 
+    fn get_methods() -> Option<&'static Methods> {
+        tuple_methods()
+    }
+
     fn is_special(_: Private) -> bool
     where
         Self: Sized,
@@ -234,7 +247,9 @@ where
 
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = Vec::new();
+        let len = self.len().saturating_mul(cmp::max(0, l) as usize);
+        heap.check_alloc_bytes(len.saturating_mul(mem::size_of::<Value>()))?;
+        let mut result = Vec::with_capacity(len);
         for _i in 0..l {
             result.extend(self.content().iter().map(|e| e.to_value()));
         }
@@ -263,7 +278,16 @@ impl<'v, V: ValueLike<'v>> Serialize for TupleGen<V> {
 
 #[cfg(test)]
 mod tests {
+    use starlark_map::small_map::SmallMap;
+
     use crate::assert;
+    use crate::values::dict::Dict;
+    use crate::values::tuple::AllocTuple;
+    use crate::values::FrozenHeap;
+    use crate::values::FrozenHeapRef;
+    use crate::values::FrozenValue;
+    use crate::values::Heap;
+    use crate::values::Value;
 
     #[test]
     fn test_to_str() {
@@ -281,4 +305,41 @@ str((1,)) == "(1,)"
         assert::eq("l = []; t = (l,); l.append(t); repr(t)", "'([(...)],)'");
         assert::eq("l = []; t = (l,); l.append(t); str(t)", "'([(...)],)'");
     }
+
+    /// Allocate `(1, "x<n>", (2, "y"))` in a fresh frozen heap.
+    fn frozen_tuple(n: i32) -> (FrozenHeapRef, FrozenValue) {
+        let heap = FrozenHeap::new();
+        let inner = heap.alloc(AllocTuple([heap.alloc(2), heap.alloc("y")]));
+        let x = heap.alloc(format!("x{}", n));
+        let tuple = heap.alloc(AllocTuple([heap.alloc(1), x, inner]));
+        (heap.into_ref(), tuple)
+    }
+
+    #[test]
+    fn test_frozen_tuple_dict_key_across_heaps() {
+        let (_heap1, a1) = frozen_tuple(1);
+        let (_heap2, b) = frozen_tuple(2);
+        let (_heap3, a3) = frozen_tuple(1);
+        assert!(!a1.to_value().ptr_eq(a3.to_value()));
+        assert!(a1.to_value().equals(a3.to_value()).unwrap());
+        assert_eq!(
+            a1.to_value().get_hash().unwrap(),
+            a3.to_value().get_hash().unwrap()
+        );
+
+        let heap = Heap::new();
+        let mut content = SmallMap::new();
+        content.insert_hashed(a1.to_value().get_hashed().unwrap(), heap.alloc("a"));
+        content.insert_hashed(b.to_value().get_hashed().unwrap(), heap.alloc("b"));
+        let dict = Dict::new(content);
+
+        let get = |key| dict.get(key).unwrap().map(|v| v.to_str());
+        assert_eq!(Some("a".to_owned()), get(a3.to_value()));
+        assert_eq!(Some("b".to_owned()), get(b.to_value()));
+        // An equal tuple allocated on an unfrozen heap is also found.
+        let x = heap.alloc("x1");
+        let inner = heap.alloc((2, "y"));
+        let unfrozen = heap.alloc(AllocTuple([Value::new_int(1), x, inner]));
+        assert_eq!(Some("a".to_owned()), get(unfrozen));
+    }
 }
@@ -35,6 +35,7 @@ mod freeze;
 mod module;
 mod serde;
 mod trace;
+mod unpack_value;
 mod visit_span;
 mod vtable;
 
@@ -131,6 +132,19 @@ pub fn derive_no_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenS
     serde::derive_no_serialize(input)
 }
 
+/// Derive the `UnpackValue` trait for an enum. A value unpacks to a variant
+/// when it is a string equal to the variant tag, or a struct whose `tag` field
+/// is equal to the variant tag. Fields of struct-like variants are unpacked
+/// from the struct fields of the same name. When a field fails to unpack,
+/// the error names that field.
+///
+/// The tag defaults to the variant name in `snake_case`, and can be overridden
+/// with `#[starlark(tag = "...")]`.
+#[proc_macro_derive(UnpackValue, attributes(starlark))]
+pub fn derive_unpack_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    unpack_value::derive_unpack_value(input)
+}
+
 /// Derive accessor methods that are designed to be used from {has,get,dir}_attr
 /// in an `impl StarlarkValue` block. All fields in the struct that are not
 /// marked with #[starlark(skip)] are exported to Starlark code as attributes.
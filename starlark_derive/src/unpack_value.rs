@@ -0,0 +1,291 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Fields;
+use syn::GenericParam;
+use syn::Lit;
+use syn::Meta;
+use syn::NestedMeta;
+use syn::Result;
+use syn::Variant;
+
+pub fn derive_unpack_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_unpack_value_derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+static STARLARK_ATTR_ERR_MSG: &str = "valid starlark attributes are {tag = \"...\"}";
+
+/// `FooBar` becomes `foo_bar`.
+fn default_tag(ident: &str) -> String {
+    let mut tag = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                tag.push('_');
+            }
+            tag.extend(c.to_lowercase());
+        } else {
+            tag.push(c);
+        }
+    }
+    tag
+}
+
+fn variant_tag(variant: &Variant) -> Result<String> {
+    let attr = match variant.attrs.iter().find(|a| a.path.is_ident("starlark")) {
+        None => return Ok(default_tag(&variant.ident.to_string())),
+        Some(attr) => attr,
+    };
+    match attr.parse_meta()? {
+        Meta::List(lst) if lst.nested.len() == 1 => match &lst.nested[0] {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("tag") => match &nv.lit {
+                Lit::Str(s) => Ok(s.value()),
+                lit => Err(Error::new(lit.span(), "tag must be a string literal")),
+            },
+            m => Err(Error::new(m.span(), STARLARK_ATTR_ERR_MSG)),
+        },
+        _ => Err(Error::new(attr.span(), STARLARK_ATTR_ERR_MSG)),
+    }
+}
+
+fn expand_unpack_value_derive(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let variants: Vec<Variant> = match input.data {
+        Data::Enum(e) => e.variants.into_iter().collect(),
+        Data::Struct(s) => {
+            return Err(Error::new(
+                s.struct_token.span(),
+                "#[derive(UnpackValue)] only supports enums",
+            ));
+        }
+        Data::Union(u) => {
+            return Err(Error::new(
+                u.union_token.span(),
+                "#[derive(UnpackValue)] only supports enums",
+            ));
+        }
+    };
+
+    let mut lifetime = None;
+    for param in &input.generics.params {
+        match param {
+            GenericParam::Lifetime(l) if lifetime.is_none() => lifetime = Some(&l.lifetime),
+            _ => {
+                return Err(Error::new(
+                    param.span(),
+                    "#[derive(UnpackValue)] supports at most one lifetime parameter",
+                ));
+            }
+        }
+    }
+    let (impl_lifetime, self_ty) = match lifetime {
+        Some(l) => (quote! { #l }, quote! { #name<#l> }),
+        None => (quote! { 'v }, quote! { #name }),
+    };
+
+    let mut alternatives = Vec::new();
+    let mut str_arms = Vec::new();
+    let mut struct_arms = Vec::new();
+    let mut field_error_arms = Vec::new();
+    let mut has_unit = false;
+    let mut has_struct = false;
+    for variant in &variants {
+        let tag = variant_tag(variant)?;
+        let ident = &variant.ident;
+        match &variant.fields {
+            Fields::Unit => {
+                has_unit = true;
+                str_arms.push(quote! { #tag => Some(Self::#ident) });
+                struct_arms.push(quote! { #tag => Some(Self::#ident) });
+                let alternative = format!("{:?}", tag);
+                alternatives.push(quote! { #alternative.to_owned() });
+            }
+            Fields::Named(fields) => {
+                has_struct = true;
+                let unpack_fields = fields.named.iter().map(|f| {
+                    let field = f.ident.as_ref().unwrap();
+                    let field_name = field.to_string();
+                    quote! {
+                        #field: starlark::values::UnpackValue::unpack_value(s.get(#field_name)?)?
+                    }
+                });
+                struct_arms.push(quote! { #tag => Some(Self::#ident { #(#unpack_fields,)* }) });
+
+                let check_fields = fields.named.iter().map(|f| {
+                    let field_name = f.ident.as_ref().unwrap().to_string();
+                    let ty = &f.ty;
+                    quote! {
+                        match s.get(#field_name) {
+                            None => {
+                                return Some((
+                                    #field_name,
+                                    <#ty as starlark::values::UnpackValue<#impl_lifetime>>::expected(),
+                                    "missing".to_owned(),
+                                ));
+                            }
+                            Some(v) => {
+                                if <#ty as starlark::values::UnpackValue<#impl_lifetime>>::unpack_value(v).is_none() {
+                                    return Some((
+                                        #field_name,
+                                        <#ty as starlark::values::UnpackValue<#impl_lifetime>>::expected(),
+                                        v.get_type().to_owned(),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                });
+                field_error_arms.push(quote! { #tag => { #(#check_fields)* None } });
+
+                let field_reprs = fields.named.iter().map(|f| {
+                    let field_name = f.ident.as_ref().unwrap().to_string();
+                    let ty = &f.ty;
+                    quote! {
+                        format!(
+                            ", {} = {}",
+                            #field_name,
+                            <#ty as starlark::values::UnpackValue<#impl_lifetime>>::expected(),
+                        )
+                    }
+                });
+                alternatives.push(quote! {
+                    {
+                        let mut alternative = format!("struct(tag = {:?}", #tag);
+                        #(alternative.push_str(&#field_reprs);)*
+                        alternative.push(')');
+                        alternative
+                    }
+                });
+            }
+            Fields::Unnamed(_) => {
+                return Err(Error::new(
+                    variant.span(),
+                    "#[derive(UnpackValue)] does not support tuple variants",
+                ));
+            }
+        }
+    }
+
+    let str_repr = quote! {
+        <String as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
+    };
+    let struct_repr = quote! {
+        <starlark::values::structs::StructRef as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
+    };
+    let type_repr = match (has_unit, has_struct) {
+        (true, true) => quote! { format!("[{}, {}]", #str_repr, #struct_repr) },
+        (false, true) => struct_repr,
+        _ => str_repr,
+    };
+
+    // Finds the first field of the variant selected by `tag` which fails to unpack,
+    // returning its name, what was expected and what was found.
+    let field_error = quote! {
+        let field_error = || -> Option<(&'static str, String, String)> {
+            let s = starlark::values::structs::StructRef::from_value(value)?;
+            match s.get("tag")?.unpack_str()? {
+                #(#field_error_arms,)*
+                _ => None,
+            }
+        };
+    };
+
+    Ok(quote! {
+        impl<#impl_lifetime> starlark::values::type_repr::StarlarkTypeRepr for #self_ty {
+            fn starlark_type_repr() -> String {
+                #type_repr
+            }
+        }
+
+        impl<#impl_lifetime> starlark::values::UnpackValue<#impl_lifetime> for #self_ty {
+            fn expected() -> String {
+                let alternatives: Vec<String> = vec![#(#alternatives),*];
+                format!("one of {}", alternatives.join(", "))
+            }
+
+            fn unpack_value(value: starlark::values::Value<#impl_lifetime>) -> Option<Self> {
+                if let Some(tag) = value.unpack_str() {
+                    return match tag {
+                        #(#str_arms,)*
+                        _ => None,
+                    };
+                }
+                let s = starlark::values::structs::StructRef::from_value(value)?;
+                match s.get("tag")?.unpack_str()? {
+                    #(#struct_arms,)*
+                    _ => None,
+                }
+            }
+
+            fn unpack_param(value: starlark::values::Value<#impl_lifetime>) -> anyhow::Result<Self> {
+                if let Some(x) = Self::unpack_value(value) {
+                    return Ok(x);
+                }
+                #field_error
+                Err(match field_error() {
+                    Some((field, expected, actual)) => {
+                        starlark::values::ValueError::IncorrectParameterTypeNamedWithExpected(
+                            field.to_owned(),
+                            expected,
+                            actual,
+                        )
+                    }
+                    None => starlark::values::ValueError::IncorrectParameterTypeWithExpected(
+                        Self::expected(),
+                        value.get_type().to_owned(),
+                    ),
+                }
+                .into())
+            }
+
+            fn unpack_named_param(
+                value: starlark::values::Value<#impl_lifetime>,
+                param_name: &str,
+            ) -> anyhow::Result<Self> {
+                if let Some(x) = Self::unpack_value(value) {
+                    return Ok(x);
+                }
+                #field_error
+                Err(match field_error() {
+                    Some((field, expected, actual)) => {
+                        starlark::values::ValueError::IncorrectParameterTypeNamedWithExpected(
+                            format!("{}.{}", param_name, field),
+                            expected,
+                            actual,
+                        )
+                    }
+                    None => starlark::values::ValueError::IncorrectParameterTypeNamedWithExpected(
+                        param_name.to_owned(),
+                        Self::expected(),
+                        value.get_type().to_owned(),
+                    ),
+                }
+                .into())
+            }
+        }
+    })
+}